@@ -0,0 +1,50 @@
+// Mic chunk path: the old per-chunk allocations versus the pooled path in
+// src/pcm.rs. Run with `cargo bench --bench audio_path`.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use criterion::{Criterion, Throughput, black_box, criterion_group, criterion_main};
+
+#[allow(dead_code)]
+#[path = "../src/pcm.rs"]
+mod pcm;
+
+/// 40 ms at 24 kHz, the default chunk.
+const FRAMES: usize = 960;
+
+fn chunk() -> Vec<i16> {
+    (0..FRAMES).map(|i| ((i as f32 * 0.07).sin() * 12_000.0) as i16).collect()
+}
+
+fn mic_chunk(c: &mut Criterion) {
+    let samples = chunk();
+    let mut group = c.benchmark_group("mic_chunk");
+    group.throughput(Throughput::Elements(FRAMES as u64));
+
+    group.bench_function("per_chunk_alloc", |b| {
+        b.iter(|| {
+            let bytes: Vec<u8> = black_box(&samples).iter().flat_map(|s| s.to_le_bytes()).collect();
+            let b64 = B64.encode(&bytes);
+            serde_json::json!({"type": "input_audio_buffer.append", "audio": b64}).to_string()
+        })
+    });
+
+    let pool = pcm::Pool::new(4, FRAMES);
+    let mut bytes = Vec::with_capacity(FRAMES * 2);
+    group.bench_function("pooled", |b| {
+        b.iter(|| {
+            let mut buf = pool.take();
+            buf.extend_from_slice(black_box(&samples));
+            pcm::le_bytes(&buf, &mut bytes);
+            let ev = pcm::append_event(&bytes);
+            pool.give(buf);
+            ev
+        })
+    });
+    group.finish();
+
+    c.bench_function("peak_level", |b| b.iter(|| pcm::peak_level(black_box(&samples))));
+}
+
+criterion_group!(benches, mic_chunk);
+criterion_main!(benches);
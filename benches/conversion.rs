@@ -0,0 +1,98 @@
+// Per-chunk conversions on the realtime path at the session rate: device
+// samples to mono PCM16, resampling, PCM16 to and from wire bytes, peak
+// metering and base64. Run with `cargo bench --bench conversion`.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use cpal::Sample;
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+
+#[allow(dead_code)]
+#[path = "../src/pcm.rs"]
+mod pcm;
+#[path = "../src/resample.rs"]
+mod resample;
+
+const SR: usize = 24_000;
+
+/// `ms` of a 440 Hz tone at `rate`.
+fn tone(rate: usize, ms: usize) -> Vec<i16> {
+    let n = rate * ms / 1000;
+    (0..n).map(|i| ((i as f32 * 440.0 / rate as f32 * std::f32::consts::TAU).sin() * 12_000.0) as i16).collect()
+}
+
+fn device_to_mono(c: &mut Criterion) {
+    // A 10 ms callback from a 48 kHz stereo f32 device, downmixed as in devices.rs
+    let frames = 480;
+    let stereo: Vec<f32> = tone(48_000, 10).iter().flat_map(|&s| [s.to_f32(), s.to_f32()]).collect();
+    let mut pcm = Vec::with_capacity(frames);
+    let mut group = c.benchmark_group("device_to_mono");
+    group.throughput(Throughput::Elements(frames as u64));
+    group.bench_function("f32_stereo", |b| {
+        b.iter(|| {
+            pcm.clear();
+            pcm.extend(black_box(&stereo).chunks(2).map(|f| {
+                (f.iter().map(|s| s.to_i16() as i32).sum::<i32>() / f.len() as i32) as i16
+            }));
+        })
+    });
+    group.finish();
+
+    // 10 ms from devices that cannot run at the session rate
+    let mut group = c.benchmark_group("resample");
+    let mut out = Vec::with_capacity(SR / 100);
+    for from in [16_000u32, 44_100, 48_000] {
+        let input = tone(from as usize, 10);
+        let mut r = resample::Resampler::new(from, SR as u32);
+        group.throughput(Throughput::Elements(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(from), &input, |b, input| {
+            b.iter(|| {
+                out.clear();
+                r.process(black_box(input), &mut out);
+            })
+        });
+    }
+    group.finish();
+}
+
+fn wire(c: &mut Criterion) {
+    // 40 ms mic chunks out, and assistant deltas in (about 100 ms each)
+    let chunk = tone(SR, 40);
+    let delta = tone(SR, 100);
+    let mut bytes = Vec::new();
+    pcm::le_bytes(&delta, &mut bytes);
+    let delta_b64 = B64.encode(&bytes);
+
+    let mut group = c.benchmark_group("wire");
+    group.throughput(Throughput::Elements(chunk.len() as u64));
+    group.bench_function("pcm16_to_le_bytes", |b| b.iter(|| pcm::le_bytes(black_box(&chunk), &mut bytes)));
+    pcm::le_bytes(&chunk, &mut bytes);
+    let mut b64 = String::new();
+    group.bench_function("base64_encode", |b| {
+        b.iter(|| {
+            b64.clear();
+            B64.encode_string(black_box(&bytes), &mut b64);
+        })
+    });
+    group.bench_function("peak_level", |b| b.iter(|| pcm::peak_level(black_box(&chunk))));
+
+    group.throughput(Throughput::Elements(delta.len() as u64));
+    let mut decoded = Vec::new();
+    group.bench_function("base64_decode", |b| {
+        b.iter(|| {
+            decoded.clear();
+            B64.decode_vec(black_box(&delta_b64), &mut decoded).unwrap();
+        })
+    });
+    let mut samples = Vec::with_capacity(delta.len());
+    group.bench_function("le_bytes_to_pcm16", |b| {
+        b.iter(|| {
+            samples.clear();
+            samples.extend(black_box(&decoded).chunks_exact(2).map(|p| i16::from_le_bytes([p[0], p[1]])));
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, device_to_mono, wire);
+criterion_main!(benches);
@@ -0,0 +1,82 @@
+// The speaker ring buffer: network deltas pushed in, output callbacks
+// draining it through the earcon mixer, as in main.rs. One iteration moves
+// one second of audio at the session rate. Run with
+// `cargo bench --bench ring_buffer`.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+
+#[allow(dead_code)]
+#[path = "../src/earcon.rs"]
+mod earcon;
+
+const SR: usize = 24_000;
+/// Assistant audio delta size (100 ms).
+const DELTA: usize = SR / 10;
+
+fn ring_buffer(c: &mut Criterion) {
+    let delta: Vec<i16> = (0..DELTA).map(|i| (i % 2000) as i16 - 1000).collect();
+    let mut group = c.benchmark_group("ring_buffer");
+    group.throughput(Throughput::Elements(SR as u64));
+
+    // Output callback sizes from low-latency (5 ms) to large (40 ms)
+    for callback in [120usize, 480, 960] {
+        let spk = Arc::new(Mutex::new(VecDeque::<i16>::with_capacity(96_000)));
+        let cues = Arc::new(Mutex::new(VecDeque::<i16>::new()));
+        let mut out = vec![0i16; callback];
+        group.bench_with_input(BenchmarkId::new("single_thread", callback), &callback, |b, _| {
+            b.iter(|| {
+                for _ in 0..SR / DELTA {
+                    spk.lock().unwrap().extend(black_box(&delta).iter().copied());
+                    while spk.lock().unwrap().len() >= callback {
+                        let mut buf = spk.lock().unwrap();
+                        let mut cues = cues.lock().unwrap();
+                        for s in out.iter_mut() {
+                            *s = earcon::mix_next(&mut buf, &mut cues);
+                        }
+                    }
+                }
+                black_box(&out);
+            })
+        });
+    }
+
+    // Producer on its own thread, as the network task is, contending for the lock
+    let callback = 480;
+    group.bench_function(BenchmarkId::new("contended", callback), |b| {
+        b.iter(|| {
+            let spk = Arc::new(Mutex::new(VecDeque::<i16>::with_capacity(96_000)));
+            let producer = {
+                let (spk, delta) = (spk.clone(), delta.clone());
+                std::thread::spawn(move || {
+                    for _ in 0..SR / DELTA {
+                        spk.lock().unwrap().extend(delta.iter().copied());
+                    }
+                })
+            };
+            let mut cues = VecDeque::new();
+            let mut out = vec![0i16; callback];
+            let mut drained = 0;
+            while drained < SR {
+                let mut buf = spk.lock().unwrap();
+                if buf.len() < callback {
+                    drop(buf);
+                    std::thread::yield_now();
+                    continue;
+                }
+                for s in out.iter_mut() {
+                    *s = earcon::mix_next(&mut buf, &mut cues);
+                }
+                drained += callback;
+            }
+            producer.join().unwrap();
+            black_box(&out);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, ring_buffer);
+criterion_main!(benches);
@@ -0,0 +1,15 @@
+// Generates the gRPC server code from proto/parlar.proto for the `grpc`
+// feature; protox parses the .proto, so no protoc install is needed.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/parlar.proto");
+        let fds = protox::compile(["parlar.proto"], ["proto"]).expect("proto/parlar.proto");
+        tonic_build::configure()
+            .build_client(false)
+            .compile_fds(fds)
+            .expect("gRPC code generation");
+    }
+}
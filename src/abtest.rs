@@ -0,0 +1,190 @@
+// A/B comparison of two personas: replies alternate between variant A and
+// variant B, each with its own instructions and/or voice, and the user
+// rates replies as they come (+ / - in the terminal UI, `/good` / `/bad`,
+// or the `rate` JSON command). Ratings go to AB_LOG as JSON lines, redacted
+// and encrypted like the other transcripts, and the tally per variant is
+// printed on exit.
+//
+//   AB_A_INSTRUCTIONS / AB_B_INSTRUCTIONS  instructions of each variant; A
+//                                          defaults to the session's own
+//   AB_A_VOICE / AB_B_VOICE                voice of each variant; A defaults
+//                                          to REALTIME_VOICE
+// Setting any AB_B_* turns the mode on. AB_BLIND (default true) keeps the
+// variant of each reply hidden until the tally.
+//
+// Instructions are changed per reply (in `response.create`), so the switch
+// is free. The server fixes the voice once a reply has been spoken, so
+// different voices mean a new connection after each reply (the
+// conversation is carried over, as with `set_voice`).
+
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use serde_json::{Value, json};
+
+use crate::control::Controls;
+use crate::{State, crypt, output, redact, shutdown};
+
+struct Variant {
+    name: &'static str,
+    instructions: Option<String>,
+    voice: Option<String>,
+}
+
+struct Config {
+    variants: [Variant; 2],
+    blind: bool,
+    log: PathBuf,
+}
+
+#[derive(Default)]
+struct Run {
+    // variant of the next reply
+    next: usize,
+    replies: u64,
+    // the last finished reply: number, variant, text, and whether it was rated
+    last: Option<(u64, usize, String, bool)>,
+    // [variant][good, bad]
+    tally: [[u64; 2]; 2],
+}
+
+static CONFIG: OnceLock<Option<Config>> = OnceLock::new();
+static RUN: Mutex<Option<Run>> = Mutex::new(None);
+
+fn config() -> Option<&'static Config> {
+    CONFIG.get().and_then(Option::as_ref)
+}
+
+/// Read the AB_* settings; call once at startup.
+pub fn init() -> Result<(), String> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let b = Variant { name: "B", instructions: var("AB_B_INSTRUCTIONS"), voice: var("AB_B_VOICE") };
+    if b.instructions.is_none() && b.voice.is_none() {
+        let _ = CONFIG.set(None);
+        return Ok(());
+    }
+    let a = Variant {
+        name: "A",
+        instructions: var("AB_A_INSTRUCTIONS"),
+        voice: var("AB_A_VOICE").or_else(|| var("REALTIME_VOICE")).or_else(|| Some("alloy".into())),
+    };
+    let b = Variant { voice: b.voice.or_else(|| a.voice.clone()), ..b };
+    if a.instructions == b.instructions && a.voice == b.voice {
+        return Err("AB_B_*: variant B is the same as variant A".into());
+    }
+    let log = PathBuf::from(var("AB_LOG").unwrap_or_else(|| "ab-ratings.jsonl".into()));
+    let _ = CONFIG.set(Some(Config { variants: [a, b], blind: crate::env_flag("AB_BLIND", true), log }));
+    *RUN.lock().unwrap() = Some(Run::default());
+    Ok(())
+}
+
+/// The voice to connect with: variant A's, when the mode is on.
+pub fn first_voice() -> Option<String> {
+    config()?.variants[0].voice.clone()
+}
+
+/// The `response.create` event for the next reply, with the instructions of
+/// its variant; a plain one when the mode is off.
+pub fn response_create(st: &State) -> Value {
+    let mut create = json!({"type": "response.create"});
+    let Some(cfg) = config() else { return create };
+    let next = RUN.lock().unwrap().as_ref().map_or(0, |r| r.next);
+    if let Some(instructions) = &cfg.variants[next].instructions {
+        create["response"] = json!({"instructions": st.instructions_with(instructions)});
+    }
+    create
+}
+
+/// A reply finished (`response.done`): note its variant and move to the
+/// other one. Only completed replies with spoken or written output count;
+/// tool calls and out-of-band responses do not.
+pub fn reply_done(evt: &Value, controls: &Controls) {
+    let Some(cfg) = config() else { return };
+    let response = &evt["response"];
+    let spoke = response["output"].as_array().into_iter().flatten().any(|item| item["type"] == "message");
+    if response["status"] != "completed" || !response["metadata"].is_null() || !spoke {
+        return;
+    }
+    let next = {
+        let mut run = RUN.lock().unwrap();
+        let Some(run) = run.as_mut() else { return };
+        run.replies += 1;
+        run.last = Some((run.replies, run.next, crate::hooks::response_text(evt), false));
+        if !cfg.blind {
+            output::notice("ab", &format!("reply {} was {}.", run.replies, cfg.variants[run.next].name));
+        }
+        run.next = 1 - run.next;
+        run.next
+    };
+    let voice = &cfg.variants[next].voice;
+    if let Some(v) = voice
+        && controls.state.lock().unwrap().voice != *v
+    {
+        controls.set_voice(v);
+    }
+}
+
+/// Rate the last reply; each reply is rated once.
+pub fn rate(good: bool) {
+    let Some(cfg) = config() else {
+        output::notice("ab", "A/B mode is off; set AB_B_INSTRUCTIONS or AB_B_VOICE.");
+        return;
+    };
+    let mut run = RUN.lock().unwrap();
+    let Some(run) = run.as_mut() else { return };
+    let Some((n, variant, text, rated)) = &mut run.last else {
+        output::notice("ab", "no reply to rate yet.");
+        return;
+    };
+    if *rated {
+        output::notice("ab", &format!("reply {n} is already rated."));
+        return;
+    }
+    *rated = true;
+    run.tally[*variant][usize::from(!good)] += 1;
+    let entry = json!({
+        "time": chrono::Local::now().to_rfc3339(),
+        "reply": n,
+        "variant": cfg.variants[*variant].name,
+        "rating": if good { "good" } else { "bad" },
+        "instructions": cfg.variants[*variant].instructions,
+        "voice": cfg.variants[*variant].voice,
+        "text": text,
+    });
+    let line = format!("{}\n", redact::apply(&entry.to_string()));
+    let written = crypt::Appender::open(&cfg.log.to_string_lossy()).and_then(|mut f| {
+        f.append(line.as_bytes()).and_then(|_| f.sync()).map_err(|e| format!("{}: {e}", cfg.log.display()))
+    });
+    if let Err(e) = written {
+        output::notice("ab", &format!("cannot write the rating: {e}"));
+    }
+    output::notice("ab", &format!("reply {n} rated {}.", if good { "good" } else { "bad" }));
+}
+
+/// Print the tally on exit; returns what is compared, for the startup
+/// banner, or `None` when the mode is off.
+pub fn start() -> Option<String> {
+    let cfg = config()?;
+    shutdown::on_exit(|| {
+        let Some(cfg) = config() else { return };
+        let Some(run) = RUN.lock().unwrap().take() else { return };
+        let line: Vec<String> = cfg
+            .variants
+            .iter()
+            .zip(run.tally)
+            .map(|(v, [good, bad])| format!("{} {good} good, {bad} bad", v.name))
+            .collect();
+        output::info(&format!("A/B: {} (over {} replies)", line.join("; "), run.replies));
+    });
+    let mut differs = Vec::new();
+    if cfg.variants[0].instructions != cfg.variants[1].instructions {
+        differs.push("instructions".to_string());
+    }
+    if let [Variant { voice: Some(a), .. }, Variant { voice: Some(b), .. }] = &cfg.variants
+        && a != b
+    {
+        differs.push(format!("voice {a} / {b}"));
+    }
+    let how = if cfg.blind { "blind" } else { "labelled" };
+    Some(format!("{} ({how}); rate replies into {}", differs.join(", "), cfg.log.display()))
+}
@@ -0,0 +1,229 @@
+// Audio backends. Everything above this layer deals in mono PCM16; a
+// backend opens devices by name and converts to and from the device's
+// native sample format.
+//
+// `AUDIO_BACKEND` picks one:
+//   default  cpal's platform host (ALSA, CoreAudio, WASAPI)
+//   jack     JACK, including PipeWire's JACK interface (build with `--features jack`)
+//   pipe     raw PCM over FIFOs, files or stdin/stdout (see pipe.rs)
+// Builds without the `audio` feature have only `pipe`; otherwise there are
+// no devices and the session runs text-only.
+
+use std::any::Any;
+
+#[cfg(feature = "audio")]
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+#[cfg(feature = "audio")]
+use crate::devices;
+
+pub type OnInput = Box<dyn FnMut(&[i16]) + Send>;
+pub type OnOutput = Box<dyn FnMut(&mut [i16]) + Send>;
+
+/// A running stream; audio stops when it is dropped.
+pub struct Opened {
+    pub(crate) _stream: Box<dyn Any>,
+    pub device: String,
+    /// Actual rate, which may differ from the one asked for.
+    pub sample_rate: u32,
+}
+
+pub trait AudioBackend {
+    fn name(&self) -> &'static str;
+    fn device_names(&self, input: bool) -> Vec<String>;
+    /// Start capturing from `device` (or the default); `on_data` runs on the audio thread.
+    fn open_input(&self, device: Option<&str>, sr_hz: u32, on_data: OnInput) -> Result<Opened, String>;
+    /// Capture what the system is playing (loopback / monitor source).
+    fn open_system_capture(&self, sr_hz: u32, on_data: OnInput) -> Result<Opened, String>;
+    /// Start playback on `device` (or the default); `fill` runs on the audio thread.
+    fn open_output(&self, device: Option<&str>, sr_hz: u32, fill: OnOutput) -> Result<Opened, String>;
+}
+
+/// AUDIO_BUFFER_FRAMES: frames per device callback (the ALSA period size)
+/// instead of the host default; larger means fewer wakeups and more latency.
+pub fn buffer_frames() -> Result<Option<u32>, String> {
+    match std::env::var("AUDIO_BUFFER_FRAMES") {
+        Ok(v) if !v.trim().is_empty() => match v.trim().parse::<u32>() {
+            Ok(n) if (16..=16384).contains(&n) => Ok(Some(n)),
+            _ => Err(format!("AUDIO_BUFFER_FRAMES: {v} (use 16 to 16384)")),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Any cpal host.
+#[cfg(feature = "audio")]
+pub struct Cpal {
+    host: cpal::Host,
+    name: &'static str,
+}
+
+#[cfg(feature = "audio")]
+impl Cpal {
+    fn open(
+        &self,
+        input: bool,
+        device: Option<&str>,
+        sr_hz: u32,
+    ) -> Result<(cpal::Device, cpal::StreamConfig), String> {
+        let dev = devices::find_in(&self.host, input, device).ok_or_else(|| {
+            let var = if input { "INPUT_DEVICE" } else { "OUTPUT_DEVICE" };
+            format!("no {} device found (check {var})", if input { "input" } else { "output" })
+        })?;
+        let cfg = devices::stream_config(&dev, input, sr_hz)?;
+        Ok((dev, cfg))
+    }
+}
+
+#[cfg(feature = "audio")]
+impl AudioBackend for Cpal {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn device_names(&self, input: bool) -> Vec<String> {
+        let devices = if input { self.host.input_devices() } else { self.host.output_devices() };
+        devices.map(|d| d.filter_map(|d| d.name().ok()).collect()).unwrap_or_default()
+    }
+
+    fn open_input(&self, device: Option<&str>, sr_hz: u32, on_data: OnInput) -> Result<Opened, String> {
+        let (dev, cfg) = self.open(true, device, sr_hz)?;
+        let format = dev.default_input_config().map_err(|e| e.to_string())?.sample_format();
+        let stream = devices::open_input(&dev, &cfg, format, sr_hz, on_data)?;
+        stream.play().map_err(|e| e.to_string())?;
+        Ok(Opened {
+            _stream: Box::new(stream),
+            device: dev.name().unwrap_or_default(),
+            sample_rate: cfg.sample_rate.0,
+        })
+    }
+
+    // WASAPI captures an output device when an input stream is built on it
+    #[cfg(windows)]
+    fn open_system_capture(&self, sr_hz: u32, on_data: OnInput) -> Result<Opened, String> {
+        let dev = self.host.default_output_device().ok_or("no output device to capture")?;
+        let default = dev.default_output_config().map_err(|e| e.to_string())?;
+        let cfg = default.config();
+        let stream = devices::open_input(&dev, &cfg, default.sample_format(), sr_hz, on_data)?;
+        stream.play().map_err(|e| e.to_string())?;
+        Ok(Opened {
+            _stream: Box::new(stream),
+            device: format!("{} (loopback)", dev.name().unwrap_or_default()),
+            sample_rate: cfg.sample_rate.0,
+        })
+    }
+
+    // PulseAudio / PipeWire: record a sink's monitor. A monitor source listed
+    // among the input devices (PipeWire's ALSA plugin, an asound.conf entry)
+    // is opened by name; otherwise the ALSA `pulse` device records whatever
+    // PULSE_SOURCE names. parlar cannot set that itself: the environment is
+    // not safe to change once other threads run, so the user sets it at launch.
+    #[cfg(target_os = "linux")]
+    fn open_system_capture(&self, sr_hz: u32, on_data: OnInput) -> Result<Opened, String> {
+        let (dev, device) = match devices::find_in(&self.host, true, Some("monitor")) {
+            Some(dev) => {
+                let name = dev.name().unwrap_or_default();
+                (dev, name)
+            }
+            None => {
+                let Some(source) = std::env::var("PULSE_SOURCE").ok().filter(|s| !s.trim().is_empty()) else {
+                    return Err("no monitor source among the input devices; start parlar with \
+                                PULSE_SOURCE=@DEFAULT_MONITOR@ to record the default sink's monitor"
+                        .into());
+                };
+                let dev = devices::find_in(&self.host, true, Some("pulse"))
+                    .ok_or("no ALSA `pulse` device; system capture needs PulseAudio or pipewire-pulse")?;
+                (dev, format!("pulse ({source})"))
+            }
+        };
+        let cfg = devices::stream_config(&dev, true, sr_hz)?;
+        let format = dev.default_input_config().map_err(|e| e.to_string())?.sample_format();
+        let stream = devices::open_input(&dev, &cfg, format, sr_hz, on_data)?;
+        stream.play().map_err(|e| e.to_string())?;
+        Ok(Opened {
+            _stream: Box::new(stream),
+            device,
+            sample_rate: cfg.sample_rate.0,
+        })
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    fn open_system_capture(&self, _sr_hz: u32, _on_data: OnInput) -> Result<Opened, String> {
+        Err("no system loopback on this platform; install a virtual device such as BlackHole, \
+             route output through it and set INPUT_DEVICE to it"
+            .into())
+    }
+
+    fn open_output(&self, device: Option<&str>, sr_hz: u32, fill: OnOutput) -> Result<Opened, String> {
+        let (dev, cfg) = self.open(false, device, sr_hz)?;
+        let stream = devices::open_output(&dev, &cfg, fill)?;
+        stream.play().map_err(|e| e.to_string())?;
+        Ok(Opened {
+            _stream: Box::new(stream),
+            device: dev.name().unwrap_or_default(),
+            sample_rate: cfg.sample_rate.0,
+        })
+    }
+}
+
+/// The cpal host named by `AUDIO_BACKEND`.
+#[cfg(feature = "audio")]
+pub fn host() -> Result<cpal::Host, String> {
+    match std::env::var("AUDIO_BACKEND").as_deref().unwrap_or("default") {
+        "pipe" => Err("AUDIO_BACKEND=pipe has no audio devices".into()),
+        "default" | "" => Ok(cpal::default_host()),
+        #[cfg(all(feature = "jack", target_os = "linux"))]
+        "jack" => cpal::host_from_id(cpal::HostId::Jack).map_err(|e| format!("JACK: {e}")),
+        #[cfg(not(all(feature = "jack", target_os = "linux")))]
+        "jack" => Err("AUDIO_BACKEND=jack needs a build with `--features jack` (Linux)".into()),
+        other => Err(format!("AUDIO_BACKEND must be default, jack or pipe (got {other})")),
+    }
+}
+
+/// Backend selected by `AUDIO_BACKEND`.
+#[cfg(feature = "audio")]
+pub fn from_env() -> Result<Box<dyn AudioBackend>, String> {
+    let name = match std::env::var("AUDIO_BACKEND").as_deref() {
+        Ok("pipe") => return Ok(Box::new(crate::pipe::Pipe::from_env()?)),
+        Ok("jack") => "jack",
+        _ => "default",
+    };
+    Ok(Box::new(Cpal { host: host()?, name }))
+}
+
+/// Stands in for the sound card in builds without the `audio` feature.
+#[cfg(not(feature = "audio"))]
+struct NoDevices;
+
+#[cfg(not(feature = "audio"))]
+impl AudioBackend for NoDevices {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn device_names(&self, _input: bool) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn open_input(&self, _device: Option<&str>, _sr_hz: u32, _on_data: OnInput) -> Result<Opened, String> {
+        Err("built without the `audio` feature".into())
+    }
+
+    fn open_system_capture(&self, _sr_hz: u32, _on_data: OnInput) -> Result<Opened, String> {
+        Err("built without the `audio` feature".into())
+    }
+
+    fn open_output(&self, _device: Option<&str>, _sr_hz: u32, _fill: OnOutput) -> Result<Opened, String> {
+        Err("built without the `audio` feature".into())
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+pub fn from_env() -> Result<Box<dyn AudioBackend>, String> {
+    match std::env::var("AUDIO_BACKEND").as_deref() {
+        Ok("pipe") => Ok(Box::new(crate::pipe::Pipe::from_env()?)),
+        Ok("default" | "jack") => Err("AUDIO_BACKEND=default and jack need a build with the `audio` feature".into()),
+        Ok(other) if !other.is_empty() => Err(format!("AUDIO_BACKEND must be pipe in this build (got {other})")),
+        _ => Ok(Box::new(NoDevices)),
+    }
+}
@@ -0,0 +1,246 @@
+// `--bug-report`: record this session for a bug report. The event log
+// (EVENT_LOG, else a new one) is kept and, with BUG_REPORT_AUDIO=true, each
+// turn's audio as with `--capture-dataset`; on the way out they go into one
+// tar archive (BUG_REPORT_FILE, default parlar-bug-<time>.tar) together
+// with the settings, secrets redacted, the audio devices and the build.
+// Files written encrypted (TRANSCRIPT_PASSPHRASE) go in encrypted.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{output, redact};
+
+const MASK: &str = "[REDACTED]";
+// Name parts of settings whose values are never written out
+const SECRET_PARTS: &[&str] = &["KEY", "KEYFILE", "TOKEN", "SECRET", "PASSWORD", "PASSPHRASE", "PIN", "AUTH"];
+// Settings always listed, as they shape every session
+const CORE: &[&str] = &[
+    "REALTIME_MODEL",
+    "REALTIME_VOICE",
+    "SR",
+    "AUDIO_FORMAT",
+    "OUTPUT_AUDIO_FORMAT",
+    "CHUNK_MS",
+    "AUDIO_BACKEND",
+    "INPUT_DEVICE",
+    "OUTPUT_DEVICE",
+    "CAPTURE_SOURCE",
+    "AUDIO_BUFFER_FRAMES",
+    "PARLAR_PROFILE",
+    "SESSION_EXTRA",
+];
+
+pub struct BugReport {
+    name: String,
+    archive: PathBuf,
+    work: PathBuf,
+    audio: bool,
+}
+
+struct Pending {
+    report: BugReport,
+    event_log: PathBuf,
+    dataset: Option<PathBuf>,
+    env_file: Option<PathBuf>,
+    sr_hz: u32,
+    model: String,
+}
+
+static PENDING: Mutex<Option<Pending>> = Mutex::new(None);
+
+impl BugReport {
+    /// Read BUG_REPORT_AUDIO and BUG_REPORT_FILE, and make a working
+    /// directory for what the session writes.
+    pub fn begin() -> Result<Self, String> {
+        let name = format!("parlar-bug-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        let archive = std::env::var("BUG_REPORT_FILE")
+            .ok()
+            .filter(|f| !f.trim().is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(format!("{name}.tar")));
+        let work = std::env::temp_dir().join(&name);
+        fs::create_dir_all(&work).map_err(|e| format!("--bug-report: {}: {e}", work.display()))?;
+        Ok(BugReport { name, archive, work, audio: crate::env_flag("BUG_REPORT_AUDIO", false) })
+    }
+
+    /// The event log to write when EVENT_LOG is not set.
+    pub fn event_log(&self) -> String {
+        self.work.join("events.jsonl").display().to_string()
+    }
+
+    /// Where turn audio goes, with BUG_REPORT_AUDIO=true.
+    pub fn audio_dir(&self) -> Option<String> {
+        self.audio.then(|| self.work.join("audio").display().to_string())
+    }
+
+    /// Keep what the session writes, for `finish` to bundle.
+    pub fn record(
+        self,
+        event_log: &str,
+        dataset: Option<&Path>,
+        env_file: Option<&Path>,
+        sr_hz: u32,
+        model: &str,
+    ) {
+        output::info(&format!("Bug report: recording this session into {}", self.archive.display()));
+        *PENDING.lock().unwrap() = Some(Pending {
+            report: self,
+            event_log: PathBuf::from(event_log),
+            dataset: dataset.map(Path::to_path_buf),
+            env_file: env_file.map(Path::to_path_buf),
+            sr_hz,
+            model: model.to_string(),
+        });
+    }
+}
+
+/// Write the archive, if `--bug-report` is on and it is not written yet;
+/// called on every way out, fatal errors included.
+pub fn finish() {
+    let Some(p) = PENDING.lock().unwrap().take() else { return };
+    match write(&p) {
+        Ok(()) => output::info(&format!(
+            "Bug report: {} (check it before you share it)",
+            p.report.archive.display()
+        )),
+        Err(e) => output::notice("bug-report", &format!("cannot write {}: {e}", p.report.archive.display())),
+    }
+    let _ = fs::remove_dir_all(&p.report.work);
+}
+
+fn write(p: &Pending) -> Result<(), String> {
+    let mut tar = Tar::default();
+    let dir = &p.report.name;
+    tar.add(&format!("{dir}/info.txt"), info(p).as_bytes());
+    tar.add(&format!("{dir}/settings.txt"), settings(p.env_file.as_deref()).as_bytes());
+    #[cfg(feature = "audio")]
+    let devices = crate::devices::report(p.sr_hz).unwrap_or_else(|e| format!("{e}\n"));
+    #[cfg(not(feature = "audio"))]
+    let devices = "built without the `audio` feature\n".to_string();
+    tar.add(&format!("{dir}/devices.txt"), devices.as_bytes());
+    match fs::read(&p.event_log) {
+        Ok(bytes) => {
+            let sealed = if crate::crypt::enabled() { crate::crypt::SUFFIX } else { "" };
+            tar.add(&format!("{dir}/events.jsonl{sealed}"), &bytes);
+        }
+        Err(e) => output::notice("bug-report", &format!("no event log ({}: {e})", p.event_log.display())),
+    }
+    if let Some(ds) = &p.dataset {
+        let mut files: Vec<PathBuf> =
+            fs::read_dir(ds).map_err(|e| format!("{}: {e}", ds.display()))?.flatten().map(|e| e.path()).collect();
+        files.sort();
+        for path in files.iter().filter(|f| f.is_file()) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let bytes = fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            tar.add(&format!("{dir}/audio/{name}"), &bytes);
+        }
+    }
+    let mut file = fs::File::create(&p.report.archive).map_err(|e| e.to_string())?;
+    file.write_all(&tar.finish()).map_err(|e| e.to_string())
+}
+
+fn info(p: &Pending) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "parlar {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "os: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let features: Vec<&str> = [
+        ("audio", cfg!(feature = "audio")),
+        ("tui", cfg!(feature = "tui")),
+        ("bridge", cfg!(feature = "bridge")),
+        ("jack", cfg!(feature = "jack")),
+        ("hotkeys", cfg!(feature = "hotkeys")),
+        ("grpc", cfg!(feature = "grpc")),
+        ("plugins", cfg!(feature = "plugins")),
+        ("scripting", cfg!(feature = "scripting")),
+        ("gpio", cfg!(feature = "gpio")),
+    ]
+    .into_iter()
+    .filter_map(|(name, on)| on.then_some(name))
+    .collect();
+    let _ = writeln!(out, "features: {}", features.join(", "));
+    let _ = writeln!(out, "model: {}", p.model);
+    let _ = writeln!(out, "sample rate: {} Hz", p.sr_hz);
+    let _ = writeln!(out, "written: {}", chrono::Local::now().to_rfc3339());
+    out
+}
+
+/// The settings from .env and parlar.toml, and the core ones, with the
+/// values in effect; secrets and URL credentials are masked.
+fn settings(env_file: Option<&Path>) -> String {
+    let mut names: Vec<String> = CORE.iter().map(|n| n.to_string()).collect();
+    if let Some(path) = env_file
+        && let Ok(iter) = dotenvy::from_path_iter(path)
+    {
+        names.extend(iter.flatten().map(|(k, _)| k));
+    }
+    if let Ok(body) = fs::read_to_string(crate::config::path())
+        && let Ok(table) = body.parse::<toml::Table>()
+    {
+        names.extend(table.keys().cloned());
+    }
+    names.sort();
+    names.dedup();
+    let mut out = String::new();
+    for name in names {
+        let Ok(value) = std::env::var(&name) else { continue };
+        let _ = writeln!(out, "{name}={}", mask(&name, &value));
+    }
+    out
+}
+
+fn mask(name: &str, value: &str) -> String {
+    if name.split('_').any(|part| SECRET_PARTS.contains(&part)) || value.trim().starts_with("sk-") {
+        return MASK.into();
+    }
+    if let Ok(mut url) = url::Url::parse(value)
+        && (!url.username().is_empty() || url.password().is_some())
+    {
+        let _ = url.set_username("redacted");
+        let _ = url.set_password(None);
+        return url.to_string();
+    }
+    redact::apply(value).into_owned()
+}
+
+/// A plain (ustar) tar archive, built in memory.
+#[derive(Default)]
+struct Tar {
+    data: Vec<u8>,
+}
+
+impl Tar {
+    fn add(&mut self, path: &str, bytes: &[u8]) {
+        let mut header = [0u8; 512];
+        let field = |header: &mut [u8; 512], at: usize, len: usize, value: &[u8]| {
+            let n = value.len().min(len);
+            header[at..at + n].copy_from_slice(&value[..n]);
+        };
+        let mtime = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        field(&mut header, 0, 100, path.as_bytes());
+        field(&mut header, 100, 8, b"0000644\0");
+        field(&mut header, 108, 8, b"0000000\0");
+        field(&mut header, 116, 8, b"0000000\0");
+        field(&mut header, 124, 12, format!("{:011o}\0", bytes.len()).as_bytes());
+        field(&mut header, 136, 12, format!("{mtime:011o}\0").as_bytes());
+        header[156] = b'0';
+        field(&mut header, 257, 8, b"ustar\x0000");
+        // The checksum is taken with its own field as spaces
+        header[148..156].fill(b' ');
+        let sum: u32 = header.iter().map(|&b| b as u32).sum();
+        field(&mut header, 148, 8, format!("{sum:06o}\0 ").as_bytes());
+        self.data.extend_from_slice(&header);
+        self.data.extend_from_slice(bytes);
+        self.data.resize(self.data.len().next_multiple_of(512), 0);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.data.resize(self.data.len() + 1024, 0);
+        self.data
+    }
+}
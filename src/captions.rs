@@ -0,0 +1,107 @@
+// SubRip / WebVTT captions for `parlar export --format srt|vtt`, on the
+// same timeline as the exported audio.
+//
+// The dataset keeps no per-word timing, so each side of a turn is split
+// into short cues at sentence and clause breaks, and the cues share that
+// side's audio span in proportion to their length.
+
+use crate::export::Session;
+
+// Longest cue, in characters (two subtitle lines)
+const CUE_CHARS: usize = 84;
+
+pub struct Cue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub speaker: &'static str,
+    pub text: String,
+}
+
+/// Split `text` into cue-sized pieces, preferring sentence ends, then
+/// commas, then any space.
+fn pieces(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    for word in text.split_whitespace() {
+        if !cur.is_empty() && cur.chars().count() + 1 + word.chars().count() > CUE_CHARS {
+            out.push(std::mem::take(&mut cur));
+        }
+        if !cur.is_empty() {
+            cur.push(' ');
+        }
+        cur.push_str(word);
+        let len = cur.chars().count();
+        let ends_sentence = word.ends_with(['.', '?', '!', '…']);
+        let ends_clause = word.ends_with([',', ';', ':']) && len >= CUE_CHARS / 2;
+        if ends_sentence || ends_clause {
+            out.push(std::mem::take(&mut cur));
+        }
+    }
+    if !cur.is_empty() {
+        out.push(cur);
+    }
+    out
+}
+
+/// Spread `text` over `start_ms..end_ms`.
+fn spread(text: &str, start_ms: u64, end_ms: u64, speaker: &'static str, cues: &mut Vec<Cue>) {
+    let pieces = pieces(text);
+    if pieces.is_empty() || end_ms <= start_ms {
+        return;
+    }
+    let total: usize = pieces.iter().map(|p| p.chars().count()).sum();
+    let span = end_ms - start_ms;
+    let mut at = start_ms;
+    let mut done = 0;
+    for text in pieces {
+        done += text.chars().count();
+        let end = start_ms + span * done as u64 / total.max(1) as u64;
+        cues.push(Cue { start_ms: at, end_ms: end, speaker, text });
+        at = end;
+    }
+}
+
+pub fn cues(session: &Session) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    for turn in &session.turns {
+        spread(&turn.transcript, turn.start_ms, turn.user_end_ms, "You", &mut cues);
+        spread(&turn.assistant_text, turn.reply_ms, turn.end_ms, "Assistant", &mut cues);
+    }
+    cues
+}
+
+fn stamp(ms: u64, frac_sep: char) -> String {
+    let s = ms / 1000;
+    format!("{:02}:{:02}:{:02}{frac_sep}{:03}", s / 3600, s / 60 % 60, s % 60, ms % 1000)
+}
+
+pub fn srt(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        out.push_str(&format!(
+            "{}\n{} --> {}\n{}: {}\n\n",
+            i + 1,
+            stamp(cue.start_ms, ','),
+            stamp(cue.end_ms, ','),
+            cue.speaker,
+            cue.text
+        ));
+    }
+    out
+}
+
+pub fn vtt(cues: &[Cue]) -> String {
+    // `<v>` voice spans carry the speaker; `&` and `<` must be escaped
+    let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;");
+    let mut out = String::from("WEBVTT\n\n");
+    for cue in cues {
+        out.push_str(&format!(
+            "{} --> {}\n<v {}>{}\n\n",
+            stamp(cue.start_ms, '.'),
+            stamp(cue.end_ms, '.'),
+            cue.speaker,
+            escape(&cue.text)
+        ));
+    }
+    out
+}
@@ -0,0 +1,100 @@
+// Capture sources: the microphone, system audio, or both mixed with
+// per-source gains (e.g. to let the assistant hear you and the meeting).
+
+use std::collections::VecDeque;
+
+use crate::audio::OnInput;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Mic,
+    System,
+    Both,
+}
+
+impl Source {
+    /// From `CAPTURE_SOURCE`.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("CAPTURE_SOURCE").as_deref().unwrap_or("mic") {
+            "mic" | "" => Ok(Source::Mic),
+            "system" => Ok(Source::System),
+            "both" => Ok(Source::Both),
+            other => Err(format!("CAPTURE_SOURCE must be mic, system or both (got {other})")),
+        }
+    }
+}
+
+/// Linear gain from `name`, 0–8 (default 1).
+pub fn gain_from_env(name: &str) -> Result<f32, String> {
+    let Ok(raw) = std::env::var(name) else { return Ok(1.0) };
+    let g: f32 = raw.trim().parse().map_err(|_| format!("{name}={raw} is not a number"))?;
+    if !(0.0..=8.0).contains(&g) {
+        return Err(format!("{name}={g} is out of range (0–8)"));
+    }
+    Ok(g)
+}
+
+fn scale(s: i16, gain: f32) -> i16 {
+    (s as f32 * gain).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+/// Wrap `sink` so every block is scaled by `gain` first.
+pub fn with_gain(mut sink: OnInput, gain: f32) -> OnInput {
+    if gain == 1.0 {
+        return sink;
+    }
+    let mut buf = Vec::new();
+    Box::new(move |pcm: &[i16]| {
+        buf.clear();
+        buf.extend(pcm.iter().map(|&s| scale(s, gain)));
+        sink(&buf);
+    })
+}
+
+/// Sums two streams that arrive in independently sized blocks. A source
+/// that falls silent (loopback delivers nothing while nothing plays) is
+/// treated as zeros once the other runs `max_lag` samples ahead.
+pub struct Mixer {
+    mic: VecDeque<i16>,
+    sys: VecDeque<i16>,
+    mic_gain: f32,
+    sys_gain: f32,
+    max_lag: usize,
+    out: Vec<i16>,
+    sink: OnInput,
+}
+
+impl Mixer {
+    pub fn new(sink: OnInput, mic_gain: f32, sys_gain: f32, max_lag: usize) -> Self {
+        Mixer {
+            mic: VecDeque::new(),
+            sys: VecDeque::new(),
+            mic_gain,
+            sys_gain,
+            max_lag,
+            out: Vec::new(),
+            sink,
+        }
+    }
+
+    pub fn push(&mut self, source: Source, pcm: &[i16]) {
+        match source {
+            Source::System => self.sys.extend(pcm.iter().map(|&s| scale(s, self.sys_gain))),
+            _ => self.mic.extend(pcm.iter().map(|&s| scale(s, self.mic_gain))),
+        }
+        self.out.clear();
+        let both = self.mic.len().min(self.sys.len());
+        for (a, b) in self.mic.drain(..both).zip(self.sys.drain(..both)) {
+            self.out.push(a.saturating_add(b));
+        }
+        for q in [&mut self.mic, &mut self.sys] {
+            if q.len() > self.max_lag {
+                let excess = q.len() - self.max_lag;
+                self.out.extend(q.drain(..excess));
+            }
+        }
+        if !self.out.is_empty() {
+            (self.sink)(&self.out);
+        }
+    }
+}
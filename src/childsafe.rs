@@ -0,0 +1,243 @@
+// Child-safe mode (CHILD_SAFE=true), for a device a family shares with
+// children. It adds, on top of whatever else is configured:
+//   - stricter instructions in front of the session instructions
+//     (CHILD_SAFE_INSTRUCTIONS replaces the built-in text)
+//   - a local blocklist (CHILD_SAFE_BLOCKLIST, one word or phrase per line,
+//     `#` comments, matched as whole words in any case): a user turn using
+//     one is dropped before it reaches the model, and a reply using one is
+//     cut off; the reply's text arrives ahead of its playback, so the word
+//     is normally not heard
+//   - a daily time limit (CHILD_SAFE_DAILY_MINUTES): time with parlar
+//     running is added up per calendar day in CHILD_SAFE_USAGE_FILE
+//     (default `parlar-usage.txt`); parlar warns when 5 minutes are left,
+//     stops when the time is up and does not start again that day
+//   - a settings lock (CHILD_SAFE_PIN): model, voice, language, verbosity
+//     and instructions cannot be changed, nor the .env file reloaded, until
+//     the PIN is entered (P in the terminal UI, `/unlock PIN`, or the
+//     `unlock` JSON command); P, `/lock` or `lock` lock them again
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use crate::control::Controls;
+use crate::earcon::{self, Earcon};
+use crate::{output, shutdown};
+
+const INSTRUCTIONS: &str = "You are talking with a child. Keep everything suitable for a young child: \
+no violence, sexual content, drugs, alcohol, weapons, gambling, self-harm, frightening or hateful content, \
+and no bad language. If the child asks about any of these, kindly decline and suggest asking a parent or \
+another trusted adult. Never ask for or repeat personal details (full name, address, school, phone number, \
+passwords), never suggest meeting anyone, visiting websites or buying things. Use simple, friendly words \
+and short answers.";
+
+// How often usage is added up and written
+const USAGE_TICK: Duration = Duration::from_secs(30);
+// Warning before the daily time is up
+const WARN_LEFT_SECS: u64 = 5 * 60;
+// Delay after a wrong PIN, so guessing takes long
+const WRONG_PIN_DELAY: Duration = Duration::from_secs(2);
+
+struct Config {
+    instructions: String,
+    blocklist: Vec<(Regex, String)>,
+    daily_secs: Option<u64>,
+    usage_file: PathBuf,
+    pin: Option<String>,
+}
+
+static CONFIG: OnceLock<Option<Config>> = OnceLock::new();
+static UNLOCKED: AtomicBool = AtomicBool::new(false);
+// Response id and the reply text so far, for checking across deltas
+static REPLY: Mutex<(String, String, bool)> = Mutex::new((String::new(), String::new(), false));
+
+fn config() -> Option<&'static Config> {
+    CONFIG.get().and_then(Option::as_ref)
+}
+
+/// Read the CHILD_SAFE_* settings; call once at startup.
+pub fn init() -> Result<(), String> {
+    if !crate::env_flag("CHILD_SAFE", false) {
+        let _ = CONFIG.set(None);
+        return Ok(());
+    }
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let mut blocklist = Vec::new();
+    if let Some(path) = var("CHILD_SAFE_BLOCKLIST") {
+        let body = std::fs::read_to_string(&path).map_err(|e| format!("CHILD_SAFE_BLOCKLIST {path}: {e}"))?;
+        for line in body.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let re = Regex::new(&format!(r"(?i)\b{}\b", regex::escape(line))).map_err(|e| format!("{path}: {e}"))?;
+            blocklist.push((re, line.to_string()));
+        }
+    }
+    let daily_secs = match var("CHILD_SAFE_DAILY_MINUTES") {
+        Some(v) => match v.trim().parse::<u64>() {
+            Ok(m) if m > 0 => Some(m * 60),
+            _ => return Err(format!("CHILD_SAFE_DAILY_MINUTES: expected a number of minutes, got {v}")),
+        },
+        None => None,
+    };
+    let pin = var("CHILD_SAFE_PIN").map(|p| p.trim().to_string());
+    if pin.as_ref().is_some_and(|p| p.len() < 4 || !p.chars().all(|c| c.is_ascii_digit())) {
+        return Err("CHILD_SAFE_PIN: use at least 4 digits".into());
+    }
+    let _ = CONFIG.set(Some(Config {
+        instructions: var("CHILD_SAFE_INSTRUCTIONS").unwrap_or_else(|| INSTRUCTIONS.into()),
+        blocklist,
+        daily_secs,
+        usage_file: PathBuf::from(var("CHILD_SAFE_USAGE_FILE").unwrap_or_else(|| "parlar-usage.txt".into())),
+        pin,
+    }));
+    Ok(())
+}
+
+/// Whether turns and replies are checked against a blocklist.
+pub fn has_blocklist() -> bool {
+    config().is_some_and(|c| !c.blocklist.is_empty())
+}
+
+/// What goes before the instructions; empty outside child-safe mode.
+pub fn preamble() -> String {
+    config().map(|c| format!("{}\n\n", c.instructions.trim())).unwrap_or_default()
+}
+
+/// The blocklist entry `text` uses, if any.
+pub fn blocked(text: &str) -> Option<&'static str> {
+    config()?.blocklist.iter().find(|(re, _)| re.is_match(text)).map(|(_, word)| word.as_str())
+}
+
+/// Follow a reply as it streams; returns the blocklist entry the first time
+/// the reply uses one. The caller cuts the reply off.
+pub fn reply_delta(response_id: &str, delta: &str) -> Option<&'static str> {
+    if !has_blocklist() {
+        return None;
+    }
+    let mut reply = REPLY.lock().unwrap();
+    if reply.0 != response_id {
+        *reply = (response_id.to_string(), String::new(), false);
+    }
+    // Reported once; later deltas of a blocked reply are ignored
+    if reply.2 {
+        return None;
+    }
+    reply.1.push_str(delta);
+    let word = blocked(&reply.1)?;
+    reply.2 = true;
+    Some(word)
+}
+
+/// A blocked turn or reply: say so and sound the error tone.
+pub fn refuse(what: &str, word: &str, controls: &Controls) {
+    output::notice("child-safe", &format!("{what} blocked (\"{word}\")."));
+    earcon::play(&controls.cue_buf, Earcon::Error, controls.sr_hz, controls.earcon_gain);
+}
+
+/// Whether settings can be changed now; when not, says what needs the PIN.
+pub fn settings_open(what: &str) -> bool {
+    if locked() {
+        output::notice("child-safe", &format!("settings are locked; {what} needs the PIN."));
+        return false;
+    }
+    true
+}
+
+pub fn has_pin() -> bool {
+    config().is_some_and(|c| c.pin.is_some())
+}
+
+pub fn locked() -> bool {
+    has_pin() && !UNLOCKED.load(Ordering::Relaxed)
+}
+
+/// Unlock the settings with `pin`; blocking for a moment when it is wrong.
+pub fn unlock(pin: &str) -> bool {
+    let Some(expected) = config().and_then(|c| c.pin.as_deref()) else {
+        output::notice("child-safe", "no PIN is set.");
+        return false;
+    };
+    if pin.trim() != expected {
+        std::thread::sleep(WRONG_PIN_DELAY);
+        output::notice("child-safe", "wrong PIN.");
+        return false;
+    }
+    UNLOCKED.store(true, Ordering::Relaxed);
+    output::notice("child-safe", "settings unlocked.");
+    true
+}
+
+pub fn lock() {
+    if has_pin() {
+        UNLOCKED.store(false, Ordering::Relaxed);
+        output::notice("child-safe", "settings locked.");
+    }
+}
+
+/// Seconds used today, from the usage file.
+fn used_today(path: &Path) -> u64 {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| {
+            let (day, secs) = s.trim().split_once(' ')?;
+            (day == today).then(|| secs.parse().ok()).flatten()
+        })
+        .unwrap_or(0)
+}
+
+fn write_usage(path: &Path, secs: u64) {
+    let line = format!("{} {secs}\n", chrono::Local::now().format("%Y-%m-%d"));
+    if let Err(e) = std::fs::write(path, line) {
+        output::notice("child-safe", &format!("cannot write {}: {e}", path.display()));
+    }
+}
+
+/// Why parlar may not start now: today's time is used up.
+pub fn used_up() -> Option<String> {
+    let c = config()?;
+    let limit = c.daily_secs?;
+    (used_today(&c.usage_file) >= limit)
+        .then(|| format!("today's {} minutes are used up; parlar can be used again tomorrow.", limit / 60))
+}
+
+/// Start counting today's time; returns the mode's summary for the startup
+/// banner, or `None` outside child-safe mode.
+pub fn start(controls: &Controls) -> Option<String> {
+    let c = config()?;
+    let mut parts = vec![match c.blocklist.len() {
+        0 => "no blocklist".to_string(),
+        n => format!("{n} blocked words"),
+    }];
+    if let Some(limit) = c.daily_secs {
+        let left = limit.saturating_sub(used_today(&c.usage_file));
+        parts.push(format!("{} of {} minutes left today", left.div_ceil(60), limit / 60));
+        let controls = controls.clone();
+        std::thread::spawn(move || {
+            let mut last = Instant::now();
+            let mut warned = false;
+            loop {
+                std::thread::sleep(USAGE_TICK);
+                // Read back each time, so another parlar on the same file counts too
+                let used = used_today(&c.usage_file) + last.elapsed().as_secs();
+                last = Instant::now();
+                write_usage(&c.usage_file, used);
+                if used >= limit {
+                    output::notice("child-safe", "time is up for today.");
+                    earcon::play(&controls.cue_buf, Earcon::Error, controls.sr_hz, controls.earcon_gain);
+                    std::thread::sleep(Duration::from_millis(earcon::duration_ms(Earcon::Error) + 100));
+                    shutdown::exit(0);
+                }
+                if !warned && limit - used <= WARN_LEFT_SECS {
+                    warned = true;
+                    output::notice("child-safe", &format!("{} minutes left today.", (limit - used).div_ceil(60)));
+                }
+            }
+        });
+    }
+    if c.pin.is_some() {
+        parts.push("settings locked".into());
+    }
+    Some(parts.join(", "))
+}
@@ -0,0 +1,64 @@
+// Minimal command-line parsing: `parlar [command] [--flag] [--key value|--key=value]`.
+//
+// Most knobs stay environment variables; flags cover per-invocation choices.
+
+use std::env;
+
+// Flags that consume the following word as their value
+const VALUE_FLAGS: &[&str] = &["capture-dataset", "append", "output", "format", "proxy", "instructions", "raw-events", "connect-events"];
+
+pub struct Cli {
+    args: Vec<String>,
+}
+
+impl Cli {
+    pub fn parse() -> Self {
+        Cli { args: env::args().skip(1).collect() }
+    }
+
+    /// Bare words, in order (flag values excluded).
+    fn words(&self) -> Vec<&str> {
+        let mut words = Vec::new();
+        let mut it = self.args.iter();
+        while let Some(a) = it.next() {
+            match a.strip_prefix("--") {
+                Some(name) if VALUE_FLAGS.contains(&name) => {
+                    it.next();
+                }
+                Some(_) => {}
+                None => words.push(a.as_str()),
+            }
+        }
+        words
+    }
+
+    /// First bare word, e.g. `doctor` in `parlar doctor`.
+    pub fn subcommand(&self) -> Option<&str> {
+        self.words().first().copied()
+    }
+
+    /// Bare words after the subcommand, e.g. the files in `parlar decrypt a b`.
+    pub fn operands(&self) -> Vec<&str> {
+        self.words().into_iter().skip(1).collect()
+    }
+
+    /// `--name` present.
+    pub fn flag(&self, name: &str) -> bool {
+        self.args.iter().any(|a| a.strip_prefix("--") == Some(name))
+    }
+
+    /// `--name value` or `--name=value`.
+    pub fn value(&self, name: &str) -> Option<String> {
+        let mut it = self.args.iter();
+        while let Some(a) = it.next() {
+            let Some(rest) = a.strip_prefix("--") else { continue };
+            if rest == name && VALUE_FLAGS.contains(&name) {
+                return it.next().cloned();
+            }
+            if let Some(v) = rest.strip_prefix(name).and_then(|r| r.strip_prefix('=')) {
+                return Some(v.to_string());
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,175 @@
+// Settings file and stored API key.
+//
+// `parlar.toml` (or `$PARLAR_CONFIG`) holds the same settings as the
+// environment, keyed by variable name:
+//   REALTIME_VOICE = "coral"
+//   INPUT_DEVICE = "USB Microphone"
+//   TURN_SIL_MS = 400
+// The environment and `.env` take precedence over the file.
+//
+// The API key is never written to the file; `parlar init` stores it in the
+// OS keyring, which is used when OPENAI_API_KEY is not set.
+//
+// Reloading the .env file while running (SIGHUP, a changed file) does not
+// touch the process environment, which other threads read. The settings are
+// layered again from scratch instead, in the order startup uses: the
+// environment parlar was started with, then .env, then parlar.toml, then the
+// PARLAR_PROFILE defaults. What a reload retunes (turn detection, the
+// instructions and their variables) reads them through `var`, so a setting
+// removed from .env falls back to what is under it.
+
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+const KEYRING_SERVICE: &str = "parlar";
+const KEYRING_USER: &str = "OPENAI_API_KEY";
+
+// A settings file's (name, value) pairs
+type Settings = Vec<(String, String)>;
+// A profile's defaults
+type Defaults = &'static [(&'static str, &'static str)];
+
+// The environment parlar was started with, before .env and parlar.toml
+static STARTED: OnceLock<HashMap<String, String>> = OnceLock::new();
+// Every setting as layered by the last reload
+static RELOADED: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+pub fn path() -> PathBuf {
+    env::var("PARLAR_CONFIG").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from("parlar.toml"))
+}
+
+/// Remember the environment parlar was started with; the first thing
+/// `main` does, before .env is loaded.
+pub fn snapshot_env() {
+    let _ = STARTED.set(env::vars().collect());
+}
+
+/// The settings in parlar.toml (or `$PARLAR_CONFIG`), if there is one.
+fn read_file() -> Result<Option<(PathBuf, Settings)>, String> {
+    let path = path();
+    let body = match std::fs::read_to_string(&path) {
+        Ok(b) => b,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("{}: {e}", path.display())),
+    };
+    let table: toml::Table = body.parse().map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut settings = Vec::new();
+    for (key, value) in table {
+        let value = match value {
+            toml::Value::String(s) => s,
+            toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => value.to_string(),
+            _ => return Err(format!("{}: {key} must be a string, number or boolean", path.display())),
+        };
+        settings.push((key, value));
+    }
+    Ok(Some((path, settings)))
+}
+
+/// Export the file's settings as environment variables that are not already set.
+/// Returns the path if a file was loaded. Only for `main`, before the runtime starts.
+pub fn load_file() -> Result<Option<PathBuf>, String> {
+    let Some((path, settings)) = read_file()? else { return Ok(None) };
+    for (key, value) in settings {
+        if env::var_os(&key).is_none() {
+            // SAFETY: `main` calls this before the runtime starts, while it is the only thread
+            unsafe { env::set_var(&key, value) };
+        }
+    }
+    Ok(Some(path))
+}
+
+/// Setting `name`: as layered by the last reload, else from the environment.
+pub fn var(name: &str) -> Option<String> {
+    match RELOADED.lock().unwrap().as_ref() {
+        Some(all) => all.get(name).cloned(),
+        None => env::var(name).ok(),
+    }
+}
+
+/// Every setting, as `var` sees them.
+pub fn vars() -> Vec<(String, String)> {
+    match RELOADED.lock().unwrap().as_ref() {
+        Some(all) => all.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        None => env::vars().collect(),
+    }
+}
+
+/// Layer the settings again, with the .env file at `path` re-read, for
+/// `var` and `vars`.
+pub fn reload(path: &Path) -> Result<(), String> {
+    // Each layer only fills in what the ones above it left unset
+    let mut all = STARTED.get().cloned().unwrap_or_default();
+    for entry in dotenvy::from_path_iter(path).map_err(|e| e.to_string())? {
+        let (key, value) = entry.map_err(|e| e.to_string())?;
+        all.entry(key).or_insert(value);
+    }
+    for (key, value) in read_file()?.map(|(_, s)| s).unwrap_or_default() {
+        all.entry(key).or_insert(value);
+    }
+    if let Some((_, defaults)) = profile(all.get("PARLAR_PROFILE").map(String::as_str))? {
+        for (key, value) in defaults {
+            all.entry(key.to_string()).or_insert(value.to_string());
+        }
+    }
+    *RELOADED.lock().unwrap() = Some(all);
+    Ok(())
+}
+
+// PARLAR_PROFILE=embedded: defaults for a Raspberry Pi or similar board,
+// fewer, larger audio chunks and device periods (fewer wakeups and WebSocket
+// messages) and no terminal animations
+const EMBEDDED: Defaults = &[
+    ("CHUNK_MS", "40"),
+    ("AUDIO_BUFFER_FRAMES", "1024"),
+    ("THINKING_INDICATOR", "false"),
+    ("TIMELINE", "false"),
+];
+
+/// The profile PARLAR_PROFILE names and its defaults.
+fn profile(name: Option<&str>) -> Result<Option<(&'static str, Defaults)>, String> {
+    match name.map(str::trim) {
+        None | Some("" | "default") => Ok(None),
+        Some("embedded") => Ok(Some(("embedded", EMBEDDED))),
+        Some(other) => Err(format!("PARLAR_PROFILE: unknown profile {other} (use default or embedded)")),
+    }
+}
+
+/// Export the defaults of PARLAR_PROFILE for variables that are not already
+/// set; call after `load_file`, before the runtime starts. Returns the profile name.
+pub fn apply_profile() -> Result<Option<&'static str>, String> {
+    let Some((name, settings)) = profile(env::var("PARLAR_PROFILE").ok().as_deref())? else { return Ok(None) };
+    for (key, value) in settings {
+        if env::var_os(key).is_none() {
+            // SAFETY: `main` calls this before the runtime starts, while it is the only thread
+            unsafe { env::set_var(key, value) };
+        }
+    }
+    Ok(Some(name))
+}
+
+// Keyring backends may drive their own async runtime, which must not be
+// started from inside ours; keep the calls on a plain thread.
+fn keyring<T: Send + 'static>(f: impl FnOnce(keyring::Entry) -> T + Send + 'static) -> Option<T> {
+    std::thread::spawn(move || keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).ok().map(f))
+        .join()
+        .ok()
+        .flatten()
+}
+
+/// OPENAI_API_KEY from the environment, else from the OS keyring.
+pub fn api_key() -> Option<String> {
+    if let Ok(k) = env::var("OPENAI_API_KEY")
+        && !k.trim().is_empty()
+    {
+        return Some(k);
+    }
+    keyring(|e| e.get_password().ok()).flatten()
+}
+
+pub fn store_api_key(key: &str) -> Result<(), String> {
+    let key = key.to_string();
+    keyring(move |e| e.set_password(&key).map_err(|e| e.to_string()))
+        .unwrap_or_else(|| Err("no keyring available".into()))
+}
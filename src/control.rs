@@ -0,0 +1,338 @@
+// Client-side actions shared by every control surface (keyboard, stdin).
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde_json::json;
+use tokio::sync::mpsc::UnboundedSender;
+use tungstenite::Message;
+
+use crate::earcon::{self, CueBuf, Earcon};
+use crate::language::Lang;
+use crate::turn::TurnConfig;
+use crate::verbosity::Verbosity;
+use crate::{State, childsafe, config, i18n, interrupt, output, passthrough, resume, template, turn_taking};
+
+#[derive(Clone)]
+pub struct Controls {
+    pub out_tx: UnboundedSender<Message>,
+    pub state: Arc<Mutex<State>>,
+    pub spk_buf: Arc<Mutex<VecDeque<i16>>>,
+    pub cue_buf: CueBuf,
+    pub sr_hz: u32,
+    pub earcon_gain: f32,
+    pub status_earcons: bool,
+    /// Asks the event loop to reconnect on the given model.
+    pub switch_tx: UnboundedSender<String>,
+    /// REALTIME_MODELS, in switching order.
+    pub models: Arc<Vec<String>>,
+}
+
+impl Controls {
+    fn send(&self, v: serde_json::Value) {
+        let _ = self.out_tx.send(Message::Text(v.to_string()));
+    }
+
+    /// Cancel the assistant: server cancel + truncate, and flush local playback.
+    pub fn interrupt(&self) {
+        interrupt::cancel(&self.state, interrupt::Path::User, &self.out_tx, &self.spk_buf);
+    }
+
+    /// Play the rest of the last interrupted reply, locally only.
+    pub fn resume(&self) {
+        let busy = {
+            let st = self.state.lock().unwrap();
+            st.responses.busy()
+        };
+        if busy {
+            output::notice("resume", i18n::tr("a new reply is under way."));
+            return;
+        }
+        match resume::take() {
+            Ok(samples) => {
+                self.spk_buf.lock().unwrap().extend(samples);
+                output::notice("resume", i18n::tr("playing the rest of the interrupted reply."));
+            }
+            Err(why) => output::notice("resume", i18n::tr(why)),
+        }
+    }
+
+    /// Set (or toggle, with `None`) the mic mute; returns the new state.
+    pub fn set_muted(&self, muted: Option<bool>) -> bool {
+        let muted = {
+            let mut st = self.state.lock().unwrap();
+            st.muted = muted.unwrap_or(!st.muted);
+            st.muted
+        };
+        output::notice("mute", if muted { i18n::tr("microphone muted.") } else { i18n::tr("microphone live.") });
+        if self.status_earcons {
+            let cue = if muted { Earcon::Muted } else { Earcon::Unmuted };
+            earcon::play(&self.cue_buf, cue, self.sr_hz, self.earcon_gain);
+        }
+        muted
+    }
+
+    /// Add a typed user message and ask for a reply. A reply under way is
+    /// cut off and the message queued behind it, so it is answered once that
+    /// reply is done, even when the interrupt is held back by its cooldown.
+    pub fn say_text(&self, text: &str) {
+        self.send(json!({
+            "type": "conversation.item.create",
+            "item": {
+                "type": "message",
+                "role": "user",
+                "content": [{"type": "input_text", "text": text}]
+            }
+        }));
+        let create = {
+            let mut st = self.state.lock().unwrap();
+            turn_taking::typed(&mut st.responses).map(|event_id| {
+                st.thinking_since = Some(Instant::now());
+                st.create_event(&event_id)
+            })
+        };
+        match create {
+            Some(create) => self.send(create),
+            None => self.interrupt(),
+        }
+    }
+
+    /// Send a client event as given (see `passthrough`).
+    pub fn send_event(&self, event: serde_json::Value) -> Result<(), String> {
+        passthrough::check(&event)?;
+        if childsafe::settings_open("sending raw events") {
+            self.send(event);
+        }
+        Ok(())
+    }
+
+    /// Replace the base instructions (the language hint is kept); a
+    /// template whose variables cannot be filled in is refused.
+    pub fn set_instructions(&self, instructions: &str) {
+        if let Err(e) = template::check(instructions) {
+            output::notice("instructions", &i18n::trf("not changed: {error}", &[("error", &e)]));
+            return;
+        }
+        let instructions = {
+            let mut st = self.state.lock().unwrap();
+            st.instructions = instructions.to_string();
+            st.session_instructions()
+        };
+        self.send(json!({
+            "type": "session.update",
+            "session": {"instructions": instructions}
+        }));
+    }
+
+    /// Set the verbosity preset, or with `None` move to the next one.
+    pub fn set_verbosity(&self, verbosity: Option<Verbosity>) {
+        if !childsafe::settings_open("the verbosity") {
+            return;
+        }
+        let (verbosity, instructions) = {
+            let mut st = self.state.lock().unwrap();
+            st.verbosity = verbosity.unwrap_or(st.verbosity.next());
+            (st.verbosity, st.session_instructions())
+        };
+        self.send(json!({
+            "type": "session.update",
+            "session": {
+                "instructions": instructions,
+                "max_response_output_tokens": verbosity.max_tokens()
+            }
+        }));
+        output::notice("verbosity", &format!("{}.", verbosity.name()));
+    }
+
+    /// Switch transcription language, reply language and barge-in keywords.
+    pub fn set_language(&self, lang: &'static Lang) {
+        let (instructions, transcription) = {
+            let mut st = self.state.lock().unwrap();
+            st.language = Some(lang);
+            st.lang_offer = None;
+            st.lang_streak = None;
+            (st.session_instructions(), st.transcription_config())
+        };
+        self.send(json!({
+            "type": "session.update",
+            "session": {
+                "instructions": instructions,
+                "input_audio_transcription": transcription
+            }
+        }));
+        output::notice("language", &i18n::trf("switched to {language}.", &[("language", &lang.name)]));
+    }
+
+    /// Suggest a language switch; the user accepts with `accept_language_offer`.
+    pub fn offer_language(&self, lang: &'static Lang) {
+        {
+            let mut st = self.state.lock().unwrap();
+            if st.lang_offer.is_some_and(|l| l.code == lang.code) {
+                return;
+            }
+            st.lang_offer = Some(lang);
+        }
+        let how = if output::json_mode() {
+            i18n::tr("send set_language").to_string()
+        } else if cfg!(feature = "tui")
+            && let Some(key) = crate::keys::label(crate::keys::Action::Language)
+        {
+            i18n::trf("press {key}", &[("key", &key)])
+        } else {
+            i18n::tr("type /language").to_string()
+        };
+        output::notice(
+            "language",
+            &i18n::trf(
+                "you seem to be speaking {language}; {how} to switch ({code}).",
+                &[("language", &lang.name), ("how", &how), ("code", &lang.code)],
+            ),
+        );
+    }
+
+    pub fn accept_language_offer(&self) {
+        if !childsafe::settings_open("the language") {
+            return;
+        }
+        let offer = self.state.lock().unwrap().lang_offer.take();
+        if let Some(lang) = offer {
+            self.set_language(lang);
+        }
+    }
+
+    /// Change the assistant voice. The server fixes it once a reply has been
+    /// spoken, so the session moves to a new connection.
+    pub fn set_voice(&self, voice: &str) {
+        if !childsafe::settings_open("the voice") {
+            return;
+        }
+        let model = {
+            let mut st = self.state.lock().unwrap();
+            if st.voice == voice {
+                drop(st);
+                output::notice("voice", &i18n::trf("already {voice}.", &[("voice", &voice)]));
+                return;
+            }
+            st.voice = voice.to_string();
+            st.model.clone()
+        };
+        output::notice("voice", &i18n::trf("switching to {voice}…", &[("voice", &voice)]));
+        let _ = self.switch_tx.send(model);
+    }
+
+    /// Move the conversation to `model`, or with `None` to the next of the
+    /// configured models.
+    pub fn switch_model(&self, model: Option<&str>) {
+        if !childsafe::settings_open("the model") {
+            return;
+        }
+        let current = self.state.lock().unwrap().model.clone();
+        let next = match model {
+            Some(m) => m.to_string(),
+            None if self.models.len() < 2 => {
+                output::notice("model", i18n::tr("only one model configured; list several in REALTIME_MODELS."));
+                return;
+            }
+            None => {
+                let at = self.models.iter().position(|m| *m == current).map_or(0, |i| i + 1);
+                self.models[at % self.models.len()].clone()
+            }
+        };
+        if next == current {
+            output::notice("model", &i18n::trf("already on {model}.", &[("model", &current)]));
+            return;
+        }
+        output::notice("model", &i18n::trf("switching to {model}…", &[("model", &next)]));
+        let _ = self.switch_tx.send(next);
+    }
+
+    /// Move the conversation to a new connection on the same model.
+    pub fn reconnect(&self) {
+        let model = self.state.lock().unwrap().model.clone();
+        output::notice("session", i18n::tr("reconnecting…"));
+        let _ = self.switch_tx.send(model);
+    }
+
+    /// Make a finished statement wait `ms` before the reply; the question
+    /// and unfinished-turn delays move by as much, so they keep their
+    /// difference to it.
+    pub fn set_delay(&self, ms: u64) {
+        if !childsafe::settings_open("the response delay") {
+            return;
+        }
+        let (question, long) = {
+            let mut st = self.state.lock().unwrap();
+            let turn = &mut st.turn;
+            let shift = |d: u64| (d as i64 + ms as i64 - turn.resp_delay_short_ms as i64).max(0) as u64;
+            (turn.resp_delay_question_ms, turn.resp_delay_long_ms) =
+                (shift(turn.resp_delay_question_ms), shift(turn.resp_delay_long_ms));
+            turn.resp_delay_short_ms = ms;
+            (turn.resp_delay_question_ms, turn.resp_delay_long_ms)
+        };
+        output::notice(
+            "delay",
+            &i18n::trf(
+                "replies wait {question} ms after a question, {ms} ms after a statement, {long} ms otherwise.",
+                &[("question", &question), ("ms", &ms), ("long", &long)],
+            ),
+        );
+    }
+
+    /// Start over on a new connection with the conversation forgotten, on
+    /// `model` or the current one; returns the model used.
+    pub fn restart(&self, model: Option<&str>) -> String {
+        let model = {
+            let mut st = self.state.lock().unwrap();
+            st.history.clear();
+            model.map_or_else(|| st.model.clone(), str::to_string)
+        };
+        output::notice("session", i18n::tr("starting a new conversation…"));
+        let _ = self.switch_tx.send(model.clone());
+        model
+    }
+
+    /// Re-read the env file and apply settings that can change mid-session.
+    pub fn reload_config(&self, env_file: &Path) {
+        if !childsafe::settings_open("reloading the .env file") {
+            return;
+        }
+        if let Err(e) = config::reload(env_file) {
+            output::notice("config", &i18n::trf("reload failed: {error}", &[("error", &e)]));
+            return;
+        }
+        self.state.lock().unwrap().cooldowns = interrupt::Cooldowns::from_env();
+        match TurnConfig::from_env() {
+            Ok(turn) => {
+                let changed = {
+                    let mut st = self.state.lock().unwrap();
+                    let changed = st.turn != turn;
+                    st.turn = turn.clone();
+                    changed
+                };
+                if changed {
+                    self.send(json!({
+                        "type": "session.update",
+                        "session": {"turn_detection": turn.to_json()}
+                    }));
+                    output::notice("config", i18n::tr("turn detection updated."));
+                }
+            }
+            // Keep running with the previous, valid settings
+            Err(e) => output::notice("config", &i18n::trf("reload rejected: {error}", &[("error", &e)])),
+        }
+        // Sent again even when unchanged, for the template variables' new values
+        match template::reload() {
+            Ok(Some(instructions)) => {
+                self.set_instructions(&instructions);
+                output::notice("config", i18n::tr("instructions updated."));
+            }
+            Ok(None) => {
+                let instructions = self.state.lock().unwrap().instructions.clone();
+                self.set_instructions(&instructions);
+            }
+            Err(e) => output::notice("config", &i18n::trf("instructions not reloaded: {error}", &[("error", &e)])),
+        }
+    }
+}
@@ -0,0 +1,388 @@
+// At-rest encryption of transcripts and recordings. With
+// TRANSCRIPT_PASSPHRASE or TRANSCRIPT_KEYFILE set, every file parlar writes
+// with conversation content (meeting notes, transcripts, notes, dataset,
+// event log, A/B ratings, memory) is sealed with XChaCha20-Poly1305;
+// `parlar decrypt` reads it back.
+//
+// Format: MAGIC, a 16-byte Argon2id salt, a random 16-byte file id, then
+// records that can be appended independently: u32 LE ciphertext length,
+// 24-byte nonce, ciphertext + tag. Each record is sealed with the salt, the
+// file id and its position in the file as associated data, so records
+// removed, reordered or copied in from another file fail to decrypt. Whole
+// records cut off the end of an append-only file look like a crash before
+// they were written and cannot be told apart; a record cut short is dropped
+// with a warning, and the ones before it are read.
+//
+// Either way, appends go out in one write each and writers sync at the end
+// of a turn, and whole files are written beside the target and renamed over
+// it, so a crash, a SIGKILL or a power cut leaves the turns before it intact.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+use crate::cli::Cli;
+
+const MAGIC: &[u8; 8] = b"PARLAR\x03\x00";
+// Files whose records are bound to their salt and position only; read only
+const MAGIC_V2: &[u8; 8] = b"PARLAR\x02\x00";
+// Files whose records are not bound to their file and position; read only
+const MAGIC_V1: &[u8; 8] = b"PARLAR\x01\x00";
+const SALT_LEN: usize = 16;
+const ID_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+/// Suffix added to sealed files written whole (dataset audio and manifests).
+pub const SUFFIX: &str = ".enc";
+
+struct Secret {
+    material: Vec<u8>,
+    /// Salt for new files, so their key is derived once per run.
+    salt: [u8; SALT_LEN],
+    keys: Mutex<HashMap<[u8; SALT_LEN], Key>>,
+}
+
+static SECRET: OnceLock<Option<Secret>> = OnceLock::new();
+
+impl Secret {
+    fn new(material: Vec<u8>) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Secret { material, salt, keys: Mutex::new(HashMap::new()) }
+    }
+
+    fn key(&self, salt: &[u8; SALT_LEN]) -> Result<Key, String> {
+        let mut keys = self.keys.lock().unwrap();
+        if let Some(k) = keys.get(salt) {
+            return Ok(*k);
+        }
+        let mut key = Key::default();
+        argon2::Argon2::default()
+            .hash_password_into(&self.material, salt, &mut key)
+            .map_err(|e| e.to_string())?;
+        keys.insert(*salt, key);
+        Ok(key)
+    }
+}
+
+/// Passphrase or key file contents from the environment, if configured.
+fn material_from_env() -> Result<Option<Vec<u8>>, String> {
+    if let Ok(path) = std::env::var("TRANSCRIPT_KEYFILE")
+        && !path.is_empty()
+    {
+        let bytes = std::fs::read(&path).map_err(|e| format!("TRANSCRIPT_KEYFILE {path}: {e}"))?;
+        if bytes.len() < 16 {
+            return Err(format!("TRANSCRIPT_KEYFILE {path}: key file must hold at least 16 bytes"));
+        }
+        return Ok(Some(bytes));
+    }
+    match std::env::var("TRANSCRIPT_PASSPHRASE") {
+        Ok(p) if !p.is_empty() => Ok(Some(p.into_bytes())),
+        _ => Ok(None),
+    }
+}
+
+/// Read the encryption settings; call once at startup.
+pub fn init() -> Result<(), String> {
+    let secret = material_from_env()?.map(Secret::new);
+    let _ = SECRET.set(secret);
+    Ok(())
+}
+
+pub fn enabled() -> bool {
+    matches!(SECRET.get(), Some(Some(_)))
+}
+
+/// A new file's header: MAGIC, the salt of this run and a fresh file id.
+fn header(salt: &[u8; SALT_LEN]) -> Vec<u8> {
+    let mut id = [0u8; ID_LEN];
+    OsRng.fill_bytes(&mut id);
+    [&MAGIC[..], salt, &id].concat()
+}
+
+// Associated data of record `index` of the file whose header, after MAGIC,
+// is `binding` (salt and file id)
+fn aad(binding: &[u8], index: u64) -> Vec<u8> {
+    [binding, &index.to_le_bytes()].concat()
+}
+
+fn seal(key: &Key, binding: &[u8], index: u64, plain: &[u8]) -> Result<Vec<u8>, String> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let payload = Payload { msg: plain, aad: &aad(binding, index) };
+    let ct = XChaCha20Poly1305::new(key).encrypt(&nonce, payload).map_err(|e| e.to_string())?;
+    let mut rec = Vec::with_capacity(4 + NONCE_LEN + ct.len());
+    rec.extend_from_slice(&(ct.len() as u32).to_le_bytes());
+    rec.extend_from_slice(&nonce);
+    rec.extend_from_slice(&ct);
+    Ok(rec)
+}
+
+// A record's nonce and ciphertext
+type Record<'a> = (&'a [u8], &'a [u8]);
+
+/// The records of a sealed file's body and the length they take up; a
+/// crash can leave a partial record after them.
+fn records(body: &[u8]) -> (Vec<Record<'_>>, usize) {
+    let mut out = Vec::new();
+    let mut rest = body;
+    while rest.len() >= 4 + NONCE_LEN {
+        let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+        let ct = &rest[4 + NONCE_LEN..];
+        if ct.len() < len {
+            break;
+        }
+        out.push((&rest[4..4 + NONCE_LEN], &ct[..len]));
+        rest = &ct[len..];
+    }
+    (out, body.len() - rest.len())
+}
+
+// Sealing state of an encrypted append-only file
+struct Sealing {
+    key: Key,
+    /// Salt and file id.
+    binding: Vec<u8>,
+    /// Position of the next record.
+    next: u64,
+}
+
+/// An append-only file; each `append` is sealed as its own record when
+/// encryption is on, so a crash loses at most the record being written.
+/// `sync` makes what was appended durable.
+pub struct Appender {
+    file: File,
+    sealing: Option<Sealing>,
+}
+
+impl Appender {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let err = |e: io::Error| format!("{path}: {e}");
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(path).map_err(err)?;
+        let len = file.metadata().map_err(err)?.len();
+        let Some(Some(secret)) = SECRET.get() else {
+            // Plain text appended to a sealed file would be lost to `parlar decrypt`
+            let mut magic = [0u8; MAGIC.len()];
+            let sealed = len >= MAGIC.len() as u64 && file.read_exact(&mut magic).is_ok();
+            if sealed && (&magic == MAGIC || &magic == MAGIC_V2 || &magic == MAGIC_V1) {
+                return Err(format!(
+                    "{path}: is encrypted; set TRANSCRIPT_PASSPHRASE or TRANSCRIPT_KEYFILE, or choose another file"
+                ));
+            }
+            return Ok(Appender { file, sealing: None });
+        };
+        if len == 0 {
+            let head = header(&secret.salt);
+            file.write_all(&head).map_err(err)?;
+            let key = secret.key(&secret.salt).map_err(|e| format!("{path}: {e}"))?;
+            let binding = head[MAGIC.len()..].to_vec();
+            return Ok(Appender { file, sealing: Some(Sealing { key, binding, next: 0 }) });
+        }
+        // Keep appending under the salt and id the file was started with, after its records
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).map_err(err)?;
+        let header = MAGIC.len() + SALT_LEN + ID_LEN;
+        let magic = data.get(..MAGIC.len());
+        if magic != Some(MAGIC) {
+            if magic == Some(MAGIC_V2) || magic == Some(MAGIC_V1) {
+                return Err(format!("{path}: was encrypted by an older parlar; choose another file"));
+            }
+            return Err(format!("{path}: exists and is not encrypted; choose another file"));
+        }
+        if data.len() < header {
+            return Err(format!("{path}: encrypted header cut short; choose another file"));
+        }
+        let salt: [u8; SALT_LEN] = data[MAGIC.len()..MAGIC.len() + SALT_LEN].try_into().unwrap();
+        let binding = data[MAGIC.len()..header].to_vec();
+        let (records, complete) = records(&data[header..]);
+        // A record cut short by a crash would end the file for `parlar decrypt`
+        if header + complete < data.len() {
+            file.set_len((header + complete) as u64).map_err(err)?;
+        }
+        let key = secret.key(&salt).map_err(|e| format!("{path}: {e}"))?;
+        Ok(Appender { file, sealing: Some(Sealing { key, binding, next: records.len() as u64 }) })
+    }
+
+    pub fn append(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match &mut self.sealing {
+            Some(s) => {
+                let rec = seal(&s.key, &s.binding, s.next, bytes).map_err(io::Error::other)?;
+                self.file.write_all(&rec)?;
+                s.next += 1;
+            }
+            None => self.file.write_all(bytes)?,
+        }
+        self.file.flush()
+    }
+
+    /// Flush what was appended to the disk; writers call it once per turn.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+}
+
+/// Write `bytes` to a temporary file beside `path`, sync it and rename it
+/// over `path`, so `path` is never left half-written.
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Write a whole file, atomically, sealed (with `SUFFIX` appended to the
+/// name) when encryption is on. Returns the path written.
+pub fn write_file(path: &Path, bytes: &[u8]) -> io::Result<PathBuf> {
+    let Some(Some(secret)) = SECRET.get() else {
+        write_atomic(path, bytes)?;
+        return Ok(path.to_path_buf());
+    };
+    let key = secret.key(&secret.salt).map_err(io::Error::other)?;
+    let mut out = header(&secret.salt);
+    let rec = seal(&key, &out[MAGIC.len()..], 0, bytes).map_err(io::Error::other)?;
+    out.extend_from_slice(&rec);
+    let mut sealed = path.as_os_str().to_owned();
+    sealed.push(SUFFIX);
+    let sealed = PathBuf::from(sealed);
+    write_atomic(&sealed, &out)?;
+    Ok(sealed)
+}
+
+/// Read a file written by `write_file`: the plain file, or else its sealed
+/// copy, decrypted with the configured passphrase or key file.
+pub fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    if path.exists() {
+        return std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()));
+    }
+    let mut sealed = path.as_os_str().to_owned();
+    sealed.push(SUFFIX);
+    let data = std::fs::read(&sealed).map_err(|e| format!("{}: {e}", path.display()))?;
+    let Some(Some(secret)) = SECRET.get() else {
+        return Err(format!(
+            "{} is encrypted; set TRANSCRIPT_PASSPHRASE or TRANSCRIPT_KEYFILE",
+            PathBuf::from(sealed).display()
+        ));
+    };
+    let sealed = PathBuf::from(sealed);
+    let opened = open_sealed(&data, &secret.material).map_err(|e| format!("{}: {e}", sealed.display()))?;
+    if opened.truncated {
+        eprintln!("[crypt] {}: {TRUNCATED}", sealed.display());
+    }
+    Ok(opened.plain)
+}
+
+/// Read a file written by `Appender`: as it is when it is plain, decrypted
+/// with the configured passphrase or key file when it is sealed.
+pub fn read_appended(path: &Path) -> io::Result<Vec<u8>> {
+    let data = std::fs::read(path)?;
+    let magic = data.get(..MAGIC.len());
+    if magic != Some(MAGIC) && magic != Some(MAGIC_V2) && magic != Some(MAGIC_V1) {
+        return Ok(data);
+    }
+    let Some(Some(secret)) = SECRET.get() else {
+        return Err(io::Error::other("is encrypted; set TRANSCRIPT_PASSPHRASE or TRANSCRIPT_KEYFILE"));
+    };
+    let opened = open_sealed(&data, &secret.material).map_err(io::Error::other)?;
+    if opened.truncated {
+        eprintln!("[crypt] {}: {TRUNCATED}", path.display());
+    }
+    Ok(opened.plain)
+}
+
+const TRUNCATED: &str = "last record cut short (a crash while writing?); read the records before it";
+
+/// What `open_sealed` could read.
+struct Opened {
+    plain: Vec<u8>,
+    /// A record cut short at the end was left out.
+    truncated: bool,
+}
+
+/// Decrypt every complete record of a sealed file, checking each is in its place.
+fn open_sealed(data: &[u8], material: &[u8]) -> Result<Opened, String> {
+    // v3 binds records to salt and file id, v2 to the salt, v1 to nothing
+    let (binding_len, bound) = match data.get(..MAGIC.len()) {
+        Some(m) if m == MAGIC => (SALT_LEN + ID_LEN, true),
+        Some(m) if m == MAGIC_V2 => (SALT_LEN, true),
+        Some(m) if m == MAGIC_V1 => (SALT_LEN, false),
+        _ => return Err("not a parlar encrypted file".into()),
+    };
+    let header = MAGIC.len() + binding_len;
+    if data.len() < header {
+        return Err("not a parlar encrypted file".into());
+    }
+    let binding = &data[MAGIC.len()..header];
+    let salt: [u8; SALT_LEN] = binding[..SALT_LEN].try_into().unwrap();
+    let mut key = Key::default();
+    argon2::Argon2::default()
+        .hash_password_into(material, &salt, &mut key)
+        .map_err(|e| e.to_string())?;
+    let cipher = XChaCha20Poly1305::new(&key);
+    let (records, complete) = records(&data[header..]);
+    let mut plain = Vec::new();
+    for (index, (nonce, ct)) in records.into_iter().enumerate() {
+        let aad = aad(binding, index as u64);
+        let payload = Payload { msg: ct, aad: if bound { &aad[..] } else { &[] } };
+        let pt = cipher.decrypt(XNonce::from_slice(nonce), payload).map_err(|_| match index {
+            0 => "wrong passphrase or key file, or the file was modified".to_string(),
+            _ => format!("record {index} was modified, moved or copied in from another file"),
+        })?;
+        plain.extend_from_slice(&pt);
+    }
+    Ok(Opened { plain, truncated: header + complete < data.len() })
+}
+
+/// `parlar decrypt FILE… [--output PATH]`: write the plaintext to stdout or PATH.
+pub fn run_decrypt(cli: &Cli) -> i32 {
+    let files = cli.operands();
+    if files.is_empty() {
+        eprintln!("usage: parlar decrypt FILE… [--output PATH]");
+        return 2;
+    }
+    let material = match material_from_env() {
+        Ok(Some(m)) => m,
+        Ok(None) => crate::init::ask_secret("Passphrase:").into_bytes(),
+        Err(e) => {
+            eprintln!("[config] {e}");
+            return 2;
+        }
+    };
+    let mut out: Box<dyn Write> = match cli.value("output") {
+        Some(path) => match File::create(&path) {
+            Ok(f) => Box::new(f),
+            Err(e) => {
+                eprintln!("{path}: {e}");
+                return 1;
+            }
+        },
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut code = 0;
+    for path in files {
+        let plain = std::fs::read(path).map_err(|e| e.to_string()).and_then(|d| open_sealed(&d, &material));
+        match plain {
+            Ok(p) => {
+                if p.truncated {
+                    eprintln!("{path}: {TRUNCATED}");
+                }
+                if let Err(e) = out.write_all(&p.plain) {
+                    eprintln!("{e}");
+                    return 1;
+                }
+            }
+            Err(e) => {
+                eprintln!("{path}: {e}");
+                code = 1;
+            }
+        }
+    }
+    let _ = out.flush();
+    code
+}
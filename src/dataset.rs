@@ -0,0 +1,282 @@
+// Per-turn dataset capture (`--capture-dataset DIR`).
+//
+// Every user turn becomes `turn_NNNN_user.wav`, `turn_NNNN_assistant.wav`
+// and a `turn_NNNN.json` manifest inside a per-session directory. User
+// audio is cut from a rolling copy of exactly what was forwarded to the
+// server, using the server's `audio_start_ms`/`audio_end_ms`, so clips line
+// up with what the model actually heard. A turn is written as soon as the
+// user stops speaking and again when the reply is done, each file replaced
+// whole, so a crash keeps every turn up to the one under way. A reconnect
+// starts a new server session, so the pending turns are written as they are
+// and slicing starts over from the new session's first audio.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+
+use crate::{crypt, redact, wav};
+
+// Forwarded mic audio kept for slicing turns
+const HISTORY_SECS: u64 = 120;
+
+#[derive(Default)]
+struct Turn {
+    index: usize,
+    user_item_id: String,
+    user_audio: Vec<i16>,
+    transcript: Option<String>,
+    // why the server could not transcribe the turn
+    transcript_error: Option<String>,
+    response_id: Option<String>,
+    assistant_text: String,
+    // the spoken reply's transcript, kept apart from the text when both come
+    assistant_transcript: String,
+    assistant_audio: Vec<i16>,
+    status: Option<String>,
+    unix_time: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    // forwarded mic samples; `history_start` is the absolute index of history[0]
+    history: VecDeque<i16>,
+    history_start: u64,
+    speech_start_ms: Option<u64>,
+    next_index: usize,
+    // turns by user item id, until their manifest is final
+    turns: HashMap<String, Turn>,
+    // user item id of the turn the next response answers
+    last_committed: Option<String>,
+    // response id -> user item id
+    by_response: HashMap<String, String>,
+    decoder: crate::wire::Decoder,
+}
+
+pub struct Dataset {
+    dir: PathBuf,
+    sr_hz: u32,
+    inner: Mutex<Inner>,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl Dataset {
+    /// Create `root/session-<unix time>/` for this run.
+    pub fn create(root: &Path, sr_hz: u32) -> std::io::Result<Self> {
+        let dir = root.join(format!("session-{}", unix_now()));
+        fs::create_dir_all(&dir)?;
+        Ok(Dataset {
+            dir,
+            sr_hz,
+            inner: Mutex::new(Inner { next_index: 1, ..Default::default() }),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Mic samples exactly as forwarded to the server.
+    pub fn on_mic(&self, samples: &[i16]) {
+        let cap = (self.sr_hz as u64 * HISTORY_SECS) as usize;
+        let mut g = self.inner.lock().unwrap();
+        g.history.extend(samples.iter().copied());
+        let excess = g.history.len().saturating_sub(cap);
+        if excess > 0 {
+            g.history.drain(..excess);
+            g.history_start += excess as u64;
+        }
+    }
+
+    /// Feed every incoming server event.
+    pub fn on_event(&self, evt: &Value) {
+        let mut g = self.inner.lock().unwrap();
+        match evt["type"].as_str().unwrap_or("") {
+            "session.created" => {
+                // A new session (a reconnect) has its own items and audio clock: write
+                // what the old one left unfinished and start over, numbering on
+                for (_, turn) in g.turns.drain().filter(|(_, t)| t.status.is_none()) {
+                    self.write_turn(&turn);
+                }
+                let next_index = g.next_index;
+                *g = Inner { next_index, ..Default::default() };
+            }
+            "input_audio_buffer.speech_started" => {
+                g.speech_start_ms = evt["audio_start_ms"].as_u64();
+            }
+            "input_audio_buffer.speech_stopped" => {
+                let item_id = evt["item_id"].as_str();
+                let (Some(item_id), Some(end_ms)) = (item_id, evt["audio_end_ms"].as_u64()) else {
+                    return;
+                };
+                let start_ms = g.speech_start_ms.take().unwrap_or(end_ms);
+                let audio = self.slice(&g, start_ms, end_ms);
+                let index = g.next_index;
+                g.next_index += 1;
+                let turn = Turn {
+                    index,
+                    user_item_id: item_id.to_string(),
+                    user_audio: audio,
+                    unix_time: unix_now(),
+                    ..Default::default()
+                };
+                // On disk from the start (status null), in case the session dies before the reply
+                self.write_turn(&turn);
+                g.turns.insert(item_id.to_string(), turn);
+            }
+            "input_audio_buffer.committed" => {
+                if let Some(item_id) = evt["item_id"].as_str() {
+                    g.last_committed = Some(item_id.to_string());
+                }
+            }
+            "conversation.item.input_audio_transcription.completed" => {
+                let item_id = evt["item_id"].as_str();
+                let (Some(item_id), Some(text)) = (item_id, evt["transcript"].as_str()) else {
+                    return;
+                };
+                let Some(turn) = g.turns.get_mut(item_id) else { return };
+                turn.transcript = Some(text.trim().to_string());
+                // The reply may already be written; refresh its manifest and forget the turn
+                if turn.status.is_some() {
+                    self.write_manifest(turn);
+                    g.turns.remove(item_id);
+                }
+            }
+            "conversation.item.input_audio_transcription.failed" => {
+                let Some(turn) = evt["item_id"].as_str().and_then(|id| g.turns.get_mut(id)) else { return };
+                turn.transcript_error = Some(evt["error"]["message"].as_str().unwrap_or("failed").to_string());
+                if turn.status.is_some() {
+                    self.write_manifest(turn);
+                }
+            }
+            "response.created" => {
+                let Some(rid) = evt["response"]["id"].as_str() else { return };
+                if let Some(item_id) = g.last_committed.take()
+                    && let Some(turn) = g.turns.get_mut(&item_id)
+                {
+                    turn.response_id = Some(rid.to_string());
+                    g.by_response.insert(rid.to_string(), item_id);
+                }
+            }
+            "response.audio.delta" => {
+                use base64::Engine;
+                let Some(b64) = evt["delta"].as_str() else { return };
+                let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(b64) else { return };
+                // Decoded even for turns not kept, so the resampler stays in step
+                let samples = g.decoder.decode(&bytes).to_vec();
+                if let Some(turn) = Self::turn_for(&mut g, evt) {
+                    turn.assistant_audio.extend(samples);
+                }
+            }
+            "response.text.delta" => {
+                if let Some(turn) = Self::turn_for(&mut g, evt)
+                    && let Some(d) = evt["delta"].as_str()
+                {
+                    turn.assistant_text.push_str(d);
+                }
+            }
+            "response.audio_transcript.delta" => {
+                if let Some(turn) = Self::turn_for(&mut g, evt)
+                    && let Some(d) = evt["delta"].as_str()
+                {
+                    turn.assistant_transcript.push_str(d);
+                }
+            }
+            "response.done" => {
+                let Some(rid) = evt["response"]["id"].as_str() else { return };
+                let Some(item_id) = g.by_response.remove(rid) else { return };
+                let status = evt["response"]["status"].as_str().unwrap_or("completed").to_string();
+                let finished_with_transcript = if let Some(turn) = g.turns.get_mut(&item_id) {
+                    turn.status = Some(status);
+                    self.write_turn(turn);
+                    turn.transcript.is_some()
+                } else {
+                    false
+                };
+                // Keep the turn around only while its transcript may still arrive
+                if finished_with_transcript {
+                    g.turns.remove(&item_id);
+                }
+                if g.turns.len() > 16 {
+                    let oldest = g.next_index.saturating_sub(16);
+                    g.turns.retain(|_, t| t.index >= oldest);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Write the turns whose reply had not finished, as they are (status
+    /// null); called on the way out.
+    pub fn flush(&self) {
+        let mut g = self.inner.lock().unwrap();
+        // Finished replies are on disk already
+        for (_, turn) in g.turns.drain().filter(|(_, t)| t.status.is_none()) {
+            self.write_turn(&turn);
+        }
+    }
+
+    fn turn_for<'a>(g: &'a mut Inner, evt: &Value) -> Option<&'a mut Turn> {
+        let item_id = g.by_response.get(evt["response_id"].as_str()?)?.clone();
+        g.turns.get_mut(&item_id)
+    }
+
+    fn slice(&self, g: &Inner, start_ms: u64, end_ms: u64) -> Vec<i16> {
+        let to_idx =
+            |ms: u64| (ms * self.sr_hz as u64 / 1000).saturating_sub(g.history_start) as usize;
+        let (a, b) = (to_idx(start_ms), to_idx(end_ms).min(g.history.len()));
+        if a >= b {
+            return Vec::new();
+        }
+        g.history.range(a..b).copied().collect()
+    }
+
+    fn name(turn: &Turn, suffix: &str) -> String {
+        format!("turn_{:04}{suffix}", turn.index)
+    }
+
+    fn write_turn(&self, turn: &Turn) {
+        let files = [("_user.wav", &turn.user_audio), ("_assistant.wav", &turn.assistant_audio)];
+        for (suffix, audio) in files {
+            let path = self.dir.join(Self::name(turn, suffix));
+            if let Err(e) = crypt::write_file(&path, &wav::pcm16_mono(self.sr_hz, audio)) {
+                eprintln!("[dataset] cannot write {}: {e}", path.display());
+            }
+        }
+        self.write_manifest(turn);
+    }
+
+    fn write_manifest(&self, turn: &Turn) {
+        let ms = |n: usize| n as u64 * 1000 / self.sr_hz as u64;
+        // The text when the reply was written as well as spoken, else what was said
+        let text = if turn.assistant_text.is_empty() { &turn.assistant_transcript } else { &turn.assistant_text };
+        let spoken = (!turn.assistant_transcript.is_empty()).then(|| redact::apply(&turn.assistant_transcript));
+        let manifest = json!({
+            "turn": turn.index,
+            "unix_time": turn.unix_time,
+            "sample_rate": self.sr_hz,
+            "user_item_id": turn.user_item_id,
+            "response_id": turn.response_id,
+            "status": turn.status,
+            "transcript": turn.transcript.as_deref().map(redact::apply),
+            "transcript_error": turn.transcript_error,
+            "assistant_text": redact::apply(text),
+            "assistant_audio_transcript": spoken,
+            "user_audio": Self::name(turn, "_user.wav"),
+            "user_audio_ms": ms(turn.user_audio.len()),
+            "assistant_audio": Self::name(turn, "_assistant.wav"),
+            "assistant_audio_ms": ms(turn.assistant_audio.len()),
+        });
+        let path = self.dir.join(Self::name(turn, ".json"));
+        let body = serde_json::to_string_pretty(&manifest).unwrap_or_default();
+        if let Err(e) = crypt::write_file(&path, body.as_bytes()) {
+            eprintln!("[dataset] cannot write {}: {e}", path.display());
+        }
+    }
+}
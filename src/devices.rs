@@ -0,0 +1,265 @@
+// `parlar devices`: every input/output device with its supported stream
+// configurations, and whether the session format (mono PCM16 at `SR`) is
+// available as-is, with a sample format conversion, or only by resampling.
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{
+    BufferSize, Device, Sample, SampleFormat, SampleRate, Stream, StreamConfig, SupportedBufferSize,
+    SupportedStreamConfigRange,
+};
+
+use crate::resample::Resampler;
+
+/// How well a device matches the session format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fit {
+    /// Mono PCM16 at the session rate.
+    Native,
+    /// Session rate in mono, but another sample format (converted on the fly).
+    Convert(SampleFormat),
+    /// Session rate not offered in mono.
+    Resample,
+}
+
+impl Fit {
+    pub fn describe(self, sr_hz: u32) -> String {
+        match self {
+            Fit::Native => format!("{sr_hz} Hz mono PCM16 native"),
+            Fit::Convert(f) => format!("{sr_hz} Hz mono as {} (converted to PCM16)", format_name(f)),
+            Fit::Resample => format!("no {sr_hz} Hz mono mode (needs resampling)"),
+        }
+    }
+}
+
+fn format_name(f: SampleFormat) -> &'static str {
+    match f {
+        SampleFormat::I16 => "i16",
+        SampleFormat::U16 => "u16",
+        SampleFormat::F32 => "f32",
+    }
+}
+
+fn fit(ranges: &[SupportedStreamConfigRange], sr_hz: u32) -> Fit {
+    let rate = SampleRate(sr_hz);
+    let mut best = Fit::Resample;
+    for r in ranges {
+        if r.channels() != 1 || r.min_sample_rate() > rate || r.max_sample_rate() < rate {
+            continue;
+        }
+        if r.sample_format() == SampleFormat::I16 {
+            return Fit::Native;
+        }
+        best = Fit::Convert(r.sample_format());
+    }
+    best
+}
+
+fn ranges(dev: &Device, input: bool) -> Vec<SupportedStreamConfigRange> {
+    let res = if input {
+        dev.supported_input_configs().map(|c| c.collect())
+    } else {
+        dev.supported_output_configs().map(|c| c.collect())
+    };
+    res.unwrap_or_default()
+}
+
+pub fn input_fit(dev: &Device, sr_hz: u32) -> Fit {
+    fit(&ranges(dev, true), sr_hz)
+}
+
+pub fn output_fit(dev: &Device, sr_hz: u32) -> Fit {
+    fit(&ranges(dev, false), sr_hz)
+}
+
+/// Device of the configured host by name (see `find_in`).
+pub fn find(input: bool, name: Option<&str>) -> Option<Device> {
+    find_in(&crate::audio::host().ok()?, input, name)
+}
+
+/// Device by name (exact, else case-insensitive substring), or the default.
+pub fn find_in(host: &cpal::Host, input: bool, name: Option<&str>) -> Option<Device> {
+    let Some(name) = name.filter(|n| !n.trim().is_empty()) else {
+        return if input { host.default_input_device() } else { host.default_output_device() };
+    };
+    let devices: Vec<Device> = if input {
+        host.input_devices().map(|d| d.collect()).unwrap_or_default()
+    } else {
+        host.output_devices().map(|d| d.collect()).unwrap_or_default()
+    };
+    let names: Vec<String> = devices.iter().map(|d| d.name().unwrap_or_default()).collect();
+    let wanted = name.to_lowercase();
+    let idx = names
+        .iter()
+        .position(|n| n == name)
+        .or_else(|| names.iter().position(|n| n.to_lowercase().contains(&wanted)))?;
+    devices.into_iter().nth(idx)
+}
+
+/// Mono stream config at the session rate if offered; otherwise the device
+/// default rate, still mono. AUDIO_BUFFER_FRAMES, if set, is kept within
+/// what the device supports.
+pub fn stream_config(dev: &Device, input: bool, sr_hz: u32) -> Result<StreamConfig, String> {
+    let rate = SampleRate(sr_hz);
+    for r in ranges(dev, input) {
+        if r.channels() == 1 && r.min_sample_rate() <= rate && r.max_sample_rate() >= rate {
+            let supported = r.with_sample_rate(rate);
+            return Ok(with_buffer(supported.config(), supported.buffer_size()));
+        }
+    }
+    let default = if input { dev.default_input_config() } else { dev.default_output_config() };
+    let default = default.map_err(|e| format!("no usable configuration: {e}"))?;
+    let mut cfg = with_buffer(default.config(), default.buffer_size());
+    cfg.channels = 1;
+    Ok(cfg)
+}
+
+fn with_buffer(mut cfg: StreamConfig, supported: &SupportedBufferSize) -> StreamConfig {
+    // Checked at startup
+    if let Ok(Some(frames)) = crate::audio::buffer_frames() {
+        cfg.buffer_size = BufferSize::Fixed(match supported {
+            SupportedBufferSize::Range { min, max } => frames.clamp(*min, *max),
+            SupportedBufferSize::Unknown => frames,
+        });
+    }
+    cfg
+}
+
+fn build_input<T: Sample>(
+    dev: &Device,
+    cfg: &StreamConfig,
+    out_hz: u32,
+    mut on_data: impl FnMut(&[i16]) + Send + 'static,
+) -> Result<Stream, String> {
+    let channels = cfg.channels.max(1) as usize;
+    let mut resampler = (cfg.sample_rate.0 != out_hz).then(|| Resampler::new(cfg.sample_rate.0, out_hz));
+    let (mut pcm, mut resampled) = (Vec::new(), Vec::new());
+    dev.build_input_stream(
+        cfg,
+        move |data: &[T], _| {
+            // Downmix interleaved frames to mono
+            pcm.clear();
+            pcm.extend(data.chunks(channels).map(|f| {
+                (f.iter().map(|s| s.to_i16() as i32).sum::<i32>() / f.len() as i32) as i16
+            }));
+            match &mut resampler {
+                Some(r) => {
+                    resampled.clear();
+                    r.process(&pcm, &mut resampled);
+                    on_data(&resampled);
+                }
+                None => on_data(&pcm),
+            }
+        },
+        |e| eprintln!("Input stream error: {e:?}"),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn build_output<T: Sample>(
+    dev: &Device,
+    cfg: &StreamConfig,
+    mut fill: impl FnMut(&mut [i16]) + Send + 'static,
+) -> Result<Stream, String> {
+    let mut pcm = Vec::new();
+    dev.build_output_stream(
+        cfg,
+        move |out: &mut [T], _| {
+            pcm.resize(out.len(), 0);
+            fill(&mut pcm);
+            for (o, s) in out.iter_mut().zip(&pcm) {
+                *o = T::from(s);
+            }
+        },
+        |e| eprintln!("Output stream error: {e:?}"),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Capture stream that hands mono PCM16 at `out_hz` to `on_data`, whatever
+/// the device's format, channel count and rate.
+pub fn open_input(
+    dev: &Device,
+    cfg: &StreamConfig,
+    format: SampleFormat,
+    out_hz: u32,
+    on_data: impl FnMut(&[i16]) + Send + 'static,
+) -> Result<Stream, String> {
+    match format {
+        SampleFormat::I16 => build_input::<i16>(dev, cfg, out_hz, on_data),
+        SampleFormat::U16 => build_input::<u16>(dev, cfg, out_hz, on_data),
+        SampleFormat::F32 => build_input::<f32>(dev, cfg, out_hz, on_data),
+    }
+}
+
+/// Playback stream that asks `fill` for PCM16 whatever the device format.
+pub fn open_output(
+    dev: &Device,
+    cfg: &StreamConfig,
+    fill: impl FnMut(&mut [i16]) + Send + 'static,
+) -> Result<Stream, String> {
+    match dev.default_output_config().map_err(|e| e.to_string())?.sample_format() {
+        SampleFormat::I16 => build_output::<i16>(dev, cfg, fill),
+        SampleFormat::U16 => build_output::<u16>(dev, cfg, fill),
+        SampleFormat::F32 => build_output::<f32>(dev, cfg, fill),
+    }
+}
+
+/// Print all devices of the default host.
+pub fn run(sr_hz: u32) -> i32 {
+    match report(sr_hz) {
+        Ok(text) => {
+            print!("{text}");
+            0
+        }
+        Err(e) => {
+            eprintln!("[config] {e}");
+            2
+        }
+    }
+}
+
+/// All devices of the default host, as `parlar devices` lists them.
+pub fn report(sr_hz: u32) -> Result<String, String> {
+    use std::fmt::Write;
+
+    let host = crate::audio::host()?;
+    let mut out = String::new();
+    let _ = writeln!(out, "Host: {}", host.id().name());
+    let default_in = host.default_input_device().and_then(|d| d.name().ok());
+    let default_out = host.default_output_device().and_then(|d| d.name().ok());
+
+    for input in [true, false] {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "{}", if input { "Input devices" } else { "Output devices" });
+        let devices: Vec<Device> = match if input { host.input_devices() } else { host.output_devices() } {
+            Ok(d) => d.collect(),
+            Err(e) => {
+                let _ = writeln!(out, "  (cannot enumerate: {e})");
+                continue;
+            }
+        };
+        if devices.is_empty() {
+            let _ = writeln!(out, "  (none)");
+        }
+        let default = if input { &default_in } else { &default_out };
+        for dev in devices {
+            let name = dev.name().unwrap_or_else(|_| "?".into());
+            let mark = if default.as_deref() == Some(name.as_str()) { " (default)" } else { "" };
+            let _ = writeln!(out, "  {name}{mark}");
+            let ranges = ranges(&dev, input);
+            if ranges.is_empty() {
+                let _ = writeln!(out, "    no supported configurations reported");
+                continue;
+            }
+            for r in &ranges {
+                let (lo, hi) = (r.min_sample_rate().0, r.max_sample_rate().0);
+                let rates = if lo == hi { format!("{lo} Hz") } else { format!("{lo}–{hi} Hz") };
+                let _ = writeln!(out, "    {} ch  {:<14} {}", r.channels(), rates, format_name(r.sample_format()));
+            }
+            let fit = fit(&ranges, sr_hz);
+            let sign = if fit == Fit::Resample { '!' } else { '✓' };
+            let _ = writeln!(out, "    {sign} {}", fit.describe(sr_hz));
+        }
+    }
+    Ok(out)
+}
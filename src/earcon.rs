@@ -0,0 +1,93 @@
+// Earcons: short locally generated tones mixed into the speaker output.
+//
+// Cues live in their own buffer so that interrupting the assistant (which
+// clears the speaker buffer) never swallows a status tone.
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
+
+pub type CueBuf = Arc<Mutex<VecDeque<i16>>>;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Earcon {
+    /// The turn was committed and we are waiting for the reply.
+    Thinking,
+    /// WebSocket session established.
+    Connected,
+    /// WebSocket closed or failed.
+    Disconnected,
+    /// The server reported an error.
+    Error,
+    /// Microphone muted.
+    Muted,
+    /// Microphone unmuted.
+    Unmuted,
+    /// A voice command was carried out.
+    Command,
+    /// A reminder is due.
+    Reminder,
+    /// A timer or alarm went off.
+    Alarm,
+    /// The reply has finished playing; the floor is the user's.
+    YourTurn,
+}
+
+impl Earcon {
+    /// (frequency Hz, duration ms) per note; 0 Hz is a rest.
+    fn notes(self) -> &'static [(f32, u32)] {
+        match self {
+            Earcon::Thinking => &[(660.0, 60), (0.0, 30), (880.0, 80)],
+            Earcon::Connected => &[(523.0, 90), (659.0, 90), (784.0, 140)],
+            Earcon::Disconnected => &[(784.0, 90), (659.0, 90), (523.0, 90), (392.0, 180)],
+            Earcon::Error => &[(220.0, 120), (0.0, 60), (220.0, 120)],
+            Earcon::Muted => &[(440.0, 80), (330.0, 120)],
+            Earcon::Unmuted => &[(330.0, 80), (440.0, 120)],
+            Earcon::Command => &[(784.0, 70), (0.0, 20), (1047.0, 110)],
+            Earcon::Reminder => &[(880.0, 110), (0.0, 40), (1175.0, 110), (0.0, 160), (880.0, 110), (0.0, 40), (1175.0, 200)],
+            Earcon::Alarm => &[(988.0, 90), (0.0, 50), (988.0, 90), (0.0, 50), (988.0, 90), (0.0, 50), (988.0, 90)],
+            Earcon::YourTurn => &[(587.0, 50), (0.0, 20), (440.0, 70)],
+        }
+    }
+}
+
+/// Total length of an earcon in milliseconds.
+pub fn duration_ms(earcon: Earcon) -> u64 {
+    earcon.notes().iter().map(|&(_, ms)| ms as u64).sum()
+}
+
+/// Render an earcon as PCM16 mono at `sr_hz`.
+pub fn render(earcon: Earcon, sr_hz: u32, gain: f32) -> Vec<i16> {
+    let amp = gain.clamp(0.0, 1.0) * i16::MAX as f32;
+    let mut out = Vec::new();
+    for &(freq, ms) in earcon.notes() {
+        let n = (sr_hz as u64 * ms as u64 / 1000) as usize;
+        // 5 ms linear fade in/out to avoid clicks
+        let fade = ((sr_hz / 200) as usize).min(n / 2).max(1);
+        for i in 0..n {
+            if freq <= 0.0 {
+                out.push(0);
+                continue;
+            }
+            let env = (i.min(n - 1 - i) as f32 / fade as f32).min(1.0);
+            let t = i as f32 / sr_hz as f32;
+            out.push((amp * env * (2.0 * PI * freq * t).sin()) as i16);
+        }
+    }
+    out
+}
+
+/// Queue an earcon for playback.
+pub fn play(cues: &CueBuf, earcon: Earcon, sr_hz: u32, gain: f32) {
+    let pcm = render(earcon, sr_hz, gain);
+    if let Ok(mut q) = cues.lock() {
+        q.extend(pcm);
+    }
+}
+
+/// Pop the next output sample: assistant audio mixed with any pending cue.
+pub fn mix_next(spk: &mut VecDeque<i16>, cues: &mut VecDeque<i16>) -> i16 {
+    let a = spk.pop_front().unwrap_or(0) as i32;
+    let b = cues.pop_front().unwrap_or(0) as i32;
+    (a + b).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
@@ -0,0 +1,60 @@
+// Echo-risk detection. A reply cancelled by detected speech within
+// ECHO_CANCEL_MS (default 1500) of its first audio is the signature of the
+// speaker leaking into the mic and tripping the server's VAD. After
+// ECHO_WARN_COUNT (default 3, 0 turns it off) of those within ten minutes,
+// parlar says so once and suggests what to change.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const SPAN: Duration = Duration::from_secs(600);
+
+#[derive(Default)]
+pub struct EchoWatch {
+    cancel_ms: u64,
+    warn_count: usize,
+    // reply whose audio is playing, and when its first delta arrived
+    reply: Option<(String, Instant)>,
+    quick_cancels: VecDeque<Instant>,
+    warned: bool,
+}
+
+impl EchoWatch {
+    pub fn from_env() -> Self {
+        let var = |name: &str, d: u64| std::env::var(name).ok().and_then(|v| v.trim().parse().ok()).unwrap_or(d);
+        EchoWatch {
+            cancel_ms: var("ECHO_CANCEL_MS", 1500),
+            warn_count: var("ECHO_WARN_COUNT", 3) as usize,
+            ..Default::default()
+        }
+    }
+
+    /// An audio delta of `response_id` arrived.
+    pub fn audio(&mut self, response_id: &str) {
+        if self.reply.as_ref().is_none_or(|(id, _)| id != response_id) {
+            self.reply = Some((response_id.to_string(), Instant::now()));
+        }
+    }
+
+    /// The playing reply was cancelled because speech was detected; returns
+    /// the warning when this looks like echo.
+    pub fn speech_cancel(&mut self) -> Option<&'static str> {
+        let (_, started) = self.reply.take()?;
+        if self.warned || self.warn_count == 0 || started.elapsed() > Duration::from_millis(self.cancel_ms) {
+            return None;
+        }
+        let now = Instant::now();
+        self.quick_cancels.retain(|t| now.duration_since(*t) < SPAN);
+        self.quick_cancels.push_back(now);
+        if self.quick_cancels.len() < self.warn_count {
+            return None;
+        }
+        self.warned = true;
+        Some(
+            "replies keep being cut off right after they start, which usually means the assistant's \
+             own voice reaches the microphone. Try headphones, turn the volume down, enable echo \
+             cancellation in the OS (e.g. PipeWire's echo-cancel module), or make the gate stricter \
+             (GATE_MODE=discard, higher INT_ONSET_PEAK).",
+        )
+    }
+}
@@ -0,0 +1,68 @@
+// End-of-reply cue (END_CUE): a short tone and/or the terminal bell once a
+// reply has finished playing, so a user who is not watching the screen
+// knows the floor is theirs.
+//
+// `response.audio.done` only says the server has sent the last of the
+// audio; several seconds of it can still be queued for the speaker. The cue
+// waits until the reply has played out (`State::playing`). An interrupted
+// reply gets no cue.
+
+use std::io::Write;
+use std::time::Duration;
+
+use crate::control::Controls;
+use crate::earcon::{self, Earcon};
+
+// How often playback is checked once a reply's audio is all in
+const POLL: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EndCue {
+    Off,
+    Earcon,
+    Bell,
+    Both,
+}
+
+impl EndCue {
+    /// Read END_CUE: off (default), earcon, bell or both.
+    pub fn from_env() -> Result<EndCue, String> {
+        match std::env::var("END_CUE").unwrap_or_default().trim().to_ascii_lowercase().as_str() {
+            "" | "off" => Ok(EndCue::Off),
+            "earcon" => Ok(EndCue::Earcon),
+            "bell" => Ok(EndCue::Bell),
+            "both" => Ok(EndCue::Both),
+            other => Err(format!("END_CUE: expected off, earcon, bell or both, got {other}")),
+        }
+    }
+}
+
+/// Watch for replies that have played out and sound `cue` after each.
+pub fn start(controls: &Controls, cue: EndCue) {
+    if cue == EndCue::Off {
+        return;
+    }
+    let controls = controls.clone();
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(POLL);
+            {
+                let mut st = controls.state.lock().unwrap();
+                // A follow-up reply (after a tool call) may already be streaming
+                if !st.end_cue_pending || st.speaking() {
+                    continue;
+                }
+                st.end_cue_pending = false;
+            }
+            if matches!(cue, EndCue::Earcon | EndCue::Both) {
+                earcon::play(&controls.cue_buf, Earcon::YourTurn, controls.sr_hz, controls.earcon_gain);
+            }
+            // Terminals that vibrate or flash on the bell make this a haptic cue
+            if matches!(cue, EndCue::Bell | EndCue::Both) {
+                let mut err = std::io::stderr();
+                let _ = err.write_all(b"\x07");
+                let _ = err.flush();
+            }
+        }
+    });
+}
@@ -0,0 +1,77 @@
+// Fatal errors of a conversation run. Each kind comes with a hint on what to
+// do about it and its own exit code, so wrapper scripts can react:
+//   2  invalid configuration (reported as `[config]` where it is read)
+//   3  no API key, or the key was rejected
+//   4  audio device missing or unusable
+//   5  the realtime server cannot be reached
+// Anything else that ends a run early exits with 1.
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ParlarError {
+    ApiKey(String),
+    Audio(String),
+    Connect(String),
+}
+
+impl ParlarError {
+    /// Sort a failed WebSocket handshake: a 401/403 is the key's fault.
+    pub fn from_connect(e: tungstenite::Error) -> Self {
+        match &e {
+            tungstenite::Error::Http(resp) if matches!(resp.status().as_u16(), 401 | 403) => {
+                ParlarError::ApiKey(format!("the realtime server rejected the API key ({})", resp.status()))
+            }
+            _ => ParlarError::Connect(format!("cannot connect to the realtime server: {e}")),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ParlarError::ApiKey(_) => 3,
+            ParlarError::Audio(_) => 4,
+            ParlarError::Connect(_) => 5,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            ParlarError::ApiKey(_) => "api key",
+            ParlarError::Audio(_) => "audio",
+            ParlarError::Connect(_) => "network",
+        }
+    }
+
+    pub fn hint(&self) -> &'static str {
+        match self {
+            ParlarError::ApiKey(_) => {
+                "set OPENAI_API_KEY (environment or .env) or store a key with `parlar init`; \
+                 `parlar doctor` verifies it"
+            }
+            ParlarError::Audio(_) => {
+                "`parlar devices` lists what is available; pick one with INPUT_DEVICE / OUTPUT_DEVICE, \
+                 or run text-only with --no-audio"
+            }
+            ParlarError::Connect(_) => {
+                "check the network, HTTPS_PROXY/--proxy and TLS settings; `parlar doctor` tests the connection"
+            }
+        }
+    }
+
+    /// Print the error and its hint, and end the process.
+    pub fn exit(&self) -> ! {
+        eprintln!("[{}] {self}\n  hint: {}", self.label(), self.hint());
+        crate::bugreport::finish();
+        std::process::exit(self.exit_code())
+    }
+}
+
+impl fmt::Display for ParlarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParlarError::ApiKey(m) | ParlarError::Audio(m) | ParlarError::Connect(m) => f.write_str(m),
+        }
+    }
+}
+
+impl std::error::Error for ParlarError {}
@@ -0,0 +1,73 @@
+// Debug event log: one JSON line per realtime event, with the response/item
+// ids it refers to, so lifecycle races can be reconstructed after the fact.
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde_json::{Value, json};
+
+use crate::crypt::Appender;
+
+pub struct EventLog {
+    file: Option<Mutex<Appender>>,
+    start: Instant,
+}
+
+impl EventLog {
+    /// Open (append) the log at `path`; `None` gives a no-op logger.
+    pub fn open(path: Option<&str>) -> Self {
+        let file = path.and_then(|p| {
+            match Appender::open(p) {
+                Ok(f) => Some(Mutex::new(f)),
+                Err(e) => {
+                    eprintln!("[eventlog] cannot open {e}");
+                    None
+                }
+            }
+        });
+        EventLog { file, start: Instant::now() }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Record an event; `dir` is "in" (server) or "out" (client).
+    pub fn record(&self, dir: &str, evt: &Value) {
+        let Some(file) = &self.file else { return };
+        let line = json!({
+            "t_ms": self.start.elapsed().as_millis() as u64,
+            "dir": dir,
+            "type": evt["type"],
+            "response_id": response_id(evt),
+            "item_id": item_id(evt),
+        });
+        if let Ok(mut f) = file.lock() {
+            let _ = f.append(format!("{}\n", crate::redact::apply(&line.to_string())).as_bytes());
+            // Synced once a turn, not on every audio delta
+            if matches!(evt["type"].as_str(), Some("response.done" | "input_audio_buffer.committed")) {
+                let _ = f.sync();
+            }
+        }
+    }
+}
+
+fn response_id(evt: &Value) -> Value {
+    if evt["response_id"].is_string() {
+        evt["response_id"].clone()
+    } else if evt["response"]["id"].is_string() {
+        evt["response"]["id"].clone()
+    } else {
+        Value::Null
+    }
+}
+
+fn item_id(evt: &Value) -> Value {
+    if evt["item_id"].is_string() {
+        evt["item_id"].clone()
+    } else if evt["item"]["id"].is_string() {
+        evt["item"]["id"].clone()
+    } else {
+        Value::Null
+    }
+}
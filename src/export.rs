@@ -0,0 +1,273 @@
+// `parlar export SESSION_DIR`: turn a `--capture-dataset` session into one
+// compressed file of the whole conversation (each turn's user audio, then
+// the reply), with a chapter per turn and a Markdown transcript next to it.
+// `--format srt|vtt` writes captions for that same audio instead.
+//
+// Encoding is left to ffmpeg (libvorbis for .ogg, libmp3lame for .mp3);
+// chapters go in through an ffmetadata file. Encrypted sessions are read
+// with the usual TRANSCRIPT_PASSPHRASE / TRANSCRIPT_KEYFILE.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde_json::Value;
+
+use crate::cli::Cli;
+use crate::{captions, crypt, wav};
+
+// Silence between the user's words and the reply, and between turns
+const REPLY_GAP_MS: u64 = 300;
+const TURN_GAP_MS: u64 = 600;
+// Transcript characters kept in a chapter title
+const TITLE_CHARS: usize = 60;
+
+pub struct Turn {
+    pub index: u64,
+    pub transcript: String,
+    pub assistant_text: String,
+    pub cancelled: bool,
+    /// Offsets into the exported audio, in ms.
+    pub start_ms: u64,
+    pub user_end_ms: u64,
+    pub reply_ms: u64,
+    pub end_ms: u64,
+}
+
+/// A session laid out on one timeline.
+pub struct Session {
+    pub sr_hz: u32,
+    pub samples: Vec<i16>,
+    pub turns: Vec<Turn>,
+}
+
+/// Manifests in `dir`, by turn number (plain or sealed).
+fn manifests(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let mut found: Vec<(u64, PathBuf)> = entries
+        .filter_map(|e| {
+            let name = e.ok()?.file_name().into_string().ok()?;
+            let stem = name.strip_suffix(crypt::SUFFIX).unwrap_or(&name).strip_suffix(".json")?;
+            let index = stem.strip_prefix("turn_")?.parse().ok()?;
+            Some((index, dir.join(format!("{stem}.json"))))
+        })
+        .collect();
+    found.sort();
+    found.dedup_by_key(|(index, _)| *index);
+    Ok(found.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Read a session directory and lay its turns out back to back.
+pub fn load(dir: &Path) -> Result<Session, String> {
+    let paths = manifests(dir)?;
+    if paths.is_empty() {
+        return Err(format!("{}: no turn_NNNN.json manifests (is it a --capture-dataset session?)", dir.display()));
+    }
+    let mut session = Session { sr_hz: 0, samples: Vec::new(), turns: Vec::new() };
+    let ms = |n: usize, sr_hz: u32| n as u64 * 1000 / sr_hz.max(1) as u64;
+    for path in paths {
+        let manifest: Value = serde_json::from_slice(&crypt::read_file(&path)?)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        let mut audio = |key: &str| -> Result<Vec<i16>, String> {
+            let Some(name) = manifest[key].as_str() else { return Ok(Vec::new()) };
+            let wav_path = dir.join(name);
+            let bytes = match crypt::read_file(&wav_path) {
+                Ok(b) => b,
+                // The manifest can be written before its audio
+                Err(_) if manifest["status"].is_null() => return Ok(Vec::new()),
+                Err(e) => return Err(e),
+            };
+            let (sr_hz, samples) =
+                wav::read_pcm16_mono(&bytes).ok_or(format!("{}: not a PCM16 mono WAV", wav_path.display()))?;
+            if session.sr_hz == 0 {
+                session.sr_hz = sr_hz;
+            } else if sr_hz != session.sr_hz {
+                return Err(format!("{}: {sr_hz} Hz, the session is {} Hz", wav_path.display(), session.sr_hz));
+            }
+            Ok(samples)
+        };
+        let user = audio("user_audio")?;
+        let reply = audio("assistant_audio")?;
+        let sr_hz = session.sr_hz.max(1);
+        let silence = |ms: u64| vec![0i16; (ms * sr_hz as u64 / 1000) as usize];
+
+        let start_ms = ms(session.samples.len(), sr_hz);
+        session.samples.extend(&user);
+        let user_end_ms = ms(session.samples.len(), sr_hz);
+        session.samples.extend(silence(REPLY_GAP_MS));
+        let reply_ms = ms(session.samples.len(), sr_hz);
+        session.samples.extend(&reply);
+        let end_ms = ms(session.samples.len(), sr_hz);
+        session.samples.extend(silence(TURN_GAP_MS));
+        session.turns.push(Turn {
+            index: manifest["turn"].as_u64().unwrap_or(session.turns.len() as u64 + 1),
+            transcript: manifest["transcript"].as_str().unwrap_or("").trim().to_string(),
+            assistant_text: manifest["assistant_text"].as_str().unwrap_or("").trim().to_string(),
+            cancelled: manifest["status"].as_str() == Some("cancelled"),
+            start_ms,
+            user_end_ms,
+            reply_ms,
+            end_ms,
+        });
+    }
+    if session.sr_hz == 0 {
+        return Err(format!("{}: the session has no audio", dir.display()));
+    }
+    Ok(session)
+}
+
+/// `h:mm:ss` or `m:ss`.
+fn clock(ms: u64) -> String {
+    let s = ms / 1000;
+    match s / 3600 {
+        0 => format!("{}:{:02}", s / 60, s % 60),
+        h => format!("{h}:{:02}:{:02}", s / 60 % 60, s % 60),
+    }
+}
+
+fn chapter_title(turn: &Turn) -> String {
+    let text: String = turn.transcript.chars().take(TITLE_CHARS).collect();
+    let more = if turn.transcript.chars().count() > TITLE_CHARS { "…" } else { "" };
+    match text.is_empty() {
+        true => format!("Turn {}", turn.index),
+        false => format!("Turn {}: {text}{more}", turn.index),
+    }
+}
+
+/// ffmetadata with one chapter per turn.
+fn ffmetadata(session: &Session, title: &str) -> String {
+    // `=`, `;`, `#`, `\` and newlines are special in ffmetadata values
+    let escape = |s: &str| {
+        let mut out = String::with_capacity(s.len());
+        for c in s.chars() {
+            if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out
+    };
+    let mut meta = format!(";FFMETADATA1\ntitle={}\n", escape(title));
+    let total_ms = session.samples.len() as u64 * 1000 / session.sr_hz as u64;
+    for (i, turn) in session.turns.iter().enumerate() {
+        let end = session.turns.get(i + 1).map_or(total_ms, |next| next.start_ms);
+        meta.push_str(&format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={end}\ntitle={}\n",
+            turn.start_ms,
+            escape(&chapter_title(turn))
+        ));
+    }
+    meta
+}
+
+fn transcript(session: &Session, title: &str) -> String {
+    let mut md = format!("# {title}\n");
+    for turn in &session.turns {
+        let you = if turn.transcript.is_empty() { "(no transcript)" } else { &turn.transcript };
+        md.push_str(&format!("\n## Turn {} [{}]\n\n**You:** {you}\n", turn.index, clock(turn.start_ms)));
+        if !turn.assistant_text.is_empty() || turn.reply_ms < turn.end_ms {
+            let cut = if turn.cancelled { " (interrupted)" } else { "" };
+            md.push_str(&format!(
+                "\n**Assistant** [{}]{cut}: {}\n",
+                clock(turn.reply_ms),
+                turn.assistant_text
+            ));
+        }
+    }
+    md
+}
+
+fn encode(session: &Session, codec: &str, meta_path: &Path, out: &Path) -> Result<(), String> {
+    let sr = session.sr_hz.to_string();
+    let mut child = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-f", "s16le", "-ar", &sr, "-ac", "1", "-i", "-"])
+        .arg("-i")
+        .arg(meta_path)
+        .args(["-map", "0:a", "-map_metadata", "1", "-map_chapters", "1", "-c:a", codec, "-q:a", "4"])
+        .arg(out)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => "ffmpeg not found; install it to export sessions".to_string(),
+            _ => format!("ffmpeg: {e}"),
+        })?;
+    let mut bytes = Vec::with_capacity(session.samples.len() * 2);
+    crate::pcm::le_bytes(&session.samples, &mut bytes);
+    let written = child.stdin.take().map(|mut stdin| stdin.write_all(&bytes));
+    let status = child.wait().map_err(|e| format!("ffmpeg: {e}"))?;
+    if !status.success() {
+        return Err(format!("ffmpeg failed ({status})"));
+    }
+    written.transpose().map_err(|e| format!("ffmpeg: {e}"))?;
+    Ok(())
+}
+
+/// `parlar export SESSION_DIR [--format ogg|mp3|srt|vtt] [--output PATH]`.
+pub fn run(cli: &Cli) -> i32 {
+    let [dir] = cli.operands()[..] else {
+        eprintln!("usage: parlar export SESSION_DIR [--format ogg|mp3|srt|vtt] [--output PATH]");
+        return 2;
+    };
+    let dir = Path::new(dir);
+    let output = cli.value("output").map(PathBuf::from);
+    let format = cli
+        .value("format")
+        .or_else(|| output.as_ref()?.extension()?.to_str().map(str::to_ascii_lowercase))
+        .unwrap_or_else(|| "ogg".into());
+    let codec = match format.as_str() {
+        "ogg" => "libvorbis",
+        "mp3" => "libmp3lame",
+        "srt" | "vtt" => "",
+        other => {
+            eprintln!("--format must be ogg, mp3, srt or vtt (got {other})");
+            return 2;
+        }
+    };
+    let session = match load(dir) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{e}");
+            return 1;
+        }
+    };
+    let name = dir.file_name().and_then(|n| n.to_str()).unwrap_or("session");
+    let output = output.unwrap_or_else(|| dir.join(format!("{name}.{format}")));
+    let title = format!("parlar {name}");
+
+    if codec.is_empty() {
+        let cues = captions::cues(&session);
+        let text = if format == "srt" { captions::srt(&cues) } else { captions::vtt(&cues) };
+        if let Err(e) = fs::write(&output, text) {
+            eprintln!("{}: {e}", output.display());
+            return 1;
+        }
+        eprintln!("Wrote {} captions to {}", cues.len(), output.display());
+        return 0;
+    }
+    let meta_path = output.with_extension("chapters.txt");
+    if let Err(e) = fs::write(&meta_path, ffmetadata(&session, &title)) {
+        eprintln!("{}: {e}", meta_path.display());
+        return 1;
+    }
+    let encoded = encode(&session, codec, &meta_path, &output);
+    let _ = fs::remove_file(&meta_path);
+    if let Err(e) = encoded {
+        eprintln!("{e}");
+        return 1;
+    }
+    let md_path = output.with_extension("md");
+    if let Err(e) = fs::write(&md_path, transcript(&session, &title)) {
+        eprintln!("{}: {e}", md_path.display());
+        return 1;
+    }
+    let total_ms = session.samples.len() as u64 * 1000 / session.sr_hz as u64;
+    eprintln!(
+        "Exported {} turns ({}) to {} with transcript {}",
+        session.turns.len(),
+        clock(total_ms),
+        output.display(),
+        md_path.display()
+    );
+    0
+}
@@ -0,0 +1,240 @@
+// Content filter for finalized user transcripts, run before a reply is
+// requested. Rules come from a file of regexes and/or an external command.
+//
+// Rules file, one rule per line (`#` comments):
+//   suppress  (?i)\bsome phrase\b
+//   alert     (?i)\bpassword\b
+//   replace   (?i)\bdamn\b => darn
+//
+// The command gets the transcript on stdin and may print a JSON verdict:
+//   {"action": "allow" | "suppress" | "replace" | "alert", "text": "...", "reason": "..."}
+// Empty output means allow. A command that fails or gives no verdict within
+// TRANSCRIPT_FILTER_TIMEOUT_MS (killed then) suppresses the turn, or with
+// TRANSCRIPT_FILTER_FAIL=open lets it through; either way with an alert.
+// Filter plugins from PLUGINS_DIR run after the command, with the same
+// input and verdict.
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, mpsc};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+
+use crate::plugins::{self, Plugin};
+
+// How long a filter plugin may take
+const PLUGIN_LIMIT: Duration = Duration::from_secs(5);
+
+enum RuleAction {
+    Suppress,
+    Alert,
+    Replace(String),
+}
+
+struct Rule {
+    re: Regex,
+    action: RuleAction,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Allow,
+    Suppress,
+    /// Answer this text instead of what was said.
+    Replace(String),
+}
+
+pub struct Verdict {
+    pub action: Action,
+    /// Reasons to surface as alerts (any action may carry some).
+    pub alerts: Vec<String>,
+}
+
+pub struct Filter {
+    rules: Vec<Rule>,
+    command: Option<String>,
+    plugins: Vec<Arc<Plugin>>,
+    /// How long the command may take.
+    timeout: Duration,
+    /// A failing command or plugin lets the turn through instead of suppressing it.
+    fail_open: bool,
+}
+
+impl Filter {
+    /// Build from `TRANSCRIPT_FILTER_RULES` (file), `TRANSCRIPT_FILTER_CMD`
+    /// (with `TRANSCRIPT_FILTER_TIMEOUT_MS` and `TRANSCRIPT_FILTER_FAIL`)
+    /// and the filter plugins.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let rules = match std::env::var("TRANSCRIPT_FILTER_RULES") {
+            Ok(path) => {
+                let body = std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+                parse_rules(&body).map_err(|e| format!("{path}: {e}"))?
+            }
+            Err(_) => Vec::new(),
+        };
+        let command = std::env::var("TRANSCRIPT_FILTER_CMD").ok().filter(|c| !c.trim().is_empty());
+        let plugins: Vec<Arc<Plugin>> = plugins::filters().cloned().collect();
+        if rules.is_empty() && command.is_none() && plugins.is_empty() {
+            return Ok(None);
+        }
+        let timeout = match std::env::var("TRANSCRIPT_FILTER_TIMEOUT_MS") {
+            Ok(v) => v.trim().parse().map_err(|_| format!("TRANSCRIPT_FILTER_TIMEOUT_MS={v} is not a number"))?,
+            Err(_) => 5000,
+        };
+        let fail_open = match std::env::var("TRANSCRIPT_FILTER_FAIL").as_deref().map(str::trim) {
+            Err(_) | Ok("" | "closed") => false,
+            Ok("open") => true,
+            Ok(other) => return Err(format!("TRANSCRIPT_FILTER_FAIL must be open or closed (got {other})")),
+        };
+        Ok(Some(Filter { rules, command, plugins, timeout: Duration::from_millis(timeout), fail_open }))
+    }
+
+    /// Run the rules, then the command and plugins. Blocking when either is
+    /// set.
+    pub fn check(&self, text: &str) -> Verdict {
+        let mut alerts = Vec::new();
+        let mut text = text.to_string();
+        let mut replaced = false;
+        for rule in &self.rules {
+            if !rule.re.is_match(&text) {
+                continue;
+            }
+            match &rule.action {
+                RuleAction::Suppress => {
+                    alerts.push(format!("suppressed by /{}/", rule.re));
+                    return Verdict { action: Action::Suppress, alerts };
+                }
+                RuleAction::Alert => alerts.push(format!("matched /{}/", rule.re)),
+                RuleAction::Replace(with) => {
+                    text = rule.re.replace_all(&text, with.as_str()).into_owned();
+                    replaced = true;
+                }
+            }
+        }
+        // The command, then each plugin; each sees the text as the ones before left it
+        let checks = self.command.iter().map(|_| None).chain(self.plugins.iter().map(Some));
+        for plugin in checks {
+            let (by, verdict) = match plugin {
+                None => (
+                    "command".to_string(),
+                    run_command(self.command.as_deref().unwrap_or(""), &text, self.timeout),
+                ),
+                Some(p) => (
+                    format!("plugin {}", p.manifest.name),
+                    plugins::run(p, &text, PLUGIN_LIMIT).and_then(|out| parse_verdict(&out)),
+                ),
+            };
+            match verdict {
+                Ok(Some(v)) => {
+                    if let Some(reason) = v["reason"].as_str() {
+                        alerts.push(reason.to_string());
+                    }
+                    match v["action"].as_str().unwrap_or("allow") {
+                        "suppress" => return Verdict { action: Action::Suppress, alerts },
+                        "replace" => {
+                            if let Some(t) = v["text"].as_str() {
+                                text = t.to_string();
+                                replaced = true;
+                            }
+                        }
+                        "alert" if v["reason"].is_null() => alerts.push(format!("flagged by {by}")),
+                        _ => {}
+                    }
+                }
+                Ok(None) => {}
+                Err(e) if self.fail_open => alerts.push(format!("filter {by} failed ({e}); letting the turn through")),
+                Err(e) => {
+                    alerts.push(format!("filter {by} failed ({e}); suppressing"));
+                    return Verdict { action: Action::Suppress, alerts };
+                }
+            }
+        }
+        let action = if replaced { Action::Replace(text) } else { Action::Allow };
+        Verdict { action, alerts }
+    }
+}
+
+fn parse_rules(body: &str) -> Result<Vec<Rule>, String> {
+    let mut rules = Vec::new();
+    for (n, line) in body.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (kind, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let bad = |e: regex::Error| format!("line {}: {e}", n + 1);
+        let rule = match kind {
+            "suppress" => Rule { re: Regex::new(rest).map_err(bad)?, action: RuleAction::Suppress },
+            "alert" => Rule { re: Regex::new(rest).map_err(bad)?, action: RuleAction::Alert },
+            "replace" => {
+                let (pat, with) = rest
+                    .split_once("=>")
+                    .ok_or_else(|| format!("line {}: replace needs `PATTERN => TEXT`", n + 1))?;
+                Rule {
+                    re: Regex::new(pat.trim()).map_err(bad)?,
+                    action: RuleAction::Replace(with.trim().to_string()),
+                }
+            }
+            other => return Err(format!("line {}: unknown rule kind `{other}`", n + 1)),
+        };
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+/// Run the filter command on `text`; killed when it has not exited within `limit`.
+fn run_command(cmd: &str, text: &str, limit: Duration) -> Result<Option<serde_json::Value>, String> {
+    let deadline = Instant::now() + limit;
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = Command::new(shell)
+        .arg(flag)
+        .arg(cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    // Input and output each on their own thread: a command may answer
+    // before it has read all of its input, and either pipe can fill up
+    if let Some(mut stdin) = child.stdin.take() {
+        let text = text.to_string();
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(text.as_bytes());
+        });
+    }
+    let (tx, rx) = mpsc::channel();
+    if let Some(mut stdout) = child.stdout.take() {
+        std::thread::spawn(move || {
+            let mut out = Vec::new();
+            let _ = tx.send(stdout.read_to_end(&mut out).map(|_| out));
+        });
+    }
+    let timed_out = |child: &mut std::process::Child| {
+        let _ = child.kill();
+        let _ = child.wait();
+        format!("no verdict within {} ms", limit.as_millis())
+    };
+    let out = match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(out) => out.map_err(|e| e.to_string())?,
+        Err(_) => return Err(timed_out(&mut child)),
+    };
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => return Err(timed_out(&mut child)),
+            None => std::thread::sleep(Duration::from_millis(10)),
+        }
+    };
+    if !status.success() {
+        return Err(format!("exit status {status}"));
+    }
+    parse_verdict(&String::from_utf8_lossy(&out))
+}
+
+fn parse_verdict(out: &str) -> Result<Option<serde_json::Value>, String> {
+    if out.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(out.trim()).map(Some).map_err(|e| format!("bad JSON verdict: {e}"))
+}
@@ -0,0 +1,142 @@
+// Mic gate while the assistant speaks: audio goes through only after a
+// clear onset (INT_ONSET_PEAK for INT_ONSET_MIN_CHUNKS chunks in a row), so
+// the assistant's own voice leaking into the mic does not barge in.
+// GATE_MODE decides what happens to the audio held back:
+//   discard  dropped (default)
+//   buffer   the last GATE_BUFFER_MS kept and sent ahead of the onset, so a
+//            soft-spoken barge-in keeps its first words
+//   always   no gate; for headsets or hardware echo cancellation
+//
+// Whatever the mode, the last PREROLL_MS of held-back audio (gated, or
+// muted for push-to-talk) is sent first when the mic starts sending again,
+// so a quick "yes" is not clipped to "-es". Audio held while the assistant
+// spoke is its echo and is dropped if the reply ends without an onset.
+
+use std::collections::VecDeque;
+
+use crate::pcm::Pool;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Discard,
+    Buffer,
+    Always,
+}
+
+pub struct Gate {
+    mode: Mode,
+    onset_peak: f32,
+    onset_min_chunks: usize,
+    loud: usize,
+    held: VecDeque<Vec<i16>>,
+    /// Some held chunk was captured while the assistant spoke.
+    held_echo: bool,
+    buffer_chunks: usize,
+    preroll_chunks: usize,
+    pool: Pool,
+}
+
+fn ms_from_env(name: &str, default: u32) -> Result<u32, String> {
+    match std::env::var(name) {
+        Ok(v) => v.trim().parse().ok().filter(|ms| *ms <= 5000).ok_or(format!("{name}: invalid {v}")),
+        Err(_) => Ok(default),
+    }
+}
+
+impl Gate {
+    pub fn from_env(chunk_ms: u32, pool: Pool) -> Result<Self, String> {
+        let mode = match std::env::var("GATE_MODE").as_deref().unwrap_or("discard") {
+            "discard" | "" => Mode::Discard,
+            "buffer" => Mode::Buffer,
+            "always" => Mode::Always,
+            other => return Err(format!("GATE_MODE must be discard, buffer or always (got {other})")),
+        };
+        let chunks = |ms: u32| ms.div_ceil(chunk_ms.max(1)) as usize;
+        let onset_peak = std::env::var("INT_ONSET_PEAK").ok().and_then(|v| v.parse().ok()).unwrap_or(0.22);
+        let onset_min_chunks = std::env::var("INT_ONSET_MIN_CHUNKS").ok().and_then(|v| v.parse().ok()).unwrap_or(2);
+        Ok(Gate {
+            mode,
+            onset_peak,
+            onset_min_chunks,
+            loud: 0,
+            held: VecDeque::new(),
+            held_echo: false,
+            buffer_chunks: chunks(ms_from_env("GATE_BUFFER_MS", 300)?),
+            preroll_chunks: chunks(ms_from_env("PREROLL_MS", 200)?),
+            pool,
+        })
+    }
+
+    /// Pass `chunk` (with its `peak` level) through the gate; whatever is
+    /// released goes to `send`, oldest first.
+    pub fn feed(&mut self, chunk: Vec<i16>, peak: f32, speaking: bool, send: &mut impl FnMut(&[i16])) {
+        if !speaking || self.mode == Mode::Always {
+            // Echo of a reply that ended without an onset is not pre-roll
+            if self.held_echo && !speaking {
+                self.clear();
+            }
+            self.loud = 0;
+            self.release(chunk, send);
+            return;
+        }
+        if peak >= self.onset_peak {
+            self.loud += 1;
+        } else {
+            self.loud = 0;
+        }
+        if self.loud >= self.onset_min_chunks {
+            self.release(chunk, send);
+        } else {
+            self.hold(chunk, true);
+        }
+    }
+
+    /// Keep `chunk` back as pre-roll while the mic is muted.
+    pub fn muted(&mut self, chunk: Vec<i16>, speaking: bool) {
+        self.loud = 0;
+        self.hold(chunk, speaking);
+    }
+
+    /// Drop `chunk` and anything held (our own playback).
+    pub fn discard(&mut self, chunk: Vec<i16>) {
+        self.loud = 0;
+        self.clear();
+        self.pool.give(chunk);
+    }
+
+    fn hold(&mut self, chunk: Vec<i16>, echo: bool) {
+        let keep = match self.mode {
+            Mode::Buffer if echo => self.buffer_chunks.max(self.preroll_chunks),
+            _ => self.preroll_chunks,
+        };
+        while self.held.len() >= keep.max(1)
+            && let Some(old) = self.held.pop_front()
+        {
+            self.pool.give(old);
+        }
+        if keep == 0 {
+            self.pool.give(chunk);
+            return;
+        }
+        self.held_echo |= echo;
+        self.held.push_back(chunk);
+    }
+
+    /// Send what was held, then `chunk`.
+    fn release(&mut self, chunk: Vec<i16>, send: &mut impl FnMut(&[i16])) {
+        for held in std::mem::take(&mut self.held) {
+            send(&held);
+            self.pool.give(held);
+        }
+        self.held_echo = false;
+        send(&chunk);
+        self.pool.give(chunk);
+    }
+
+    fn clear(&mut self) {
+        for held in self.held.drain(..) {
+            self.pool.give(held);
+        }
+        self.held_echo = false;
+    }
+}
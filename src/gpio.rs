@@ -0,0 +1,204 @@
+// GPIO buttons and LEDs, for voice appliances without a terminal (a
+// Raspberry Pi or any Linux board with a GPIO character device).
+//
+// Buttons, by line offset on GPIO_CHIP (default /dev/gpiochip0):
+//   GPIO_PTT        hold to talk; the mic is muted otherwise, and pressing
+//                   it while a reply plays interrupts the reply first
+//   GPIO_MUTE       toggles mute
+//   GPIO_INTERRUPT  stops the reply
+// Buttons are taken to pull the line low when pressed (wired to ground,
+// with the pull-up set in the board's config, e.g. `gpio=17=ip,pu` on a
+// Pi); GPIO_ACTIVE_LOW=false is for buttons that pull it high.
+//
+// LEDs, by line offset:
+//   GPIO_LED_LISTENING  lit while the user is speaking
+//   GPIO_LED_SPEAKING   lit while the assistant speaks, blinking while it
+//                       thinks; both blink together after an error
+// The LEDs are one of the state indicators (see indicator.rs).
+//
+// Needs a build with `--features gpio` (Linux).
+
+use crate::control::Controls;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Button {
+    PushToTalk,
+    Mute,
+    Interrupt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Led {
+    Listening,
+    Speaking,
+}
+
+struct Config {
+    chip: String,
+    active_low: bool,
+    buttons: Vec<(Button, u32)>,
+    leds: Vec<(Led, u32)>,
+}
+
+fn line(var: &str) -> Result<Option<u32>, String> {
+    match std::env::var(var) {
+        Ok(v) if !v.trim().is_empty() => {
+            v.trim().parse().map(Some).map_err(|_| format!("{var}: not a GPIO line number: {v}"))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn config() -> Result<Option<Config>, String> {
+    let mut buttons = Vec::new();
+    for (button, var) in
+        [(Button::PushToTalk, "GPIO_PTT"), (Button::Mute, "GPIO_MUTE"), (Button::Interrupt, "GPIO_INTERRUPT")]
+    {
+        if let Some(n) = line(var)? {
+            buttons.push((button, n));
+        }
+    }
+    let mut leds = Vec::new();
+    for (led, var) in [(Led::Listening, "GPIO_LED_LISTENING"), (Led::Speaking, "GPIO_LED_SPEAKING")] {
+        if let Some(n) = line(var)? {
+            leds.push((led, n));
+        }
+    }
+    if buttons.is_empty() && leds.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Config {
+        chip: std::env::var("GPIO_CHIP").unwrap_or_else(|_| "/dev/gpiochip0".into()),
+        active_low: crate::env_flag("GPIO_ACTIVE_LOW", true),
+        buttons,
+        leds,
+    }))
+}
+
+/// Claim the configured lines and start following the buttons and driving
+/// the LEDs; returns the lines as text for the startup banner, or `None` when
+/// no GPIO line is configured. With a push-to-talk button the microphone
+/// starts muted.
+pub fn start(controls: &Controls) -> Result<Option<String>, String> {
+    let Some(cfg) = config()? else { return Ok(None) };
+    let summary = cfg
+        .buttons
+        .iter()
+        .map(|(b, n)| {
+            let what = match b {
+                Button::PushToTalk => "push-to-talk",
+                Button::Mute => "mute",
+                Button::Interrupt => "interrupt",
+            };
+            format!("{n} {what}")
+        })
+        .chain(cfg.leds.iter().map(|(l, n)| {
+            format!("{n} {} LED", if *l == Led::Listening { "listening" } else { "speaking" })
+        }))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let ptt = cfg.buttons.iter().any(|(b, _)| *b == Button::PushToTalk);
+    let chip = cfg.chip.clone();
+    let polarity = if cfg.active_low || cfg.buttons.is_empty() { "" } else { " (buttons active high)" };
+    claim(cfg, controls.clone())?;
+    if ptt {
+        controls.set_muted(Some(true));
+    }
+    Ok(Some(format!("{chip}: {summary}{polarity}")))
+}
+
+#[cfg(not(all(feature = "gpio", target_os = "linux")))]
+fn claim(_cfg: Config, _controls: Controls) -> Result<(), String> {
+    Err("GPIO_* need a build with `--features gpio` (Linux)".into())
+}
+
+#[cfg(all(feature = "gpio", target_os = "linux"))]
+fn claim(cfg: Config, controls: Controls) -> Result<(), String> {
+    use std::time::Duration;
+
+    use gpio_cdev::{Chip, EventRequestFlags, LineHandle, LineRequestFlags};
+
+    use crate::indicator::{self, Indication, Indicator};
+
+    // Contacts bounce for a few ms; the level is read once they have settled
+    const SETTLE: Duration = Duration::from_millis(20);
+
+    let mut chip = Chip::new(&cfg.chip).map_err(|e| format!("GPIO_CHIP {}: {e}", cfg.chip))?;
+    let mut input = LineRequestFlags::INPUT;
+    if cfg.active_low {
+        input |= LineRequestFlags::ACTIVE_LOW;
+    }
+    for (button, offset) in cfg.buttons {
+        let mut events = chip
+            .get_line(offset)
+            .and_then(|l| l.events(input, EventRequestFlags::BOTH_EDGES, "parlar"))
+            .map_err(|e| format!("GPIO line {offset}: {e}"))?;
+        let controls = controls.clone();
+        std::thread::spawn(move || {
+            let mut pressed = events.get_value().is_ok_and(|v| v == 1);
+            while events.get_event().is_ok() {
+                std::thread::sleep(SETTLE);
+                let now = events.get_value().is_ok_and(|v| v == 1);
+                if now == pressed {
+                    continue;
+                }
+                pressed = now;
+                match (button, pressed) {
+                    (Button::PushToTalk, true) => {
+                        if controls.state.lock().is_ok_and(|st| st.speaking()) {
+                            controls.interrupt();
+                        }
+                        controls.set_muted(Some(false));
+                    }
+                    (Button::PushToTalk, false) => {
+                        controls.set_muted(Some(true));
+                    }
+                    (Button::Mute, true) => {
+                        controls.set_muted(None);
+                    }
+                    (Button::Interrupt, true) => controls.interrupt(),
+                    _ => {}
+                }
+            }
+            crate::output::notice("gpio", &format!("line {offset}: no more events."));
+        });
+    }
+
+    if cfg.leds.is_empty() {
+        return Ok(());
+    }
+    struct Leds(Vec<(Led, LineHandle)>);
+
+    impl Indicator for Leds {
+        fn show(&mut self, _now: Indication) {}
+
+        fn tick(&mut self, now: Indication, tick: u32) {
+            let blink = tick % 4 < 2;
+            for (led, handle) in &self.0 {
+                let lit = match (led, now) {
+                    (Led::Listening, Indication::Listening) | (Led::Speaking, Indication::Speaking) => true,
+                    (Led::Speaking, Indication::Thinking) | (_, Indication::Error) => blink,
+                    _ => false,
+                };
+                let _ = handle.set_value(lit as u8);
+            }
+        }
+
+        fn off(&mut self) {
+            for (_, handle) in &self.0 {
+                let _ = handle.set_value(0);
+            }
+        }
+    }
+
+    let mut leds = Vec::new();
+    for (led, offset) in cfg.leds {
+        let handle = chip
+            .get_line(offset)
+            .and_then(|l| l.request(LineRequestFlags::OUTPUT, 0, "parlar"))
+            .map_err(|e| format!("GPIO line {offset}: {e}"))?;
+        leds.push((led, handle));
+    }
+    indicator::add(Box::new(Leds(leds)));
+    Ok(())
+}
@@ -0,0 +1,193 @@
+// gRPC server, for embedding parlar as a sidecar voice agent in services
+// written in other languages. Listens on GRPC_ADDR (e.g. `127.0.0.1:50051`)
+// and serves proto/parlar.proto: start/stop session, interrupt, transcript,
+// and bidirectional audio streaming. Needs a build with `--features grpc`.
+//
+// Streamed-in audio joins the microphone path (mute and gating apply);
+// every stream gets the assistant's audio as it arrives from the server.
+
+use std::sync::{Arc, OnceLock};
+
+use tokio::sync::broadcast;
+
+use crate::control::Controls;
+
+/// Takes PCM16 at the session rate into the microphone path.
+pub type MicFeed = Arc<dyn Fn(&[i16]) + Send + Sync>;
+
+/// What the audio streams carry out.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+enum Out {
+    Pcm(Vec<u8>),
+    Flush,
+}
+
+static OUT: OnceLock<broadcast::Sender<Out>> = OnceLock::new();
+
+/// Start serving on GRPC_ADDR; returns the address for the startup banner,
+/// or `None` when GRPC_ADDR is not set.
+pub fn start(controls: &Controls, mic: MicFeed) -> Result<Option<String>, String> {
+    let Some(addr) = std::env::var("GRPC_ADDR").ok().filter(|a| !a.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let addr: std::net::SocketAddr = addr.trim().parse().map_err(|e| format!("GRPC_ADDR: {e}"))?;
+    serve(addr, controls.clone(), mic)?;
+    Ok(Some(addr.to_string()))
+}
+
+/// Assistant audio, as it is queued for playback.
+pub fn assistant_audio(samples: &[i16]) {
+    if let Some(tx) = OUT.get()
+        && tx.receiver_count() > 0
+    {
+        let mut bytes = Vec::with_capacity(samples.len() * 2);
+        crate::pcm::le_bytes(samples, &mut bytes);
+        let _ = tx.send(Out::Pcm(bytes));
+    }
+}
+
+/// The reply was cut off; clients drop what they have not played yet.
+pub fn flush() {
+    if let Some(tx) = OUT.get() {
+        let _ = tx.send(Out::Flush);
+    }
+}
+
+#[cfg(not(feature = "grpc"))]
+fn serve(_addr: std::net::SocketAddr, _controls: Controls, _mic: MicFeed) -> Result<(), String> {
+    Err("GRPC_ADDR needs a build with `--features grpc`".into())
+}
+
+#[cfg(feature = "grpc")]
+fn serve(addr: std::net::SocketAddr, controls: Controls, mic: MicFeed) -> Result<(), String> {
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    // Bind here so a taken port is reported at startup
+    let listener = std::net::TcpListener::bind(addr).map_err(|e| format!("GRPC_ADDR {addr}: {e}"))?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let listener = tokio::net::TcpListener::from_std(listener).map_err(|e| e.to_string())?;
+    let (tx, _) = broadcast::channel(256);
+    let _ = OUT.set(tx);
+    let service = pb::parlar_server::ParlarServer::new(Service { controls, mic });
+    tokio::spawn(async move {
+        let served = tonic::transport::Server::builder()
+            .add_service(service)
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await;
+        if let Err(e) = served {
+            crate::output::notice("grpc", &format!("server stopped: {e}"));
+        }
+    });
+    Ok(())
+}
+
+#[cfg(feature = "grpc")]
+mod pb {
+    tonic::include_proto!("parlar.v1");
+}
+
+#[cfg(feature = "grpc")]
+struct Service {
+    controls: Controls,
+    mic: MicFeed,
+}
+
+#[cfg(feature = "grpc")]
+impl Service {
+    fn session_state(&self, model: Option<String>) -> pb::SessionState {
+        let st = self.controls.state.lock().unwrap();
+        pb::SessionState {
+            model: model.unwrap_or_else(|| st.model.clone()),
+            muted: st.muted,
+            sample_rate: self.controls.sr_hz,
+        }
+    }
+}
+
+#[cfg(feature = "grpc")]
+#[tonic::async_trait]
+impl pb::parlar_server::Parlar for Service {
+    async fn start_session(
+        &self,
+        request: tonic::Request<pb::StartSessionRequest>,
+    ) -> Result<tonic::Response<pb::SessionState>, tonic::Status> {
+        let model = Some(request.into_inner().model).filter(|m| !m.is_empty());
+        if model.is_some() && !crate::childsafe::settings_open("the model") {
+            return Err(tonic::Status::permission_denied("settings are locked (child-safe PIN)"));
+        }
+        let model = self.controls.restart(model.as_deref());
+        self.controls.set_muted(Some(false));
+        Ok(tonic::Response::new(self.session_state(Some(model))))
+    }
+
+    async fn stop_session(
+        &self,
+        _request: tonic::Request<pb::StopSessionRequest>,
+    ) -> Result<tonic::Response<pb::SessionState>, tonic::Status> {
+        let busy = {
+            let st = self.controls.state.lock().unwrap();
+            st.responses.busy()
+        };
+        if busy {
+            self.controls.interrupt();
+        }
+        self.controls.set_muted(Some(true));
+        Ok(tonic::Response::new(self.session_state(None)))
+    }
+
+    async fn interrupt(
+        &self,
+        _request: tonic::Request<pb::InterruptRequest>,
+    ) -> Result<tonic::Response<pb::InterruptResponse>, tonic::Status> {
+        self.controls.interrupt();
+        Ok(tonic::Response::new(pb::InterruptResponse {}))
+    }
+
+    async fn get_transcript(
+        &self,
+        _request: tonic::Request<pb::GetTranscriptRequest>,
+    ) -> Result<tonic::Response<pb::Transcript>, tonic::Status> {
+        let st = self.controls.state.lock().unwrap();
+        let turns = st
+            .history
+            .turns()
+            .map(|(role, text)| pb::Turn { role: role.to_string(), text: text.to_string() })
+            .collect();
+        Ok(tonic::Response::new(pb::Transcript { turns }))
+    }
+
+    type StreamAudioStream =
+        std::pin::Pin<Box<dyn tokio_stream::Stream<Item = Result<pb::AudioOut, tonic::Status>> + Send>>;
+
+    async fn stream_audio(
+        &self,
+        request: tonic::Request<tonic::Streaming<pb::AudioIn>>,
+    ) -> Result<tonic::Response<Self::StreamAudioStream>, tonic::Status> {
+        use tokio_stream::StreamExt;
+        use tokio_stream::wrappers::BroadcastStream;
+
+        let mut incoming = request.into_inner();
+        let mic = self.mic.clone();
+        tokio::spawn(async move {
+            let mut samples = Vec::new();
+            while let Ok(Some(msg)) = incoming.message().await {
+                samples.clear();
+                samples.extend(msg.pcm.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])));
+                mic(&samples);
+            }
+        });
+        let Some(out) = OUT.get() else {
+            return Err(tonic::Status::unavailable("server not started"));
+        };
+        // A client that falls behind skips audio rather than stalling the others
+        let outgoing = BroadcastStream::new(out.subscribe()).filter_map(|out| {
+            let kind = match out.ok()? {
+                Out::Pcm(pcm) => pb::audio_out::Kind::Pcm(pcm),
+                Out::Flush => pb::audio_out::Kind::Flush(pb::Flush {}),
+            };
+            Some(Ok(pb::AudioOut { kind: Some(kind) }))
+        });
+        Ok(tonic::Response::new(Box::pin(outgoing)))
+    }
+}
@@ -0,0 +1,82 @@
+// The finished turns of the conversation, replayed as conversation items
+// when the session moves to a new connection (session.rs). Each turn is
+// kept with the server item it came from, so a turn taken out of the
+// conversation (a voice command, noise, a filtered or blocked turn) is
+// taken out here as well, even when its transcript only arrives after the
+// item was deleted; a new session never hears what the old one did not.
+
+use std::collections::VecDeque;
+
+use serde_json::json;
+
+/// Turns kept for replay; only the most recent ones.
+const LEN: usize = 40;
+
+/// Prefix of the ids given to replayed items, so their echoes are not shown
+/// (or remembered) a second time.
+pub const REPLAY_ID_PREFIX: &str = "replay_";
+
+struct Turn {
+    role: &'static str,
+    text: String,
+    item_id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct History {
+    turns: VecDeque<Turn>,
+    // items taken out of the conversation, newest last
+    forgotten: VecDeque<String>,
+}
+
+impl History {
+    /// Keep a finished turn of server item `item_id` (when known).
+    pub fn remember(&mut self, role: &'static str, text: &str, item_id: Option<&str>) {
+        if text.trim().is_empty() || item_id.is_some_and(|id| self.forgotten.iter().any(|f| f == id)) {
+            return;
+        }
+        if self.turns.len() == LEN {
+            self.turns.pop_front();
+        }
+        self.turns.push_back(Turn { role, text: text.to_string(), item_id: item_id.map(str::to_string) });
+    }
+
+    /// Item `item_id` was deleted from the conversation: drop its turn, and
+    /// the transcript that may still come for it.
+    pub fn forget(&mut self, item_id: &str) {
+        self.turns.retain(|t| t.item_id.as_deref() != Some(item_id));
+        if self.forgotten.len() == LEN {
+            self.forgotten.pop_front();
+        }
+        self.forgotten.push_back(item_id.to_string());
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+
+    /// The turns as (role, text), oldest first.
+    pub fn turns(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.turns.iter().map(|t| (t.role, t.text.as_str()))
+    }
+
+    /// `conversation.item.create` events that rebuild the remembered turns.
+    pub fn replay_events(&self) -> Vec<String> {
+        self.turns()
+            .enumerate()
+            .map(|(n, (role, text))| {
+                let kind = if role == "user" { "input_text" } else { "text" };
+                json!({
+                    "type": "conversation.item.create",
+                    "item": {
+                        "id": format!("{REPLAY_ID_PREFIX}{n}"),
+                        "type": "message",
+                        "role": role,
+                        "content": [{"type": kind, "text": text}]
+                    }
+                })
+                .to_string()
+            })
+            .collect()
+    }
+}
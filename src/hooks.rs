@@ -0,0 +1,219 @@
+// Hooks: external commands and webhooks fired on conversation events, for
+// automations and dashboards outside parlar (note-taking apps, home
+// automation, speaking the answer on another device).
+//
+// RESPONSE_HOOK runs after each reply with the reply text on stdin and
+// metadata in the environment:
+//   PARLAR_RESPONSE_ID, PARLAR_RESPONSE_STATUS (completed, cancelled, …),
+//   PARLAR_MODEL, PARLAR_USER_TEXT (the turn it answers)
+//
+// Turn events go, as one JSON object each, to EVENT_HOOK (on stdin) and/or
+// WEBHOOK_URL (POST), in order; HOOK_EVENTS picks which:
+//   {"event": "speech_started", "time": "…"}
+//   {"event": "transcript", "time": "…", "item_id": "…", "text": "…"}
+//   {"event": "interrupted", "time": "…", "by": "user" | "speech" | "keyword"}
+//   {"event": "response_done", "time": "…", "response_id": "…", "status": "…", "text": "…"}
+//
+// Transcripts and replies are redacted (redact.rs) before they leave, as for
+// every other sink, and a hook command still running after
+// HOOK_TIMEOUT_SECS is killed.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+
+use crate::{net, output, redact};
+
+const EVENTS: [&str; 4] = ["speech_started", "transcript", "interrupted", "response_done"];
+
+static RESPONSE_HOOK: OnceLock<Option<String>> = OnceLock::new();
+static TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+struct EventHooks {
+    events: Vec<String>,
+    tx: UnboundedSender<serde_json::Value>,
+}
+
+static EVENT_HOOKS: OnceLock<Option<EventHooks>> = OnceLock::new();
+
+/// Read the hook settings; call once at startup, inside the runtime.
+pub fn init() -> Result<(), String> {
+    let cmd = std::env::var("RESPONSE_HOOK").ok().filter(|c| !c.trim().is_empty());
+    let _ = RESPONSE_HOOK.set(cmd);
+    let secs = match std::env::var("HOOK_TIMEOUT_SECS") {
+        Ok(v) => v.trim().parse().map_err(|_| format!("HOOK_TIMEOUT_SECS={v} is not a number"))?,
+        Err(_) => 10,
+    };
+    let _ = TIMEOUT.set(Duration::from_secs(secs));
+
+    let command = std::env::var("EVENT_HOOK").ok().filter(|c| !c.trim().is_empty());
+    let webhook = match std::env::var("WEBHOOK_URL").ok().filter(|u| !u.trim().is_empty()) {
+        Some(u) => {
+            let url = url::Url::parse(u.trim()).map_err(|e| format!("WEBHOOK_URL: {e}"))?;
+            let host = url.host_str().unwrap_or("").to_string();
+            let builder = reqwest::Client::builder().timeout(Duration::from_secs(5));
+            let client = net::http_client(builder, &host).map_err(|e| format!("WEBHOOK_URL: {e}"))?;
+            Some((client, url))
+        }
+        None => None,
+    };
+    if command.is_none() && webhook.is_none() {
+        let _ = EVENT_HOOKS.set(None);
+        return Ok(());
+    }
+    let events = match std::env::var("HOOK_EVENTS") {
+        Ok(list) if !list.trim().is_empty() => {
+            let events: Vec<String> = list.split(',').map(|e| e.trim().to_string()).filter(|e| !e.is_empty()).collect();
+            if let Some(bad) = events.iter().find(|e| !EVENTS.contains(&e.as_str())) {
+                return Err(format!("HOOK_EVENTS: unknown event {bad} (use {})", EVENTS.join(", ")));
+            }
+            events
+        }
+        _ => EVENTS.iter().map(|e| e.to_string()).collect(),
+    };
+
+    // One worker delivers events in the order they happened
+    let (tx, mut rx) = unbounded_channel::<serde_json::Value>();
+    tokio::spawn(async move {
+        while let Some(payload) = rx.recv().await {
+            let body = payload.to_string();
+            if let Some(cmd) = &command {
+                let (cmd, body) = (cmd.clone(), body.clone());
+                if let Ok(Err(e)) = tokio::task::spawn_blocking(move || run(&cmd, &body, &[])).await {
+                    output::notice("hook", &format!("EVENT_HOOK: {e}"));
+                }
+            }
+            if let Some((client, url)) = &webhook {
+                let sent = client
+                    .post(url.clone())
+                    .header("Content-Type", "application/json")
+                    .body(body)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status());
+                if let Err(e) = sent {
+                    output::notice("hook", &format!("WEBHOOK_URL: {e}"));
+                }
+            }
+        }
+    });
+    let _ = EVENT_HOOKS.set(Some(EventHooks { events, tx }));
+    Ok(())
+}
+
+/// Queue `event` with `fields` for EVENT_HOOK and WEBHOOK_URL.
+fn emit(event: &str, fields: serde_json::Value) {
+    let Some(Some(hooks)) = EVENT_HOOKS.get() else { return };
+    if !hooks.events.iter().any(|e| e == event) {
+        return;
+    }
+    let mut payload = json!({ "event": event, "time": chrono::Local::now().to_rfc3339() });
+    if let (Some(p), serde_json::Value::Object(f)) = (payload.as_object_mut(), fields) {
+        p.extend(f);
+    }
+    let _ = hooks.tx.send(payload);
+}
+
+pub fn speech_started() {
+    emit("speech_started", json!({}));
+}
+
+pub fn transcript(item_id: &str, text: &str) {
+    let mut payload = json!({ "item_id": item_id, "text": redact::apply(text) });
+    if let Some(name) = crate::speaker::current() {
+        payload["speaker"] = json!(name);
+    }
+    emit("transcript", payload);
+}
+
+/// The assistant was cut off: `by` is `user` (key, command or hotkey),
+/// `speech` (talking over it) or `keyword`.
+pub fn interrupted(by: &str) {
+    emit("interrupted", json!({ "by": by }));
+}
+
+/// Text of a `response.done` event: text parts and audio transcripts.
+pub fn response_text(evt: &serde_json::Value) -> String {
+    let mut text = String::new();
+    for item in evt["response"]["output"].as_array().into_iter().flatten() {
+        let parts = item["content"].as_array().map(Vec::as_slice).unwrap_or_default();
+        // With both modalities an item has the text and the audio's transcript; take one
+        let written: String = parts.iter().filter_map(|p| p["text"].as_str()).collect();
+        if written.is_empty() {
+            text.extend(parts.iter().filter_map(|p| p["transcript"].as_str()));
+        } else {
+            text.push_str(&written);
+        }
+    }
+    text
+}
+
+/// Report a finished reply and run RESPONSE_HOOK for it, off the calling thread.
+pub fn response_done(evt: &serde_json::Value, model: &str, user_text: &str) {
+    let text = redact::apply(&response_text(evt)).into_owned();
+    emit(
+        "response_done",
+        json!({
+            "response_id": evt["response"]["id"],
+            "status": evt["response"]["status"],
+            "text": text,
+        }),
+    );
+    let Some(Some(cmd)) = RESPONSE_HOOK.get() else { return };
+    if text.trim().is_empty() {
+        return;
+    }
+    let env = [
+        ("PARLAR_RESPONSE_ID", evt["response"]["id"].as_str().unwrap_or("").to_string()),
+        ("PARLAR_RESPONSE_STATUS", evt["response"]["status"].as_str().unwrap_or("").to_string()),
+        ("PARLAR_MODEL", model.to_string()),
+        ("PARLAR_USER_TEXT", redact::apply(user_text).into_owned()),
+    ];
+    let cmd = cmd.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = run(&cmd, &text, &env) {
+            output::notice("hook", &format!("RESPONSE_HOOK: {e}"));
+        }
+    });
+}
+
+/// Run a hook command with `stdin`; killed when it has not exited within HOOK_TIMEOUT_SECS.
+fn run(cmd: &str, stdin: &str, env: &[(&str, String)]) -> Result<(), String> {
+    let limit = TIMEOUT.get().copied().unwrap_or(Duration::from_secs(10));
+    let deadline = Instant::now() + limit;
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = Command::new(shell)
+        .arg(flag)
+        .arg(cmd)
+        .envs(env.iter().map(|(k, v)| (*k, v)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    // A hook that never reads its input must not hold up the deadline
+    if let Some(mut pipe) = child.stdin.take() {
+        let stdin = stdin.to_string();
+        std::thread::spawn(move || {
+            let _ = pipe.write_all(stdin.as_bytes());
+        });
+    }
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("killed after {} s", limit.as_secs()));
+            }
+            None => std::thread::sleep(Duration::from_millis(20)),
+        }
+    };
+    if !status.success() {
+        return Err(format!("exit status {status}"));
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+// Global hotkeys: push-to-talk, mute and interrupt while the terminal is not
+// focused (a Stream Deck or similar can send these key combinations too).
+//
+// Bindings come from HOTKEY_PTT, HOTKEY_MUTE and HOTKEY_INTERRUPT, e.g.
+// `ctrl+alt+space`. Needs a build with `--features hotkeys`; X11 and Windows.
+
+use crate::control::Controls;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    PushToTalk,
+    Mute,
+    Interrupt,
+}
+
+fn bindings() -> Vec<(Action, String)> {
+    [
+        (Action::PushToTalk, "HOTKEY_PTT"),
+        (Action::Mute, "HOTKEY_MUTE"),
+        (Action::Interrupt, "HOTKEY_INTERRUPT"),
+    ]
+    .into_iter()
+    .filter_map(|(action, var)| {
+        let spec = std::env::var(var).ok()?;
+        (!spec.trim().is_empty()).then(|| (action, spec.trim().to_string()))
+    })
+    .collect()
+}
+
+/// Register the configured hotkeys; returns the bindings as text for the
+/// startup banner, or `None` when no hotkey is configured. With a
+/// push-to-talk key the microphone starts muted.
+pub fn start(controls: &Controls) -> Result<Option<String>, String> {
+    let bindings = bindings();
+    if bindings.is_empty() {
+        return Ok(None);
+    }
+    let summary = bindings
+        .iter()
+        .map(|(action, spec)| {
+            let what = match action {
+                Action::PushToTalk => "push-to-talk",
+                Action::Mute => "mute",
+                Action::Interrupt => "interrupt",
+            };
+            format!("{spec} {what}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    register(bindings.clone(), controls.clone())?;
+    if bindings.iter().any(|(a, _)| *a == Action::PushToTalk) {
+        controls.set_muted(Some(true));
+    }
+    Ok(Some(summary))
+}
+
+#[cfg(not(feature = "hotkeys"))]
+fn register(_bindings: Vec<(Action, String)>, _controls: Controls) -> Result<(), String> {
+    Err("HOTKEY_* need a build with `--features hotkeys`".into())
+}
+
+#[cfg(all(feature = "hotkeys", target_os = "macos"))]
+fn register(_bindings: Vec<(Action, String)>, _controls: Controls) -> Result<(), String> {
+    Err("global hotkeys are not supported on macOS (they need the main thread's event loop)".into())
+}
+
+#[cfg(all(feature = "hotkeys", not(target_os = "macos")))]
+fn register(bindings: Vec<(Action, String)>, controls: Controls) -> Result<(), String> {
+    use global_hotkey::hotkey::HotKey;
+    use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+
+    // Windows delivers hotkeys to the message loop of the registering thread,
+    // so the manager lives on a thread of its own
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let registered = (|| {
+            let manager = GlobalHotKeyManager::new().map_err(|e| e.to_string())?;
+            let mut ids = Vec::new();
+            for (action, spec) in &bindings {
+                let key: HotKey = spec.parse().map_err(|e| format!("{spec}: {e}"))?;
+                manager.register(key).map_err(|e| format!("{spec}: {e}"))?;
+                ids.push((key.id(), *action));
+            }
+            Ok::<_, String>((manager, ids))
+        })();
+        let (_manager, ids) = match registered {
+            Ok(r) => r,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        GlobalHotKeyEvent::set_event_handler(Some(move |ev: GlobalHotKeyEvent| {
+            let Some(&(_, action)) = ids.iter().find(|(id, _)| *id == ev.id) else { return };
+            match (action, ev.state) {
+                (Action::PushToTalk, HotKeyState::Pressed) => {
+                    controls.set_muted(Some(false));
+                }
+                (Action::PushToTalk, HotKeyState::Released) => {
+                    controls.set_muted(Some(true));
+                }
+                (Action::Mute, HotKeyState::Pressed) => {
+                    controls.set_muted(None);
+                }
+                (Action::Interrupt, HotKeyState::Pressed) => controls.interrupt(),
+                _ => {}
+            }
+        }));
+        let _ = ready_tx.send(Ok(()));
+        pump();
+    });
+    ready_rx.recv().map_err(|_| "hotkey thread exited".to_string())?
+}
+
+#[cfg(all(feature = "hotkeys", windows))]
+fn pump() {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{DispatchMessageW, GetMessageW, MSG, TranslateMessage};
+    // SAFETY: a plain Win32 message loop on this thread with a zeroed MSG
+    unsafe {
+        let mut msg: MSG = std::mem::zeroed();
+        while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
+
+// X11 listens on its own thread; just keep the manager alive
+#[cfg(all(feature = "hotkeys", not(any(windows, target_os = "macos"))))]
+fn pump() {
+    loop {
+        std::thread::park();
+    }
+}
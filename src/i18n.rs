@@ -0,0 +1,451 @@
+// Interface language. The setup wizard, the startup and connection
+// messages, the control notices (mute, interrupts, tool questions, model
+// and session changes) and the dates and times parlar shows are looked up
+// here by their English text; UI_LANG picks the language (en, es or de),
+// and without it the locale (LC_ALL, LC_MESSAGES, then LANG, e.g.
+// `es_ES.UTF-8`) does, falling back to English. A string a catalog lacks is
+// shown in English. The conversation language (LANGUAGE) is separate, as
+// are the notice tags (`[mute]`) and JSON mode's event names, which scripts
+// match on. Diagnostics (filters, plugins, hooks, scripts, JSON command
+// errors) stay in English.
+//
+// To add a language, add it to LANGS with a catalog of (English, translated)
+// pairs and its date names; `{name}` placeholders are kept as written.
+
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Datelike, Local};
+
+struct Lang {
+    code: &'static str,
+    catalog: &'static [(&'static str, &'static str)],
+    weekdays: [&'static str; 7],
+    months: [&'static str; 12],
+    // date and time: {wd} {d} {mon} {y} {m} {time}
+    date: &'static str,
+    date_time: &'static str,
+}
+
+const LANGS: &[Lang] = &[
+    Lang {
+        code: "en",
+        catalog: &[],
+        weekdays: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+        months: ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"],
+        date: "{y}-{m}-{d}",
+        date_time: "{wd} {d} {mon} {time}",
+    },
+    Lang {
+        code: "es",
+        catalog: ES,
+        weekdays: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+        months: ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic"],
+        date: "{d}/{m}/{y}",
+        date_time: "{wd} {d} {mon} {time}",
+    },
+    Lang {
+        code: "de",
+        catalog: DE,
+        weekdays: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+        months: ["Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez"],
+        date: "{d}.{m}.{y}",
+        date_time: "{wd}, {d}. {mon} {time}",
+    },
+];
+
+static LANG: OnceLock<&'static Lang> = OnceLock::new();
+
+fn lang() -> &'static Lang {
+    LANG.get().copied().unwrap_or(&LANGS[0])
+}
+
+/// Read UI_LANG, or the locale; call once at startup.
+pub fn init() -> Result<(), String> {
+    let find = |code: &str| LANGS.iter().find(|l| l.code == code);
+    let chosen = match std::env::var("UI_LANG").ok().filter(|v| !v.trim().is_empty()) {
+        Some(v) => {
+            let codes: Vec<&str> = LANGS.iter().map(|l| l.code).collect();
+            find(&v.trim().to_ascii_lowercase())
+                .ok_or_else(|| format!("UI_LANG: {} (use {})", v.trim(), codes.join(", ")))?
+        }
+        // `es_ES.UTF-8`, `de_AT@euro`; C and POSIX are English
+        None => ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|v| !v.trim().is_empty()))
+            .and_then(|locale| {
+                let code = locale.split(['_', '.', '@', '-']).next().unwrap_or("").to_ascii_lowercase();
+                find(&code)
+            })
+            .unwrap_or(&LANGS[0]),
+    };
+    let _ = LANG.set(chosen);
+    Ok(())
+}
+
+/// `text` in the interface language.
+pub fn tr(text: &'static str) -> &'static str {
+    lang().catalog.iter().find(|(en, _)| *en == text).map_or(text, |(_, t)| t)
+}
+
+/// `text` in the interface language with its `{name}` placeholders filled in.
+pub fn trf(text: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter().fold(tr(text).to_string(), |out, (name, value)| out.replace(&format!("{{{name}}}"), &value.to_string()))
+}
+
+/// Whether a typed answer means yes (`y`, or the language's own word).
+pub fn yes(answer: &str) -> bool {
+    let answer = answer.trim().to_lowercase();
+    answer.starts_with('y') || answer.starts_with(tr("y"))
+}
+
+fn fill(pattern: &str, at: &DateTime<Local>) -> String {
+    let l = lang();
+    pattern
+        .replace("{wd}", l.weekdays[at.weekday().num_days_from_monday() as usize])
+        .replace("{mon}", l.months[at.month0() as usize])
+        .replace("{d}", &at.day().to_string())
+        .replace("{m}", &format!("{:02}", at.month()))
+        .replace("{y}", &at.year().to_string())
+        .replace("{time}", &at.format("%H:%M").to_string())
+}
+
+/// A date as written in the interface language (2026-10-16, 16/10/2026, 16.10.2026).
+pub fn date(at: &DateTime<Local>) -> String {
+    fill(lang().date, at)
+}
+
+/// A weekday, date and time (Fri 16 Oct 14:05, vie 16 oct 14:05, Fr, 16. Okt 14:05).
+pub fn date_time(at: &DateTime<Local>) -> String {
+    fill(lang().date_time, at)
+}
+
+const ES: &[(&str, &str)] = &[
+    // setup wizard
+    ("y", "s"),
+    ("[Y/n]", "[S/n]"),
+    ("[y/N]", "[s/N]"),
+    (
+        "parlar setup — press Enter to accept the [default] at each step.",
+        "Configuración de parlar — pulse Intro para aceptar el [valor predeterminado] en cada paso.",
+    ),
+    ("Setup aborted: an API key is required.", "Configuración cancelada: hace falta una clave de API."),
+    ("Saved {path}. Run `parlar` to start talking.", "Guardado {path}. Ejecute `parlar` para empezar a hablar."),
+    ("Could not write the config: {error}", "No se pudo escribir la configuración: {error}"),
+    ("An API key is already configured. Keep it?", "Ya hay una clave de API configurada. ¿Conservarla?"),
+    ("OpenAI API key (sk-...):", "Clave de API de OpenAI (sk-...):"),
+    ("Checking…", "Comprobando…"),
+    ("ok, {model} is available.", "correcto, {model} está disponible."),
+    (
+        "rejected (401). Try again, or press Enter to abort.",
+        "rechazada (401). Inténtelo de nuevo o pulse Intro para cancelar.",
+    ),
+    (
+        "the key works, but {model} is not available (set REALTIME_MODEL).",
+        "la clave funciona, pero {model} no está disponible (configure REALTIME_MODEL).",
+    ),
+    ("could not verify (HTTP {code}); keeping it.", "no se pudo comprobar (HTTP {code}); se conserva."),
+    ("could not verify ({error}); keeping it.", "no se pudo comprobar ({error}); se conserva."),
+    ("Stored in the system keyring.", "Guardada en el llavero del sistema."),
+    (
+        "Keyring unavailable ({error}); add OPENAI_API_KEY=… to .env instead.",
+        "Llavero no disponible ({error}); añada OPENAI_API_KEY=… a .env.",
+    ),
+    ("Microphone", "Micrófono"),
+    ("Speaker", "Altavoz"),
+    ("{kind}: no devices found; skipping.", "{kind}: no se encontraron dispositivos; se omite."),
+    ("{kind} devices:", "{kind} — dispositivos:"),
+    ("system default", "predeterminado del sistema"),
+    ("could not open the device: {error}", "no se pudo abrir el dispositivo: {error}"),
+    ("Use this microphone?", "¿Usar este micrófono?"),
+    ("Use this speaker?", "¿Usar este altavoz?"),
+    ("Say something ({secs} s):", "Diga algo ({secs} s):"),
+    (
+        "No signal picked up — check the mic is unmuted and permitted.",
+        "No se captó señal: compruebe que el micrófono no está silenciado y tiene permiso.",
+    ),
+    ("Playing a test tone…", "Reproduciendo un tono de prueba…"),
+    (
+        "Voices (type a number to hear it, Enter to keep the last one played):",
+        "Voces (escriba un número para oírla, Intro para quedarse con la última):",
+    ),
+    ("Voice [{voice}]:", "Voz [{voice}]:"),
+    ("Pick 1–{n}.", "Elija 1–{n}."),
+    ("playback failed: {error}", "falló la reproducción: {error}"),
+    ("could not fetch a sample ({error})", "no se pudo obtener una muestra ({error})"),
+    // startup and connection
+    ("Profile: {profile}", "Perfil: {profile}"),
+    (
+        "Service mode: no terminal controls; stop with SIGTERM.",
+        "Modo servicio: sin controles de terminal; se detiene con SIGTERM.",
+    ),
+    (
+        "Text only: type a message and press Enter; /quit exits.",
+        "Solo texto: escriba un mensaje y pulse Intro; /quit sale.",
+    ),
+    (
+        "Commands (type, then Enter): /interrupt  /resume  /mute  /language  /quit; other lines are sent as messages",
+        "Comandos (escriba y pulse Intro): /interrupt  /resume  /mute  /language  /quit; las demás líneas se envían como mensajes",
+    ),
+    ("Commands: {commands}", "Comandos: {commands}"),
+    ("Interrupt", "Interrumpir"),
+    ("Resume", "Reanudar"),
+    ("Mute", "Silenciar"),
+    ("Verbosity", "Extensión"),
+    ("Timeline", "Cronología"),
+    ("Traffic", "Tráfico"),
+    ("Next model", "Siguiente modelo"),
+    ("Quit", "Salir"),
+    ("Connecting to OpenAI Realtime…", "Conectando con OpenAI Realtime…"),
+    (
+        "Connected — meeting mode: transcribing to {path}, summary every {secs}s; press Q to quit.",
+        "Conectado — modo reunión: transcribiendo en {path}, resumen cada {secs} s; pulse Q para salir.",
+    ),
+    ("Connected — reading commands from stdin.", "Conectado — leyendo comandos de la entrada estándar."),
+    (
+        "Connected — text only; type a message and press Enter, /quit to exit.",
+        "Conectado — solo texto; escriba un mensaje y pulse Intro, /quit para salir.",
+    ),
+    ("Connected — speak to talk; press {keys}.", "Conectado — hable para conversar; pulse {keys}."),
+    ("{key} to interrupt", "{key} para interrumpir"),
+    ("{key} to mute", "{key} para silenciar"),
+    ("{key} to quit", "{key} para salir"),
+    ("Connection closed.", "Conexión cerrada."),
+    ("Quit.", "Fin."),
+    ("Traffic: {summary}", "Tráfico: {summary}"),
+    ("SIGHUP: reloading {path}.", "SIGHUP: recargando {path}."),
+    ("SIGHUP: no .env file to reload.", "SIGHUP: no hay archivo .env que recargar."),
+    // controls
+    ("a new reply is under way.", "ya hay una respuesta nueva en curso."),
+    ("playing the rest of the interrupted reply.", "reproduciendo el resto de la respuesta interrumpida."),
+    ("off (RESUME_WINDOW_MS=0).", "desactivado (RESUME_WINDOW_MS=0)."),
+    ("nothing to resume.", "nada que reanudar."),
+    ("too late, the interrupted reply was dropped.", "demasiado tarde, la respuesta interrumpida se descartó."),
+    ("microphone muted.", "micrófono silenciado."),
+    ("microphone live.", "micrófono activo."),
+    ("not changed: {error}", "sin cambios: {error}"),
+    ("switched to {language}.", "cambiado a {language}."),
+    (
+        "you seem to be speaking {language}; {how} to switch ({code}).",
+        "parece que habla {language}; {how} para cambiar ({code}).",
+    ),
+    ("press {key}", "pulse {key}"),
+    ("type /language", "escriba /language"),
+    ("send set_language", "envíe set_language"),
+    ("already {voice}.", "ya es {voice}."),
+    ("switching to {voice}…", "cambiando a {voice}…"),
+    (
+        "only one model configured; list several in REALTIME_MODELS.",
+        "solo hay un modelo configurado; indique varios en REALTIME_MODELS.",
+    ),
+    ("already on {model}.", "ya está en {model}."),
+    ("switching to {model}…", "cambiando a {model}…"),
+    ("reconnecting…", "reconectando…"),
+    (
+        "replies wait {question} ms after a question, {ms} ms after a statement, {long} ms otherwise.",
+        "las respuestas esperan {question} ms tras una pregunta, {ms} ms tras una afirmación y {long} ms en otro caso.",
+    ),
+    ("starting a new conversation…", "empezando una conversación nueva…"),
+    ("reload failed: {error}", "falló la recarga: {error}"),
+    ("turn detection updated.", "detección de turnos actualizada."),
+    ("reload rejected: {error}", "recarga rechazada: {error}"),
+    ("instructions updated.", "instrucciones actualizadas."),
+    ("instructions not reloaded: {error}", "instrucciones no recargadas: {error}"),
+    ("no command matches \"{word}\".", "ningún comando coincide con \"{word}\"."),
+    ("{n} turns written to {path}.", "{n} turnos escritos en {path}."),
+    ("cannot write the transcript: {error}", "no se puede escribir la transcripción: {error}"),
+    ("not switched: {error}", "sin cambiar: {error}"),
+    ("usage: /search words", "uso: /search palabras"),
+    ("nothing matches \"{query}\".", "nada coincide con \"{query}\"."),
+    ("set for {when}: {text}", "programado para {when}: {text}"),
+    ("{label}: set for {time}", "{label}: programado para las {time}"),
+    ("{label}: time is up.", "{label}: se acabó el tiempo."),
+    // interrupts, tools, turns and the session
+    ("assistant canceled.", "asistente interrumpido."),
+    ("assistant canceled (keyword).", "asistente interrumpido (palabra clave)."),
+    ("the model called an unknown tool {name}.", "el modelo llamó a una herramienta desconocida, {name}."),
+    ("running {name}…", "ejecutando {name}…"),
+    ("no answer; {name} not run.", "sin respuesta; {name} no se ejecutó."),
+    ("type /yes or /no", "escriba /yes o /no"),
+    ("say yes or no, or press Y / N", "diga sí o no, o pulse Y / N"),
+    ("press Y to run it, N to decline", "pulse Y para ejecutarla, N para rechazarla"),
+    ("run {name} with {args}? ({how})", "¿ejecutar {name} con {args}? ({how})"),
+    ("approved.", "aprobada."),
+    ("declined.", "rechazada."),
+    ("tool calls stopped.", "llamadas a herramientas detenidas."),
+    ("no question is waiting.", "no hay ninguna pregunta pendiente."),
+    ("a reply is under way; turn not answered.", "hay una respuesta en curso; turno sin contestar."),
+    ("PIN entry cancelled.", "introducción del PIN cancelada."),
+    ("type the PIN and press Enter.", "escriba el PIN y pulse Intro."),
+    ("renewed ({n} turns carried over).", "renovada ({n} turnos conservados)."),
+    ("now on {model} ({n} turns carried over).", "ahora con {model} ({n} turnos conservados)."),
+    ("expired and could not be renewed: {error}", "caducó y no se pudo renovar: {error}"),
+    ("renewal failed, retrying in 30s: {error}", "falló la renovación, nuevo intento en 30 s: {error}"),
+    ("cannot switch to {model}: {error}", "no se puede cambiar a {model}: {error}"),
+    ("expired; renewing.", "caducada; renovando."),
+];
+
+const DE: &[(&str, &str)] = &[
+    // setup wizard
+    ("y", "j"),
+    ("[Y/n]", "[J/n]"),
+    ("[y/N]", "[j/N]"),
+    (
+        "parlar setup — press Enter to accept the [default] at each step.",
+        "parlar-Einrichtung — Enter übernimmt bei jedem Schritt die [Vorgabe].",
+    ),
+    ("Setup aborted: an API key is required.", "Einrichtung abgebrochen: Ein API-Schlüssel wird benötigt."),
+    ("Saved {path}. Run `parlar` to start talking.", "{path} gespeichert. Mit `parlar` geht es los."),
+    ("Could not write the config: {error}", "Die Konfiguration konnte nicht geschrieben werden: {error}"),
+    ("An API key is already configured. Keep it?", "Es ist bereits ein API-Schlüssel eingerichtet. Behalten?"),
+    ("OpenAI API key (sk-...):", "OpenAI-API-Schlüssel (sk-...):"),
+    ("Checking…", "Prüfe…"),
+    ("ok, {model} is available.", "ok, {model} ist verfügbar."),
+    (
+        "rejected (401). Try again, or press Enter to abort.",
+        "abgelehnt (401). Noch einmal versuchen oder mit Enter abbrechen.",
+    ),
+    (
+        "the key works, but {model} is not available (set REALTIME_MODEL).",
+        "der Schlüssel funktioniert, aber {model} ist nicht verfügbar (REALTIME_MODEL setzen).",
+    ),
+    ("could not verify (HTTP {code}); keeping it.", "konnte nicht geprüft werden (HTTP {code}); wird behalten."),
+    ("could not verify ({error}); keeping it.", "konnte nicht geprüft werden ({error}); wird behalten."),
+    ("Stored in the system keyring.", "Im Schlüsselbund des Systems gespeichert."),
+    (
+        "Keyring unavailable ({error}); add OPENAI_API_KEY=… to .env instead.",
+        "Schlüsselbund nicht verfügbar ({error}); stattdessen OPENAI_API_KEY=… in .env eintragen.",
+    ),
+    ("Microphone", "Mikrofon"),
+    ("Speaker", "Lautsprecher"),
+    ("{kind}: no devices found; skipping.", "{kind}: keine Geräte gefunden; übersprungen."),
+    ("{kind} devices:", "{kind} — Geräte:"),
+    ("system default", "Systemvorgabe"),
+    ("could not open the device: {error}", "das Gerät konnte nicht geöffnet werden: {error}"),
+    ("Use this microphone?", "Dieses Mikrofon verwenden?"),
+    ("Use this speaker?", "Diesen Lautsprecher verwenden?"),
+    ("Say something ({secs} s):", "Sagen Sie etwas ({secs} s):"),
+    (
+        "No signal picked up — check the mic is unmuted and permitted.",
+        "Kein Signal — ist das Mikrofon eingeschaltet und freigegeben?",
+    ),
+    ("Playing a test tone…", "Spiele einen Testton…"),
+    (
+        "Voices (type a number to hear it, Enter to keep the last one played):",
+        "Stimmen (Nummer eingeben zum Anhören, Enter behält die zuletzt gespielte):",
+    ),
+    ("Voice [{voice}]:", "Stimme [{voice}]:"),
+    ("Pick 1–{n}.", "Bitte 1–{n} wählen."),
+    ("playback failed: {error}", "Wiedergabe fehlgeschlagen: {error}"),
+    ("could not fetch a sample ({error})", "Hörprobe nicht abrufbar ({error})"),
+    // startup and connection
+    ("Profile: {profile}", "Profil: {profile}"),
+    (
+        "Service mode: no terminal controls; stop with SIGTERM.",
+        "Dienstmodus: keine Tastensteuerung; Beenden mit SIGTERM.",
+    ),
+    (
+        "Text only: type a message and press Enter; /quit exits.",
+        "Nur Text: Nachricht eingeben und Enter drücken; /quit beendet.",
+    ),
+    (
+        "Commands (type, then Enter): /interrupt  /resume  /mute  /language  /quit; other lines are sent as messages",
+        "Befehle (eingeben, dann Enter): /interrupt  /resume  /mute  /language  /quit; andere Zeilen werden als Nachricht gesendet",
+    ),
+    ("Commands: {commands}", "Befehle: {commands}"),
+    ("Interrupt", "Unterbrechen"),
+    ("Resume", "Fortsetzen"),
+    ("Mute", "Stumm"),
+    ("Verbosity", "Ausführlichkeit"),
+    ("Timeline", "Zeitleiste"),
+    ("Traffic", "Datenverkehr"),
+    ("Next model", "Nächstes Modell"),
+    ("Quit", "Beenden"),
+    ("Connecting to OpenAI Realtime…", "Verbinde mit OpenAI Realtime…"),
+    (
+        "Connected — meeting mode: transcribing to {path}, summary every {secs}s; press Q to quit.",
+        "Verbunden — Besprechungsmodus: Mitschrift in {path}, Zusammenfassung alle {secs} s; Q beendet.",
+    ),
+    ("Connected — reading commands from stdin.", "Verbunden — lese Befehle von der Standardeingabe."),
+    (
+        "Connected — text only; type a message and press Enter, /quit to exit.",
+        "Verbunden — nur Text; Nachricht eingeben und Enter drücken, /quit beendet.",
+    ),
+    ("Connected — speak to talk; press {keys}.", "Verbunden — einfach sprechen; {keys}."),
+    ("{key} to interrupt", "{key} unterbricht"),
+    ("{key} to mute", "{key} schaltet stumm"),
+    ("{key} to quit", "{key} beendet"),
+    ("Connection closed.", "Verbindung geschlossen."),
+    ("Quit.", "Beendet."),
+    ("Traffic: {summary}", "Datenverkehr: {summary}"),
+    ("SIGHUP: reloading {path}.", "SIGHUP: lade {path} neu."),
+    ("SIGHUP: no .env file to reload.", "SIGHUP: keine .env-Datei zum Neuladen."),
+    // controls
+    ("a new reply is under way.", "eine neue Antwort läuft bereits."),
+    ("playing the rest of the interrupted reply.", "spiele den Rest der unterbrochenen Antwort."),
+    ("off (RESUME_WINDOW_MS=0).", "aus (RESUME_WINDOW_MS=0)."),
+    ("nothing to resume.", "nichts fortzusetzen."),
+    ("too late, the interrupted reply was dropped.", "zu spät, die unterbrochene Antwort wurde verworfen."),
+    ("microphone muted.", "Mikrofon stumm."),
+    ("microphone live.", "Mikrofon an."),
+    ("not changed: {error}", "nicht geändert: {error}"),
+    ("switched to {language}.", "umgestellt auf {language}."),
+    (
+        "you seem to be speaking {language}; {how} to switch ({code}).",
+        "Sie sprechen wohl {language}; zum Umstellen {how} ({code}).",
+    ),
+    ("press {key}", "{key} drücken"),
+    ("type /language", "/language eingeben"),
+    ("send set_language", "set_language senden"),
+    ("already {voice}.", "bereits {voice}."),
+    ("switching to {voice}…", "wechsle zu {voice}…"),
+    (
+        "only one model configured; list several in REALTIME_MODELS.",
+        "nur ein Modell eingerichtet; mehrere in REALTIME_MODELS angeben.",
+    ),
+    ("already on {model}.", "bereits auf {model}."),
+    ("switching to {model}…", "wechsle zu {model}…"),
+    ("reconnecting…", "verbinde neu…"),
+    (
+        "replies wait {question} ms after a question, {ms} ms after a statement, {long} ms otherwise.",
+        "Antworten warten {question} ms nach einer Frage, {ms} ms nach einer Aussage, sonst {long} ms.",
+    ),
+    ("starting a new conversation…", "beginne ein neues Gespräch…"),
+    ("reload failed: {error}", "Neuladen fehlgeschlagen: {error}"),
+    ("turn detection updated.", "Sprecherwechsel-Erkennung aktualisiert."),
+    ("reload rejected: {error}", "Neuladen abgelehnt: {error}"),
+    ("instructions updated.", "Anweisungen aktualisiert."),
+    ("instructions not reloaded: {error}", "Anweisungen nicht neu geladen: {error}"),
+    ("no command matches \"{word}\".", "kein Befehl passt zu \"{word}\"."),
+    ("{n} turns written to {path}.", "{n} Beiträge nach {path} geschrieben."),
+    ("cannot write the transcript: {error}", "Mitschrift kann nicht geschrieben werden: {error}"),
+    ("not switched: {error}", "nicht gewechselt: {error}"),
+    ("usage: /search words", "Aufruf: /search Wörter"),
+    ("nothing matches \"{query}\".", "nichts passt zu \"{query}\"."),
+    ("set for {when}: {text}", "gestellt auf {when}: {text}"),
+    ("{label}: set for {time}", "{label}: gestellt auf {time}"),
+    ("{label}: time is up.", "{label}: Zeit ist um."),
+    // interrupts, tools, turns and the session
+    ("assistant canceled.", "Assistent unterbrochen."),
+    ("assistant canceled (keyword).", "Assistent unterbrochen (Stichwort)."),
+    ("the model called an unknown tool {name}.", "Das Modell hat ein unbekanntes Werkzeug aufgerufen: {name}."),
+    ("running {name}…", "{name} läuft…"),
+    ("no answer; {name} not run.", "keine Antwort; {name} nicht ausgeführt."),
+    ("type /yes or /no", "/yes oder /no eingeben"),
+    ("say yes or no, or press Y / N", "ja oder nein sagen, oder Y / N drücken"),
+    ("press Y to run it, N to decline", "Y führt es aus, N lehnt ab"),
+    ("run {name} with {args}? ({how})", "{name} mit {args} ausführen? ({how})"),
+    ("approved.", "genehmigt."),
+    ("declined.", "abgelehnt."),
+    ("tool calls stopped.", "Werkzeugaufrufe gestoppt."),
+    ("no question is waiting.", "keine Frage offen."),
+    ("a reply is under way; turn not answered.", "eine Antwort läuft; Beitrag nicht beantwortet."),
+    ("PIN entry cancelled.", "PIN-Eingabe abgebrochen."),
+    ("type the PIN and press Enter.", "PIN eingeben und Enter drücken."),
+    ("renewed ({n} turns carried over).", "erneuert ({n} Beiträge übernommen)."),
+    ("now on {model} ({n} turns carried over).", "jetzt mit {model} ({n} Beiträge übernommen)."),
+    ("expired and could not be renewed: {error}", "abgelaufen und nicht erneuerbar: {error}"),
+    ("renewal failed, retrying in 30s: {error}", "Erneuerung fehlgeschlagen, neuer Versuch in 30 s: {error}"),
+    ("cannot switch to {model}: {error}", "Wechsel zu {model} nicht möglich: {error}"),
+    ("expired; renewing.", "abgelaufen; wird erneuert."),
+];
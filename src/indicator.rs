@@ -0,0 +1,225 @@
+// State indicators: what parlar is doing (idle, listening, thinking,
+// speaking, muted, or an error just happened), shown on whatever can show
+// it. The state is polled like the other status displays and each
+// indicator is told when it changes.
+//
+// INDICATORS is a comma-separated list of:
+//   terminal  the terminal window title, e.g. "parlar: listening"
+//   command   INDICATOR_CMD runs on every change with PARLAR_STATE set, e.g.
+//             a script driving an LED ring
+//   osc       an Open Sound Control message `/parlar/state <state>` over UDP
+//             to INDICATOR_OSC (host:port), for streaming overlays, OBS or
+//             TouchDesigner
+// GPIO LEDs (GPIO_LED_*, see gpio.rs) are an indicator too and need no entry.
+
+use std::io::Write;
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::timeline::Track;
+use crate::{State, output, shutdown};
+
+// How long an error is shown before the state returns
+const ERROR_SHOWN: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indication {
+    Idle,
+    Listening,
+    Thinking,
+    Speaking,
+    Muted,
+    Error,
+}
+
+impl Indication {
+    pub fn name(self) -> &'static str {
+        match self {
+            Indication::Idle => "idle",
+            Indication::Listening => "listening",
+            Indication::Thinking => "thinking",
+            Indication::Speaking => "speaking",
+            Indication::Muted => "muted",
+            Indication::Error => "error",
+        }
+    }
+}
+
+/// What the session is doing; errors are not part of the state (see `error`).
+pub fn state(st: &State) -> Indication {
+    if st.muted {
+        Indication::Muted
+    } else if st.responses.active || st.playing() {
+        Indication::Speaking
+    } else if st.thinking_since.is_some() || st.responses.inflight {
+        Indication::Thinking
+    } else if st.timeline.is_open(Track::User) {
+        Indication::Listening
+    } else {
+        Indication::Idle
+    }
+}
+
+/// Something that shows the state.
+pub trait Indicator: Send {
+    /// The state changed to `now`.
+    fn show(&mut self, now: Indication);
+    /// Called every 100 ms with the current state, for blinking and the like.
+    fn tick(&mut self, _now: Indication, _tick: u32) {}
+    /// parlar is exiting.
+    fn off(&mut self) {}
+}
+
+static INDICATORS: Mutex<Vec<Box<dyn Indicator>>> = Mutex::new(Vec::new());
+static LAST_ERROR: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Add an indicator; takes effect from the next poll.
+pub fn add(indicator: Box<dyn Indicator>) {
+    INDICATORS.lock().unwrap().push(indicator);
+}
+
+/// Show the error state for a moment (a realtime error, a lost connection).
+pub fn error() {
+    *LAST_ERROR.lock().unwrap() = Some(Instant::now());
+}
+
+/// Set up the INDICATORS list and start following `state`; returns their
+/// names for the startup banner, or `None` when there is nothing to drive.
+pub fn start(state: Arc<Mutex<State>>) -> Result<Option<String>, String> {
+    let list = std::env::var("INDICATORS").unwrap_or_default();
+    let mut names = Vec::new();
+    for name in list.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let indicator: Box<dyn Indicator> = match name {
+            "terminal" => Box::new(Title),
+            "command" => Box::new(Command {
+                cmd: std::env::var("INDICATOR_CMD").map_err(|_| "INDICATORS=command needs INDICATOR_CMD")?,
+            }),
+            "osc" => Box::new(Osc::from_env()?),
+            other => return Err(format!("INDICATORS: unknown indicator {other} (use terminal, command or osc)")),
+        };
+        add(indicator);
+        names.push(name);
+    }
+    if INDICATORS.lock().unwrap().is_empty() {
+        return Ok(None);
+    }
+    shutdown::on_exit(|| {
+        for i in INDICATORS.lock().unwrap().iter_mut() {
+            i.off();
+        }
+    });
+    std::thread::spawn(move || {
+        let mut last = None;
+        let mut tick = 0u32;
+        loop {
+            let errored = LAST_ERROR.lock().unwrap().is_some_and(|t| t.elapsed() < ERROR_SHOWN);
+            let now = if errored {
+                Indication::Error
+            } else {
+                state.lock().map(|st| self::state(&st)).unwrap_or(Indication::Error)
+            };
+            let mut indicators = INDICATORS.lock().unwrap();
+            for i in indicators.iter_mut() {
+                if last != Some(now) {
+                    i.show(now);
+                }
+                i.tick(now, tick);
+            }
+            drop(indicators);
+            last = Some(now);
+            tick = tick.wrapping_add(1);
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    });
+    Ok(Some(if names.is_empty() { "GPIO LEDs".into() } else { names.join(", ") }))
+}
+
+/// The terminal window title (OSC 2 on stderr).
+struct Title;
+
+impl Title {
+    fn set(text: &str) {
+        let mut err = std::io::stderr();
+        let _ = write!(err, "\x1b]2;{text}\x07");
+        let _ = err.flush();
+    }
+}
+
+impl Indicator for Title {
+    fn show(&mut self, now: Indication) {
+        Title::set(&format!("parlar: {}", now.name()));
+    }
+
+    fn off(&mut self) {
+        Title::set("");
+    }
+}
+
+/// INDICATOR_CMD with PARLAR_STATE, not waited for.
+struct Command {
+    cmd: String,
+}
+
+impl Command {
+    fn run(&self, state: &str) {
+        let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+        match std::process::Command::new(shell).arg(flag).arg(&self.cmd).env("PARLAR_STATE", state).spawn() {
+            // Reaped on a thread of its own so a slow command holds nothing up
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(e) => output::notice("indicator", &format!("INDICATOR_CMD: {e}")),
+        }
+    }
+}
+
+impl Indicator for Command {
+    fn show(&mut self, now: Indication) {
+        self.run(now.name());
+    }
+
+    fn off(&mut self) {
+        self.run("off");
+    }
+}
+
+/// OSC `/parlar/state ,s <state>` datagrams.
+struct Osc {
+    socket: UdpSocket,
+    target: String,
+}
+
+impl Osc {
+    fn from_env() -> Result<Self, String> {
+        let target = std::env::var("INDICATOR_OSC").map_err(|_| "INDICATORS=osc needs INDICATOR_OSC (host:port)")?;
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("INDICATOR_OSC: {e}"))?;
+        socket.connect(&target).map_err(|e| format!("INDICATOR_OSC {target}: {e}"))?;
+        Ok(Osc { socket, target })
+    }
+
+    fn send(&self, state: &str) {
+        // OSC strings are NUL-terminated and padded to a multiple of 4 bytes
+        fn put(buf: &mut Vec<u8>, s: &str) {
+            buf.extend_from_slice(s.as_bytes());
+            buf.resize((buf.len() / 4 + 1) * 4, 0);
+        }
+        let mut msg = Vec::new();
+        put(&mut msg, "/parlar/state");
+        put(&mut msg, ",s");
+        put(&mut msg, state);
+        if let Err(e) = self.socket.send(&msg) {
+            output::notice("indicator", &format!("INDICATOR_OSC {}: {e}", self.target));
+        }
+    }
+}
+
+impl Indicator for Osc {
+    fn show(&mut self, now: Indication) {
+        self.send(now.name());
+    }
+
+    fn off(&mut self) {
+        self.send("off");
+    }
+}
@@ -0,0 +1,327 @@
+// `parlar init`: first-run setup. Asks for the API key (kept in the OS
+// keyring), lets the user try input/output devices with a live level meter
+// and a test tone, plays voice samples, and writes the choices to parlar.toml.
+
+use std::collections::VecDeque;
+use std::env;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tui")]
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+
+use crate::earcon::{self, Earcon};
+use crate::audio::AudioBackend;
+use crate::{audio, config, doctor, i18n, net};
+
+// Voices available to the realtime models
+pub const VOICES: &[&str] = &["alloy", "ash", "ballad", "coral", "echo", "sage", "shimmer", "verse"];
+const SAMPLE_TEXT: &str = "Hi, I'm your parlar assistant. This is how I sound.";
+const METER_SECS: u64 = 5;
+
+fn ask(question: &str) -> String {
+    print!("{question} ");
+    let _ = io::stdout().flush();
+    let mut line = String::new();
+    let _ = io::stdin().read_line(&mut line);
+    line.trim().to_string()
+}
+
+fn confirm(question: &str, default: bool) -> bool {
+    let hint = if default { i18n::tr("[Y/n]") } else { i18n::tr("[y/N]") };
+    match ask(&format!("{question} {hint}")).as_str() {
+        "" => default,
+        a => i18n::yes(a),
+    }
+}
+
+// Read a line without echoing it (one `*` per character)
+#[cfg(feature = "tui")]
+pub fn ask_secret(question: &str) -> String {
+    print!("{question} ");
+    let _ = io::stdout().flush();
+    if crossterm::terminal::enable_raw_mode().is_err() {
+        return ask("");
+    }
+    let mut secret = String::new();
+    loop {
+        let Ok(Event::Key(k)) = event::read() else { continue };
+        match k.code {
+            KeyCode::Enter => break,
+            KeyCode::Char('c') if k.modifiers.contains(KeyModifiers::CONTROL) => {
+                secret.clear();
+                break;
+            }
+            KeyCode::Backspace if secret.pop().is_some() => print!("\u{8} \u{8}"),
+            KeyCode::Char(c) => {
+                secret.push(c);
+                print!("*");
+            }
+            _ => {}
+        }
+        let _ = io::stdout().flush();
+    }
+    let _ = crossterm::terminal::disable_raw_mode();
+    println!();
+    secret.trim().to_string()
+}
+
+// No raw mode without the `tui` feature: the key is echoed
+#[cfg(not(feature = "tui"))]
+pub fn ask_secret(question: &str) -> String {
+    ask(question)
+}
+
+pub async fn run() -> i32 {
+    println!("{}\n", i18n::tr("parlar setup — press Enter to accept the [default] at each step."));
+    let model = env::var("REALTIME_MODEL").unwrap_or_else(|_| "gpt-realtime".into());
+    let sr_hz: u32 = env::var("SR").ok().and_then(|v| v.parse().ok()).unwrap_or(24_000);
+
+    let backend = match audio::from_env() {
+        Ok(b) => b,
+        Err(e) => {
+            println!("{e}");
+            return 2;
+        }
+    };
+
+    let Some(api_key) = setup_key(&model).await else {
+        println!("{}", i18n::tr("Setup aborted: an API key is required."));
+        return 1;
+    };
+    println!();
+    let input = pick_device(backend.as_ref(), true, sr_hz);
+    println!();
+    let output = pick_device(backend.as_ref(), false, sr_hz);
+    println!();
+    let voice = pick_voice(backend.as_ref(), &api_key, output.as_deref(), sr_hz).await;
+
+    let mut settings = vec![("REALTIME_VOICE", voice)];
+    if let Some(name) = input {
+        settings.push(("INPUT_DEVICE", name));
+    }
+    if let Some(name) = output {
+        settings.push(("OUTPUT_DEVICE", name));
+    }
+    match write_config(&settings) {
+        Ok(path) => {
+            println!("\n{}", i18n::trf("Saved {path}. Run `parlar` to start talking.", &[("path", &path.display())]));
+            0
+        }
+        Err(e) => {
+            println!("\n{}", i18n::trf("Could not write the config: {error}", &[("error", &e)]));
+            1
+        }
+    }
+}
+
+async fn setup_key(model: &str) -> Option<String> {
+    if let Some(key) = config::api_key()
+        && confirm(i18n::tr("An API key is already configured. Keep it?"), true)
+    {
+        return Some(key);
+    }
+    loop {
+        let key = ask_secret(i18n::tr("OpenAI API key (sk-...):"));
+        if key.is_empty() {
+            return None;
+        }
+        print!("{} ", i18n::tr("Checking…"));
+        let _ = io::stdout().flush();
+        match doctor::key_status(&key, model).await {
+            Ok(200) => println!("{}", i18n::trf("ok, {model} is available.", &[("model", &model)])),
+            Ok(401) => {
+                println!("{}", i18n::tr("rejected (401). Try again, or press Enter to abort."));
+                continue;
+            }
+            Ok(404) => println!(
+                "{}",
+                i18n::trf("the key works, but {model} is not available (set REALTIME_MODEL).", &[("model", &model)])
+            ),
+            Ok(code) => println!("{}", i18n::trf("could not verify (HTTP {code}); keeping it.", &[("code", &code)])),
+            Err(e) => println!("{}", i18n::trf("could not verify ({error}); keeping it.", &[("error", &e)])),
+        }
+        match config::store_api_key(&key) {
+            Ok(()) => println!("{}", i18n::tr("Stored in the system keyring.")),
+            Err(e) => println!(
+                "{}",
+                i18n::trf("Keyring unavailable ({error}); add OPENAI_API_KEY=… to .env instead.", &[("error", &e)])
+            ),
+        }
+        return Some(key);
+    }
+}
+
+/// Choose a device and try it; `None` keeps the system default.
+fn pick_device(backend: &dyn AudioBackend, input: bool, sr_hz: u32) -> Option<String> {
+    let kind = if input { i18n::tr("Microphone") } else { i18n::tr("Speaker") };
+    let names = backend.device_names(input);
+    if names.is_empty() {
+        println!("{}", i18n::trf("{kind}: no devices found; skipping.", &[("kind", &kind)]));
+        return None;
+    }
+    loop {
+        println!("{}", i18n::trf("{kind} devices:", &[("kind", &kind)]));
+        println!("  0) {}", i18n::tr("system default"));
+        for (i, n) in names.iter().enumerate() {
+            println!("  {}) {n}", i + 1);
+        }
+        let choice = ask(&format!("{kind} [0]:"));
+        let idx: usize = choice.parse().unwrap_or(0);
+        let name = (idx > 0).then(|| names.get(idx - 1).cloned()).flatten();
+        #[cfg(feature = "audio")]
+        if let Some(dev) = crate::devices::find(input, name.as_deref()) {
+            use crate::devices;
+            let fit =
+                if input { devices::input_fit(&dev, sr_hz) } else { devices::output_fit(&dev, sr_hz) };
+            println!("  {}", fit.describe(sr_hz));
+        }
+        let tried = if input {
+            meter(backend, name.as_deref(), sr_hz)
+        } else {
+            test_tone(backend, name.as_deref(), sr_hz)
+        };
+        if let Err(e) = tried {
+            println!("  {}", i18n::trf("could not open the device: {error}", &[("error", &e)]));
+            continue;
+        }
+        let question = if input { i18n::tr("Use this microphone?") } else { i18n::tr("Use this speaker?") };
+        if confirm(question, true) {
+            return name;
+        }
+    }
+}
+
+/// Live level meter for a few seconds.
+fn meter(backend: &dyn AudioBackend, device: Option<&str>, sr_hz: u32) -> Result<(), String> {
+    let level = Arc::new(Mutex::new(0.0f32));
+    let level_in = level.clone();
+    let _stream = backend.open_input(
+        device,
+        sr_hz,
+        Box::new(move |pcm| *level_in.lock().unwrap() = crate::pcm::peak_level(pcm)),
+    )?;
+    println!("  {}", i18n::trf("Say something ({secs} s):", &[("secs", &METER_SECS)]));
+    let start = Instant::now();
+    let mut loudest = 0.0f32;
+    while start.elapsed() < Duration::from_secs(METER_SECS) {
+        let l = *level.lock().unwrap();
+        loudest = loudest.max(l);
+        let bar = "█".repeat((l * 40.0) as usize);
+        print!("\r  [{bar:<40}] {:>3}%", (l * 100.0) as u32);
+        let _ = io::stdout().flush();
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    println!();
+    if loudest < 0.02 {
+        println!("  {}", i18n::tr("No signal picked up — check the mic is unmuted and permitted."));
+    }
+    Ok(())
+}
+
+/// Play PCM16 mono on `device` and wait until it has finished.
+fn play(
+    backend: &dyn AudioBackend,
+    device: Option<&str>,
+    samples: Vec<i16>,
+    sr_hz: u32,
+) -> Result<(), String> {
+    let q = Arc::new(Mutex::new(VecDeque::from(samples)));
+    let q_out = q.clone();
+    let _stream = backend.open_output(
+        device,
+        sr_hz,
+        Box::new(move |out| {
+            let mut q = q_out.lock().unwrap();
+            for s in out.iter_mut() {
+                *s = q.pop_front().unwrap_or(0);
+            }
+        }),
+    )?;
+    while !q.lock().unwrap().is_empty() {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    // Let the device drain its own buffer
+    std::thread::sleep(Duration::from_millis(200));
+    Ok(())
+}
+
+fn test_tone(backend: &dyn AudioBackend, device: Option<&str>, sr_hz: u32) -> Result<(), String> {
+    println!("  {}", i18n::tr("Playing a test tone…"));
+    play(backend, device, earcon::render(Earcon::Connected, sr_hz, 0.3), sr_hz)
+}
+
+/// Synthesize a short sample in `voice` (24 kHz PCM16 mono).
+async fn voice_sample(api_key: &str, voice: &str) -> Result<Vec<i16>, String> {
+    let resp = net::http_client(reqwest::Client::builder(), "api.openai.com")?
+        .post("https://api.openai.com/v1/audio/speech")
+        .bearer_auth(api_key)
+        .json(&serde_json::json!({
+            "model": "gpt-4o-mini-tts",
+            "voice": voice,
+            "input": SAMPLE_TEXT,
+            "response_format": "pcm"
+        }))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("HTTP {}", resp.status()));
+    }
+    let bytes = resp.bytes().await.map_err(|e| e.to_string())?;
+    Ok(bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect())
+}
+
+async fn pick_voice(
+    backend: &dyn AudioBackend,
+    api_key: &str,
+    output: Option<&str>,
+    sr_hz: u32,
+) -> String {
+    let current = env::var("REALTIME_VOICE").unwrap_or_else(|_| "alloy".into());
+    println!("{}", i18n::tr("Voices (type a number to hear it, Enter to keep the last one played):"));
+    for (i, v) in VOICES.iter().enumerate() {
+        println!("  {}) {v}", i + 1);
+    }
+    let mut chosen = current;
+    loop {
+        let choice = ask(&i18n::trf("Voice [{voice}]:", &[("voice", &chosen)]));
+        if choice.is_empty() {
+            return chosen;
+        }
+        let picked = choice.parse::<usize>().ok().and_then(|i| VOICES.get(i.wrapping_sub(1)));
+        let Some(voice) = picked else {
+            println!("  {}", i18n::trf("Pick 1–{n}.", &[("n", &VOICES.len())]));
+            continue;
+        };
+        match voice_sample(api_key, voice).await {
+            // Samples are 24 kHz; played through the session-rate stream
+            Ok(pcm) => {
+                if let Err(e) = play(backend, output, pcm, sr_hz) {
+                    println!("  {}", i18n::trf("playback failed: {error}", &[("error", &e)]));
+                }
+            }
+            Err(e) => println!("  {}", i18n::trf("could not fetch a sample ({error})", &[("error", &e)])),
+        }
+        chosen = voice.to_string();
+    }
+}
+
+/// Merge `settings` into the config file, keeping any other keys.
+fn write_config(settings: &[(&str, String)]) -> Result<std::path::PathBuf, String> {
+    let path = config::path();
+    let mut table: toml::Table = match std::fs::read_to_string(&path) {
+        Ok(body) => body.parse().map_err(|e| format!("{}: {e}", path.display()))?,
+        Err(_) => toml::Table::new(),
+    };
+    for (key, value) in settings {
+        table.insert(key.to_string(), toml::Value::String(value.clone()));
+    }
+    let body = format!(
+        "# parlar settings (written by `parlar init`). Keys are the environment\n\
+         # variable names from the README; the environment and .env take precedence.\n\n{table}"
+    );
+    std::fs::write(&path, body).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,108 @@
+// Every way a reply gets cut off ends up here: the I key and the interrupt
+// commands (stdin, hotkeys, gRPC, MQTT), the server detecting speech, and a
+// barge-in keyword in the live transcript. Each path has its own cooldown,
+// measured from the previous cancel on any path, so one burst of noise
+// cannot cancel reply after reply:
+//   CANCEL_COOLDOWN_SPEECH_MS   speech detected (default CANCEL_COOLDOWN_MS)
+//   CANCEL_COOLDOWN_KEYWORD_MS  barge-in keyword (default CANCEL_COOLDOWN_MS)
+//   CANCEL_COOLDOWN_USER_MS     explicit interrupt (default 0)
+// CANCEL_COOLDOWN_MS defaults to 400.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tokio::sync::mpsc::UnboundedSender;
+use tungstenite::Message;
+
+use crate::responses::Cancel;
+use crate::{State, config, grpc, hooks, i18n, output, resume, script, tools, truncate_event, turn_taking};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Path {
+    User,
+    Speech,
+    Keyword,
+}
+
+impl Path {
+    pub fn name(self) -> &'static str {
+        match self {
+            Path::User => "user",
+            Path::Speech => "speech",
+            Path::Keyword => "keyword",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cooldowns {
+    user: Duration,
+    speech: Duration,
+    keyword: Duration,
+}
+
+impl Cooldowns {
+    /// The CANCEL_COOLDOWN_* settings, as last reloaded.
+    pub fn from_env() -> Self {
+        let ms = |name: &str| config::var(name).and_then(|v| v.trim().parse().ok());
+        let base = ms("CANCEL_COOLDOWN_MS").unwrap_or(400);
+        Cooldowns {
+            user: Duration::from_millis(ms("CANCEL_COOLDOWN_USER_MS").unwrap_or(0)),
+            speech: Duration::from_millis(ms("CANCEL_COOLDOWN_SPEECH_MS").unwrap_or(base)),
+            keyword: Duration::from_millis(ms("CANCEL_COOLDOWN_KEYWORD_MS").unwrap_or(base)),
+        }
+    }
+
+    fn get(&self, path: Path) -> Duration {
+        match path {
+            Path::User => self.user,
+            Path::Speech => self.speech,
+            Path::Keyword => self.keyword,
+        }
+    }
+}
+
+/// Cancel the reply in progress via `path`, unless its cooldown is still
+/// running: server cancel + truncate, and local playback flushed (kept for
+/// `resume`). Returns whether it was cancelled.
+pub fn cancel(
+    state: &Mutex<State>,
+    path: Path,
+    out_tx: &UnboundedSender<Message>,
+    spk_buf: &Mutex<VecDeque<i16>>,
+) -> bool {
+    let (response_id, item_id) = {
+        let mut st = state.lock().unwrap();
+        let cooldown = st.cooldowns.get(path);
+        let st = &mut *st;
+        let Some(Cancel { response_id, truncate }) =
+            turn_taking::cut_off(&mut st.responses, &mut st.last_cancel_at, Instant::now(), cooldown)
+        else {
+            return false;
+        };
+        st.mark_cancelled();
+        (response_id, truncate)
+    };
+    let _ = out_tx.send(Message::Text(json!({"type": "response.cancel"}).to_string()));
+    if let Some(item_id) = item_id {
+        let _ = out_tx.send(truncate_event(&item_id));
+    }
+    if let Ok(mut q) = spk_buf.lock() {
+        resume::keep(response_id, &mut q);
+    }
+    match path {
+        Path::User => output::notice("interrupt", i18n::tr("assistant canceled.")),
+        Path::Keyword => output::notice("interrupt", i18n::tr("assistant canceled (keyword).")),
+        Path::Speech => {}
+    }
+    // Talking over a reply is not meant to stop a tool (or the answer to its question)
+    if path != Path::Speech {
+        tools::cancel();
+    }
+    hooks::interrupted(path.name());
+    script::interrupted(path.name());
+    grpc::flush();
+    true
+}
@@ -0,0 +1,263 @@
+// Terminal keys. Each control is an action bound to one or more keys;
+// KEYMAP rebinds them, e.g. `KEYMAP="mute=f2, interrupt=space, quit=ctrl+q"`.
+// A key is a character or a key name (space, tab, enter, esc, backspace,
+// up, down, left, right, home, end, pageup, pagedown, insert, delete,
+// f1–f24), with ctrl+, alt+ in front as needed; `none` unbinds the action.
+// Binding an action replaces its default keys.
+//
+// Letters match in either case and Shift is ignored for characters, so `+`
+// and `/` work on layouts where they need it. Only key presses count: the
+// releases and auto-repeats that Windows (and terminals with the kitty
+// keyboard protocol) report do not toggle a setting twice. On a Cyrillic or
+// Greek layout a letter that is not bound itself acts as the Latin letter
+// on the same key, so M mutes whatever the layout. Where the terminal
+// supports it, escape codes are disambiguated, so Esc, Alt+key and Ctrl+I
+// (Tab) arrive as themselves.
+
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Quit,
+    Interrupt,
+    Resume,
+    Mute,
+    Language,
+    Traffic,
+    Lock,
+    Yes,
+    No,
+    Verbosity,
+    Timeline,
+    Practice,
+    Good,
+    Bad,
+    Scrollback,
+    Search,
+    Palette,
+}
+
+// Each action's name in KEYMAP and its default keys
+const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Quit, "quit", "q ctrl+c"),
+    (Action::Interrupt, "interrupt", "i"),
+    (Action::Resume, "resume", "u"),
+    (Action::Mute, "mute", "m"),
+    (Action::Language, "language", "l"),
+    (Action::Traffic, "traffic", "s"),
+    (Action::Lock, "lock", "p"),
+    (Action::Yes, "yes", "y"),
+    (Action::No, "no", "n"),
+    (Action::Verbosity, "verbosity", "v"),
+    (Action::Timeline, "timeline", "t"),
+    (Action::Practice, "practice", "w"),
+    (Action::Good, "good", "+"),
+    (Action::Bad, "bad", "-"),
+    (Action::Scrollback, "scrollback", "h"),
+    (Action::Search, "search", "/"),
+    (Action::Palette, "palette", ":"),
+];
+
+const NAMED: &[&str] = &[
+    "tab", "enter", "esc", "backspace", "up", "down", "left", "right", "home", "end", "pageup", "pagedown",
+    "insert", "delete",
+];
+
+// Letters of other layouts, each followed by the US letter on the same key
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+const LAYOUTS: &[(&str, &str)] = &[
+    // Russian (ЙЦУКЕН), with the Ukrainian letters
+    ("йцукенгшщзфывапролдячсмитьіїє", "qwertyuiopasdfghjklzxcvbnms]'"),
+    // Greek
+    ("ςερτυθιοπασδφγηξκλζχψωβνμ", "wertyuiopasdfghjklzxcvbnm"),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Code {
+    Char(char),
+    F(u8),
+    Named(&'static str),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Key {
+    code: Code,
+    ctrl: bool,
+    alt: bool,
+}
+
+impl Key {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let mut key = Key { code: Code::Char(' '), ctrl: false, alt: false };
+        let mut rest = spec.trim();
+        // `ctrl++` binds Ctrl and the plus key
+        while let Some((modifier, tail)) = rest.split_once('+').filter(|(_, tail)| !tail.is_empty()) {
+            match modifier.trim().to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => key.ctrl = true,
+                "alt" | "option" | "meta" => key.alt = true,
+                "shift" => {}
+                _ => break,
+            }
+            rest = tail;
+        }
+        let name = rest.trim().to_lowercase();
+        let mut chars = name.chars();
+        key.code = match (chars.next(), chars.next()) {
+            (Some(c), None) => Code::Char(c),
+            _ if name == "space" => Code::Char(' '),
+            _ if name.starts_with('f') && name[1..].parse::<u8>().is_ok_and(|n| (1..=24).contains(&n)) => {
+                Code::F(name[1..].parse().unwrap_or(1))
+            }
+            _ => match NAMED.iter().find(|n| **n == name) {
+                Some(n) => Code::Named(n),
+                None => return Err(format!("not a key: {spec}")),
+            },
+        };
+        Ok(key)
+    }
+
+    fn label(&self) -> String {
+        let code = match self.code {
+            Code::Char(' ') => "Space".to_string(),
+            Code::Char(c) => c.to_uppercase().to_string(),
+            Code::F(n) => format!("F{n}"),
+            Code::Named(n) => {
+                let mut chars = n.chars();
+                chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+            }
+        };
+        let ctrl = if self.ctrl { "Ctrl+" } else { "" };
+        let alt = if self.alt { "Alt+" } else { "" };
+        format!("{ctrl}{alt}{code}")
+    }
+}
+
+static BINDINGS: OnceLock<Vec<(Action, Key)>> = OnceLock::new();
+
+fn bindings() -> &'static [(Action, Key)] {
+    BINDINGS.get_or_init(|| defaults().unwrap_or_default())
+}
+
+fn defaults() -> Result<Vec<(Action, Key)>, String> {
+    let mut out = Vec::new();
+    for &(action, _, keys) in ACTIONS {
+        for spec in keys.split_whitespace() {
+            out.push((action, Key::parse(spec)?));
+        }
+    }
+    Ok(out)
+}
+
+/// Read KEYMAP; call once at startup.
+pub fn init() -> Result<(), String> {
+    let mut bindings = defaults()?;
+    let keymap = std::env::var("KEYMAP").unwrap_or_default();
+    let mut rebound = Vec::new();
+    for entry in keymap.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, spec) = entry.split_once('=').ok_or_else(|| format!("KEYMAP: {entry} (use action=key)"))?;
+        let name = name.trim().to_ascii_lowercase();
+        let &(action, _, _) = ACTIONS.iter().find(|(_, n, _)| *n == name).ok_or_else(|| {
+            let names: Vec<&str> = ACTIONS.iter().map(|(_, n, _)| *n).collect();
+            format!("KEYMAP: unknown action {name} (use {})", names.join(", "))
+        })?;
+        if !rebound.contains(&action) {
+            bindings.retain(|(a, _)| *a != action);
+            rebound.push(action);
+        }
+        if spec.trim().eq_ignore_ascii_case("none") {
+            continue;
+        }
+        let key = Key::parse(spec).map_err(|e| format!("KEYMAP: {e}"))?;
+        bindings.push((action, key));
+    }
+    // The same key for two actions would only ever do the first
+    for (i, (action, key)) in bindings.iter().enumerate() {
+        if let Some((other, _)) = bindings[..i].iter().find(|(a, k)| k == key && a != action) {
+            let name = |a: &Action| ACTIONS.iter().find(|(x, _, _)| x == a).map(|(_, n, _)| *n).unwrap_or("");
+            return Err(format!("KEYMAP: {} is bound to both {} and {}", key.label(), name(other), name(action)));
+        }
+    }
+    let _ = BINDINGS.set(bindings);
+    Ok(())
+}
+
+/// The first key bound to `action`, as shown to the user (`M`, `F2`,
+/// `Ctrl+Q`); `None` when it is unbound.
+pub fn label(action: Action) -> Option<String> {
+    bindings().iter().find(|(a, _)| *a == action).map(|(_, k)| k.label())
+}
+
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+fn lookup(key: Key) -> Option<Action> {
+    bindings().iter().find(|(_, k)| *k == key).map(|(a, _)| *a)
+}
+
+#[cfg(feature = "tui")]
+pub use term::{action, disambiguate, pressed};
+
+#[cfg(feature = "tui")]
+mod term {
+    use crossterm::event::{
+        KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    };
+
+    use super::{Action, Code, Key, LAYOUTS, lookup};
+    use crate::output;
+
+    /// Whether `k` is a press (or auto-repeat), not a release.
+    pub fn pressed(k: &KeyEvent) -> bool {
+        k.kind != KeyEventKind::Release
+    }
+
+    /// The action of a key event; releases and repeats have none.
+    pub fn action(k: &KeyEvent) -> Option<Action> {
+        if k.kind != KeyEventKind::Press {
+            return None;
+        }
+        let code = match k.code {
+            KeyCode::Char(c) => Code::Char(c.to_lowercase().next().unwrap_or(c)),
+            KeyCode::F(n) => Code::F(n),
+            KeyCode::Tab => Code::Named("tab"),
+            KeyCode::Enter => Code::Named("enter"),
+            KeyCode::Esc => Code::Named("esc"),
+            KeyCode::Backspace => Code::Named("backspace"),
+            KeyCode::Up => Code::Named("up"),
+            KeyCode::Down => Code::Named("down"),
+            KeyCode::Left => Code::Named("left"),
+            KeyCode::Right => Code::Named("right"),
+            KeyCode::Home => Code::Named("home"),
+            KeyCode::End => Code::Named("end"),
+            KeyCode::PageUp => Code::Named("pageup"),
+            KeyCode::PageDown => Code::Named("pagedown"),
+            KeyCode::Insert => Code::Named("insert"),
+            KeyCode::Delete => Code::Named("delete"),
+            _ => return None,
+        };
+        let key = Key {
+            code,
+            ctrl: k.modifiers.contains(KeyModifiers::CONTROL),
+            alt: k.modifiers.contains(KeyModifiers::ALT),
+        };
+        lookup(key).or_else(|| {
+            let Code::Char(c) = code else { return None };
+            let us = LAYOUTS
+                .iter()
+                .find_map(|(letters, us)| letters.chars().position(|l| l == c).and_then(|i| us.chars().nth(i)))?;
+            lookup(Key { code: Code::Char(us), ..key })
+        })
+    }
+
+    /// Ask the terminal for unambiguous escape codes (the kitty keyboard
+    /// protocol), where it supports them; undone on the way out.
+    pub fn disambiguate() {
+        if crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            let _ = crossterm::execute!(
+                output::terminal(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+            );
+            crate::shutdown::on_exit(|| {
+                let _ = crossterm::execute!(output::terminal(), crossterm::event::PopKeyboardEnhancementFlags);
+            });
+        }
+    }
+}
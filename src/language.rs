@@ -0,0 +1,141 @@
+// Conversation language: per-language barge-in keywords and instruction
+// hints, plus a stopword heuristic to notice when the user speaks another
+// language than the one configured (transcription carries no language tag).
+
+pub struct Lang {
+    pub code: &'static str,
+    pub name: &'static str,
+    stopwords: &'static [&'static str],
+    /// Phrases that interrupt the assistant when heard mid-reply. None may
+    /// be a word of ordinary speech: "para" is "stop!" but also "for".
+    pub barge_in: &'static [&'static str],
+    /// Words that open a question.
+    pub question_words: &'static [&'static str],
+    /// Words a finished sentence rarely ends on (conjunctions, articles,
+    /// prepositions, fillers): the speaker is probably not done.
+    pub dangling: &'static [&'static str],
+}
+
+pub static LANGS: &[Lang] = &[
+    Lang {
+        code: "en",
+        name: "English",
+        stopwords: &["the", "and", "is", "you", "what", "to", "of", "it", "that", "i", "a", "this", "are", "can"],
+        barge_in: &["stop", "wait", "hold on", "hey"],
+        question_words: &[
+            "what", "why", "how", "when", "where", "who", "which", "whose", "do", "does", "did", "is", "are",
+            "was", "can", "could", "would", "should", "will", "have", "has",
+        ],
+        dangling: &[
+            "and", "but", "or", "so", "because", "the", "a", "an", "to", "of", "with", "for", "if", "that",
+            "um", "uh", "like",
+        ],
+    },
+    Lang {
+        code: "es",
+        name: "Spanish",
+        stopwords: &["el", "la", "que", "de", "y", "es", "en", "los", "por", "qué", "una", "un", "no", "como", "pero"],
+        barge_in: &["espera", "oye", "un momento", "basta"],
+        question_words: &["qué", "cómo", "cuándo", "dónde", "quién", "cuál", "cuánto", "puedes", "hay"],
+        dangling: &[
+            "y", "pero", "o", "porque", "el", "la", "los", "las", "un", "una", "de", "con", "para", "que",
+            "eh", "este",
+        ],
+    },
+    Lang {
+        code: "fr",
+        name: "French",
+        stopwords: &["le", "la", "et", "est", "les", "des", "que", "je", "vous", "une", "pas", "c'est", "pour", "dans"],
+        barge_in: &["stop", "attends", "arrête", "hé", "un instant"],
+        question_words: &[
+            "qu'est", "est", "quoi", "pourquoi", "comment", "quand", "où", "qui", "quel", "quelle", "peux",
+            "combien",
+        ],
+        dangling: &[
+            "et", "mais", "ou", "donc", "parce", "le", "la", "les", "un", "une", "de", "avec", "pour", "que",
+            "euh",
+        ],
+    },
+    Lang {
+        code: "de",
+        name: "German",
+        stopwords: &["der", "die", "das", "und", "ist", "ich", "nicht", "du", "ein", "eine", "zu", "mit", "was", "wie"],
+        barge_in: &["stopp", "warte", "halt", "hey", "moment"],
+        question_words: &[
+            "was", "warum", "wie", "wann", "wo", "wer", "welche", "welcher", "kannst", "ist", "gibt", "hast",
+        ],
+        dangling: &[
+            "und", "aber", "oder", "weil", "der", "die", "das", "ein", "eine", "mit", "für", "zu", "dass",
+            "äh", "ähm",
+        ],
+    },
+    Lang {
+        code: "it",
+        name: "Italian",
+        stopwords: &["il", "che", "di", "è", "e", "la", "non", "per", "una", "sono", "come", "questo", "mi", "gli"],
+        barge_in: &["stop", "aspetta", "ferma", "ehi", "un attimo"],
+        question_words: &["cosa", "che", "perché", "come", "quando", "dove", "chi", "quale", "quanto", "puoi"],
+        dangling: &["e", "ma", "o", "il", "la", "un", "una", "di", "con", "per", "che", "ehm"],
+    },
+    Lang {
+        code: "pt",
+        name: "Portuguese",
+        stopwords: &["o", "que", "de", "não", "é", "um", "uma", "os", "para", "com", "você", "isso", "mas", "do"],
+        barge_in: &["espera", "ei", "pare", "um momento"],
+        question_words: &["que", "como", "quando", "onde", "quem", "qual", "quanto", "pode"],
+        dangling: &["e", "mas", "ou", "o", "a", "os", "um", "uma", "de", "com", "para", "que", "hum"],
+    },
+    Lang {
+        code: "nl",
+        name: "Dutch",
+        stopwords: &["de", "het", "een", "en", "is", "ik", "niet", "dat", "je", "van", "wat", "hoe", "zijn", "op"],
+        barge_in: &["stop", "wacht", "hé", "hou op", "momentje"],
+        question_words: &["wat", "waarom", "hoe", "wanneer", "waar", "wie", "welke", "kun", "kan", "is", "heb"],
+        dangling: &[
+            "en", "maar", "of", "want", "omdat", "de", "het", "een", "met", "voor", "van", "dat", "eh",
+            "uhm",
+        ],
+    },
+];
+
+pub fn by_code(code: &str) -> Option<&'static Lang> {
+    let code = code.trim().to_ascii_lowercase();
+    LANGS.iter().find(|l| l.code == code)
+}
+
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+}
+
+/// Best-guess language of `text`, when the evidence is clear enough.
+pub fn detect(text: &str) -> Option<&'static Lang> {
+    let words: Vec<String> = words(text).collect();
+    if words.len() < 4 {
+        return None;
+    }
+    let mut scores: Vec<(usize, &'static Lang)> = LANGS
+        .iter()
+        .map(|l| (words.iter().filter(|w| l.stopwords.contains(&w.as_str())).count(), l))
+        .collect();
+    scores.sort_by_key(|s| std::cmp::Reverse(s.0));
+    let (best, lang) = scores[0];
+    let runner_up = scores.get(1).map(|s| s.0).unwrap_or(0);
+    // Need a few hits and a clear margin; short overlapping words are common
+    (best >= 2 && best >= runner_up + 2).then_some(lang)
+}
+
+/// True if `text` contains one of the barge-in phrases as whole words.
+pub fn has_barge_in(text: &str, lang: &Lang) -> bool {
+    let words: Vec<String> = words(text).collect();
+    lang.barge_in.iter().any(|phrase| {
+        let p: Vec<&str> = phrase.split(' ').collect();
+        words.windows(p.len()).any(|w| w.iter().zip(&p).all(|(a, b)| a == b))
+    })
+}
+
+/// Instruction suffix asking the model to reply in `lang`.
+pub fn instruction_hint(lang: &Lang) -> String {
+    format!(" Always respond in {}.", lang.name)
+}
@@ -12,26 +12,132 @@
 // anyhow = "1.0"
 // dotenvy = "0.15"
 
+mod abtest;
+mod audio;
+mod bugreport;
+mod captions;
+mod childsafe;
+mod capture;
+mod cli;
+mod config;
+mod control;
+mod crypt;
+mod dataset;
+#[cfg(feature = "audio")]
+mod devices;
+mod doctor;
+mod earcon;
+mod echo;
+mod endcue;
+mod error;
+mod eventlog;
+mod export;
+mod filter;
+mod gate;
+mod gpio;
+mod grpc;
+mod history;
+mod hooks;
+mod hotkeys;
+mod i18n;
+mod indicator;
+mod init;
+mod interrupt;
+mod keys;
+mod language;
+mod meeting;
+mod memory;
+mod micwatch;
+mod mqtt;
+mod net;
+mod noise;
+#[cfg(feature = "tui")]
+mod note;
+mod notify;
+mod output;
+mod palette;
+mod passthrough;
+mod pcm;
+mod pipe;
+mod playback;
+mod practice;
+mod plugins;
+mod postprocess;
+mod rag;
+mod redact;
+mod reminders;
+mod report;
+mod responses;
+mod resume;
+mod resample;
+mod retranscribe;
+mod scribe;
+mod script;
+mod scrollback;
+mod service;
+mod session;
+mod shutdown;
+mod speaker;
+mod timeline;
+mod timers;
+mod template;
+mod tools;
+mod traffic;
+mod tts;
+mod turn;
+mod turn_taking;
+mod turnaudio;
+mod typing;
+mod verbosity;
+mod voicecmd;
+mod wav;
+mod wire;
+
 use std::collections::VecDeque;
 use std::env;
+use std::io::Write;
+use std::path::PathBuf;
 use std::process;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use base64;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{BufferSize, SampleFormat, SampleRate, StreamConfig};
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+#[cfg(feature = "tui")]
 use crossterm::event::{self, Event as CEvent, KeyCode};
 use futures_util::{SinkExt, StreamExt};
 use serde_json::json;
 use tokio::sync::mpsc;
-use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use http::HeaderValue;
 use tungstenite::Message;
 
+use cli::Cli;
+use control::Controls;
+use dataset::Dataset;
+use earcon::{CueBuf, Earcon};
+use echo::EchoWatch;
+use error::ParlarError;
+use eventlog::EventLog;
+use filter::{Action as FilterAction, Filter};
+use gate::Gate;
+use history::History;
+use language::Lang;
+use meeting::Meeting;
+use micwatch::MicWatch;
+use postprocess::{LineBuf, PostProcess};
+use responses::Responses;
+use timeline::{Mark, Timeline, Track};
+use turn::{Ending, TurnConfig};
+use turn_taking::{Policy, Turn};
+use verbosity::Verbosity;
+
+/// Playback counts as going on this long after the speaker queue ran dry,
+/// for the audio still in the device buffer and the room's echo.
+const PLAYBACK_TAIL: Duration = Duration::from_millis(150);
+
 #[derive(Default)]
 struct State {
     // lightweight meters
@@ -39,430 +145,1407 @@ struct State {
     spk_level: f32,
     mic_bytes: usize,
     spk_bytes: usize,
+    // speaker queue and lag behind real time, from the output callback
+    playback: playback::Readout,
+    // when the output callback last took assistant audio from the queue
+    played_at: Option<Instant>,
 
     // latest utterances
     last_user: String,
     last_assistant: String,
+    // the assistant item on screen and which of its texts is shown: with
+    // both modalities a reply comes as text and as its audio's transcript
+    reply_channel: Option<(String, &'static str)>,
 
-    // response lifecycle
-    response_active: bool,
-    response_inflight: bool,
-    last_assistant_item_id: Option<String>,
+    // response lifecycle, and the conversation items
+    responses: Responses,
 
     // interruption + transcript
     last_cancel_at: Option<Instant>,
+    cooldowns: interrupt::Cooldowns,
+    echo: EchoWatch,
     last_user_partial: String,
+
+    // turn committed, waiting for the first reply delta
+    thinking_since: Option<Instant>,
+    // when the server last detected the start of user speech
+    speech_started_at: Option<Instant>,
+    // all audio of the reply received; the end-of-reply cue plays once it has played out
+    end_cue_pending: bool,
+
+    // mic muted by the user (nothing is forwarded)
+    muted: bool,
+    // no audio devices: typed input and text-only replies
+    text_only: bool,
+
+    // VAD/turn-taking timeline and whether its strip is shown
+    timeline: Timeline,
+    show_timeline: bool,
+    // practice figures shown on the bottom row instead of the strip
+    show_practice: bool,
+
+    // session instructions (without the language hint) and conversation language
+    instructions: String,
+    verbosity: Verbosity,
+    language: Option<&'static Lang>,
+    // consecutive transcripts detected in another language, and a pending switch offer
+    lang_streak: Option<(&'static Lang, u32)>,
+    lang_offer: Option<&'static Lang>,
+
+    // turn-taking settings currently in effect
+    turn: TurnConfig,
+
+    // assistant voice; changing it takes a new connection
+    voice: String,
+
+    // realtime model of the current connection, and the finished turns
+    // replayed when the session moves to a new connection
+    model: String,
+    history: History,
 }
 
-fn chunk_peak_level_i16(samples: &[i16]) -> f32 {
-    if samples.is_empty() {
-        return 0.0;
+impl State {
+    /// Assistant audio is still coming out of the speaker. The server is
+    /// done with a reply (`response.audio.done`) well before it has played.
+    fn playing(&self) -> bool {
+        self.played_at.is_some_and(|t| t.elapsed() < PLAYBACK_TAIL)
+    }
+
+    /// The assistant is speaking or about to: a reply is under way on the
+    /// server or has not finished playing. The mic is gated meanwhile.
+    fn speaking(&self) -> bool {
+        turn_taking::speaking(&self.responses, self.playing())
     }
-    let mut peak = 0i16;
-    for &s in samples {
-        let a = s.wrapping_abs();
-        if a > peak {
-            peak = a;
+
+    /// Whether this assistant text event (`response.text.*` or
+    /// `response.audio_transcript.*`) goes on screen: per item, whichever of
+    /// the two starts first is shown, so the reply is not printed twice.
+    fn shows(&mut self, evt: &serde_json::Value) -> bool {
+        let item = evt["item_id"].as_str().unwrap_or("");
+        let channel = if evt["type"].as_str().unwrap_or("").starts_with("response.text.") { "text" } else { "audio" };
+        match &self.reply_channel {
+            Some((id, shown)) if id == item => *shown == channel,
+            _ => {
+                self.reply_channel = Some((item.to_string(), channel));
+                true
+            }
         }
     }
-    (peak as f32 / i16::MAX as f32).min(1.0)
+
+    /// The `response.create` for turns queued behind the last reply, when
+    /// turn-taking asked for it as `next`.
+    fn queued_reply(&mut self, next: Option<String>) -> Option<serde_json::Value> {
+        let event_id = next?;
+        self.thinking_since = Some(Instant::now());
+        Some(self.create_event(&event_id))
+    }
+
+    /// The `response.create` for a reply already asked for as `event_id`.
+    fn create_event(&self, event_id: &str) -> serde_json::Value {
+        let mut create = abtest::response_create(self);
+        create["event_id"] = json!(event_id);
+        create
+    }
+
+    /// Give back a reply claimed with `try_ask` but not asked for; returns
+    /// the `response.create` for a turn queued behind it meanwhile.
+    fn withdraw(&mut self, event_id: &str) -> Option<serde_json::Value> {
+        let next = turn_taking::withdraw(&mut self.responses, event_id);
+        self.queued_reply(next)
+    }
+
+    /// Instructions as sent to the server, including the language hint.
+    fn session_instructions(&self) -> String {
+        self.instructions_with(&self.instructions)
+    }
+
+    /// The session instructions with `base` (a template) in place of the
+    /// user's own.
+    fn instructions_with(&self, base: &str) -> String {
+        let memory = if template::uses_memory(base) { String::new() } else { memory::preamble() };
+        let base = template::expand(base);
+        match self.language {
+            Some(lang) => format!(
+                "{}{}{}{}{}{}",
+                childsafe::preamble(),
+                memory,
+                speaker::preamble(),
+                base,
+                self.verbosity.instruction_hint(),
+                language::instruction_hint(lang)
+            ),
+            None => format!(
+                "{}{}{}{}{}",
+                childsafe::preamble(),
+                memory,
+                speaker::preamble(),
+                base,
+                self.verbosity.instruction_hint()
+            ),
+        }
+    }
+
+    fn transcription_config(&self) -> serde_json::Value {
+        let mut config = match self.language {
+            Some(lang) => json!({ "model": "whisper-1", "language": lang.code }),
+            None => json!({ "model": "whisper-1" }),
+        };
+        if let Some(prompt) = practice::transcription_prompt() {
+            config["prompt"] = json!(prompt);
+        }
+        config
+    }
+
+    /// Forget the old connection's responses and items after a reconnect.
+    fn reset_connection(&mut self) {
+        self.responses.reset();
+        self.thinking_since = None;
+        self.timeline.stop(Track::Assistant);
+    }
+
+    /// Language used for barge-in keywords.
+    fn barge_in_lang(&self) -> &'static Lang {
+        self.language.unwrap_or(&language::LANGS[0])
+    }
+
+    /// The reply under way was cut off.
+    fn mark_cancelled(&mut self) {
+        self.thinking_since = None;
+        self.end_cue_pending = false;
+        // The queue is flushed; the user is talking over the reply, so no tail
+        self.played_at = None;
+        self.timeline.mark(Mark::Cancel);
+        self.timeline.stop(Track::Assistant);
+    }
 }
 
-#[tokio::main(flavor = "multi_thread", worker_threads = 4)]
-async fn main() -> Result<()> {
-    dotenvy::dotenv().ok();
+fn truncate_event(item_id: &str) -> Message {
+    Message::Text(
+        json!({
+            "type": "conversation.item.truncate",
+            "item_id": item_id,
+            "content_index": 0,
+            "audio_end_ms": 0
+        })
+        .to_string(),
+    )
+}
+
+/// Transcript of user item `item_id`, waiting up to `wait_ms` for it to arrive.
+async fn await_transcript(state: &Arc<Mutex<State>>, item_id: &str, wait_ms: u64) -> Option<String> {
+    let deadline = Instant::now() + Duration::from_millis(wait_ms);
+    loop {
+        let t = state.lock().unwrap().responses.items.get(item_id).and_then(|it| it.transcript.clone());
+        if t.is_some() || Instant::now() >= deadline {
+            return t;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+// WebSocket handshake request for the realtime endpoint
+fn realtime_request(model: &str, api_key: &str) -> http::Request<()> {
+    let model = percent_encoding::utf8_percent_encode(model, percent_encoding::NON_ALPHANUMERIC);
+    let url = format!("wss://api.openai.com/v1/realtime?model={}", model);
+    let mut request = url
+        .as_str()
+        .into_client_request()
+        .expect("a fixed URL with an encoded query is valid");
+    {
+        let headers = request.headers_mut();
+        // A key that is no valid header value is sent empty, and rejected by
+        // the server like any other bad key (the main run checks it upfront)
+        headers.insert(
+            "Authorization",
+            HeaderValue::from_str(&format!("Bearer {}", api_key)).unwrap_or(HeaderValue::from_static("")),
+        );
+        // Historically required during beta; harmless if GA keeps accepting it.
+        headers.insert(
+            "OpenAI-Beta",
+            HeaderValue::from_static("realtime=v1"),
+        );
+    }
+    request
+}
+
+/// Thread: typed lines, for text-only sessions and builds without the `tui`
+/// feature; slash commands stand in for the control keys.
+fn spawn_line_input(controls: Controls) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines() {
+            let Ok(line) = line else { break };
+            match line.trim() {
+                "" => {}
+                "/quit" | "/q" => break,
+                "/interrupt" | "/i" => controls.interrupt(),
+                "/resume" | "/u" => controls.resume(),
+                "/language" | "/l" => controls.accept_language_offer(),
+                "/lock" => childsafe::lock(),
+                "/practice" => output::notice("practice", &practice::summary()),
+                query if query.starts_with("/search ") || query == "/search" => {
+                    scrollback::search(query.trim_start_matches("/search"));
+                }
+                "/good" | "/bad" => abtest::rate(line.trim() == "/good"),
+                pin if pin.starts_with("/unlock") => {
+                    childsafe::unlock(pin.trim_start_matches("/unlock"));
+                }
+                "/yes" | "/no" => {
+                    if !tools::answer(line.trim() == "/yes", &controls) {
+                        output::notice("tool", i18n::tr("no question is waiting."));
+                    }
+                }
+                "/mute" | "/m" => {
+                    controls.set_muted(None);
+                }
+                palette if palette.starts_with(':') => palette::run(&controls, &palette[1..]),
+                text => controls.say_text(text),
+            }
+        }
+        shutdown::exit(0);
+    });
+}
+
+/// Thread: keyboard (I=interrupt, M=mute, Q=quit; see `keys`) — macOS/Linux
+#[cfg(feature = "tui")]
+fn spawn_keyboard(controls: Controls) {
+    use keys::Action;
+    std::thread::spawn(move || {
+        let _ = crossterm::terminal::enable_raw_mode();
+        keys::disambiguate();
+        // Focus reports let desktop notifications skip replies you are watching
+        let _ = crossterm::execute!(output::terminal(), event::EnableFocusChange);
+        // Digits typed so far after P, while the child-safe PIN is asked for
+        let mut pin: Option<String> = None;
+        loop {
+            let ev = event::read();
+            if let Ok(CEvent::Key(k)) = &ev
+                && keys::pressed(k)
+            {
+                typing::key_pressed();
+            }
+            match ev {
+                Ok(CEvent::Key(k)) if !keys::pressed(&k) => {}
+                Ok(CEvent::Key(k)) if let Some(typed) = &mut pin => match k.code {
+                    KeyCode::Char(c) if c.is_ascii_digit() => typed.push(c),
+                    KeyCode::Backspace => {
+                        typed.pop();
+                    }
+                    KeyCode::Enter => {
+                        childsafe::unlock(typed);
+                        pin = None;
+                    }
+                    _ => {
+                        output::notice("child-safe", i18n::tr("PIN entry cancelled."));
+                        pin = None;
+                    }
+                },
+                Ok(CEvent::FocusGained) => notify::set_focused(true),
+                Ok(CEvent::FocusLost) => notify::set_focused(false),
+                Ok(CEvent::Key(k)) => match keys::action(&k) {
+                    Some(Action::Quit) => shutdown::exit(0),
+                    Some(Action::Interrupt) => controls.interrupt(),
+                    Some(Action::Resume) => controls.resume(),
+                    Some(Action::Mute) => {
+                        controls.set_muted(None);
+                    }
+                    Some(Action::Language) => controls.accept_language_offer(),
+                    Some(Action::Traffic) => output::notice("traffic", &traffic::summary()),
+                    Some(Action::Lock) if childsafe::has_pin() => {
+                        if childsafe::locked() {
+                            output::notice("child-safe", i18n::tr("type the PIN and press Enter."));
+                            pin = Some(String::new());
+                        } else {
+                            childsafe::lock();
+                        }
+                    }
+                    // Y / N answer a tool question; otherwise N is the next model
+                    Some(Action::Yes) => {
+                        tools::answer(true, &controls);
+                    }
+                    Some(Action::No) if !tools::answer(false, &controls) => controls.switch_model(None),
+                    Some(Action::Verbosity) => controls.set_verbosity(None),
+                    Some(Action::Timeline) => {
+                        let mut st = controls.state.lock().unwrap();
+                        st.show_timeline = !st.show_timeline;
+                        st.show_practice = false;
+                    }
+                    Some(a @ (Action::Good | Action::Bad)) => abtest::rate(a == Action::Good),
+                    Some(Action::Practice) => {
+                        let mut st = controls.state.lock().unwrap();
+                        st.show_practice = !st.show_practice;
+                        st.show_timeline = false;
+                    }
+                    Some(a @ (Action::Scrollback | Action::Search)) => scrollback::open(a == Action::Search),
+                    Some(Action::Palette) => palette::open(&controls),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    });
+}
+
+// Without a terminal UI the controls are typed commands
+#[cfg(not(feature = "tui"))]
+fn spawn_keyboard(controls: Controls) {
+    spawn_line_input(controls);
+}
+
+fn env_flag(name: &str, default: bool) -> bool {
+    match env::var(name) {
+        Ok(v) => matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"),
+        Err(_) => default,
+    }
+}
+
+fn main() -> Result<()> {
+    // The environment is filled in while the process has one thread, before
+    // the runtime starts its own: changing it while others may read it is unsound
+    config::snapshot_env();
+    let env_file = dotenvy::dotenv().ok();
+    // parlar.toml fills in whatever the environment and .env leave unset
+    let profile = config::load_file().and_then(|_| config::apply_profile()).unwrap_or_else(|e| {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    });
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .enable_all()
+        .build()?
+        .block_on(run(env_file, profile))
+}
+
+async fn run(env_file: Option<PathBuf>, profile: Option<&'static str>) -> Result<()> {
+    let cli = Cli::parse();
+    net::init(cli.value("proxy"));
+    if let Err(e) = crypt::init().and_then(|_| redact::init()).and_then(|_| i18n::init()) {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    }
+    match cli.subcommand() {
+        None => {}
+        Some("init") => process::exit(init::run().await),
+        #[cfg(feature = "tui")]
+        Some("note") => process::exit(note::run(&cli).await),
+        #[cfg(not(feature = "tui"))]
+        Some("note") => {
+            eprintln!("`parlar note` needs a build with the `tui` feature");
+            process::exit(2);
+        }
+        Some("decrypt") => process::exit(crypt::run_decrypt(&cli)),
+        Some("export") => process::exit(export::run(&cli)),
+        Some("enroll") => process::exit(speaker::run_enroll(&cli)),
+        Some("doctor") => process::exit(doctor::run(env_file.as_deref()).await),
+        #[cfg(feature = "audio")]
+        Some("devices") => {
+            let sr_hz = env::var("SR").ok().and_then(|v| v.parse().ok()).unwrap_or(24_000);
+            process::exit(devices::run(sr_hz));
+        }
+        #[cfg(not(feature = "audio"))]
+        Some("devices") => {
+            eprintln!("`parlar devices` needs a build with the `audio` feature");
+            process::exit(2);
+        }
+        Some(other) => {
+            eprintln!("unknown command: {other} (available: init, note, decrypt, export, enroll, doctor, devices)");
+            process::exit(2);
+        }
+    }
+
+    // JSON mode: NDJSON events on stdout, NDJSON commands on stdin
+    let json_mode = cli.flag("json") || env_flag("JSON_MODE", false);
+    output::init(json_mode);
+    // Service mode: a daemon under systemd/launchd, no terminal controls
+    let service = cli.flag("service");
+    if json_mode && (pipe::reads_stdin() || pipe::writes_stdout()) {
+        eprintln!("[config] JSON mode needs stdin and stdout; set INPUT_DEVICE and OUTPUT_DEVICE to FIFOs or files");
+        process::exit(2);
+    }
+    if pipe::writes_stdout() {
+        output::audio_on_stdout();
+    }
+    // Accessible mode: no redraws, whole lines of assistant text, typed commands
+    let accessible = cli.flag("accessible") || env_flag("ACCESSIBLE", false);
+    if accessible {
+        output::set_accessible();
+    }
+    if let Err(e) = output::init_style(cli.flag("no-color")) {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    }
+    notify::init(env_flag("DESKTOP_NOTIFY", false), env_flag("NOTIFY_OSC9", false));
+    if let Err(e) = plugins::init()
+        .and_then(|_| memory::init())
+        .and_then(|_| hooks::init())
+        .and_then(|_| resume::init())
+        .and_then(|_| reminders::init())
+        .and_then(|_| tools::init())
+        .and_then(|_| childsafe::init())
+        .and_then(|_| report::check_prices())
+        .and_then(|_| practice::init())
+        .and_then(|_| abtest::init()) {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    }
+    if let Some(why) = childsafe::used_up() {
+        eprintln!("[child-safe] {why}");
+        process::exit(1);
+    }
 
     // ------------------- Config (env) -------------------
-    let api_key = env::var("OPENAI_API_KEY")
-        .expect("OPENAI_API_KEY must be set (in env or .env)");
+    let api_key = config::api_key().unwrap_or_else(|| {
+        ParlarError::ApiKey("no OpenAI API key (OPENAI_API_KEY, .env or the keyring)".into()).exit()
+    });
+    if HeaderValue::from_str(&api_key).is_err() {
+        ParlarError::ApiKey("the API key contains characters that cannot be sent (a line break?)".into()).exit();
+    }
 
-    let model = env::var("REALTIME_MODEL").unwrap_or_else(|_| "gpt-realtime".into());
-    let voice = env::var("REALTIME_VOICE").unwrap_or_else(|_| "alloy".into());
+    // Models to switch between at runtime (N key / set_model); the first one,
+    // or REALTIME_MODEL, is used at startup
+    let mut models: Vec<String> = env::var("REALTIME_MODELS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .collect();
+    let model = env::var("REALTIME_MODEL")
+        .ok()
+        .or_else(|| models.first().cloned())
+        .unwrap_or_else(|| "gpt-realtime".into());
+    if !models.contains(&model) {
+        models.insert(0, model.clone());
+    }
+    let (instructions, persona_voice) = template::init().unwrap_or_else(|e| {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    });
+    let voice = abtest::first_voice()
+        .or(persona_voice)
+        .unwrap_or_else(|| env::var("REALTIME_VOICE").unwrap_or_else(|_| "alloy".into()));
 
     let sr_hz: u32 = env::var("SR").ok().and_then(|v| v.parse().ok()).unwrap_or(24_000);
     let chunk_ms: u32 = env::var("CHUNK_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(20);
+    if let Err(e) = speaker::init(sr_hz).and_then(|_| noise::init(sr_hz)).and_then(|_| wire::init(sr_hz)) {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    }
+    if let Err(e) = passthrough::init(cli.value("raw-events"), cli.value("connect-events")) {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    }
+    // Events made locally (retried transcriptions), handled with the server's
+    let (local_tx, mut local_rx) = mpsc::unbounded_channel::<serde_json::Value>();
+    if let Err(e) = retranscribe::init(sr_hz, &api_key, local_tx) {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    }
+    // Raw PCM in binary WebSocket frames instead of JSON + base64 (~33% smaller);
+    // only for gateways that accept it, the OpenAI endpoint does not
+    let binary_frames = env_flag("AUDIO_BINARY_FRAMES", false);
+    if let Err(e) = audio::buffer_frames() {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    }
 
-    // While assistant speaks, gate mic by onset to reduce echo-triggered interrupts
-    let onset_peak: f32 = env::var("INT_ONSET_PEAK").ok().and_then(|v| v.parse().ok()).unwrap_or(0.22);
-    let onset_min_chunks: usize = env::var("INT_ONSET_MIN_CHUNKS").ok().and_then(|v| v.parse().ok()).unwrap_or(2);
-    let cancel_cooldown_ms: u64 = env::var("CANCEL_COOLDOWN_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(400);
+    let verbosity = match Verbosity::from_env() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    };
 
-    // Server VAD tuning: make the system more patient by default
-    let vad_silence_ms: u64 = env::var("TURN_SIL_MS")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(350);
-    let vad_threshold: f32 = env::var("TURN_VAD_THRESH")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(0.55);
+    // Turn detection (server_vad or semantic_vad) and adaptive response delays;
+    // retunable at runtime by editing .env
+    let turn_cfg = match TurnConfig::from_env() {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    };
 
-    // Adaptive response delays (in addition to VAD commit)
-    let resp_delay_short_ms: u64 = env::var("RESP_DELAY_SHORT_MS")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(200);
-    let resp_delay_long_ms: u64 = env::var("RESP_DELAY_LONG_MS")
-        .ok()
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(700);
+    // Server-side input noise reduction: near_field (headset/laptop), far_field (room mic), or off
+    let noise_reduction = match env::var("NOISE_REDUCTION").as_deref().unwrap_or("off") {
+        "off" | "" => None,
+        nr @ ("near_field" | "far_field") => Some(nr.to_string()),
+        other => {
+            eprintln!("[config] NOISE_REDUCTION must be near_field, far_field or off (got {other})");
+            process::exit(2);
+        }
+    };
 
-    println!("Parlar Realtime (Rust) — model={model} voice={voice} SR={sr_hz}Hz chunk={chunk_ms}ms");
-    println!("Commands: [I] Interrupt  [Q] Quit");
+    // What to listen to: the microphone, what the system is playing
+    // (loopback / monitor, to follow a call or video), or both mixed
+    let (capture_source, mic_gain, system_gain) = match (
+        capture::Source::from_env(),
+        capture::gain_from_env("MIC_GAIN"),
+        capture::gain_from_env("SYSTEM_GAIN"),
+    ) {
+        (Ok(src), Ok(mg), Ok(sg)) => (src, mg, sg),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    };
+    // Local monitor: OUTPUT_DEVICE then carries only the assistant's voice (e.g. a
+    // virtual cable into a call) and MONITOR_DEVICE plays a copy plus the earcons
+    let monitor_gain = capture::gain_from_env("MONITOR_GAIN").unwrap_or_else(|e| {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    });
 
-    // ------------------- Audio I/O -------------------
-    let host = cpal::default_host();
-    let input_device = host
-        .default_input_device()
-        .expect("No input audio device found");
-    let output_device = host
-        .default_output_device()
-        .expect("No output audio device found");
-
-    // Try to pick a 24 kHz mono config; otherwise fall back to default but keep mono.
-    let desired_rate = SampleRate(sr_hz);
-    let channels = 1u16;
-
-    let pick_input_cfg = || -> StreamConfig {
-        if let Ok(configs) = input_device.supported_input_configs() {
-            for range in configs {
-                if range.channels() == channels
-                    && range.min_sample_rate() <= desired_rate
-                    && range.max_sample_rate() >= desired_rate
-                {
-                    return range.with_sample_rate(desired_rate).config();
-                }
+    // Meeting notes: transcribe only, with periodic out-of-band summaries
+    let meeting = cli.flag("meeting").then(|| {
+        let path = env::var("MEETING_NOTES")
+            .unwrap_or_else(|_| chrono::Local::now().format("meeting-%Y%m%d-%H%M.md").to_string());
+        match Meeting::open(&path) {
+            Ok(m) => Arc::new(Mutex::new(m)),
+            Err(e) => {
+                eprintln!("[meeting] {e}");
+                process::exit(2);
             }
         }
-        let mut cfg = input_device
-            .default_input_config()
-            .expect("No default input config")
-            .config();
-        cfg.channels = channels;
-        cfg
+    });
+    // Realtime sessions have a hard lifetime; renew on a fresh connection
+    // before it runs out (0 turns renewal off)
+    let session_renew_secs: u64 = env::var("SESSION_RENEW_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(1740);
+    let meeting_summary_secs: u64 =
+        env::var("MEETING_SUMMARY_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(300).max(30);
+
+    // --bug-report: an event log and optionally the turns' audio, bundled on exit
+    let bug_report = cli.flag("bug-report").then(bugreport::BugReport::begin).transpose().unwrap_or_else(|e| {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    });
+
+    // Optional NDJSON debug log of every event with its response/item ids
+    let event_log_path = env::var("EVENT_LOG").ok().or_else(|| bug_report.as_ref().map(|b| b.event_log()));
+    let event_log = Arc::new(EventLog::open(event_log_path.as_deref()));
+
+    // Optional per-turn audio + transcript capture for building datasets
+    let dataset_dir = cli.value("capture-dataset").or_else(|| bug_report.as_ref().and_then(|b| b.audio_dir()));
+    let dataset: Option<Arc<Dataset>> = match dataset_dir {
+        Some(dir) => match Dataset::create(std::path::Path::new(&dir), sr_hz) {
+            Ok(ds) => {
+                output::info(&format!("Capturing dataset to {}", ds.dir().display()));
+                Some(Arc::new(ds))
+            }
+            Err(e) => {
+                eprintln!("[dataset] cannot create {dir}: {e}");
+                None
+            }
+        },
+        None => None,
     };
-    let pick_output_cfg = || -> StreamConfig {
-        if let Ok(configs) = output_device.supported_output_configs() {
-            for range in configs {
-                if range.channels() == channels
-                    && range.min_sample_rate() <= desired_rate
-                    && range.max_sample_rate() >= desired_rate
-                {
-                    return range.with_sample_rate(desired_rate).config();
+
+    if let (Some(b), Some(log)) = (bug_report, &event_log_path) {
+        b.record(log, dataset.as_ref().map(|d| d.dir()), env_file.as_deref(), sr_hz, &model);
+    }
+
+    // Scribe: a separate verbatim transcript of both sides of the conversation
+    let scribe = match env::var("SCRIBE_TRANSCRIPT") {
+        Ok(path) if !path.is_empty() => {
+            let cfg = scribe::Config {
+                path: path.clone(),
+                model: env::var("SCRIBE_MODEL").unwrap_or_else(|_| model.clone()),
+                api_key: api_key.clone(),
+                language: env::var("LANGUAGE").ok().as_deref().and_then(language::by_code).map(|l| l.code),
+                sr_hz,
+            };
+            match scribe::Scribe::start(cfg) {
+                Ok(s) => {
+                    output::info(&format!("Scribe: transcribing both sides to {path}"));
+                    Some(s)
+                }
+                Err(e) => {
+                    eprintln!("[scribe] {e}");
+                    process::exit(2);
                 }
             }
         }
-        let mut cfg = output_device
-            .default_output_config()
-            .expect("No default output config")
-            .config();
-        cfg.channels = channels;
-        cfg
+        _ => None,
     };
 
-    let mut input_cfg = pick_input_cfg();
-    input_cfg.buffer_size = BufferSize::Default;
+    let transcript = scrollback::init().unwrap_or_else(|e| {
+        eprintln!("[config] TRANSCRIPT_FILE {e}");
+        process::exit(2);
+    });
+    if let Some(path) = &transcript {
+        output::info(&format!("Transcript: appending to {path}"));
+    }
 
-    let mut output_cfg = pick_output_cfg();
-    output_cfg.buffer_size = BufferSize::Default;
+    if crypt::enabled()
+        && (meeting.is_some() || scribe.is_some() || dataset.is_some() || event_log.enabled() || transcript.is_some())
+    {
+        output::info("Transcripts and recordings are encrypted at rest (read them with `parlar decrypt`).");
+    }
 
-    // Shared output audio ring buffer (PCM16)
-    let spk_buf: Arc<Mutex<VecDeque<i16>>> = Arc::new(Mutex::new(VecDeque::with_capacity(96_000)));
+    // Feedback while waiting for the reply: spinner on stderr, optional earcon on commit
+    // TUI=false: typed commands and no terminal drawing, even in a `tui` build
+    let tui = env_flag("TUI", true) && !service && !accessible;
+    let thinking_indicator = env_flag("THINKING_INDICATOR", true) && !json_mode && tui;
+    let thinking_earcon = env_flag("THINKING_EARCON", false);
+    let earcon_gain: f32 = env::var("EARCON_GAIN").ok().and_then(|v| v.parse().ok()).unwrap_or(0.15);
+    // Status earcons (connected, disconnected, error, mute) for eyes-free use
+    let status_earcons = env_flag("EARCONS", true);
+    if let Err(e) = keys::init().and_then(|_| typing::init()) {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    }
+    let end_cue = endcue::EndCue::from_env().unwrap_or_else(|e| {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    });
+    // Turn-taking timeline strip on the bottom terminal row (toggle with T)
+    // Practice figures: on the bottom row with the terminal UI, else a line per turn
+    let show_practice = practice::enabled() && tui && !json_mode;
+    let practice_lines = practice::enabled() && !show_practice;
+    let show_timeline = env_flag("TIMELINE", false) && tui && !show_practice;
+
+    // Conversation language (ISO 639-1) and what to do when the user speaks another one
+    let conv_language = match env::var("LANGUAGE") {
+        Ok(code) => {
+            let lang = language::by_code(&code);
+            if lang.is_none() {
+                eprintln!("[language] unsupported LANGUAGE={code}; using auto-detect");
+            }
+            lang
+        }
+        Err(_) => None,
+    };
+    let lang_switch = env::var("LANG_SWITCH").unwrap_or_else(|_| "offer".into());
+    // Spoken commands to parlar itself ("parlar, mute yourself")
+    let voice_commands = voicecmd::Grammar::from_env().map(Arc::new);
+
+    // Content filter on finalized user transcripts before a reply is requested
+    let transcript_filter: Option<Arc<Filter>> = match Filter::from_env() {
+        Ok(f) => f.map(Arc::new),
+        Err(e) => {
+            eprintln!("[filter] invalid configuration: {e}");
+            process::exit(2);
+        }
+    };
+    let filter_wait_ms: u64 = env::var("TRANSCRIPT_FILTER_WAIT_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3000);
+
+    // Excerpts from local documents for questions about them
+    let rag: Option<Arc<rag::Rag>> = match rag::Rag::from_env(&api_key) {
+        Ok(r) => r.map(Arc::new),
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    };
+
+    // Rewriting of assistant text (markdown stripping, replacements, external command)
+    let postprocess: Option<Arc<PostProcess>> = match PostProcess::from_env() {
+        Ok(p) => p.map(Arc::new),
+        Err(e) => {
+            eprintln!("[postprocess] invalid configuration: {e}");
+            process::exit(2);
+        }
+    };
 
-    // Mic -> network channel (raw PCM16 bytes per chunk)
-    let (mic_tx, mic_rx): (Sender<Vec<u8>>, Receiver<Vec<u8>>) = unbounded();
+    output::info(&format!(
+        "Parlar Realtime (Rust) — model={model} voice={voice} SR={sr_hz}Hz chunk={chunk_ms}ms"
+    ));
+    if let Some(p) = profile {
+        output::info(&i18n::trf("Profile: {profile}", &[("profile", &p)]));
+    }
+    // --no-audio, or no usable device (see below): typed input, text replies
+    let mut text_only = cli.flag("no-audio") || env_flag("NO_AUDIO", false);
+    if service {
+        output::info(i18n::tr("Service mode: no terminal controls; stop with SIGTERM."));
+    } else if text_only && !json_mode {
+        output::info(i18n::tr("Text only: type a message and press Enter; /quit exits."));
+    } else if !json_mode && (!tui || cfg!(not(feature = "tui"))) {
+        output::info(i18n::tr(
+            "Commands (type, then Enter): /interrupt  /resume  /mute  /language  /quit; other lines are sent as messages",
+        ));
+    } else if !json_mode {
+        use keys::Action;
+        let mut commands = vec![
+            (Action::Interrupt, "Interrupt"),
+            (Action::Resume, "Resume"),
+            (Action::Mute, "Mute"),
+            (Action::Verbosity, "Verbosity"),
+            (Action::Timeline, "Timeline"),
+            (Action::Traffic, "Traffic"),
+        ];
+        if models.len() > 1 {
+            commands.push((Action::No, "Next model"));
+        }
+        commands.push((Action::Quit, "Quit"));
+        let commands: Vec<String> = commands
+            .into_iter()
+            .filter_map(|(a, what)| Some(format!("[{}] {}", keys::label(a)?, i18n::tr(what))))
+            .collect();
+        output::info(&i18n::trf("Commands: {commands}", &[("commands", &commands.join("  "))]));
+    }
+
+    // ------------------- Audio I/O -------------------
+    let backend = match audio::from_env() {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    };
+    // INPUT_DEVICE / OUTPUT_DEVICE select by name (see `parlar devices`)
+    let input_name = env::var("INPUT_DEVICE").ok();
+    let output_name = env::var("OUTPUT_DEVICE").ok();
+    let monitor_name = env::var("MONITOR_DEVICE").ok().filter(|n| !n.is_empty());
 
-    let state = Arc::new(Mutex::new(State::default()));
+    // Shared output audio ring buffer (PCM16)
+    let spk_buf: Arc<Mutex<VecDeque<i16>>> = Arc::new(Mutex::new(VecDeque::with_capacity(96_000)));
+    // Local earcons, mixed on top of assistant audio
+    let cue_buf: CueBuf = Arc::new(Mutex::new(VecDeque::new()));
+    // Assistant audio as played on the output, copied for the monitor device
+    let mon_buf: Option<Arc<Mutex<VecDeque<i16>>>> =
+        monitor_name.as_ref().map(|_| Arc::new(Mutex::new(VecDeque::new())));
 
-    // Input stream (capture mic)
-    let input_sample_format = input_device
-        .default_input_config()
-        .expect("no default input config")
-        .sample_format();
+    // Mic -> network channel (PCM16 chunks); buffers go back through the pool
+    let (mic_tx, mic_rx): (Sender<Vec<i16>>, Receiver<Vec<i16>>) = unbounded();
 
-    let frames_per_chunk =
-        (input_cfg.sample_rate.0 as u32 * chunk_ms / 1000).max(1) as usize;
+    let state = Arc::new(Mutex::new(State {
+        show_timeline,
+        show_practice,
+        instructions,
+        verbosity,
+        language: conv_language,
+        turn: turn_cfg.clone(),
+        model: model.clone(),
+        voice,
+        cooldowns: interrupt::Cooldowns::from_env(),
+        echo: EchoWatch::from_env(),
+        ..Default::default()
+    }));
 
+    // Input stream (capture mic); the backend picks 24 kHz mono if the
+    // device offers it, otherwise the device default rate, still mono.
+    let frames_per_chunk = (sr_hz * chunk_ms / 1000).max(1) as usize;
+    let mic_pool = pcm::Pool::new(64, frames_per_chunk);
+    // While assistant speaks, gate mic by onset to reduce echo-triggered interrupts
+    let mut gate = match Gate::from_env(chunk_ms, mic_pool.clone()) {
+        Ok(g) => g,
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    };
+    // Warn when the input stays digitally silent (no permission, device gone)
+    let mic_watch = MicWatch::from_env().unwrap_or_else(|e| {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    });
     let mic_tx_clone = mic_tx.clone();
-    let state_for_input = state.clone();
-    let input_stream = match input_sample_format {
-        SampleFormat::I16 => input_device.build_input_stream(
-            &input_cfg,
-            move |data: &[i16], _| {
-                // Slice by frames_per_chunk into fixed chunks → convert to bytes
-                for frame_chunk in data.chunks(frames_per_chunk) {
-                    let peak = chunk_peak_level_i16(frame_chunk);
-                    if let Ok(mut st) = state_for_input.lock() {
-                        st.mic_level = peak;
-                        st.mic_bytes += frame_chunk.len() * 2;
-                    }
-                    let bytes = unsafe {
-                        std::slice::from_raw_parts(
-                            frame_chunk.as_ptr() as *const u8,
-                            frame_chunk.len() * 2,
-                        )
-                    };
-                    let _ = mic_tx_clone.send(bytes.to_vec());
-                }
-            },
-            |e| eprintln!("Input stream error: {e:?}"),
-        )?,
-        SampleFormat::F32 => input_device.build_input_stream(
-            &input_cfg,
-            move |data: &[f32], _| {
-                for frame_chunk in data.chunks(frames_per_chunk) {
-                    // convert to i16
-                    let mut pcm = Vec::with_capacity(frame_chunk.len());
-                    for &s in frame_chunk {
-                        let v = (s * i16::MAX as f32)
-                            .round()
-                            .clamp(i16::MIN as f32, i16::MAX as f32) as i16;
-                        pcm.push(v);
-                    }
-                    let peak = chunk_peak_level_i16(&pcm);
-                    if let Ok(mut st) = state_for_input.lock() {
-                        st.mic_level = peak;
-                        st.mic_bytes += pcm.len() * 2;
-                    }
-                    let bytes = unsafe {
-                        std::slice::from_raw_parts(pcm.as_ptr() as *const u8, pcm.len() * 2)
-                    };
-                    let _ = mic_tx_clone.send(bytes.to_vec());
-                }
-            },
-            |e| eprintln!("Input stream error: {e:?}"),
-        )?,
-        SampleFormat::U16 => input_device.build_input_stream(
-            &input_cfg,
-            move |data: &[u16], _| {
-                for frame_chunk in data.chunks(frames_per_chunk) {
-                    let mut pcm = Vec::with_capacity(frame_chunk.len());
-                    for &s in frame_chunk {
-                        pcm.push((s as i32 - 32768) as i16);
-                    }
-                    let peak = chunk_peak_level_i16(&pcm);
-                    if let Ok(mut st) = state_for_input.lock() {
-                        st.mic_level = peak;
-                        st.mic_bytes += pcm.len() * 2;
-                    }
-                    let bytes = unsafe {
-                        std::slice::from_raw_parts(pcm.as_ptr() as *const u8, pcm.len() * 2)
-                    };
-                    let _ = mic_tx_clone.send(bytes.to_vec());
-                }
-            },
-            |e| eprintln!("Input stream error: {e:?}"),
-        )?,
+    let pool_for_input = mic_pool.clone();
+    let feed_mic = move |data: &[i16]| {
+        // Slice by frames_per_chunk into fixed chunks; metering happens on the mic thread
+        pcm::split(data, frames_per_chunk, &pool_for_input, |chunk| {
+            let _ = mic_tx_clone.send(chunk);
+        });
+    };
+    let on_input: audio::OnInput = Box::new(feed_mic.clone());
+    // Without a device the run goes on text-only, unless a device was asked for
+    let explicit_devices = input_name.is_some() || output_name.is_some() || pipe::selected();
+    let fall_back = |what: &str, e: String| {
+        if explicit_devices {
+            ParlarError::Audio(format!("cannot open the {what}: {e}")).exit();
+        }
+        output::notice(
+            "audio",
+            &format!("cannot open the {what} ({e}); continuing text only, type a message and press Enter."),
+        );
+    };
+    let inputs = if text_only {
+        Ok(Vec::new())
+    } else {
+        match capture_source {
+            capture::Source::Mic => backend
+                .open_input(input_name.as_deref(), sr_hz, capture::with_gain(on_input, mic_gain))
+                .map(|i| vec![i]),
+            capture::Source::System => backend
+                .open_system_capture(sr_hz, capture::with_gain(on_input, system_gain))
+                .map(|i| vec![i]),
+            capture::Source::Both => {
+                let mixer = Arc::new(Mutex::new(capture::Mixer::new(
+                    on_input,
+                    mic_gain,
+                    system_gain,
+                    (sr_hz / 10) as usize,
+                )));
+                let mixer_mic = mixer.clone();
+                let state_for_sys = state.clone();
+                let mut quiet = Vec::new();
+                backend
+                    .open_input(
+                        input_name.as_deref(),
+                        sr_hz,
+                        Box::new(move |pcm| mixer_mic.lock().unwrap().push(capture::Source::Mic, pcm)),
+                    )
+                    .and_then(|mic| {
+                        let sys = backend.open_system_capture(
+                            sr_hz,
+                            Box::new(move |pcm| {
+                                // Our own playback is in the system mix; keep only the mic then
+                                let speaking = state_for_sys.lock().map(|s| s.speaking()).unwrap_or(false);
+                                let pcm = if speaking {
+                                    quiet.resize(pcm.len(), 0);
+                                    &quiet[..]
+                                } else {
+                                    pcm
+                                };
+                                mixer.lock().unwrap().push(capture::Source::System, pcm);
+                            }),
+                        )?;
+                        Ok(vec![mic, sys])
+                    })
+            }
+        }
     };
-    input_stream.play()?;
+    let mut inputs = inputs.unwrap_or_else(|e| {
+        fall_back("input", e);
+        text_only = true;
+        Vec::new()
+    });
 
     // Output stream (play assistant audio)
-    let out_sf = output_device
-        .default_output_config()
-        .expect("no default output config")
-        .sample_format();
+    let mut lag_tracker = playback::Tracker::from_env(sr_hz).unwrap_or_else(|e| {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    });
     let spk_buf_for_out = spk_buf.clone();
+    let cue_buf_for_out = cue_buf.clone();
+    let mon_buf_for_out = mon_buf.clone();
     let state_for_out = state.clone();
-    let output_stream = match out_sf {
-        SampleFormat::I16 => output_device.build_output_stream(
-            &output_cfg,
-            move |out: &mut [i16], _| {
-                let mut buf = spk_buf_for_out.lock().unwrap();
-                for s in out.iter_mut() {
-                    *s = buf.pop_front().unwrap_or(0);
-                }
-                // update level (cheap peak over this callback)
-                let peak = chunk_peak_level_i16(out);
-                if let Ok(mut st) = state_for_out.lock() {
-                    st.spk_level = peak;
-                    st.spk_bytes += out.len() * 2;
-                }
-            },
-            |e| eprintln!("Output stream error: {e:?}"),
-        )?,
-        SampleFormat::F32 => output_device.build_output_stream(
-            &output_cfg,
-            move |out: &mut [f32], _| {
-                let mut buf = spk_buf_for_out.lock().unwrap();
-                for s in out.iter_mut() {
-                    if let Some(v) = buf.pop_front() {
-                        *s = (v as f32) / (i16::MAX as f32);
-                    } else {
-                        *s = 0.0;
-                    }
-                }
-                // derive level from a temporary i16 vec (approx)
-                let tmp: Vec<i16> = out
-                    .iter()
-                    .map(|f| (f * i16::MAX as f32) as i16)
-                    .collect();
-                let peak = chunk_peak_level_i16(&tmp);
-                if let Ok(mut st) = state_for_out.lock() {
-                    st.spk_level = peak;
-                    st.spk_bytes += out.len() * 2;
-                }
-            },
-            |e| eprintln!("Output stream error: {e:?}"),
-        )?,
-        SampleFormat::U16 => output_device.build_output_stream(
-            &output_cfg,
-            move |out: &mut [u16], _| {
-                let mut buf = spk_buf_for_out.lock().unwrap();
-                for s in out.iter_mut() {
-                    if let Some(v) = buf.pop_front() {
-                        *s = (v as i32 + 32768).clamp(0, 65535) as u16;
-                    } else {
-                        *s = 32768;
-                    }
-                }
-                // level (approx)
-                let tmp: Vec<i16> = out.iter().map(|u| (*u as i32 - 32768) as i16).collect();
-                let peak = chunk_peak_level_i16(&tmp);
-                if let Ok(mut st) = state_for_out.lock() {
-                    st.spk_level = peak;
-                    st.spk_bytes += out.len() * 2;
-                }
-            },
-            |e| eprintln!("Output stream error: {e:?}"),
-        )?,
+    let fill: audio::OnOutput = Box::new(move |out: &mut [i16]| {
+        let mut buf = spk_buf_for_out.lock().unwrap();
+        let had_audio = !buf.is_empty();
+        let readout = lag_tracker.fill(&mut buf, out.len());
+        if let Some(mon) = &mon_buf_for_out {
+            // Earcons stay local: they play on the monitor, not into the call
+            let mut mon = mon.lock().unwrap();
+            for s in out.iter_mut() {
+                let a = buf.pop_front();
+                *s = a.unwrap_or(0);
+                mon.extend(a);
+            }
+            // The two devices run on separate clocks; bound the monitor's lag
+            let excess = mon.len().saturating_sub(sr_hz as usize / 5);
+            mon.drain(..excess);
+        } else {
+            let mut cues = cue_buf_for_out.lock().unwrap();
+            for s in out.iter_mut() {
+                *s = earcon::mix_next(&mut buf, &mut cues);
+            }
+        }
+        // update level (cheap peak over this callback)
+        let peak = pcm::peak_level(out);
+        if let Ok(mut st) = state_for_out.lock() {
+            st.spk_level = peak;
+            st.spk_bytes += out.len() * 2;
+            st.playback = readout;
+            if had_audio {
+                st.played_at = Some(Instant::now());
+            }
+        }
+    });
+    let output = match text_only {
+        true => None,
+        false => match backend.open_output(output_name.as_deref(), sr_hz, fill) {
+            Ok(o) => Some(o),
+            Err(e) => {
+                fall_back("output", e);
+                text_only = true;
+                inputs.clear();
+                None
+            }
+        },
     };
-    output_stream.play()?;
-
-    // ------------------- WebSocket -------------------
-    let url = format!("wss://api.openai.com/v1/realtime?model={}", model);
-    let mut request = url
-        .as_str()
-        .into_client_request()
-        .expect("Failed to build WS request");
-    {
-        let headers = request.headers_mut();
-        headers.insert(
-            "Authorization",
-            HeaderValue::from_str(&format!("Bearer {}", api_key)).expect("invalid API key"),
-        );
-        // Historically required during beta; harmless if GA keeps accepting it.
-        headers.insert(
-            "OpenAI-Beta",
-            HeaderValue::from_static("realtime=v1"),
-        );
+    let monitor = mon_buf.filter(|_| !text_only).map(|mon| {
+        let cues = cue_buf.clone();
+        backend
+            .open_output(
+                monitor_name.as_deref(),
+                sr_hz,
+                Box::new(move |out: &mut [i16]| {
+                    let mut mon = mon.lock().unwrap();
+                    let mut cues = cues.lock().unwrap();
+                    for s in out.iter_mut() {
+                        let a = mon.pop_front().unwrap_or(0) as f32 * monitor_gain;
+                        let b = cues.pop_front().unwrap_or(0) as f32;
+                        *s = (a + b).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+                    }
+                }),
+            )
+            .unwrap_or_else(|e| ParlarError::Audio(format!("cannot open the monitor output: {e}")).exit())
+    });
+    for input in &inputs {
+        output::info(&format!("Audio ({}): in \"{}\" {} Hz", backend.name(), input.device, input.sample_rate));
+    }
+    if let Some(output) = &output {
+        output::info(&format!("Audio ({}): out \"{}\" {} Hz", backend.name(), output.device, output.sample_rate));
+    }
+    state.lock().unwrap().text_only = text_only;
+    if let Err(e) = tts::init(sr_hz, &spk_buf, text_only) {
+        eprintln!("[config] {e}");
+        process::exit(2);
+    }
+    if let Some(m) = &monitor {
+        output::info(&format!("Audio ({}): monitor \"{}\" {} Hz", backend.name(), m.device, m.sample_rate));
     }
 
-    println!("Connecting to OpenAI Realtime…");
-    let (ws_stream, _) = connect_async(request).await.expect("WS connect failed");
-    println!("Connected — speak to talk; press I to interrupt, Q to quit.");
-    let (mut ws_tx, mut ws_rx) = ws_stream.split();
+    // ------------------- WebSocket -------------------
+    let extra = match session::extra_from_env() {
+        Ok(extra) => extra,
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    };
+    let settings = session::Settings { api_key, noise_reduction, extra };
 
     // Configure session: audio+text, server VAD (manual response.create), PCM16 in/out, voice
-    let session_update = json!({
-        "type": "session.update",
-        "session": {
-            "modalities": ["audio", "text"],
-            "voice": voice,
-            "instructions": "You are a concise, helpful assistant.",
-            "input_audio_format": "pcm16",
-            "output_audio_format": "pcm16",
-            // Let server VAD detect end-of-speech, but do NOT auto-create responses
-            "turn_detection": {
-                "type": "server_vad",
-                "threshold": vad_threshold,
-                "silence_duration_ms": vad_silence_ms,
-                "prefix_padding_ms": 100,
-                "create_response": false
-            },
-            // Realtime's built-in input transcription (to print "User: ...")
-            "input_audio_transcription": { "model": "whisper-1" }
+    output::info(i18n::tr("Connecting to OpenAI Realtime…"));
+    let (ws_tx, mut ws_rx) = match session::open(&model, &settings, &state).await {
+        Ok((tx, rx, _)) => (tx, rx),
+        Err(e) => {
+            if status_earcons {
+                earcon::play(&cue_buf, Earcon::Error, sr_hz, earcon_gain);
+                std::thread::sleep(Duration::from_millis(earcon::duration_ms(Earcon::Error) + 100));
+            }
+            e.exit();
         }
-    });
-    ws_tx.send(Message::Text(session_update.to_string())).await?;
-
-    // Outgoing sender task (forward Text/Binary to WS)
+    };
+    if let Some(m) = &meeting {
+        let path = m.lock().unwrap().path.clone();
+        output::info(&i18n::trf(
+            "Connected — meeting mode: transcribing to {path}, summary every {secs}s; press Q to quit.",
+            &[("path", &path), ("secs", &meeting_summary_secs)],
+        ));
+    } else if json_mode {
+        output::info(i18n::tr("Connected — reading commands from stdin."));
+    } else if text_only {
+        output::info(i18n::tr("Connected — text only; type a message and press Enter, /quit to exit."));
+    } else {
+        use keys::Action;
+        let press: Vec<String> = [
+            (Action::Interrupt, "{key} to interrupt"),
+            (Action::Mute, "{key} to mute"),
+            (Action::Quit, "{key} to quit"),
+        ]
+        .into_iter()
+        .filter_map(|(a, what)| Some(i18n::trf(what, &[("key", &keys::label(a)?)])))
+        .collect();
+        output::info(&i18n::trf("Connected — speak to talk; press {keys}.", &[("keys", &press.join(", "))]));
+    }
+    if status_earcons {
+        earcon::play(&cue_buf, Earcon::Connected, sr_hz, earcon_gain);
+    }
+    // Outgoing sender task (forward Text/Binary to WS); the socket is swapped
+    // underneath it when the session moves to a new connection
+    let ws_tx = Arc::new(tokio::sync::Mutex::new(ws_tx));
     let (out_tx, mut out_rx) = mpsc::unbounded_channel::<Message>();
+    let (switch_tx, mut switch_rx) = mpsc::unbounded_channel::<String>();
+    let event_log_tx = event_log.clone();
+    let ws_tx_send = ws_tx.clone();
     let send_task = tokio::spawn(async move {
         while let Some(msg) = out_rx.recv().await {
-            if let Err(e) = ws_tx.send(msg).await {
+            if event_log_tx.enabled()
+                && let Message::Text(t) = &msg
+                && let Ok(v) = serde_json::from_str::<serde_json::Value>(t)
+                && v["type"] != "input_audio_buffer.append"
+            {
+                event_log_tx.record("out", &v);
+            }
+            traffic::sent(msg.len());
+            if let Err(e) = ws_tx_send.lock().await.send(msg).await {
                 eprintln!("WS send error: {e:?}");
                 break;
             }
         }
     });
 
+    // Meeting mode: summarize what was said since the last summary, periodically
+    if let Some(m) = &meeting {
+        let (m, out) = (m.clone(), out_tx.clone());
+        tokio::spawn(async move {
+            let period = Duration::from_secs(meeting_summary_secs);
+            let mut tick = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                tick.tick().await;
+                let Some(req) = m.lock().unwrap().summary_request() else { continue };
+                if out.send(Message::Text(req.to_string())).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
     // Thread: mic → input_audio_buffer.append (simple onset gate while speaking)
     let out_tx_audio = out_tx.clone();
     let state_for_mic = state.clone();
+    let dataset_mic = dataset.clone();
+    let scribe_mic = scribe.clone();
+    let log_mic = event_log.clone();
+    // System audio is silent whenever nothing plays
+    let mut mic_watch = (!text_only && capture_source != capture::Source::System).then_some(mic_watch);
     std::thread::spawn(move || {
-        let mut loud_consecutive: usize = 0;
-        while let Ok(bytes) = mic_rx.recv() {
-            // compute peak of this chunk
-            let peak = {
-                let samples = unsafe {
-                    std::slice::from_raw_parts(bytes.as_ptr() as *const i16, bytes.len() / 2)
-                };
-                chunk_peak_level_i16(samples)
+        let mut bytes = Vec::with_capacity(frames_per_chunk * 2);
+        let mut encoder = wire::Encoder::default();
+        let mut watch = |peak: Option<f32>| {
+            let Some(alert) = mic_watch.as_mut().and_then(|w| w.feed(peak)) else { return };
+            output::notice("mic", &alert.message());
+            alert.follow_up();
+            let kind = if matches!(alert, micwatch::Alert::Back) { "mic.back" } else { "mic.silent" };
+            log_mic.record("local", &json!({"type": kind}));
+        };
+        loop {
+            let mut chunk = match mic_rx.recv_timeout(Duration::from_secs(1)) {
+                Ok(chunk) => chunk,
+                // No audio at all counts as silence
+                Err(RecvTimeoutError::Timeout) => {
+                    watch(None);
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
             };
-
-            // update mic meter
-            if let Ok(mut st) = state_for_mic.lock() {
-                st.mic_level = peak;
-                st.mic_bytes += bytes.len();
+            let mut peak = pcm::peak_level(&chunk);
+            watch(Some(peak));
+            // Keyboard noise right after a key press
+            if typing::suppress(&mut chunk) {
+                peak = pcm::peak_level(&chunk);
             }
 
-            // Only gate while the assistant is speaking to avoid echo false-positives
-            let speaking = state_for_mic
-                .lock()
-                .map(|s| s.response_active || s.response_inflight)
-                .unwrap_or(false);
-            if speaking {
-                if peak >= onset_peak { loud_consecutive += 1; } else { loud_consecutive = 0; }
-                if loud_consecutive < onset_min_chunks { continue; }
-            } else {
-                loud_consecutive = 0;
+            // update mic meter; only gate while the assistant is speaking
+            // to avoid echo false-positives
+            let (speaking, muted) = match state_for_mic.lock() {
+                Ok(mut st) => {
+                    st.mic_level = peak;
+                    st.mic_bytes += chunk.len() * 2;
+                    (st.speaking(), st.muted)
+                }
+                Err(_) => (false, false),
+            };
+            if muted {
+                gate.muted(chunk, speaking);
+                continue;
+            }
+            // The scribe hears everything said, gated or not
+            if let Some(s) = &scribe_mic {
+                s.user(&chunk);
             }
+            // System capture includes our own playback; never send it back
+            if capture_source == capture::Source::System && speaking {
+                gate.discard(chunk);
+                continue;
+            }
+            let mut sent = true;
+            gate.feed(chunk, peak, speaking, &mut |pcm: &[i16]| {
+                if let Some(ds) = &dataset_mic {
+                    ds.on_mic(pcm);
+                }
+                turnaudio::on_mic(pcm);
+                encoder.encode(pcm, &mut bytes);
+                traffic::sent_audio(bytes.len());
+                // The WebSocket takes ownership, so each message is one allocation
+                let msg = if binary_frames {
+                    Message::Binary(bytes.clone())
+                } else {
+                    Message::Text(pcm::append_event(&bytes))
+                };
+                sent &= out_tx_audio.send(msg).is_ok();
+            });
+            if !sent {
+                break;
+            }
+        }
+    });
 
-            // forward mic chunk
-            let b64 = base64::encode(&bytes);
-            let ev = json!({"type": "input_audio_buffer.append", "audio": b64});
-            if out_tx_audio.send(Message::Text(ev.to_string())).is_err() { break; }
+    let controls = Controls {
+        out_tx: out_tx.clone(),
+        state: state.clone(),
+        spk_buf: spk_buf.clone(),
+        cue_buf: cue_buf.clone(),
+        sr_hz,
+        earcon_gain,
+        status_earcons,
+        switch_tx,
+        models: Arc::new(models),
+    };
+
+    // Optional global hotkeys, usable while the terminal is in the background
+    match hotkeys::start(&controls) {
+        Ok(Some(keys)) => output::info(&format!("Global hotkeys: {keys}")),
+        Ok(None) => {}
+        Err(e) => output::notice("hotkeys", &e),
+    }
+
+    // Optional GPIO buttons and LEDs for terminal-free appliances
+    match gpio::start(&controls) {
+        Ok(Some(lines)) => output::info(&format!("GPIO: {lines}")),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    }
+
+    // State indicators (window title, command, OSC, the GPIO LEDs)
+    match indicator::start(state.clone()) {
+        Ok(Some(names)) => output::info(&format!("Indicators: {names}")),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    }
+
+    report::start(&model);
+
+    // Child-safe mode: count today's time against the daily limit
+    if let Some(summary) = childsafe::start(&controls) {
+        output::info(&format!("Child-safe: {summary}"));
+    }
+
+    if let Some(summary) = abtest::start() {
+        output::info(&format!("A/B test: {summary}"));
+    }
+
+    // Optional cue once a reply has played out: the floor is the user's
+    if !text_only {
+        endcue::start(&controls, end_cue);
+    }
+
+    // Optional MQTT front-end (Home Assistant): state, transcripts and commands
+    match mqtt::start(&controls) {
+        Ok(Some(broker)) => output::info(&format!("MQTT: {broker}")),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    }
+    mqtt::watch_state(state.clone());
+
+    // Optional gRPC server for services embedding parlar as a sidecar
+    match grpc::start(&controls, Arc::new(feed_mic)) {
+        Ok(Some(addr)) => output::info(&format!("gRPC: listening on {addr}")),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    }
+
+    // Reminders, timers and alarms set through the built-in tools
+    reminders::start(&controls);
+    timers::start(&controls);
+
+    // Optional Rhai script with hooks on conversation events
+    match script::start(&controls) {
+        Ok(Some(path)) => output::info(&format!("Script: {path}")),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("[config] {e}");
+            process::exit(2);
+        }
+    }
+
+    // On the way out: turns not yet on disk, the meeting notes tail, then a
+    // close frame so the server ends the session rather than timing it out
+    if let Some(ds) = dataset.clone() {
+        shutdown::on_exit(move || ds.flush());
+    }
+    if let Some(m) = meeting.clone() {
+        shutdown::on_exit(move || m.lock().unwrap().close());
+    }
+    let out_close = out_tx.clone();
+    shutdown::on_exit(move || {
+        if out_close.send(Message::Close(None)).is_ok() {
+            std::thread::sleep(Duration::from_millis(300));
         }
     });
+    shutdown::watch_signals(&controls, env_file.clone());
 
-    // Thread: keyboard (I=interrupt, Q=quit) — macOS/Linux
-    {
-        let out_tx_ctrl = out_tx.clone();
-        let spk_buf_ctrl = spk_buf.clone();
-        let state_ctrl = state.clone();
+    if service {
+        // No stdin: under a service manager it is /dev/null
+    } else if json_mode {
+        // Thread: newline-delimited JSON commands on stdin (JSON mode replaces hotkeys)
+        let controls = controls.clone();
         std::thread::spawn(move || {
-            let _ = crossterm::terminal::enable_raw_mode();
+            for line in std::io::stdin().lines() {
+                let Ok(line) = line else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let Ok(cmd) = serde_json::from_str::<serde_json::Value>(&line) else {
+                    output::notice("command_error", &format!("invalid JSON: {line}"));
+                    continue;
+                };
+                match cmd["cmd"].as_str().unwrap_or("") {
+                    "say_text" => match cmd["text"].as_str() {
+                        Some(text) => controls.say_text(text),
+                        None => output::notice("command_error", "say_text requires \"text\""),
+                    },
+                    "interrupt" => controls.interrupt(),
+                    "resume" => controls.resume(),
+                    "mute" => {
+                        controls.set_muted(cmd["on"].as_bool());
+                    }
+                    "set_instructions" if !childsafe::settings_open("set_instructions") => {}
+                    "set_instructions" => match cmd["instructions"].as_str() {
+                        Some(text) => controls.set_instructions(text),
+                        None => output::notice(
+                            "command_error",
+                            "set_instructions requires \"instructions\"",
+                        ),
+                    },
+                    "set_language" if !childsafe::settings_open("set_language") => {}
+                    "set_language" => match cmd["language"].as_str().and_then(language::by_code) {
+                        Some(lang) => controls.set_language(lang),
+                        None => output::notice(
+                            "command_error",
+                            "set_language requires a supported \"language\" code",
+                        ),
+                    },
+                    "confirm_tool" => match cmd["approve"].as_bool() {
+                        Some(yes) => {
+                            if !tools::answer(yes, &controls) {
+                                output::notice("command_error", "no tool call is waiting for confirmation");
+                            }
+                        }
+                        None => output::notice("command_error", "confirm_tool requires \"approve\": true or false"),
+                    },
+                    "traffic" => output::notice("traffic", &traffic::summary()),
+                    "set_model" => controls.switch_model(cmd["model"].as_str()),
+                    "set_verbosity" => match cmd["verbosity"].as_str() {
+                        Some(v) => match Verbosity::parse(v) {
+                            Some(v) => controls.set_verbosity(Some(v)),
+                            None => output::notice("command_error", "verbosity must be terse, normal or chatty"),
+                        },
+                        None => controls.set_verbosity(None),
+                    },
+                    "unlock" => match cmd["pin"].as_str() {
+                        Some(pin) => {
+                            childsafe::unlock(pin);
+                        }
+                        None => output::notice("command_error", "unlock requires \"pin\""),
+                    },
+                    "lock" => childsafe::lock(),
+                    "rate" => match cmd["good"].as_bool() {
+                        Some(good) => abtest::rate(good),
+                        None => output::notice("command_error", "rate requires \"good\": true or false"),
+                    },
+                    "send_event" => {
+                        if let Err(e) = controls.send_event(cmd["event"].clone()) {
+                            output::notice("command_error", &format!("send_event: {e}"));
+                        }
+                    }
+                    "raw_events" => match cmd["events"].as_str() {
+                        Some(list) => passthrough::subscribe(list),
+                        None => output::notice("command_error", "raw_events requires \"events\""),
+                    },
+                    "quit" => shutdown::exit(0),
+                    other => output::notice("command_error", &format!("unknown cmd: {other}")),
+                }
+            }
+        });
+    } else if text_only || !tui {
+        spawn_line_input(controls.clone());
+    } else {
+        spawn_keyboard(controls.clone());
+    }
+
+    if service {
+        service::start(&controls);
+    }
+
+    // Thread: hot-reload of settings when the .env file changes
+    if let Some(path) = env_file {
+        let controls = controls.clone();
+        std::thread::spawn(move || {
+            let mtime = |p: &std::path::Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+            let mut last = mtime(&path);
+            loop {
+                std::thread::sleep(Duration::from_secs(1));
+                let now = mtime(&path);
+                if now != last {
+                    last = now;
+                    controls.reload_config(&path);
+                }
+            }
+        });
+    }
+
+    // Thread: "thinking" spinner between turn commit and the first reply delta
+    if thinking_indicator {
+        let state_spin = state.clone();
+        std::thread::spawn(move || {
+            const FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+            let mut tick = 0usize;
+            let mut shown = false;
             loop {
-                if let Ok(CEvent::Key(k)) = event::read() {
-                    match k.code {
-                        KeyCode::Char('q') | KeyCode::Char('Q') => {
-                            println!("\nQuit.");
-                            process::exit(0);
+                std::thread::sleep(Duration::from_millis(100));
+                // The partial transcript line has the cursor's line
+                if output::held() || output::partial_shown() {
+                    shown = false;
+                    continue;
+                }
+                let since = state_spin.lock().map(|s| s.thinking_since).unwrap_or(None);
+                match since {
+                    Some(t) => {
+                        let secs = t.elapsed().as_secs_f32();
+                        eprint!("\r{} thinking… {secs:.1}s", FRAMES[tick % FRAMES.len()]);
+                        std::io::stderr().flush().ok();
+                        tick += 1;
+                        shown = true;
+                    }
+                    None if shown => {
+                        eprint!("\r\x1b[2K");
+                        std::io::stderr().flush().ok();
+                        shown = false;
+                    }
+                    None => {}
+                }
+            }
+        });
+    }
+
+    // Thread: timeline strip redraw
+    if !json_mode && tui {
+        let state_tl = state.clone();
+        std::thread::spawn(move || {
+            let mut shown = false;
+            loop {
+                std::thread::sleep(Duration::from_millis(250));
+                // The scrollback covers the screen; reserve the row again after it
+                if output::held() {
+                    shown = false;
+                    continue;
+                }
+                let st = state_tl.lock().unwrap();
+                match (st.show_timeline || st.show_practice, shown) {
+                    (true, _) => {
+                        if !shown {
+                            timeline::reserve_row();
+                            shown = true;
                         }
-                        KeyCode::Char('i') | KeyCode::Char('I') => {
-                            let _ = out_tx_ctrl.send(Message::Text(
-                                json!({"type": "response.cancel"}).to_string(),
-                            ));
-                            if let Some(item_id) =
-                                state_ctrl.lock().unwrap().last_assistant_item_id.clone()
-                            {
-                                let _ = out_tx_ctrl.send(Message::Text(
-                                    json!({
-                                        "type": "conversation.item.truncate",
-                                        "item_id": item_id,
-                                        "content_index": 0,
-                                        "audio_end_ms": 0
-                                    })
-                                    .to_string(),
-                                ));
-                            }
-                            if let Ok(mut q) = spk_buf_ctrl.lock() {
-                                q.clear();
-                            }
-                            eprintln!("\n[interrupt] assistant canceled.");
+                        if st.show_practice {
+                            timeline::draw_line(&format!("Practice: {}", practice::summary()));
+                        } else {
+                            timeline::draw(&st.timeline, st.playback);
                         }
-                        _ => {}
                     }
+                    (false, true) => {
+                        timeline::release_row();
+                        shown = false;
+                    }
+                    (false, false) => {}
                 }
             }
         });
@@ -471,11 +1554,95 @@ async fn main() -> Result<()> {
     // --------------- Incoming events loop ---------------
     let state_for_rx = state.clone();
     let spk_buf_for_rx = spk_buf.clone();
+    let mut text_buf = LineBuf::default();
+    let mut decoder = wire::Decoder::default();
+
+    // Session renewal: when it is due, when the current connection was made
+    // (a busy turn postpones renewal by at most 1/30 of the period past due),
+    // and whether the server already expired the session
+    let renew_period = (session_renew_secs > 0).then(|| Duration::from_secs(session_renew_secs));
+    let mut connected_at = tokio::time::Instant::now();
+    let mut renew_at = renew_period.map(|p| connected_at + p);
+    let mut session_expired = false;
 
     // Print a tiny status line once
-    println!("--- live ---");
+    output::info("--- live ---");
 
-    while let Some(msg) = ws_rx.next().await {
+    loop {
+        let renew_due = async {
+            match renew_at {
+                Some(t) => tokio::time::sleep_until(t).await,
+                None => std::future::pending().await,
+            }
+        };
+        let mut reconnect_to = None;
+        let mut local = false;
+        let msg = tokio::select! {
+            msg = ws_rx.next() => msg,
+            Some(evt) = local_rx.recv() => {
+                local = true;
+                Some(Ok(Message::Text(evt.to_string())))
+            }
+            Some(model) = switch_rx.recv() => {
+                reconnect_to = Some(model);
+                None
+            }
+            _ = renew_due => {
+                // Wait for a quiet moment: nobody speaking and no reply pending
+                let st = state_for_rx.lock().unwrap();
+                let busy = st.responses.busy()
+                    || st.thinking_since.is_some()
+                    || st.timeline.is_open(Track::User);
+                let overdue = renew_period.is_some_and(|p| connected_at.elapsed() >= p + p / 30);
+                if busy && !overdue && !session_expired {
+                    drop(st);
+                    renew_at = renew_at.map(|t| t + Duration::from_secs(2));
+                    continue;
+                }
+                reconnect_to = Some(st.model.clone());
+                None
+            }
+        };
+        // The server closing an expired session renews it instead of ending the run
+        if msg.is_none() && reconnect_to.is_none() && session_expired {
+            reconnect_to = Some(state_for_rx.lock().unwrap().model.clone());
+        }
+        if let Some(model) = reconnect_to {
+            let renewal = model == state_for_rx.lock().unwrap().model;
+            match session::reconnect(&model, &settings, &state_for_rx, &ws_tx).await {
+                Ok((rx, replayed)) => {
+                    ws_rx = rx;
+                    report::connected(&model);
+                    session_expired = false;
+                    connected_at = tokio::time::Instant::now();
+                    renew_at = renew_period.map(|p| connected_at + p);
+                    if renewal {
+                        let renewed = i18n::trf("renewed ({n} turns carried over).", &[("n", &replayed)]);
+                        output::notice("session", &renewed);
+                    } else {
+                        let now_on = i18n::trf(
+                            "now on {model} ({n} turns carried over).",
+                            &[("model", &model), ("n", &replayed)],
+                        );
+                        output::notice("model", &now_on);
+                    }
+                }
+                Err(e) if session_expired => {
+                    output::notice("session", &i18n::trf("expired and could not be renewed: {error}", &[("error", &e)]));
+                    break;
+                }
+                Err(e) if renewal => {
+                    output::notice("session", &i18n::trf("renewal failed, retrying in 30s: {error}", &[("error", &e)]));
+                    renew_at = Some(tokio::time::Instant::now() + Duration::from_secs(30));
+                }
+                Err(e) => output::notice(
+                    "model",
+                    &i18n::trf("cannot switch to {model}: {error}", &[("model", &model), ("error", &e)]),
+                ),
+            }
+            continue;
+        }
+        let Some(msg) = msg else { break };
         let msg = match msg {
             Ok(m) => m,
             Err(e) => {
@@ -483,6 +1650,9 @@ async fn main() -> Result<()> {
                 break;
             }
         };
+        if !local {
+            traffic::received(msg.len());
+        }
         if !msg.is_text() {
             continue;
         }
@@ -491,188 +1661,605 @@ async fn main() -> Result<()> {
             continue;
         };
         let et = evt["type"].as_str().unwrap_or("");
+        event_log.record(if local { "local" } else { "in" }, &evt);
+        if let Some(ds) = &dataset {
+            ds.on_event(&evt);
+        }
+        passthrough::on_event(&evt);
+        turnaudio::on_event(&evt);
+        speaker::on_event(&evt, &controls);
+        retranscribe::on_event(&evt);
+        noise::on_event(&evt);
+        tts::on_event(&evt);
+        report::on_event(&evt);
+        if let Some(figures) = practice::on_event(&evt)
+            && practice_lines
+        {
+            output::notice("practice", &figures);
+        }
+        // Summary responses are out of band: keep them away from the conversation state
+        if let Some(m) = &meeting
+            && m.lock().unwrap().handle_event(&evt)
+        {
+            continue;
+        }
 
         match et {
             "session.created" => { /* no-op */ }
             "error" => {
                 let code = evt["error"]["code"].as_str().unwrap_or("");
                 let msg = evt["error"]["message"].as_str().unwrap_or("");
-                if code != "response_cancel_not_active" {
-                    eprintln!("\n[realtime error] {code} {msg}");
+                // A refused response.create: no reply is coming for it
+                if let Some(event_id) = evt["error"]["event_id"].as_str() {
+                    let mut st = state_for_rx.lock().unwrap();
+                    let next = turn_taking::refused(&mut st.responses, event_id);
+                    if !st.responses.busy() {
+                        st.thinking_since = None;
+                    }
+                    if let Some(create) = st.queued_reply(next) {
+                        let _ = out_tx.send(Message::Text(create.to_string()));
+                    }
+                }
+                if code == "session_expired" {
+                    // Renew right away; the server closes this connection next
+                    output::notice("session", i18n::tr("expired; renewing."));
+                    session_expired = true;
+                    renew_at = Some(tokio::time::Instant::now());
+                } else if code != "response_cancel_not_active" {
+                    output::notice("realtime_error", &format!("{code} {msg}"));
+                    indicator::error();
+                    if status_earcons {
+                        earcon::play(&cue_buf, Earcon::Error, sr_hz, earcon_gain);
+                    }
                 }
             }
 
             // Server VAD: when the buffer is committed, schedule exactly one response
+            "input_audio_buffer.committed" if meeting.is_some() => {
+                let mut st = state_for_rx.lock().unwrap();
+                st.timeline.mark(Mark::Commit);
+                st.responses.item_added(evt["item_id"].as_str().unwrap_or(""), "user", None);
+            }
             "input_audio_buffer.committed" => {
                 // schedule response after adaptive pause
                 let (out, st_arc) = (out_tx.clone(), state_for_rx.clone());
-                let delay_ms = {
-                    let st = st_arc.lock().unwrap();
-                    let u = st.last_user.clone();
-                    if u.ends_with('.') || u.ends_with('!') || u.ends_with('?') {
-                        resp_delay_short_ms
-                    } else {
-                        resp_delay_long_ms
+                let item_id = evt["item_id"].as_str().unwrap_or("").to_string();
+                let (filter, log) = (transcript_filter.clone(), event_log.clone());
+                let (commands, controls) = (voice_commands.clone(), controls.clone());
+                let (rag, spk_buf) = (rag.clone(), spk_buf_for_rx.clone());
+                {
+                    let mut st = st_arc.lock().unwrap();
+                    st.timeline.mark(Mark::Commit);
+                    st.responses.item_added(&item_id, "user", None);
+                    if !st.responses.busy() {
+                        st.thinking_since = Some(Instant::now());
+                        if thinking_earcon {
+                            earcon::play(&cue_buf, Earcon::Thinking, sr_hz, earcon_gain);
+                        }
                     }
-                };
+                }
                 tokio::spawn(async move {
-                    tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                    // Too little speech to be a turn: a cough, a knock
+                    if let Some(ms) = noise::too_short(&item_id) {
+                        let _ = out.send(Message::Text(
+                            json!({"type": "conversation.item.delete", "item_id": item_id}).to_string(),
+                        ));
+                        {
+                            let mut st = st_arc.lock().unwrap();
+                            st.thinking_since = None;
+                            st.history.forget(&item_id);
+                        }
+                        log.record("local", &json!({"type": "turn.short", "item_id": item_id}));
+                        output::notice("noise", &format!("ignored a turn with {ms} ms of speech."));
+                        return;
+                    }
+                    let committed_at = Instant::now();
+                    let (turn, lang) = {
+                        let st = st_arc.lock().unwrap();
+                        (st.turn.clone(), st.language)
+                    };
+                    // The transcript usually completes after the commit; give it a
+                    // moment so the delay is chosen on this turn, not the last one
+                    let mut transcript = None;
+                    if !turn.semantic {
+                        transcript = await_transcript(&st_arc, &item_id, turn.transcript_wait_ms).await;
+                    }
+                    let ending = Ending::classify(transcript.as_deref().unwrap_or(""), lang);
+                    let delay_ms = turn.delay_for(ending);
+                    log.record(
+                        "local",
+                        &json!({
+                            "type": "turn.delay",
+                            "item_id": item_id,
+                            "ending": ending.name(),
+                            "transcript_wait_ms": committed_at.elapsed().as_millis() as u64,
+                            "delay_ms": delay_ms
+                        }),
+                    );
+                    // Time spent waiting for the transcript counts toward the pause
+                    tokio::time::sleep(Duration::from_millis(delay_ms).saturating_sub(committed_at.elapsed())).await;
+
+                    // Answers to a tool question and commands to parlar are handled
+                    // here and never reach the model
+                    let awaiting = tools::awaiting_voice();
+                    if awaiting || commands.is_some() {
+                        if transcript.is_none() {
+                            transcript = await_transcript(&st_arc, &item_id, turn.transcript_wait_ms).await;
+                        }
+                        let answer = transcript.as_deref().filter(|_| awaiting).and_then(tools::spoken_answer);
+                        let command = transcript.as_deref().and_then(|t| commands.as_ref()?.parse(t));
+                        if answer.is_some() || command.is_some() {
+                            let _ = out.send(Message::Text(
+                                json!({"type": "conversation.item.delete", "item_id": item_id}).to_string(),
+                            ));
+                            {
+                                let mut st = st_arc.lock().unwrap();
+                                st.thinking_since = None;
+                                st.history.forget(&item_id);
+                            }
+                            match (answer, command) {
+                                (Some(yes), _) => {
+                                    tools::answer(yes, &controls);
+                                }
+                                (None, Some(Ok(command))) => {
+                                    log.record(
+                                        "local",
+                                        &json!({"type": "voice.command", "item_id": item_id, "command": command.name()}),
+                                    );
+                                    voicecmd::execute(command, &controls);
+                                }
+                                (None, Some(Err(why))) => voicecmd::reject(&why, &controls),
+                                (None, None) => {}
+                            }
+                            return;
+                        }
+                    }
+
+                    // A quiet turn heard as a word or two is noise the transcription made up
+                    if noise::quiet(&item_id) {
+                        if transcript.is_none() {
+                            transcript = await_transcript(&st_arc, &item_id, turn.transcript_wait_ms).await;
+                        }
+                        if let Some(heard) = transcript.clone().filter(|t| noise::short(t)) {
+                            let _ = out.send(Message::Text(
+                                json!({"type": "conversation.item.delete", "item_id": item_id}).to_string(),
+                            ));
+                            {
+                                let mut st = st_arc.lock().unwrap();
+                                st.thinking_since = None;
+                                st.history.forget(&item_id);
+                            }
+                            log.record("local", &json!({"type": "turn.noise", "item_id": item_id}));
+                            output::notice("noise", &format!("ignored a quiet turn heard as \"{}\".", heard.trim()));
+                            return;
+                        }
+                    }
+
+                    // Child-safe mode drops a turn using a blocked word
+                    if childsafe::has_blocklist() {
+                        if transcript.is_none() {
+                            transcript = await_transcript(&st_arc, &item_id, filter_wait_ms).await;
+                        }
+                        if let Some(word) = transcript.as_deref().and_then(childsafe::blocked) {
+                            let _ = out.send(Message::Text(
+                                json!({"type": "conversation.item.delete", "item_id": item_id}).to_string(),
+                            ));
+                            {
+                                let mut st = st_arc.lock().unwrap();
+                                st.thinking_since = None;
+                                st.history.forget(&item_id);
+                            }
+                            log.record("local", &json!({"type": "childsafe.blocked", "item_id": item_id, "what": "turn"}));
+                            childsafe::refuse("turn", word, &controls);
+                            return;
+                        }
+                    }
+
+                    // The filter needs this turn's transcript, which may still be on its way
+                    if let Some(filter) = filter {
+                        if transcript.is_none() {
+                            transcript = await_transcript(&st_arc, &item_id, filter_wait_ms).await;
+                        }
+                        let verdict = match transcript.clone() {
+                            Some(t) => tokio::task::spawn_blocking(move || filter.check(&t)).await.ok(),
+                            None => None,
+                        };
+                        let Some(verdict) = verdict else {
+                            // Unchecked audio must not stay in the conversation for the next reply to answer
+                            let _ = out.send(Message::Text(
+                                json!({"type": "conversation.item.delete", "item_id": item_id}).to_string(),
+                            ));
+                            output::notice("filter", "no transcript to check; turn suppressed.");
+                            let mut st = st_arc.lock().unwrap();
+                            st.thinking_since = None;
+                            st.history.forget(&item_id);
+                            return;
+                        };
+                        for reason in &verdict.alerts {
+                            output::notice("filter", reason);
+                            log.record(
+                                "local",
+                                &json!({"type": "filter.alert", "item_id": item_id, "reason": reason}),
+                            );
+                        }
+                        match verdict.action {
+                            FilterAction::Allow => {}
+                            FilterAction::Suppress => {
+                                // Drop the turn from the conversation so it never reaches the model
+                                let _ = out.send(Message::Text(
+                                    json!({"type": "conversation.item.delete", "item_id": item_id})
+                                        .to_string(),
+                                ));
+                                {
+                                    let mut st = st_arc.lock().unwrap();
+                                    st.thinking_since = None;
+                                    st.history.forget(&item_id);
+                                }
+                                output::notice("filter", "turn suppressed.");
+                                return;
+                            }
+                            FilterAction::Replace(text) => {
+                                // The replacement is remembered when its item comes back
+                                st_arc.lock().unwrap().history.forget(&item_id);
+                                let _ = out.send(Message::Text(
+                                    json!({"type": "conversation.item.delete", "item_id": item_id})
+                                        .to_string(),
+                                ));
+                                let _ = out.send(Message::Text(
+                                    json!({
+                                        "type": "conversation.item.create",
+                                        "item": {
+                                            "type": "message",
+                                            "role": "user",
+                                            "content": [{"type": "input_text", "text": text}]
+                                        }
+                                    })
+                                    .to_string(),
+                                ));
+                                transcript = Some(text);
+                            }
+                        }
+                    }
+
+                    // A question about local documents gets excerpts from them first
+                    if let Some(rag) = rag {
+                        if transcript.is_none() {
+                            transcript = await_transcript(&st_arc, &item_id, turn.transcript_wait_ms).await;
+                        }
+                        if let Some(t) = transcript.as_deref().filter(|t| rag.wants(t)) {
+                            match rag.search(t).await {
+                                Ok(found) if !found.is_empty() => {
+                                    let sources: Vec<&str> = found.iter().map(|s| s.source.as_str()).collect();
+                                    output::notice("rag", &format!("{} excerpt(s): {}", found.len(), sources.join(", ")));
+                                    log.record(
+                                        "local",
+                                        &json!({"type": "rag.context", "item_id": item_id, "sources": sources}),
+                                    );
+                                    let _ = out.send(Message::Text(rag::context_event(&found)));
+                                }
+                                Ok(_) => {}
+                                Err(e) => output::notice("rag", &e),
+                            }
+                        }
+                    }
+
                     let mut st = st_arc.lock().unwrap();
-                    if !st.response_inflight && !st.response_active {
-                        st.response_inflight = true;
-                        let _ = out.send(Message::Text(json!({"type":"response.create"}).to_string()));
+                    // The user went on after a short pause: the next commit answers both parts
+                    let merge = Duration::from_millis(turn.merge_ms);
+                    if st.speech_started_at.is_some_and(|t| t > committed_at && t - committed_at < merge) {
+                        st.thinking_since = None;
+                        log.record("local", &json!({"type": "turn.merged", "item_id": item_id}));
+                        return;
+                    }
+                    let busy = match turn_taking::committed(&mut st.responses, Policy::parse(&turn.queue)) {
+                        Turn::Answer(event_id) => {
+                            let create = st.create_event(&event_id);
+                            let _ = out.send(Message::Text(create.to_string()));
+                            return;
+                        }
+                        busy => busy,
+                    };
+                    // Another reply is under way
+                    log.record("local", &json!({"type": "turn.busy", "item_id": item_id, "policy": turn.queue}));
+                    match busy {
+                        Turn::Dropped => {
+                            st.thinking_since = None;
+                            output::notice("turn", i18n::tr("a reply is under way; turn not answered."));
+                        }
+                        Turn::CutOff => {
+                            drop(st);
+                            // Answered when the cut-off reply is done
+                            interrupt::cancel(&st_arc, interrupt::Path::Speech, &out, &spk_buf);
+                        }
+                        _ => {}
                     }
                 });
             }
 
-            // Track assistant message item id for truncate
+            // Track which response is streaming so cancelled ones can be filtered
+            "response.created" => {
+                if let Some(id) = evt["response"]["id"].as_str() {
+                    let mut st = state_for_rx.lock().unwrap();
+                    st.responses.created(id);
+                    st.last_assistant.clear();
+                    text_buf.clear();
+                }
+            }
+
+            // Track assistant message items per response for truncate
             "response.output_item.added" => {
                 if let Some(id) = evt["item"]["id"].as_str() {
-                    state_for_rx.lock().unwrap().last_assistant_item_id =
-                        Some(id.to_string());
+                    let role = evt["item"]["role"].as_str().unwrap_or("assistant");
+                    state_for_rx
+                        .lock()
+                        .unwrap()
+                        .responses
+                        .item_added(id, role, evt["response_id"].as_str());
                 }
             }
+            // The model called a tool
+            "response.output_item.done" if evt["item"]["type"] == "function_call" => {
+                event_log.record(
+                    "local",
+                    &json!({"type": "tool.call", "name": evt["item"]["name"], "call_id": evt["item"]["call_id"]}),
+                );
+                tools::call(&evt["item"], &controls);
+            }
             "conversation.item.created" => {
                 let role = evt["item"]["role"].as_str().unwrap_or("");
-                if role == "assistant" {
-                    if let Some(id) = evt["item"]["id"].as_str() {
-                        state_for_rx.lock().unwrap().last_assistant_item_id =
-                            Some(id.to_string());
-                    }
-                } else if role == "user" {
+                let id = evt["item"]["id"].as_str().unwrap_or("");
+                // Turns replayed into a new session were shown when they happened
+                if id.starts_with(history::REPLAY_ID_PREFIX) {
+                    continue;
+                }
+                if !id.is_empty() {
+                    state_for_rx.lock().unwrap().responses.item_added(id, role, None);
+                }
+                if role == "user" {
                     // Show the finalized transcript/text for the user turn, but do not schedule
                     // response here; rely on input_audio_buffer.committed for turn-taking.
                     if let Some(s) = evt["item"]["content"][0]["transcript"].as_str() {
-                        println!("\nUser: {}", s);
+                        output::user(s);
                         state_for_rx.lock().unwrap().last_user = s.to_string();
                     } else if let Some(s) = evt["item"]["content"][0]["text"].as_str() {
-                        println!("\nUser: {}", s);
-                        state_for_rx.lock().unwrap().last_user = s.to_string();
+                        output::user(s);
+                        let mut st = state_for_rx.lock().unwrap();
+                        st.last_user = s.to_string();
+                        st.history.remember("user", s, Some(id));
                     }
                 }
             }
 
             // Assistant audio streaming
             "response.audio.delta" => {
-                if let Some(b64) = evt["delta"].as_str() {
-                    if let Ok(bytes) = base64::decode(b64) {
-                        let samples = unsafe {
-                            std::slice::from_raw_parts(
-                                bytes.as_ptr() as *const i16,
-                                bytes.len() / 2,
-                            )
-                        };
-                        {
-                            let mut st = state_for_rx.lock().unwrap();
-                            st.response_active = true;
+                if let Some(b64) = evt["delta"].as_str()
+                    && let Ok(bytes) = B64.decode(b64)
+                {
+                    traffic::received_audio(bytes.len());
+                    let samples = decoder.decode(&bytes);
+                    {
+                        let mut st = state_for_rx.lock().unwrap();
+                        if !st.responses.audio(evt["response_id"].as_str()) {
+                            resume::late(evt["response_id"].as_str().unwrap_or(""), samples);
+                            continue;
                         }
-                        // push to speaker ring buffer
-                        let mut rb = spk_buf_for_rx.lock().unwrap();
-                        rb.extend(samples.iter().copied());
+                        st.thinking_since = None;
+                        st.timeline.start(Track::Assistant);
+                        st.echo.audio(evt["response_id"].as_str().unwrap_or(""));
+                    }
+                    resume::forget();
+                    if let Some(s) = &scribe {
+                        s.assistant(samples);
                     }
+                    grpc::assistant_audio(samples);
+                    // push to speaker ring buffer
+                    let mut rb = spk_buf_for_rx.lock().unwrap();
+                    rb.extend(samples.iter().copied());
                 }
             }
             "response.audio.done" => {
                 let mut st = state_for_rx.lock().unwrap();
-                st.response_active = false;
-                st.response_inflight = false;
+                if !st.responses.audio_done(evt["response_id"].as_str()) {
+                    continue;
+                }
+                st.end_cue_pending = true;
+                st.timeline.stop(Track::Assistant);
             }
 
-            // Assistant text streaming
-            "response.text.delta" => {
+            // Assistant text streaming: the text, or the spoken reply's transcript
+            "response.text.delta" | "response.audio_transcript.delta" => {
                 if let Some(delta) = evt["delta"].as_str() {
-                    print!("{}", delta);
-                    use std::io::Write;
-                    std::io::stdout().flush().ok();
-                    state_for_rx.lock().unwrap().last_assistant.push_str(delta);
+                    let mut st = state_for_rx.lock().unwrap();
+                    if st.responses.is_cancelled(evt["response_id"].as_str()) || !st.shows(&evt) {
+                        continue;
+                    }
+                    if let Some(word) = childsafe::reply_delta(evt["response_id"].as_str().unwrap_or(""), delta) {
+                        drop(st);
+                        controls.interrupt();
+                        event_log.record("local", &json!({"type": "childsafe.blocked", "what": "reply"}));
+                        childsafe::refuse("reply", word, &controls);
+                        continue;
+                    }
+                    st.thinking_since = None;
+                    match &postprocess {
+                        None => {
+                            output::assistant_delta(delta);
+                            st.last_assistant.push_str(delta);
+                        }
+                        Some(pp) => {
+                            if let Some(lines) = text_buf.push(delta, pp) {
+                                if pp.streams() {
+                                    output::assistant_delta(&lines);
+                                }
+                                st.last_assistant.push_str(&lines);
+                            }
+                        }
+                    }
                 }
             }
-            "response.text.done" => {
-                println!();
-                state_for_rx.lock().unwrap().response_inflight = false;
+            "response.text.done" | "response.audio_transcript.done" => {
+                let mut st = state_for_rx.lock().unwrap();
+                if st.responses.is_cancelled(evt["response_id"].as_str()) || !st.shows(&evt) {
+                    continue;
+                }
+                match &postprocess {
+                    None => output::assistant_done(&st.last_assistant),
+                    Some(pp) => {
+                        let rest = text_buf.flush(pp);
+                        st.last_assistant.push_str(&rest);
+                        if pp.streams() {
+                            output::assistant_delta(&rest);
+                            output::assistant_done(&st.last_assistant);
+                        } else {
+                            // The command sees the whole message; keep it off the event loop
+                            let (pp, text) = (pp.clone(), st.last_assistant.clone());
+                            tokio::task::spawn_blocking(move || {
+                                let text = pp.run_command(&text);
+                                output::assistant_delta(&text);
+                                output::assistant_done(&text);
+                            });
+                        }
+                    }
+                }
+                let text = st.last_assistant.clone();
+                st.history.remember("assistant", &text, evt["item_id"].as_str());
             }
             "response.done" => {
+                // Cancelled replies end here too
+                if let Some(s) = &scribe {
+                    s.assistant_done();
+                }
+                abtest::reply_done(&evt, &controls);
                 let mut st = state_for_rx.lock().unwrap();
-                st.response_active = false;
-                st.response_inflight = false;
+                hooks::response_done(&evt, &st.model, &st.last_user);
+                mqtt::assistant(&hooks::response_text(&evt));
+                script::assistant_done(&hooks::response_text(&evt), evt["response"]["status"].as_str().unwrap_or(""));
+                // Nothing more will arrive for this response; a cancelled one
+                // finishing must not clear a newer one's flags
+                let done = turn_taking::done(&mut st.responses, evt["response"]["id"].as_str());
+                if done.ended {
+                    st.timeline.stop(Track::Assistant);
+                    st.thinking_since = None;
+                }
+                // Turns that came in meanwhile get their reply now
+                if let Some(create) = st.queued_reply(done.next) {
+                    let _ = out_tx.send(Message::Text(create.to_string()));
+                }
             }
 
             // Server indicates start of user speech — cancel and flush audio
             "input_audio_buffer.speech_started" => {
+                hooks::speech_started();
                 let mut st = state_for_rx.lock().unwrap();
-                if st.response_active || st.response_inflight {
-                    st.response_active = false;
-                    st.response_inflight = false;
-                    st.last_cancel_at = Some(Instant::now());
-                    drop(st);
-                    let _ = out_tx.send(Message::Text(json!({"type":"response.cancel"}).to_string()));
-                    if let Some(item_id) = state_for_rx.lock().unwrap().last_assistant_item_id.clone() {
-                        let _ = out_tx.send(Message::Text(json!({
-                            "type":"conversation.item.truncate",
-                            "item_id": item_id,
-                            "content_index": 0,
-                            "audio_end_ms": 0
-                        }).to_string()));
+                st.thinking_since = None;
+                st.speech_started_at = Some(Instant::now());
+                st.timeline.start(Track::User);
+                let speaking = st.speaking();
+                drop(st);
+                if speaking && interrupt::cancel(&state_for_rx, interrupt::Path::Speech, &out_tx, &spk_buf_for_rx) {
+                    let echo_warning = state_for_rx.lock().unwrap().echo.speech_cancel();
+                    if let Some(warning) = echo_warning {
+                        output::notice("echo", warning);
+                        event_log.record("local", &json!({"type": "echo.warning"}));
                     }
-                    let mut q = spk_buf_for_rx.lock().unwrap();
-                    q.clear();
                 }
             }
 
+            "input_audio_buffer.speech_stopped" => {
+                state_for_rx.lock().unwrap().timeline.stop(Track::User);
+            }
+
             // When enabled in session: finalized input transcript event
             "conversation.item.input_audio_transcription.completed" => {
                 if let Some(tr) = evt["transcript"].as_str() {
-                    println!("\nUser: {}", tr);
+                    output::user(tr);
+                    hooks::transcript(evt["item_id"].as_str().unwrap_or(""), tr);
+                    mqtt::transcript(tr);
+                    script::user_transcript(tr);
+                    if let Some(m) = &meeting {
+                        m.lock().unwrap().on_transcript(tr);
+                    }
                     let mut st = state_for_rx.lock().unwrap();
+                    if let Some(item_id) = evt["item_id"].as_str() {
+                        st.responses.item_added(item_id, "user", None);
+                        if let Some(it) = st.responses.items.get_mut(item_id) {
+                            it.transcript = Some(tr.to_string());
+                        }
+                    }
                     st.last_user = tr.to_string();
                     st.last_user_partial.clear();
+                    st.history.remember("user", tr, evt["item_id"].as_str());
+
+                    // Language drift: the same other language twice in a row → offer or switch
+                    let current = st.barge_in_lang();
+                    let drifted = match language::detect(tr) {
+                        Some(lang) if lang.code != current.code => {
+                            let n = match st.lang_streak {
+                                Some((l, n)) if l.code == lang.code => n + 1,
+                                _ => 1,
+                            };
+                            st.lang_streak = Some((lang, n));
+                            (n >= 2).then_some(lang)
+                        }
+                        Some(_) => {
+                            st.lang_streak = None;
+                            None
+                        }
+                        None => None,
+                    };
+                    drop(st);
+                    if let Some(lang) = drifted {
+                        match lang_switch.as_str() {
+                            "off" => {}
+                            "auto" => controls.set_language(lang),
+                            _ => controls.offer_language(lang),
+                        }
+                    }
                 }
             }
 
-            // Incremental transcription deltas (for continuous recognition + barge-in keywords)
+            "conversation.item.input_audio_transcription.failed" => {
+                let language = state_for_rx.lock().unwrap().language.map(|l| l.code);
+                retranscribe::failed(&evt, language);
+            }
+
+            // Incremental transcription deltas (live transcript + barge-in keywords)
             "conversation.item.input_audio_transcription.delta" => {
                 if let Some(delta) = evt["delta"].as_str() {
+                    output::user_partial(evt["item_id"].as_str().unwrap_or(""), delta);
                     let mut st = state_for_rx.lock().unwrap();
                     st.last_user_partial.push_str(delta);
-                    let speaking = st.response_active || st.response_inflight;
-                    let now = Instant::now();
-                    let cooldown_ok = st
-                        .last_cancel_at
-                        .map(|t| now.duration_since(t) >= Duration::from_millis(cancel_cooldown_ms))
-                        .unwrap_or(true);
-                    let text_lc = st.last_user_partial.to_lowercase();
-                    let contains_hot = text_lc.contains(" stop")
-                        || text_lc.starts_with("stop")
-                        || text_lc.contains(" wait")
-                        || text_lc.contains(" hold on")
-                        || text_lc.contains(" hey");
-                    if speaking && cooldown_ok && contains_hot {
-                        st.last_cancel_at = Some(now);
-                        drop(st);
-                        let _ = out_tx
-                            .send(Message::Text(json!({"type":"response.cancel"}).to_string()));
-                        if let Some(item_id) = state_for_rx.lock().unwrap().last_assistant_item_id.clone() {
-                            let _ = out_tx.send(Message::Text(
-                                json!({"type":"conversation.item.truncate","item_id":item_id,"content_index":0,"audio_end_ms":0}).to_string()
-                            ));
-                        }
-                        if let Ok(mut q) = spk_buf_for_rx.lock() { q.clear(); }
-                        let mut st2 = state_for_rx.lock().unwrap();
-                        st2.last_user_partial.clear();
-                        st2.response_active = false;
-                        st2.response_inflight = false;
-                        eprintln!("\n[interrupt:keyword] assistant canceled.");
+                    let speaking = st.speaking();
+                    let contains_hot = language::has_barge_in(&st.last_user_partial, st.barge_in_lang());
+                    drop(st);
+                    if speaking
+                        && contains_hot
+                        && interrupt::cancel(&state_for_rx, interrupt::Path::Keyword, &out_tx, &spk_buf_for_rx)
+                    {
+                        state_for_rx.lock().unwrap().last_user_partial.clear();
                     }
                 }
             }
 
-            _ => { /* ignore others */ }
+            _ => passthrough::on_unhandled(&evt),
         }
     }
 
     drop(out_tx);
     let _ = send_task.await;
 
-    println!("Connection closed.");
+    if !json_mode {
+        #[cfg(feature = "tui")]
+        let _ = crossterm::execute!(output::terminal(), event::DisableFocusChange);
+        timeline::release_row();
+    }
+    output::info(i18n::tr("Connection closed."));
+    indicator::error();
+    output::info(&i18n::trf("Traffic: {summary}", &[("summary", &traffic::summary())]));
+    if status_earcons {
+        earcon::play(&cue_buf, Earcon::Disconnected, sr_hz, earcon_gain);
+        tokio::time::sleep(Duration::from_millis(earcon::duration_ms(Earcon::Disconnected) + 100)).await;
+    }
+    bugreport::finish();
     Ok(())
 }
@@ -0,0 +1,130 @@
+// Meeting notes mode: transcribe continuously without replying, and every
+// few minutes ask for an out-of-band summary (a response outside the
+// conversation, so it never speaks) of what was said since the last one.
+// Transcript lines and summaries go to one Markdown file as they arrive.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Local};
+use serde_json::{Value, json};
+
+use crate::crypt::Appender;
+
+const PURPOSE: &str = "meeting_summary";
+const INSTRUCTIONS: &str = "You are taking meeting notes. Summarize the transcript excerpt below \
+    as 2-6 concise Markdown bullets, then a line `**Action items**` followed by one `- [ ]` \
+    checkbox per action item with its owner if named (write `- none` if there are none). \
+    Output only the Markdown.";
+
+pub struct Meeting {
+    file: Appender,
+    pub path: String,
+    /// Transcript not yet summarized, and when it started.
+    pending: String,
+    pending_since: Option<DateTime<Local>>,
+    /// Summary responses in flight, and the spans they cover in request order.
+    inflight: HashSet<String>,
+    spans: Vec<(DateTime<Local>, DateTime<Local>)>,
+}
+
+impl Meeting {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let mut file = Appender::open(path)?;
+        let now = Local::now();
+        let title = format!("# Meeting notes — {} {}\n\n", crate::i18n::date(&now), now.format("%H:%M"));
+        file.append(title.as_bytes()).map_err(|e| format!("{path}: {e}"))?;
+        Ok(Meeting {
+            file,
+            path: path.to_string(),
+            pending: String::new(),
+            pending_since: None,
+            inflight: HashSet::new(),
+            spans: Vec::new(),
+        })
+    }
+
+    fn write(&mut self, text: &str) {
+        let text = crate::redact::apply(text);
+        if let Err(e) = self.file.append(text.as_bytes()).and_then(|_| self.file.sync()) {
+            crate::output::notice("meeting", &format!("{}: {e}", self.path));
+        }
+    }
+
+    pub fn on_transcript(&mut self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let now = Local::now();
+        self.write(&format!("[{}] {text}\n\n", now.format("%H:%M:%S")));
+        self.pending_since.get_or_insert(now);
+        self.pending.push_str(text);
+        self.pending.push('\n');
+    }
+
+    /// `response.create` for an out-of-band summary of the pending transcript, if any.
+    pub fn summary_request(&mut self) -> Option<Value> {
+        let since = self.pending_since.take()?;
+        let text = std::mem::take(&mut self.pending);
+        self.spans.push((since, Local::now()));
+        Some(json!({
+            "type": "response.create",
+            "response": {
+                "conversation": "none",
+                "modalities": ["text"],
+                "metadata": {"purpose": PURPOSE},
+                "instructions": INSTRUCTIONS,
+                "input": [{
+                    "type": "message",
+                    "role": "user",
+                    "content": [{"type": "input_text", "text": text}]
+                }]
+            }
+        }))
+    }
+
+    /// Note what was left unsummarized when the session ends.
+    pub fn close(&mut self) {
+        let since = self.spans.first().map(|s| s.0).or(self.pending_since);
+        if let Some(since) = since {
+            let note = format!("_Session ended before the summary from {} was written._\n", since.format("%H:%M"));
+            self.write(&note);
+        }
+    }
+
+    /// Consume events that belong to summary responses; true if handled.
+    pub fn handle_event(&mut self, evt: &Value) -> bool {
+        match evt["type"].as_str().unwrap_or("") {
+            "response.created" if evt["response"]["metadata"]["purpose"] == PURPOSE => {
+                if let Some(id) = evt["response"]["id"].as_str() {
+                    self.inflight.insert(id.to_string());
+                }
+                true
+            }
+            "response.done" => {
+                let Some(id) = evt["response"]["id"].as_str() else { return false };
+                if !self.inflight.remove(id) {
+                    return false;
+                }
+                let text: String = evt["response"]["output"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|item| item["content"].as_array().into_iter().flatten())
+                    .filter_map(|c| c["text"].as_str())
+                    .collect();
+                // Summaries complete in request order
+                let (from, to) = if self.spans.is_empty() {
+                    (Local::now(), Local::now())
+                } else {
+                    self.spans.remove(0)
+                };
+                let heading = format!("### Summary {}–{}", from.format("%H:%M"), to.format("%H:%M"));
+                self.write(&format!("{heading}\n\n{}\n\n", text.trim()));
+                crate::output::notice("meeting", &format!("summary written to {}.", self.path));
+                true
+            }
+            _ => evt["response_id"].as_str().is_some_and(|id| self.inflight.contains(id)),
+        }
+    }
+}
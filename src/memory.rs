@@ -0,0 +1,59 @@
+// User memory: facts and preferences kept in a local Markdown file
+// (MEMORY_FILE, e.g. `memory.md`) and put in front of the instructions of
+// every session, so the assistant knows them from one run to the next
+// without anything stored on the server. The file is plain text to edit by
+// hand; the voice command "parlar, remember that …" appends a line to it,
+// redacted, and sealed like the transcripts when encryption is on (such a
+// file is read back with `parlar decrypt`).
+
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+static PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+// The file's contents, as last read or written
+static FACTS: Mutex<String> = Mutex::new(String::new());
+
+/// Read MEMORY_FILE; call once at startup. A file that does not exist yet is
+/// created by the first fact remembered.
+pub fn init() -> Result<(), String> {
+    let path = std::env::var("MEMORY_FILE").ok().filter(|p| !p.trim().is_empty()).map(PathBuf::from);
+    if let Some(p) = &path {
+        match crate::crypt::read_appended(p) {
+            Ok(text) => *FACTS.lock().unwrap() = String::from_utf8_lossy(&text).into_owned(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("MEMORY_FILE {}: {e}", p.display())),
+        }
+    }
+    let _ = PATH.set(path);
+    Ok(())
+}
+
+/// What goes before the instructions; empty without remembered facts.
+pub fn preamble() -> String {
+    let facts = FACTS.lock().unwrap();
+    if facts.trim().is_empty() {
+        return String::new();
+    }
+    format!("What you know about the user from earlier conversations:\n{}\n\n", facts.trim())
+}
+
+/// The remembered facts, for the {{memory}} template variable.
+pub fn facts() -> String {
+    FACTS.lock().unwrap().trim().to_string()
+}
+
+/// Append `fact` to the memory file, as a list item.
+pub fn remember(fact: &str) -> Result<(), String> {
+    let Some(Some(path)) = PATH.get() else {
+        return Err("nowhere to remember it: MEMORY_FILE is not set.".into());
+    };
+    let mut facts = FACTS.lock().unwrap();
+    let fact = crate::redact::apply(fact);
+    let line = format!("{}- {}\n", if facts.is_empty() || facts.ends_with('\n') { "" } else { "\n" }, fact);
+    let path = path.to_string_lossy();
+    crate::crypt::Appender::open(&path)
+        .and_then(|mut f| f.append(line.as_bytes()).and_then(|_| f.sync()).map_err(|e| format!("{path}: {e}")))
+        .map_err(|e| format!("cannot write {e}"))?;
+    facts.push_str(&line);
+    Ok(())
+}
@@ -0,0 +1,92 @@
+// Mic watchdog. A device that lost its permission or its connection often
+// goes on delivering buffers of zeros, or nothing at all, and parlar looks
+// connected while it hears nothing. A real microphone always picks up some
+// noise, so input that stays at digital silence for MIC_WATCHDOG_SECS
+// (default 10, 0 turns it off) is reported once, with what to check; a
+// second notice says when sound is back.
+//
+// On macOS a terminal that was never given the microphone (TCC) captures
+// zeros without any error. Silence from the very start is taken for that:
+// the notice walks through granting the permission, and the Microphone
+// page of the privacy settings is opened.
+
+use std::time::{Duration, Instant};
+
+// A peak at or under this (a few steps of 16-bit audio) is digital silence
+const SILENT_PEAK: f32 = 4.0 / i16::MAX as f32;
+
+pub struct MicWatch {
+    limit: Option<Duration>,
+    silent_since: Option<Instant>,
+    warned: bool,
+    // any sound at all since the start
+    heard: bool,
+}
+
+/// What the watchdog has to say.
+pub enum Alert {
+    /// Silent this long; `from_start` when nothing was ever heard.
+    Silent { after: Duration, from_start: bool },
+    Back,
+}
+
+impl MicWatch {
+    pub fn from_env() -> Result<Self, String> {
+        let secs = match std::env::var("MIC_WATCHDOG_SECS") {
+            Ok(v) => v.trim().parse::<u64>().map_err(|_| format!("MIC_WATCHDOG_SECS: not a number of seconds: {v}"))?,
+            Err(_) => 10,
+        };
+        let limit = (secs > 0).then(|| Duration::from_secs(secs));
+        Ok(MicWatch { limit, silent_since: Some(Instant::now()), warned: false, heard: false })
+    }
+
+    /// Feed the peak level of each chunk, or `None` when no audio came for
+    /// a while; returns an alert when the input went or came back.
+    pub fn feed(&mut self, peak: Option<f32>) -> Option<Alert> {
+        let limit = self.limit?;
+        if peak.is_some_and(|p| p > SILENT_PEAK) {
+            self.silent_since = None;
+            self.heard = true;
+            return std::mem::take(&mut self.warned).then_some(Alert::Back);
+        }
+        let since = *self.silent_since.get_or_insert_with(Instant::now);
+        if self.warned || since.elapsed() < limit {
+            return None;
+        }
+        self.warned = true;
+        Some(Alert::Silent { after: since.elapsed(), from_start: !self.heard })
+    }
+}
+
+const MACOS_PERMISSION: &str = "no sound from the microphone since parlar started; macOS has probably \
+not given this terminal access to it. To allow it: (1) in System Settings, open Privacy & Security > \
+Microphone (opened for you now); (2) turn on the terminal app parlar runs in (Terminal, iTerm2, …); \
+(3) quit that app completely, start it again and run parlar. If the app is not in the list, run \
+`tccutil reset Microphone` and start parlar again to be asked for the permission.";
+
+impl Alert {
+    pub fn message(&self) -> String {
+        match self {
+            Alert::Silent { from_start: true, .. } if cfg!(target_os = "macos") => MACOS_PERMISSION.into(),
+            Alert::Silent { after: d, .. } => format!(
+                "no sound from the microphone for {} s. Check that this terminal may use the microphone \
+                 (the system's privacy settings), that the device is connected and not muted, and that \
+                 INPUT_DEVICE names the right one (`parlar devices`).",
+                d.as_secs()
+            ),
+            Alert::Back => "sound from the microphone again.".into(),
+        }
+    }
+
+    /// Follow up on the alert: on macOS, open the microphone privacy
+    /// settings when the permission is the likely cause.
+    pub fn follow_up(&self) {
+        if cfg!(target_os = "macos") && matches!(self, Alert::Silent { from_start: true, .. }) {
+            let _ = std::process::Command::new("open")
+                .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Microphone")
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn();
+        }
+    }
+}
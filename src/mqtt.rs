@@ -0,0 +1,225 @@
+// MQTT integration, for using parlar as the voice front-end of a Home
+// Assistant (or any MQTT) setup. With MQTT_URL set, under MQTT_TOPIC
+// (default `parlar`):
+//
+//   published   <topic>/status      online / offline (retained, last will)
+//               <topic>/state       idle, listening, thinking, speaking, muted (retained)
+//               <topic>/transcript  each finalized user turn
+//               <topic>/assistant   each finished reply
+//   subscribed  <topic>/cmd/say        payload: text to send as a user message
+//               <topic>/cmd/mute       payload: on, off or toggle
+//               <topic>/cmd/interrupt  any payload
+//
+// Home Assistant MQTT discovery entities are announced unless
+// MQTT_DISCOVERY=false. The client is part of the `bridge` feature; without
+// it, setting MQTT_URL is an error.
+
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "bridge")]
+use std::sync::OnceLock;
+use std::time::Duration;
+
+#[cfg(feature = "bridge")]
+use percent_encoding::percent_decode_str;
+#[cfg(feature = "bridge")]
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+#[cfg(feature = "bridge")]
+use serde_json::json;
+
+use crate::control::Controls;
+use crate::{State, indicator, redact};
+#[cfg(feature = "bridge")]
+use crate::output;
+
+#[cfg(feature = "bridge")]
+struct Mqtt {
+    client: AsyncClient,
+    topic: String,
+}
+
+#[cfg(feature = "bridge")]
+static MQTT: OnceLock<Mqtt> = OnceLock::new();
+
+#[cfg(feature = "bridge")]
+fn options(url: &str, topic: &str) -> Result<MqttOptions, String> {
+    let url = url::Url::parse(url).map_err(|e| format!("MQTT_URL: {e}"))?;
+    if url.scheme() != "mqtt" {
+        return Err(format!("MQTT_URL: unsupported scheme {} (use mqtt://host:port)", url.scheme()));
+    }
+    let host = url.host_str().ok_or("MQTT_URL: missing host")?;
+    let id = format!("parlar-{}", std::process::id());
+    let mut opts = MqttOptions::new(id, host, url.port().unwrap_or(1883));
+    opts.set_keep_alive(Duration::from_secs(30));
+    opts.set_last_will(LastWill::new(format!("{topic}/status"), "offline", QoS::AtLeastOnce, true));
+    if !url.username().is_empty() {
+        let decode = |s: &str| percent_decode_str(s).decode_utf8_lossy().into_owned();
+        opts.set_credentials(decode(url.username()), decode(url.password().unwrap_or("")));
+    }
+    Ok(opts)
+}
+
+/// Connect to MQTT_URL (if set) and serve the command topics; returns the
+/// broker address for the startup banner.
+#[cfg(feature = "bridge")]
+pub fn start(controls: &Controls) -> Result<Option<String>, String> {
+    let Some(url) = std::env::var("MQTT_URL").ok().filter(|u| !u.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let topic = std::env::var("MQTT_TOPIC")
+        .ok()
+        .map(|t| t.trim().trim_end_matches('/').to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "parlar".into());
+    let discovery = crate::env_flag("MQTT_DISCOVERY", true);
+    let opts = options(url.trim(), &topic)?;
+    let (host, port) = opts.broker_address();
+    let (client, mut eventloop) = AsyncClient::new(opts, 64);
+    let _ = MQTT.set(Mqtt { client: client.clone(), topic: topic.clone() });
+
+    let controls = controls.clone();
+    tokio::spawn(async move {
+        let mut connected = false;
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    // Subscriptions and retained topics are set up again on every (re)connect
+                    connected = true;
+                    let _ = client.subscribe(format!("{topic}/cmd/#"), QoS::AtLeastOnce).await;
+                    let _ = client.publish(format!("{topic}/status"), QoS::AtLeastOnce, true, "online").await;
+                    if discovery {
+                        announce(&client, &topic).await;
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(p))) => {
+                    let payload = String::from_utf8_lossy(&p.payload).trim().to_string();
+                    match p.topic.strip_prefix(&format!("{topic}/cmd/")) {
+                        Some("say") if !payload.is_empty() => controls.say_text(&payload),
+                        Some("mute") => {
+                            controls.set_muted(match payload.to_ascii_lowercase().as_str() {
+                                "on" | "true" | "1" => Some(true),
+                                "off" | "false" | "0" => Some(false),
+                                _ => None,
+                            });
+                        }
+                        Some("interrupt") => controls.interrupt(),
+                        _ => {}
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    if connected {
+                        output::notice("mqtt", &format!("connection lost: {e}; retrying."));
+                        connected = false;
+                    }
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+        }
+    });
+    Ok(Some(format!("{host}:{port}, topic {}", MQTT.get().map(|m| m.topic.as_str()).unwrap_or(""))))
+}
+
+/// Home Assistant discovery: sensors for state, transcript and reply, and
+/// buttons/switch for the commands.
+#[cfg(feature = "bridge")]
+async fn announce(client: &AsyncClient, topic: &str) {
+    let node = topic.replace('/', "_");
+    let device = json!({ "identifiers": [node], "name": "Parlar", "model": "parlar" });
+    let availability = format!("{topic}/status");
+    // Home Assistant rejects states over 255 characters
+    let text = "{{ value[:255] }}";
+    let entities = [
+        ("sensor", "state", json!({ "name": "State", "state_topic": format!("{topic}/state") })),
+        (
+            "sensor",
+            "transcript",
+            json!({ "name": "Last transcript", "state_topic": format!("{topic}/transcript"), "value_template": text }),
+        ),
+        (
+            "sensor",
+            "assistant",
+            json!({ "name": "Last reply", "state_topic": format!("{topic}/assistant"), "value_template": text }),
+        ),
+        ("button", "interrupt", json!({ "name": "Interrupt", "command_topic": format!("{topic}/cmd/interrupt") })),
+        (
+            "switch",
+            "mute",
+            json!({
+                "name": "Mute",
+                "command_topic": format!("{topic}/cmd/mute"),
+                "state_topic": format!("{topic}/state"),
+                "payload_on": "on",
+                "payload_off": "off",
+                "value_template": "{{ 'on' if value == 'muted' else 'off' }}",
+                "state_on": "on",
+                "state_off": "off"
+            }),
+        ),
+    ];
+    for (component, id, mut config) in entities {
+        config["unique_id"] = json!(format!("{node}_{id}"));
+        config["availability_topic"] = json!(availability);
+        config["device"] = device.clone();
+        let _ = client
+            .publish(
+                format!("homeassistant/{component}/{node}/{id}/config"),
+                QoS::AtLeastOnce,
+                true,
+                config.to_string(),
+            )
+            .await;
+    }
+}
+
+#[cfg(not(feature = "bridge"))]
+pub fn start(_controls: &Controls) -> Result<Option<String>, String> {
+    match std::env::var("MQTT_URL") {
+        Ok(u) if !u.trim().is_empty() => Err("MQTT_URL needs a build with the `bridge` feature".into()),
+        _ => Ok(None),
+    }
+}
+
+fn connected() -> bool {
+    #[cfg(feature = "bridge")]
+    return MQTT.get().is_some();
+    #[cfg(not(feature = "bridge"))]
+    false
+}
+
+fn publish(sub: &str, payload: &str, retain: bool) {
+    #[cfg(feature = "bridge")]
+    if let Some(m) = MQTT.get() {
+        let _ = m.client.try_publish(format!("{}/{sub}", m.topic), QoS::AtLeastOnce, retain, payload);
+    }
+    #[cfg(not(feature = "bridge"))]
+    let _ = (sub, payload, retain);
+}
+
+// Transcripts and replies leave the machine, so they are redacted as on disk
+pub fn transcript(text: &str) {
+    publish("transcript", &redact::apply(text), false);
+}
+
+pub fn assistant(text: &str) {
+    if !text.trim().is_empty() {
+        publish("assistant", &redact::apply(text), false);
+    }
+}
+
+/// Publish state changes; polls like the other status displays.
+pub fn watch_state(state: Arc<Mutex<State>>) {
+    if !connected() {
+        return;
+    }
+    std::thread::spawn(move || {
+        let mut last = "";
+        loop {
+            let now = state.lock().map(|st| indicator::state(&st).name()).unwrap_or("idle");
+            if now != last {
+                publish("state", now, true);
+                last = now;
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    });
+}
@@ -0,0 +1,307 @@
+// Outbound connections for corporate networks: an optional HTTP CONNECT or
+// SOCKS5 proxy (`--proxy` or HTTPS_PROXY / ALL_PROXY, honouring NO_PROXY)
+// and TLS settings: an extra CA bundle (CA_BUNDLE) for TLS-intercepting
+// gateways, the system roots on or off (TLS_NATIVE_ROOTS), and optional
+// pinning of the server certificate (TLS_PIN_SHA256). Used by the realtime
+// WebSocket and the REST calls alike.
+
+use std::io::{self, BufReader};
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ServerName};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream, client_async_tls_with_config};
+use tungstenite::handshake::client::{Request, Response};
+use url::Url;
+
+static PROXY_FLAG: OnceLock<Option<String>> = OnceLock::new();
+
+/// Record `--proxy`; it takes precedence over the environment.
+pub fn init(proxy: Option<String>) {
+    let _ = PROXY_FLAG.set(proxy);
+}
+
+struct Proxy {
+    url: Url,
+    socks: bool,
+}
+
+impl Proxy {
+    fn credentials(&self) -> Option<(String, String)> {
+        let decode = |s: &str| percent_encoding::percent_decode_str(s).decode_utf8_lossy().into_owned();
+        (!self.url.username().is_empty())
+            .then(|| (decode(self.url.username()), decode(self.url.password().unwrap_or(""))))
+    }
+}
+
+fn proxy_url() -> Option<String> {
+    if let Some(Some(p)) = PROXY_FLAG.get() {
+        return Some(p.clone());
+    }
+    ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .iter()
+        .find_map(|v| std::env::var(v).ok().filter(|p| !p.trim().is_empty()))
+}
+
+fn bypassed(host: &str) -> bool {
+    let Ok(list) = std::env::var("NO_PROXY").or_else(|_| std::env::var("no_proxy")) else { return false };
+    list.split(',').map(|p| p.trim().trim_start_matches('.')).any(|p| {
+        p == "*" || (!p.is_empty() && (host == p || host.ends_with(&format!(".{p}"))))
+    })
+}
+
+/// The proxy to use for `host`, if any.
+fn proxy_for(host: &str) -> Result<Option<Proxy>, String> {
+    let Some(raw) = proxy_url() else { return Ok(None) };
+    if bypassed(host) {
+        return Ok(None);
+    }
+    // A bare host:port means an HTTP proxy
+    let raw = if raw.contains("://") { raw } else { format!("http://{raw}") };
+    let url = Url::parse(&raw).map_err(|e| format!("proxy {raw}: {e}"))?;
+    let socks = match url.scheme() {
+        "http" => false,
+        "socks5" | "socks5h" => true,
+        other => return Err(format!("proxy scheme {other} is not supported (use http, socks5 or socks5h)")),
+    };
+    if url.host_str().is_none() {
+        return Err(format!("proxy {raw}: missing host"));
+    }
+    Ok(Some(Proxy { url, socks }))
+}
+
+/// Human-readable proxy in use for `host` (credentials hidden), for status output.
+pub fn describe_proxy(host: &str) -> Result<Option<String>, String> {
+    Ok(proxy_for(host)?.map(|p| {
+        let mut url = p.url.clone();
+        let _ = url.set_password(None);
+        let _ = url.set_username("");
+        url.to_string().trim_end_matches('/').to_string()
+    }))
+}
+
+async fn http_connect(proxy: &Proxy, host: &str, port: u16) -> io::Result<TcpStream> {
+    let addr = (proxy.url.host_str().unwrap_or(""), proxy.url.port_or_known_default().unwrap_or(8080));
+    let mut stream = TcpStream::connect(addr).await?;
+    let mut req = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if let Some((user, pass)) = proxy.credentials() {
+        req.push_str(&format!("Proxy-Authorization: Basic {}\r\n", B64.encode(format!("{user}:{pass}"))));
+    }
+    req.push_str("\r\n");
+    stream.write_all(req.as_bytes()).await?;
+    // Read the response head byte by byte so no tunnel data is consumed
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > 8192 {
+            return Err(io::Error::other("proxy response header too large"));
+        }
+        head.push(stream.read_u8().await?);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status = head.lines().next().unwrap_or("");
+    match status.split_whitespace().nth(1) {
+        Some("200") => Ok(stream),
+        Some("407") => Err(io::Error::other(format!("proxy authentication required ({status})"))),
+        _ => Err(io::Error::other(format!("proxy refused CONNECT: {status}"))),
+    }
+}
+
+async fn socks5_connect(proxy: &Proxy, host: &str, port: u16) -> io::Result<TcpStream> {
+    let addr = (proxy.url.host_str().unwrap_or(""), proxy.url.port().unwrap_or(1080));
+    let mut stream = TcpStream::connect(addr).await?;
+    let creds = proxy.credentials();
+    // Greeting: offer no-auth, plus username/password when configured
+    let methods: &[u8] = if creds.is_some() { &[0x00, 0x02] } else { &[0x00] };
+    stream.write_all(&[0x05, methods.len() as u8]).await?;
+    stream.write_all(methods).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    match (reply[1], &creds) {
+        (0x00, _) => {}
+        (0x02, Some((user, pass))) => {
+            if user.len() > 255 || pass.len() > 255 {
+                return Err(io::Error::other("SOCKS5 credentials longer than 255 bytes"));
+            }
+            let mut auth = vec![0x01, user.len() as u8];
+            auth.extend_from_slice(user.as_bytes());
+            auth.push(pass.len() as u8);
+            auth.extend_from_slice(pass.as_bytes());
+            stream.write_all(&auth).await?;
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0x00 {
+                return Err(io::Error::other("SOCKS5 proxy rejected the credentials"));
+            }
+        }
+        _ => return Err(io::Error::other("SOCKS5 proxy offers no usable authentication method")),
+    }
+    // CONNECT by domain name, so the proxy resolves it
+    if host.len() > 255 {
+        return Err(io::Error::other("SOCKS5 host name longer than 255 bytes"));
+    }
+    let mut req = vec![0x05, 0x01, 0x00, 0x03, host.len() as u8];
+    req.extend_from_slice(host.as_bytes());
+    req.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&req).await?;
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head).await?;
+    if head[1] != 0x00 {
+        return Err(io::Error::other(format!("SOCKS5 connect failed (reply code {})", head[1])));
+    }
+    let addr_len = match head[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        other => return Err(io::Error::other(format!("SOCKS5 reply has unknown address type {other}"))),
+    };
+    let mut rest = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut rest).await?;
+    Ok(stream)
+}
+
+/// A TCP stream to `host:port`, through the proxy when one applies.
+pub async fn tcp_connect(host: &str, port: u16) -> io::Result<TcpStream> {
+    match proxy_for(host).map_err(io::Error::other)? {
+        Some(p) if p.socks => socks5_connect(&p, host, port).await,
+        Some(p) => http_connect(&p, host, port).await,
+        None => TcpStream::connect((host, port)).await,
+    }
+}
+
+/// PEM certificates from CA_BUNDLE, as DER.
+fn extra_roots() -> Result<Vec<Vec<u8>>, String> {
+    let Ok(path) = std::env::var("CA_BUNDLE") else { return Ok(Vec::new()) };
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let file = std::fs::File::open(&path).map_err(|e| format!("CA_BUNDLE {path}: {e}"))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file)).map_err(|e| format!("CA_BUNDLE {path}: {e}"))?;
+    if certs.is_empty() {
+        return Err(format!("CA_BUNDLE {path}: no PEM certificates found"));
+    }
+    Ok(certs)
+}
+
+/// SHA-256 fingerprints from TLS_PIN_SHA256 (hex, `:` separators allowed).
+fn pins() -> Result<Vec<[u8; 32]>, String> {
+    let Ok(raw) = std::env::var("TLS_PIN_SHA256") else { return Ok(Vec::new()) };
+    raw.split(',')
+        .map(|p| p.trim().replace(':', ""))
+        .filter(|p| !p.is_empty())
+        .map(|hex| {
+            let bytes: Option<Vec<u8>> = (0..hex.len())
+                .step_by(2)
+                .map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+                .collect();
+            bytes
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(|| format!("TLS_PIN_SHA256: {hex} is not a SHA-256 fingerprint (64 hex digits)"))
+        })
+        .collect()
+}
+
+/// Normal chain verification, then the leaf certificate must match a pin.
+struct Pinned {
+    inner: WebPkiVerifier,
+    pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for Pinned {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.inner.verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now)?;
+        let fingerprint: [u8; 32] = Sha256::digest(&end_entity.0).into();
+        if !self.pins.contains(&fingerprint) {
+            let hex: String = fingerprint.iter().map(|b| format!("{b:02x}")).collect();
+            return Err(rustls::Error::General(format!(
+                "server certificate {hex} does not match TLS_PIN_SHA256"
+            )));
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+fn tls_config() -> Result<Arc<rustls::ClientConfig>, String> {
+    let mut roots = rustls::RootCertStore::empty();
+    if crate::env_flag("TLS_NATIVE_ROOTS", true)
+        && let Ok(native) = rustls_native_certs::load_native_certs()
+    {
+        for cert in native {
+            let _ = roots.add(&Certificate(cert.0));
+        }
+    }
+    for der in extra_roots()? {
+        roots.add(&Certificate(der)).map_err(|e| format!("CA_BUNDLE: {e}"))?;
+    }
+    if roots.is_empty() {
+        return Err("no trusted root certificates (set CA_BUNDLE, or TLS_NATIVE_ROOTS=true)".into());
+    }
+    let pins = pins()?;
+    let builder = rustls::ClientConfig::builder().with_safe_defaults();
+    let config = if pins.is_empty() {
+        builder.with_root_certificates(roots).with_no_client_auth()
+    } else {
+        let inner = WebPkiVerifier::new(roots, None);
+        builder.with_custom_certificate_verifier(Arc::new(Pinned { inner, pins })).with_no_client_auth()
+    };
+    Ok(Arc::new(config))
+}
+
+/// One-line summary of the TLS trust settings, for `parlar doctor`.
+pub fn describe_tls() -> Result<String, String> {
+    tls_config()?;
+    let mut parts = Vec::new();
+    if crate::env_flag("TLS_NATIVE_ROOTS", true) {
+        parts.push("system roots".to_string());
+    }
+    let extra = extra_roots()?.len();
+    if extra > 0 {
+        parts.push(format!("{extra} from CA_BUNDLE"));
+    }
+    let mut msg = parts.join(" + ");
+    let pins = pins()?.len();
+    if pins > 0 {
+        msg.push_str(&format!(", pinned to {pins} certificate(s)"));
+    }
+    Ok(msg)
+}
+
+/// `connect_async` with the proxy and TLS settings applied.
+pub async fn connect(
+    request: Request,
+) -> Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, Response), tungstenite::Error> {
+    let uri = request.uri();
+    let host = uri.host().unwrap_or_default().to_string();
+    let tls = uri.scheme_str() == Some("wss");
+    let port = uri.port_u16().unwrap_or(if tls { 443 } else { 80 });
+    let stream = tcp_connect(&host, port).await?;
+    let connector = if tls {
+        Connector::Rustls(tls_config().map_err(io::Error::other)?)
+    } else {
+        Connector::Plain
+    };
+    client_async_tls_with_config(request, stream, None, Some(connector)).await
+}
+
+/// A reqwest client with the same proxy and TLS settings.
+pub fn http_client(builder: reqwest::ClientBuilder, host: &str) -> Result<reqwest::Client, String> {
+    let mut builder = builder;
+    if let Some(p) = proxy_for(host)? {
+        let proxy = reqwest::Proxy::all(p.url.as_str()).map_err(|e| format!("proxy: {e}"))?;
+        builder = builder.proxy(proxy);
+    }
+    let tls = Arc::try_unwrap(tls_config()?).unwrap_or_else(|c| (*c).clone());
+    builder.use_preconfigured_tls(tls).build().map_err(|e| e.to_string())
+}
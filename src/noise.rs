@@ -0,0 +1,122 @@
+// Noise turns. Background noise or a breath can be taken for speech, and
+// the transcription model then often "hears" a stock phrase ("Thank you.",
+// "Bye.") that the assistant dutifully answers. A turn whose audio is very
+// quiet (RMS level under NOISE_MAX_LEVEL, default 0.015 of full scale) and
+// whose transcript is very short (NOISE_MAX_WORDS words or fewer, default
+// 3) is taken for noise: it is removed from the conversation and gets no
+// reply. NOISE_FILTER=false turns this off.
+//
+// MIN_SPEECH_MS (default 0: off) drops turns with less voiced audio than
+// that, whatever the transcript: a cough or a chair squeak that the server
+// took for a turn. Voiced audio is the 20 ms frames at or above
+// NOISE_MAX_LEVEL.
+//
+// Both are measured over what was forwarded between the server's speech
+// start and end, cut from the mic audio kept in turnaudio.rs.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+
+use serde_json::Value;
+
+use crate::turnaudio;
+
+// Forwarded mic audio kept for cutting out turns
+const HISTORY_SECS: u64 = 60;
+// Turns whose level is kept until their reply is decided
+const TURNS: usize = 8;
+
+struct Config {
+    sr_hz: u32,
+    filter: bool,
+    max_words: usize,
+    max_level: f32,
+    min_speech_ms: u64,
+}
+
+static CONFIG: OnceLock<Option<Config>> = OnceLock::new();
+// RMS level and voiced milliseconds of the latest turns, by item
+static LEVELS: Mutex<VecDeque<(String, f32, u64)>> = Mutex::new(VecDeque::new());
+
+fn config() -> Option<&'static Config> {
+    CONFIG.get().and_then(Option::as_ref)
+}
+
+/// Read NOISE_FILTER, NOISE_MAX_WORDS, NOISE_MAX_LEVEL and MIN_SPEECH_MS;
+/// call once at startup.
+pub fn init(sr_hz: u32) -> Result<(), String> {
+    let filter = crate::env_flag("NOISE_FILTER", true);
+    let min_speech_ms = match std::env::var("MIN_SPEECH_MS") {
+        Ok(v) => v.trim().parse().map_err(|_| format!("MIN_SPEECH_MS: not a number of milliseconds: {v}"))?,
+        Err(_) => 0,
+    };
+    if !filter && min_speech_ms == 0 {
+        let _ = CONFIG.set(None);
+        return Ok(());
+    }
+    let max_words = match std::env::var("NOISE_MAX_WORDS") {
+        Ok(v) => v.trim().parse().map_err(|_| format!("NOISE_MAX_WORDS: not a number of words: {v}"))?,
+        Err(_) => 3,
+    };
+    let max_level = match std::env::var("NOISE_MAX_LEVEL") {
+        Ok(v) => v
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|l| (0.0..=1.0).contains(l))
+            .ok_or_else(|| format!("NOISE_MAX_LEVEL: {v} (use 0 to 1)"))?,
+        Err(_) => 0.015,
+    };
+    turnaudio::keep(sr_hz, HISTORY_SECS);
+    let _ = CONFIG.set(Some(Config { sr_hz, filter, max_words, max_level, min_speech_ms }));
+    Ok(())
+}
+
+/// Follow a server event: measure each turn as it ends.
+pub fn on_event(evt: &Value) {
+    let Some(cfg) = config() else { return };
+    if evt["type"] != "input_audio_buffer.speech_stopped" {
+        return;
+    }
+    let Some(item_id) = evt["item_id"].as_str() else { return };
+    let audio = turnaudio::turn(evt);
+    if audio.is_empty() {
+        return;
+    }
+    let square = |s: &i16| (*s as f64 / i16::MAX as f64).powi(2);
+    let level = (audio.iter().map(square).sum::<f64>() / audio.len() as f64).sqrt() as f32;
+    let frame = (cfg.sr_hz as usize / 50).max(1);
+    let voiced: usize = audio
+        .chunks(frame)
+        .filter(|f| (f.iter().map(square).sum::<f64>() / f.len() as f64).sqrt() >= cfg.max_level as f64)
+        .map(<[i16]>::len)
+        .sum();
+    let mut levels = LEVELS.lock().unwrap();
+    if levels.len() == TURNS {
+        levels.pop_front();
+    }
+    levels.push_back((item_id.to_string(), level, voiced as u64 * 1000 / cfg.sr_hz as u64));
+}
+
+fn turn(item_id: &str) -> Option<(f32, u64)> {
+    let levels = LEVELS.lock().unwrap();
+    levels.iter().find(|(id, _, _)| id == item_id).map(|&(_, level, voiced)| (level, voiced))
+}
+
+/// Whether the turn was quiet enough to be noise.
+pub fn quiet(item_id: &str) -> bool {
+    let Some(cfg) = config().filter(|c| c.filter) else { return false };
+    turn(item_id).is_some_and(|(level, _)| level < cfg.max_level)
+}
+
+/// The turn's voiced milliseconds, when they are under MIN_SPEECH_MS.
+pub fn too_short(item_id: &str) -> Option<u64> {
+    let cfg = config()?;
+    let (_, voiced) = turn(item_id)?;
+    (voiced < cfg.min_speech_ms).then_some(voiced)
+}
+
+/// Whether a quiet turn's transcript is short enough to be made up.
+pub fn short(transcript: &str) -> bool {
+    config().is_some_and(|c| transcript.split_whitespace().count() <= c.max_words)
+}
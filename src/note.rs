@@ -0,0 +1,265 @@
+// `parlar note`: one-shot dictation. Hold Space (or press it twice where the
+// terminal cannot report key releases), speak, and the transcript goes to
+// the clipboard and optionally a notes file; then the process exits.
+//
+// Uses the realtime session with transcription only: no turn detection and
+// no responses, the buffer is committed when the key is released.
+
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{execute, terminal};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tungstenite::Message;
+
+use crate::cli::Cli;
+use crate::{audio, config, crypt, language, net, redact};
+
+const TRANSCRIPT_TIMEOUT: Duration = Duration::from_secs(30);
+// Keep capturing briefly after release so the last word is not clipped
+const TAIL: Duration = Duration::from_millis(300);
+
+enum Key {
+    Done,
+    Cancel,
+}
+
+/// Wait for the start key, set `recording`, then wait for the stop key.
+fn push_to_talk(recording: &AtomicBool) -> Key {
+    let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    if terminal::enable_raw_mode().is_err() {
+        return Key::Cancel;
+    }
+    if releases {
+        let _ = execute!(
+            std::io::stdout(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        );
+        eprint!("Hold Space and speak; release to finish (Esc cancels).\r\n");
+    } else {
+        eprint!("Press Space, speak, then press Space again (Esc cancels).\r\n");
+    }
+    let outcome = loop {
+        let Ok(Event::Key(k)) = event::read() else { continue };
+        let cancel = k.code == KeyCode::Esc
+            || (k.code == KeyCode::Char('c') && k.modifiers.contains(KeyModifiers::CONTROL));
+        if cancel {
+            break Key::Cancel;
+        }
+        if k.code != KeyCode::Char(' ') || k.kind == KeyEventKind::Repeat {
+            continue;
+        }
+        let started = recording.load(Ordering::Relaxed);
+        match k.kind {
+            KeyEventKind::Press if !started => {
+                recording.store(true, Ordering::Relaxed);
+                eprint!("● recording…\r\n");
+            }
+            KeyEventKind::Release if started => break Key::Done,
+            KeyEventKind::Press if started && !releases => break Key::Done,
+            _ => {}
+        }
+    };
+    if releases {
+        let _ = execute!(std::io::stdout(), PopKeyboardEnhancementFlags);
+    }
+    let _ = terminal::disable_raw_mode();
+    outcome
+}
+
+/// Copy with `CLIPBOARD_CMD`, else the platform's clipboard tool.
+fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    let cmd = env::var("CLIPBOARD_CMD").unwrap_or_else(|_| {
+        if cfg!(target_os = "macos") {
+            "pbcopy".into()
+        } else if cfg!(windows) {
+            "clip".into()
+        } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+            "wl-copy".into()
+        } else {
+            "xclip -selection clipboard".into()
+        }
+    });
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = Command::new(shell)
+        .arg(flag)
+        .arg(&cmd)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{cmd}: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("{cmd}: exit status {status}"));
+    }
+    Ok(())
+}
+
+fn append_note(path: &str, text: &str) -> Result<(), String> {
+    let mut f = crypt::Appender::open(path)?;
+    f.append(format!("{}\n", redact::apply(text)).as_bytes())
+        .and_then(|_| f.sync())
+        .map_err(|e| format!("{path}: {e}"))
+}
+
+pub async fn run(cli: &Cli) -> i32 {
+    let Some(api_key) = config::api_key() else {
+        eprintln!("OPENAI_API_KEY must be set (in env or .env), or stored with `parlar init`");
+        return 2;
+    };
+    let model = env::var("REALTIME_MODEL").unwrap_or_else(|_| "gpt-realtime".into());
+    let sr_hz: u32 = env::var("SR").ok().and_then(|v| v.parse().ok()).unwrap_or(24_000);
+    let notes_file = cli.value("append").or_else(|| env::var("NOTES_FILE").ok());
+    let transcription = match env::var("LANGUAGE").ok().as_deref().and_then(language::by_code) {
+        Some(lang) => json!({ "model": "whisper-1", "language": lang.code }),
+        None => json!({ "model": "whisper-1" }),
+    };
+
+    let backend = match audio::from_env() {
+        Ok(b) => b,
+        Err(e) => {
+            eprintln!("[config] {e}");
+            return 2;
+        }
+    };
+    let ws = match net::connect(crate::realtime_request(&model, &api_key)).await {
+        Ok((ws, _)) => ws,
+        Err(e) => {
+            eprintln!("WS connect failed: {e}");
+            return 1;
+        }
+    };
+    let (mut ws_tx, mut ws_rx) = ws.split();
+    let session = json!({
+        "type": "session.update",
+        "session": {
+            "modalities": ["text"],
+            "input_audio_format": "pcm16",
+            "input_audio_transcription": transcription,
+            "turn_detection": null
+        }
+    });
+    if ws_tx.send(Message::Text(session.to_string())).await.is_err() {
+        eprintln!("Connection closed.");
+        return 1;
+    }
+
+    // Mic → input_audio_buffer.append while the key is held
+    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<Message>();
+    let send_task = tokio::spawn(async move {
+        while let Some(msg) = out_rx.recv().await {
+            if ws_tx.send(msg).await.is_err() {
+                break;
+            }
+        }
+    });
+    let recording = Arc::new(AtomicBool::new(false));
+    let captured = Arc::new(AtomicUsize::new(0));
+    let stream = {
+        let (recording, captured, out_tx) = (recording.clone(), captured.clone(), out_tx.clone());
+        backend.open_input(
+            env::var("INPUT_DEVICE").ok().as_deref(),
+            sr_hz,
+            Box::new(move |pcm| {
+                if !recording.load(Ordering::Relaxed) {
+                    return;
+                }
+                captured.fetch_add(pcm.len(), Ordering::Relaxed);
+                let bytes: Vec<u8> = pcm.iter().flat_map(|s| s.to_le_bytes()).collect();
+                let ev = json!({"type": "input_audio_buffer.append", "audio": B64.encode(&bytes)});
+                let _ = out_tx.send(Message::Text(ev.to_string()));
+            }),
+        )
+    };
+    let stream = match stream {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Input stream error: {e}");
+            return 1;
+        }
+    };
+
+    let rec = recording.clone();
+    let key = tokio::task::spawn_blocking(move || push_to_talk(&rec)).await;
+    if !matches!(key, Ok(Key::Done)) {
+        eprintln!("Cancelled.");
+        return 1;
+    }
+    tokio::time::sleep(TAIL).await;
+    recording.store(false, Ordering::Relaxed);
+    drop(stream);
+    // The server rejects commits of less than 100 ms of audio
+    if captured.load(Ordering::Relaxed) < (sr_hz / 10) as usize {
+        eprintln!("Nothing recorded.");
+        return 1;
+    }
+    let _ = out_tx.send(Message::Text(json!({"type": "input_audio_buffer.commit"}).to_string()));
+    eprintln!("Transcribing…");
+
+    let wait = async {
+        while let Some(Ok(Message::Text(txt))) = ws_rx.next().await {
+            let Ok(v) = serde_json::from_str::<serde_json::Value>(&txt) else { continue };
+            match v["type"].as_str().unwrap_or("") {
+                "conversation.item.input_audio_transcription.completed" => {
+                    return Ok(v["transcript"].as_str().unwrap_or("").trim().to_string());
+                }
+                "conversation.item.input_audio_transcription.failed" | "error" => {
+                    let msg = v["error"]["message"].as_str().unwrap_or("transcription failed");
+                    return Err(msg.to_string());
+                }
+                _ => {}
+            }
+        }
+        Err("connection closed".to_string())
+    };
+    let text = match tokio::time::timeout(TRANSCRIPT_TIMEOUT, wait).await {
+        Ok(Ok(t)) if !t.is_empty() => t,
+        Ok(Ok(_)) => {
+            eprintln!("No speech recognized.");
+            return 1;
+        }
+        Ok(Err(e)) => {
+            eprintln!("Transcription failed: {e}");
+            return 1;
+        }
+        Err(_) => {
+            eprintln!("Transcription timed out.");
+            return 1;
+        }
+    };
+    send_task.abort();
+
+    println!("{text}");
+    let mut code = 0;
+    match copy_to_clipboard(&text) {
+        Ok(()) => eprintln!("Copied to clipboard."),
+        Err(e) => {
+            eprintln!("Clipboard: {e} (set CLIPBOARD_CMD)");
+            code = 1;
+        }
+    }
+    if let Some(path) = notes_file {
+        match append_note(&path, &text) {
+            Ok(()) => eprintln!("Appended to {path}."),
+            Err(e) => {
+                eprintln!("Notes: {e}");
+                code = 1;
+            }
+        }
+    }
+    code
+}
@@ -0,0 +1,58 @@
+// Desktop notifications of finished assistant replies, so answers are not
+// missed while the terminal is in the background. Terminals that report
+// focus changes suppress them while focused; others always notify.
+// NOTIFY_OSC9 sends them through the terminal instead (OSC 9, shown by
+// iTerm2, Windows Terminal, ConEmu, kitty and others), which also works
+// over SSH and is announced by screen readers that follow notifications.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+const FOCUS_UNKNOWN: u8 = 0;
+const FOCUSED: u8 = 1;
+#[cfg(feature = "tui")]
+const UNFOCUSED: u8 = 2;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static OSC9: AtomicBool = AtomicBool::new(false);
+static FOCUS: AtomicU8 = AtomicU8::new(FOCUS_UNKNOWN);
+
+// Notification servers truncate long bodies anyway
+const MAX_CHARS: usize = 300;
+
+pub fn init(enabled: bool, osc9: bool) {
+    ENABLED.store(enabled || osc9, Ordering::Relaxed);
+    OSC9.store(osc9, Ordering::Relaxed);
+}
+
+// Focus reports come from the terminal UI
+#[cfg(feature = "tui")]
+pub fn set_focused(focused: bool) {
+    FOCUS.store(if focused { FOCUSED } else { UNFOCUSED }, Ordering::Relaxed);
+}
+
+/// Show `text` as a notification unless disabled or the terminal has focus.
+pub fn assistant_reply(text: &str) {
+    let text = text.trim();
+    if !ENABLED.load(Ordering::Relaxed) || text.is_empty() || FOCUS.load(Ordering::Relaxed) == FOCUSED {
+        return;
+    }
+    let mut body: String = text.chars().take(MAX_CHARS).collect();
+    if text.chars().count() > MAX_CHARS {
+        body.push('…');
+    }
+    if OSC9.load(Ordering::Relaxed) {
+        // Control characters would end the sequence early
+        let body: String = body.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+        let mut err = std::io::stderr();
+        let _ = write!(err, "\x1b]9;{body}\x07");
+        let _ = err.flush();
+        return;
+    }
+    // Showing talks to the notification daemon; keep it off the caller's thread
+    std::thread::spawn(move || {
+        if let Err(e) = notify_rust::Notification::new().appname("parlar").summary("parlar").body(&body).show() {
+            crate::output::notice("notify", &e.to_string());
+        }
+    });
+}
@@ -0,0 +1,398 @@
+// User-facing output. In text mode this is the usual terminal transcript;
+// in JSON mode stdout carries one JSON object per line for a controlling
+// program, and free-form status text moves to stderr. When stdout carries
+// audio (the pipe backend), the text transcript moves to stderr as well.
+// In accessible mode assistant text is written a whole line at a time,
+// each reply starting with "Assistant:", so screen readers read complete
+// lines rather than fragments.
+//
+// Text mode wraps the transcript at word boundaries, at TEXT_WIDTH columns
+// (default 100; 0 leaves it to the terminal) or the terminal's width when
+// that is narrower, and colours user text, assistant text and notices by
+// COLOR_THEME: `dark` (default), `light`, `mono` or `none`. Colour is only
+// used on a terminal, and `--no-color` or NO_COLOR turns it off.
+//
+// While the user speaks, the partial transcript is shown on its own line
+// and rewritten in place as it grows, then replaced by the final one
+// (LIVE_TRANSCRIPT, default true; only on a terminal, and not while a reply
+// is being written). JSON mode gets it as `user.partial` events.
+
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use serde_json::{Value, json};
+
+static JSON_MODE: OnceLock<bool> = OnceLock::new();
+static AUDIO_ON_STDOUT: AtomicBool = AtomicBool::new(false);
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+// Accessible mode: the unfinished line of the reply, and whether the reply has started
+static LINE: Mutex<(String, bool)> = Mutex::new((String::new(), false));
+static STYLE: OnceLock<Style> = OnceLock::new();
+// Wrapping: column of the transcript line, and the word not yet written
+static WRAP: Mutex<(usize, String)> = Mutex::new((0, String::new()));
+// Output held while the scrollback covers the screen: text, and whether it goes to stderr
+static HELD: Mutex<Option<Vec<(String, bool)>>> = Mutex::new(None);
+// Live partial transcript: its item, its text so far, and whether it is on screen
+static PARTIAL: Mutex<(String, String, bool)> = Mutex::new((String::new(), String::new(), false));
+// The last transcript text written did not end a line
+static MID_LINE: AtomicBool = AtomicBool::new(false);
+
+// SGR parameters of each kind of text; empty leaves it plain
+struct Theme {
+    user: &'static str,
+    assistant: &'static str,
+    notice: &'static str,
+}
+
+const THEMES: &[(&str, Theme)] = &[
+    ("dark", Theme { user: "1;36", assistant: "92", notice: "2;37" }),
+    ("light", Theme { user: "1;34", assistant: "32", notice: "2" }),
+    ("mono", Theme { user: "1", assistant: "", notice: "2" }),
+];
+
+struct Style {
+    width: usize,
+    theme: Option<&'static Theme>,
+    // the transcript / the notices on stderr go to a terminal
+    out_tty: bool,
+    err_tty: bool,
+    live: bool,
+}
+
+pub fn init(json_mode: bool) {
+    let _ = JSON_MODE.set(json_mode);
+}
+
+pub fn json_mode() -> bool {
+    *JSON_MODE.get().unwrap_or(&false)
+}
+
+/// Line-at-a-time assistant text for screen readers.
+pub fn set_accessible() {
+    ACCESSIBLE.store(true, Ordering::Relaxed);
+}
+
+pub fn accessible() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+}
+
+/// Stdout carries raw audio from now on; keep all text off it.
+pub fn audio_on_stdout() {
+    AUDIO_ON_STDOUT.store(true, Ordering::Relaxed);
+}
+
+/// Read TEXT_WIDTH, COLOR_THEME, NO_COLOR and LIVE_TRANSCRIPT; `no_color`
+/// is `--no-color`.
+/// Call after `set_accessible` and `audio_on_stdout`.
+pub fn init_style(no_color: bool) -> Result<(), String> {
+    let width = match std::env::var("TEXT_WIDTH") {
+        Ok(v) => v.trim().parse().map_err(|_| format!("TEXT_WIDTH: not a number of columns: {v}"))?,
+        Err(_) => 100,
+    };
+    let name = std::env::var("COLOR_THEME").unwrap_or_else(|_| "dark".into());
+    let theme = match THEMES.iter().find(|(n, _)| n.eq_ignore_ascii_case(name.trim())) {
+        Some((_, theme)) => Some(theme),
+        None if name.trim().eq_ignore_ascii_case("none") => None,
+        None => return Err(format!("COLOR_THEME: {name} (use dark, light, mono or none)")),
+    };
+    let no_color = no_color || std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty());
+    let theme = theme.filter(|_| !no_color && !accessible());
+    let out_tty = if AUDIO_ON_STDOUT.load(Ordering::Relaxed) {
+        std::io::stderr().is_terminal()
+    } else {
+        std::io::stdout().is_terminal()
+    };
+    let width = if accessible() { 0 } else { width };
+    // Screen readers would read every rewrite
+    let live = crate::env_flag("LIVE_TRANSCRIPT", true) && !accessible();
+    let _ = STYLE.set(Style { width, theme, out_tty, err_tty: std::io::stderr().is_terminal(), live });
+    Ok(())
+}
+
+// Columns of the terminal the transcript goes to, when known
+fn columns() -> Option<usize> {
+    if !STYLE.get().is_some_and(|s| s.out_tty) {
+        return None;
+    }
+    #[cfg(feature = "tui")]
+    return crossterm::terminal::size().ok().map(|(cols, _)| cols as usize);
+    #[cfg(not(feature = "tui"))]
+    std::env::var("COLUMNS").ok().and_then(|c| c.trim().parse().ok())
+}
+
+// Columns to wrap at; 0 for none
+fn width() -> usize {
+    let Some(style) = STYLE.get().filter(|s| s.width > 0) else { return 0 };
+    columns().map_or(style.width, |cols| style.width.min(cols))
+}
+
+// `text` in the theme's colour for the transcript (`err`: for stderr)
+fn paint(pick: fn(&Theme) -> &'static str, err: bool, text: &str) -> String {
+    let code = STYLE
+        .get()
+        .filter(|s| if err { s.err_tty } else { s.out_tty })
+        .and_then(|s| s.theme)
+        .map(pick)
+        .filter(|c| !c.is_empty());
+    match code {
+        Some(c) if !text.is_empty() => format!("\x1b[{c}m{text}\x1b[0m"),
+        _ => text.to_string(),
+    }
+}
+
+// Wrap streamed text at `width`; a word is held back until it is complete.
+fn wrap(w: &mut (usize, String), width: usize, text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        if c == '\n' {
+            put_word(w, width, &mut out);
+            out.push('\n');
+            w.0 = 0;
+        } else if c.is_whitespace() {
+            put_word(w, width, &mut out);
+            if w.0 > 0 && w.0 < width {
+                out.push(' ');
+                w.0 += 1;
+            }
+        } else {
+            w.1.push(c);
+        }
+    }
+    out
+}
+
+fn put_word(w: &mut (usize, String), width: usize, out: &mut String) {
+    let n = w.1.chars().count();
+    if n == 0 {
+        return;
+    }
+    if w.0 > 0 && w.0 + n > width {
+        out.push('\n');
+        w.0 = 0;
+    }
+    out.push_str(&w.1);
+    w.1.clear();
+    w.0 += n;
+}
+
+// Wrap a whole piece of text that starts at column `col`.
+fn wrap_all(text: &str, col: usize) -> String {
+    let width = width();
+    if width == 0 {
+        return text.to_string();
+    }
+    let mut w = (col, String::new());
+    let mut out = wrap(&mut w, width, text);
+    put_word(&mut w, width, &mut out);
+    out
+}
+
+// The reply's held-back word, when another line is about to be written.
+fn flush_reply() -> String {
+    let mut w = WRAP.lock().unwrap();
+    let mut out = String::new();
+    put_word(&mut w, width().max(1), &mut out);
+    w.0 = 0;
+    paint(|t| t.assistant, false, &out)
+}
+
+/// Where text-mode output and terminal control sequences go.
+pub fn terminal() -> Box<dyn Write> {
+    if AUDIO_ON_STDOUT.load(Ordering::Relaxed) {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    }
+}
+
+/// Hold text output (e.g. while a full-screen view is open).
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn hold() {
+    *HELD.lock().unwrap() = Some(Vec::new());
+}
+
+pub fn held() -> bool {
+    HELD.lock().unwrap().is_some()
+}
+
+/// Write what was held, and stop holding.
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn release() {
+    let Some(held) = HELD.lock().unwrap().take() else { return };
+    for (text, err) in held {
+        if err {
+            eprint!("{text}");
+        } else {
+            put(format_args!("{text}"));
+        }
+    }
+}
+
+// Keep `text` back while output is held; true if it was.
+fn keep(text: &dyn Fn() -> String, err: bool) -> bool {
+    let mut held = HELD.lock().unwrap();
+    let Some(held) = held.as_mut() else { return false };
+    held.push((text(), err));
+    true
+}
+
+// Erase the partial transcript line, before anything else is written.
+fn unshow_partial() -> &'static str {
+    let mut partial = PARTIAL.lock().unwrap();
+    if std::mem::take(&mut partial.2) { "\r\x1b[2K" } else { "" }
+}
+
+fn put(args: std::fmt::Arguments) {
+    if keep(&|| args.to_string(), false) {
+        return;
+    }
+    let text = args.to_string();
+    if !text.is_empty() {
+        MID_LINE.store(!text.ends_with('\n'), Ordering::Relaxed);
+    }
+    let mut out = terminal();
+    let _ = write!(out, "{}{text}", unshow_partial());
+    let _ = out.flush();
+}
+
+fn emit(v: Value) {
+    let mut out = std::io::stdout().lock();
+    let _ = writeln!(out, "{v}");
+    let _ = out.flush();
+}
+
+/// Startup/status text that is not part of the conversation.
+pub fn info(msg: &str) {
+    if json_mode() {
+        eprintln!("{msg}");
+    } else {
+        put(format_args!("{msg}\n"));
+    }
+}
+
+/// A finalized user utterance, tagged with the speaker when recognized.
+pub fn user(text: &str) {
+    let speaker = crate::speaker::current();
+    match &speaker {
+        Some(name) => crate::scrollback::record(&format!("User ({name})"), text),
+        None => crate::scrollback::record("User", text),
+    }
+    if json_mode() {
+        let mut v = json!({"event": "user", "text": text});
+        if let Some(name) = speaker {
+            v["speaker"] = json!(name);
+        }
+        emit(v);
+    } else {
+        let label = match speaker {
+            Some(name) => format!("User ({name}): "),
+            None => "User: ".to_string(),
+        };
+        let line = format!("{label}{}", wrap_all(text, label.chars().count()));
+        put(format_args!("{}\n{}\n", flush_reply(), paint(|t| t.user, false, &line)));
+    }
+}
+
+/// The partial transcript is on screen, on the current line.
+pub fn partial_shown() -> bool {
+    PARTIAL.lock().unwrap().2
+}
+
+/// A piece of the transcript of user item `item_id`, while it is spoken.
+pub fn user_partial(item_id: &str, delta: &str) {
+    let Some(style) = STYLE.get().filter(|s| s.live) else { return };
+    let mut partial = PARTIAL.lock().unwrap();
+    if partial.0 != item_id {
+        *partial = (item_id.to_string(), String::new(), partial.2);
+    }
+    partial.1.push_str(delta);
+    if json_mode() {
+        emit(json!({"event": "user.partial", "item_id": item_id, "text": partial.1}));
+        return;
+    }
+    // Rewritten in place, so it has to stay on one line
+    if !style.out_tty || held() || MID_LINE.load(Ordering::Relaxed) {
+        return;
+    }
+    let cols = columns().unwrap_or(80).saturating_sub(1);
+    let text = partial.1.trim();
+    let room = cols.saturating_sub("User: …".chars().count());
+    let n = text.chars().count();
+    let line = if n > room {
+        format!("User: …{}", text.chars().skip(n - room).collect::<String>())
+    } else {
+        format!("User: {text}")
+    };
+    partial.2 = true;
+    let mut out = terminal();
+    let _ = write!(out, "\r\x1b[2K{}", paint(|t| t.user, false, &line));
+    let _ = out.flush();
+}
+
+pub fn assistant_delta(delta: &str) {
+    if json_mode() {
+        emit(json!({"event": "assistant.delta", "text": delta}));
+    } else if accessible() {
+        let mut line = LINE.lock().unwrap();
+        line.0.push_str(delta);
+        if let Some(cut) = line.0.rfind('\n') {
+            let lines: String = line.0.drain(..=cut).collect();
+            let head = if line.1 { "" } else { "\nAssistant: " };
+            line.1 = true;
+            put(format_args!("{head}{lines}"));
+        }
+    } else {
+        let width = width();
+        let text = if width == 0 { delta.to_string() } else { wrap(&mut WRAP.lock().unwrap(), width, delta) };
+        put(format_args!("{}", paint(|t| t.assistant, false, &text)));
+    }
+}
+
+/// End of an assistant message; `text` is the full message.
+pub fn assistant_done(text: &str) {
+    crate::notify::assistant_reply(text);
+    crate::scrollback::record("Assistant", text);
+    if json_mode() {
+        emit(json!({"event": "assistant.done", "text": text}));
+    } else if accessible() {
+        let (rest, started) = std::mem::take(&mut *LINE.lock().unwrap());
+        let head = if started { "" } else { "\nAssistant: " };
+        if started || !rest.trim().is_empty() {
+            put(format_args!("{head}{}\n", rest.trim_end()));
+        }
+    } else {
+        put(format_args!("{}\n", flush_reply()));
+    }
+}
+
+/// The session report at exit, as a `report` event; JSON mode only.
+pub fn report(report: &Value) {
+    if json_mode() {
+        let mut v = json!({"event": "report"});
+        if let (Some(to), Some(from)) = (v.as_object_mut(), report.as_object()) {
+            to.extend(from.clone());
+        }
+        emit(v);
+    }
+}
+
+/// A server event passed through as it came, as a `raw` event; JSON mode only.
+pub fn raw(evt: &Value) {
+    if json_mode() {
+        emit(json!({"event": "raw", "data": evt}));
+    }
+}
+
+/// Tagged notice, e.g. `notice("interrupt", "assistant canceled.")`.
+pub fn notice(tag: &str, msg: &str) {
+    if json_mode() {
+        emit(json!({"event": tag, "message": msg}));
+    } else {
+        WRAP.lock().unwrap().0 = 0;
+        let text = format!("\n{}\n", paint(|t| t.notice, true, &format!("[{tag}] {msg}")));
+        if !keep(&|| text.clone(), true) {
+            eprint!("{}{text}", unshow_partial());
+        }
+    }
+}
@@ -0,0 +1,334 @@
+// Command palette. `:` in the terminal UI opens a prompt over the session
+// with parlar's commands, so the less common ones need no key of their
+// own. Typing narrows the list by fuzzy match (the letters in order: `rcn`
+// finds reconnect), ↑/↓ choose, Tab completes, Enter runs the command with
+// what follows its name, Esc closes. Output that arrives meanwhile is held
+// until then. In text mode a line starting with `:` runs the same
+// commands, e.g. `:delay 400`.
+//
+// `persona NAME` switches to one of the personas (see `template`);
+// `event {…}` sends a raw client event (see `passthrough`).
+
+use std::path::PathBuf;
+
+use crate::control::Controls;
+use crate::verbosity::Verbosity;
+use crate::template::personas;
+use crate::{childsafe, i18n, language, output, practice, scrollback, shutdown, template, traffic};
+
+struct Command {
+    name: &'static str,
+    // `<x>` is required, `[x]` optional
+    args: &'static str,
+    help: &'static str,
+}
+
+const COMMANDS: &[Command] = &[
+    Command { name: "interrupt", args: "", help: "cut off the reply" },
+    Command { name: "resume", args: "", help: "play the rest of the interrupted reply" },
+    Command { name: "mute", args: "", help: "mute the microphone" },
+    Command { name: "unmute", args: "", help: "unmute the microphone" },
+    Command { name: "persona", args: "<name>", help: "switch to a persona (PERSONA_<NAME>_*)" },
+    Command { name: "voice", args: "<voice>", help: "switch the assistant's voice" },
+    Command { name: "model", args: "[model]", help: "switch the model (the next one by default)" },
+    Command { name: "delay", args: "<ms>", help: "how long replies wait after a finished statement" },
+    Command { name: "verbosity", args: "<terse|normal|chatty>", help: "set the reply length preset" },
+    Command { name: "language", args: "<code>", help: "switch the language, e.g. de" },
+    Command { name: "export", args: "[file]", help: "write the transcript so far to a file" },
+    Command { name: "reconnect", args: "", help: "move the conversation to a new connection" },
+    Command { name: "restart", args: "", help: "start a new conversation" },
+    Command { name: "search", args: "<words>", help: "print the turns that contain the words" },
+    Command { name: "practice", args: "", help: "print the speaking-practice figures" },
+    Command { name: "traffic", args: "", help: "print the traffic so far" },
+    Command { name: "event", args: "<json>", help: "send a raw client event to the server" },
+    Command { name: "lock", args: "", help: "lock the settings (child-safe mode)" },
+    Command { name: "quit", args: "", help: "quit parlar" },
+];
+
+/// How well `query` matches `name`: its letters must come in order; a
+/// match at the start and runs of adjacent letters score higher, gaps lower.
+fn score(query: &str, name: &str) -> Option<i32> {
+    let name: Vec<char> = name.chars().collect();
+    let (mut at, mut last, mut score) = (0, None, 0);
+    for q in query.to_lowercase().chars() {
+        let i = (at..name.len()).find(|&i| name[i] == q)?;
+        score += match last {
+            None if i == 0 => 10,
+            Some(l) if i == l + 1 => 5,
+            _ => 1,
+        };
+        score -= (i - at) as i32;
+        last = Some(i);
+        at = i + 1;
+    }
+    Some(score)
+}
+
+/// `names` that match `query`, best first.
+fn ranked<'a, T>(query: &str, items: &'a [T], name: impl Fn(&T) -> &str) -> Vec<&'a T> {
+    let mut hits: Vec<(i32, &T)> = items.iter().filter_map(|t| Some((score(query, name(t))?, t))).collect();
+    // Stable: equal scores keep the list's order
+    hits.sort_by_key(|(s, _)| -s);
+    hits.into_iter().map(|(_, t)| t).collect()
+}
+
+/// The command `word` names: exactly, or its best fuzzy match.
+fn command(word: &str) -> Option<&'static Command> {
+    let word = word.trim().to_lowercase();
+    COMMANDS.iter().find(|c| c.name == word).or_else(|| ranked(&word, COMMANDS, |c| c.name).first().copied())
+}
+
+/// Run a palette line, e.g. `delay 400` or `rcn`.
+pub fn run(controls: &Controls, line: &str) {
+    let line = line.trim();
+    let (word, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let arg = arg.trim();
+    if word.is_empty() {
+        let list: Vec<String> = COMMANDS.iter().map(|c| format!("  :{} {} — {}", c.name, c.args, c.help)).collect();
+        output::info(&format!("\n{}", list.join("\n")));
+        return;
+    }
+    let Some(cmd) = command(word) else {
+        output::notice("palette", &i18n::trf("no command matches \"{word}\".", &[("word", &word)]));
+        return;
+    };
+    let usage = || output::notice("palette", &format!("usage: {} {}", cmd.name, cmd.args));
+    match cmd.name {
+        "interrupt" => controls.interrupt(),
+        "resume" => controls.resume(),
+        "mute" | "unmute" => {
+            controls.set_muted(Some(cmd.name == "mute"));
+        }
+        "persona" => persona(controls, arg),
+        "voice" if !arg.is_empty() => controls.set_voice(arg),
+        "model" => controls.switch_model((!arg.is_empty()).then_some(arg)),
+        "delay" => match arg.trim_end_matches("ms").trim().parse::<u64>() {
+            Ok(ms) if ms <= 10_000 => controls.set_delay(ms),
+            _ => usage(),
+        },
+        "verbosity" => match Verbosity::parse(arg) {
+            Some(v) => controls.set_verbosity(Some(v)),
+            None => usage(),
+        },
+        "language" => match language::by_code(arg) {
+            Some(lang) if childsafe::settings_open("the language") => controls.set_language(lang),
+            Some(_) => {}
+            None => usage(),
+        },
+        "export" => {
+            let path = match arg {
+                "" => PathBuf::from(format!("parlar-transcript-{}.txt", chrono::Local::now().format("%Y%m%d-%H%M%S"))),
+                path => PathBuf::from(path),
+            };
+            match scrollback::export(&path) {
+                Ok((file, n)) => {
+                    let written = i18n::trf("{n} turns written to {path}.", &[("n", &n), ("path", &file.display())]);
+                    output::notice("export", &written)
+                }
+                Err(e) => {
+                    output::notice("export", &i18n::trf("cannot write the transcript: {error}", &[("error", &e)]))
+                }
+            }
+        }
+        "reconnect" => controls.reconnect(),
+        "restart" => {
+            controls.restart(None);
+        }
+        "search" => scrollback::search(arg),
+        "practice" => output::notice("practice", &practice::summary()),
+        "traffic" => output::notice("traffic", &traffic::summary()),
+        "event" => match serde_json::from_str(arg) {
+            Ok(event) => {
+                if let Err(e) = controls.send_event(event) {
+                    output::notice("palette", &e);
+                }
+            }
+            Err(_) => usage(),
+        },
+        "lock" => childsafe::lock(),
+        "quit" => shutdown::exit(0),
+        _ => usage(),
+    }
+}
+
+fn persona(controls: &Controls, name: &str) {
+    let all = personas();
+    if all.is_empty() {
+        output::notice("persona", "no personas; set PERSONA_<NAME>_INSTRUCTIONS or PERSONA_<NAME>_VOICE.");
+        return;
+    }
+    let names: Vec<&str> = all.iter().map(|p| p.name.as_str()).collect();
+    if name.is_empty() {
+        output::notice("persona", &format!("usage: persona <name> ({})", names.join(", ")));
+        return;
+    }
+    let exact = all.iter().find(|p| p.name == name.to_lowercase());
+    let Some(p) = exact.or_else(|| ranked(name, &all, |p| &p.name).first().copied()) else {
+        output::notice("persona", &format!("no persona matches \"{name}\" ({}).", names.join(", ")));
+        return;
+    };
+    if !childsafe::settings_open("the persona") {
+        return;
+    }
+    let before = template::set_persona(Some(p.name.clone()));
+    // Without instructions of its own, the current ones get its fields
+    let instructions = match &p.instructions {
+        Some(i) => i.clone(),
+        None => controls.state.lock().unwrap().instructions.clone(),
+    };
+    if let Err(e) = template::check(&instructions) {
+        template::set_persona(before);
+        output::notice("persona", &i18n::trf("not switched: {error}", &[("error", &e)]));
+        return;
+    }
+    controls.set_instructions(&instructions);
+    output::notice("persona", &format!("{}.", p.name));
+    if let Some(voice) = &p.voice {
+        controls.set_voice(voice);
+    }
+}
+
+#[cfg(feature = "tui")]
+pub use view::open;
+
+#[cfg(feature = "tui")]
+mod view {
+    use std::io::Write;
+
+    use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+    use crossterm::{cursor, execute, queue, terminal};
+
+    use super::{COMMANDS, command, personas, ranked, run};
+    use crate::control::Controls;
+    use crate::output;
+
+    const HELP: &str = "↑↓ choose · Tab complete · Enter run · Esc close";
+
+    struct Item {
+        // what Tab puts in the prompt and Enter runs
+        line: String,
+        label: String,
+        help: String,
+        // Enter completes instead of running, for the missing argument
+        needs_arg: bool,
+    }
+
+    fn items(query: &str) -> Vec<Item> {
+        match query.split_once(' ') {
+            Some((word, arg)) if command(word).is_some_and(|c| c.name == "persona") => {
+                let all = personas();
+                ranked(arg.trim(), &all, |p| &p.name)
+                    .into_iter()
+                    .map(|p| {
+                        let voice = p.voice.as_deref().map(|v| format!("voice {v}"));
+                        let instructions = p.instructions.as_ref().map(|_| "instructions".to_string());
+                        Item {
+                            line: format!("persona {}", p.name),
+                            label: p.name.clone(),
+                            help: [instructions, voice].into_iter().flatten().collect::<Vec<_>>().join(", "),
+                            needs_arg: false,
+                        }
+                    })
+                    .collect()
+            }
+            Some((word, _)) => command(word)
+                .map(|c| Item {
+                    line: query.to_string(),
+                    label: format!("{} {}", c.name, c.args),
+                    help: c.help.into(),
+                    needs_arg: false,
+                })
+                .into_iter()
+                .collect(),
+            None => ranked(query, COMMANDS, |c| c.name)
+                .into_iter()
+                .map(|c| Item {
+                    line: c.name.into(),
+                    label: format!("{} {}", c.name, c.args),
+                    help: c.help.into(),
+                    needs_arg: c.args.starts_with('<'),
+                })
+                .collect(),
+        }
+    }
+
+    fn draw(out: &mut impl Write, query: &str, items: &[Item], selected: usize, cols: usize, rows: usize) {
+        let _ = queue!(out, terminal::Clear(terminal::ClearType::All));
+        let shown = rows.saturating_sub(2);
+        // Keep the selection in view
+        let first = selected.saturating_sub(shown.saturating_sub(1));
+        for (row, (i, item)) in items.iter().enumerate().skip(first).take(shown).enumerate() {
+            let line: String = format!("  {:<28} {}", item.label, item.help).chars().take(cols).collect();
+            let _ = queue!(out, cursor::MoveTo(0, row as u16 + 1));
+            if i == selected {
+                let _ = write!(out, "\x1b[7m{line}\x1b[0m");
+            } else {
+                let _ = write!(out, "{line}");
+            }
+        }
+        let status = if items.is_empty() { "no command matches" } else { HELP };
+        let status: String = status.chars().take(cols).collect();
+        let _ = queue!(out, cursor::MoveTo(0, rows.saturating_sub(1) as u16));
+        let _ = write!(out, "\x1b[7m{status}\x1b[0m");
+        let prompt: String = format!(":{query}").chars().take(cols.saturating_sub(1)).collect();
+        let _ = queue!(out, cursor::MoveTo(0, 0));
+        let _ = write!(out, "{prompt}");
+        let _ = out.flush();
+    }
+
+    /// Show the palette until a command is chosen (then run it) or it is
+    /// closed. Runs on the keyboard thread, in raw mode.
+    pub fn open(controls: &Controls) {
+        output::hold();
+        let mut out = output::terminal();
+        let _ = execute!(out, terminal::EnterAlternateScreen);
+        // The timeline's scroll region would keep the last row
+        let _ = write!(out, "\x1b[r");
+        let mut query = String::new();
+        let mut selected = 0;
+        let chosen = loop {
+            let (cols, rows) = terminal::size().map(|(c, r)| (c as usize, r as usize)).unwrap_or((80, 24));
+            let list = items(&query);
+            selected = selected.min(list.len().saturating_sub(1));
+            draw(&mut out, &query, &list, selected, cols, rows);
+            let Ok(Event::Key(k)) = event::read() else { continue };
+            if !crate::keys::pressed(&k) {
+                continue;
+            }
+            crate::typing::key_pressed();
+            match k.code {
+                KeyCode::Esc => break None,
+                KeyCode::Char('c') if k.modifiers.contains(KeyModifiers::CONTROL) => break None,
+                KeyCode::Up => selected = selected.saturating_sub(1),
+                KeyCode::Down => selected += 1,
+                KeyCode::Tab | KeyCode::Enter if list.is_empty() => {}
+                KeyCode::Tab => {
+                    let item = &list[selected];
+                    query = item.line.clone();
+                    if item.needs_arg || item.label.contains('[') {
+                        query.push(' ');
+                    }
+                }
+                KeyCode::Enter if list[selected].needs_arg => {
+                    query = format!("{} ", list[selected].line);
+                }
+                KeyCode::Enter => break Some(list[selected].line.clone()),
+                KeyCode::Backspace if query.is_empty() => break None,
+                KeyCode::Backspace => {
+                    query.pop();
+                    selected = 0;
+                }
+                KeyCode::Char(c) => {
+                    query.push(c);
+                    selected = 0;
+                }
+                _ => {}
+            }
+        };
+        let _ = execute!(out, terminal::LeaveAlternateScreen);
+        output::release();
+        if let Some(line) = chosen {
+            run(controls, &line);
+        }
+    }
+}
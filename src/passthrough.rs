@@ -0,0 +1,80 @@
+// Raw protocol events, so new server features are usable before parlar
+// knows them. Client events are sent as given: `{"cmd":"send_event",
+// "event":{…}}` in JSON mode, `:event {…}` from the palette, and
+// CONNECT_EVENTS (or `--connect-events`), a JSON array of events sent on
+// every connect once the session is configured. In JSON mode, server events
+// whose type RAW_EVENTS (or `--raw-events`) lists are written to stdout as
+// `{"event":"raw","data":{…}}`; the list is comma-separated types,
+// prefixes ending in `*` (`response.*`), `unhandled` for every event parlar
+// itself ignores, or `all`. `{"cmd":"raw_events","events":"…"}` replaces
+// it while running.
+
+use std::sync::{Mutex, OnceLock};
+
+use serde_json::Value;
+
+use crate::output;
+
+static CONNECT: OnceLock<Vec<String>> = OnceLock::new();
+static SUBSCRIBED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Read RAW_EVENTS and CONNECT_EVENTS, or the flags that override them;
+/// call once at startup.
+pub fn init(raw_events: Option<String>, connect_events: Option<String>) -> Result<(), String> {
+    subscribe(&raw_events.or_else(|| std::env::var("RAW_EVENTS").ok()).unwrap_or_default());
+    let connect = match connect_events.or_else(|| std::env::var("CONNECT_EVENTS").ok()) {
+        Some(raw) if !raw.trim().is_empty() => {
+            let events: Vec<Value> =
+                serde_json::from_str(&raw).map_err(|e| format!("CONNECT_EVENTS: not a JSON array of events: {e}"))?;
+            events
+                .iter()
+                .map(|e| check(e).map(|_| e.to_string()))
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("CONNECT_EVENTS: {e}"))?
+        }
+        _ => Vec::new(),
+    };
+    let _ = CONNECT.set(connect);
+    Ok(())
+}
+
+/// Replace the server event types forwarded in JSON mode.
+pub fn subscribe(list: &str) {
+    *SUBSCRIBED.lock().unwrap() = list.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect();
+}
+
+/// The CONNECT_EVENTS, serialized.
+pub fn connect_events() -> Vec<String> {
+    CONNECT.get().cloned().unwrap_or_default()
+}
+
+/// Whether `event` can go to the server: an object with a `type`.
+pub fn check(event: &Value) -> Result<(), String> {
+    match event["type"].as_str() {
+        Some(t) if !t.is_empty() => Ok(()),
+        _ => Err(format!("not an event (an object with a \"type\"): {event}")),
+    }
+}
+
+fn matches(pattern: &str, et: &str) -> bool {
+    pattern == "all" || pattern == et || pattern.strip_suffix('*').is_some_and(|p| et.starts_with(p))
+}
+
+/// Every server event: forward it if its type is subscribed.
+pub fn on_event(evt: &Value) {
+    let et = evt["type"].as_str().unwrap_or("");
+    if output::json_mode() && SUBSCRIBED.lock().unwrap().iter().any(|p| matches(p, et)) {
+        output::raw(evt);
+    }
+}
+
+/// A server event parlar does nothing with: forward it for `unhandled`,
+/// unless `on_event` already has.
+pub fn on_unhandled(evt: &Value) {
+    let et = evt["type"].as_str().unwrap_or("");
+    let subscribed = SUBSCRIBED.lock().unwrap();
+    if output::json_mode() && subscribed.iter().any(|p| p == "unhandled") && !subscribed.iter().any(|p| matches(p, et))
+    {
+        output::raw(evt);
+    }
+}
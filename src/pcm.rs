@@ -0,0 +1,73 @@
+// Helpers for the real-time audio path that do not allocate per chunk:
+// peak metering, a pool of reusable chunk buffers, and building the
+// `input_audio_buffer.append` event in a single allocation.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as B64;
+use crossbeam_channel::{Receiver, Sender, bounded};
+
+/// Peak level of `samples`, 0–1.
+pub fn peak_level(samples: &[i16]) -> f32 {
+    let peak = samples.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0);
+    (peak as f32 / i16::MAX as f32).min(1.0)
+}
+
+/// Reusable sample buffers handed from the audio callback to a consumer
+/// thread and back, so steady-state capture allocates nothing.
+#[derive(Clone)]
+pub struct Pool {
+    free_tx: Sender<Vec<i16>>,
+    free_rx: Receiver<Vec<i16>>,
+    capacity: usize,
+}
+
+impl Pool {
+    /// Up to `buffers` spare buffers of `capacity` samples each.
+    pub fn new(buffers: usize, capacity: usize) -> Self {
+        let (free_tx, free_rx) = bounded(buffers);
+        Pool { free_tx, free_rx, capacity }
+    }
+
+    /// An empty buffer, recycled when one is available.
+    pub fn take(&self) -> Vec<i16> {
+        self.free_rx.try_recv().unwrap_or_else(|_| Vec::with_capacity(self.capacity))
+    }
+
+    /// Return a buffer for reuse; dropped if the pool is full.
+    pub fn give(&self, mut buf: Vec<i16>) {
+        buf.clear();
+        let _ = self.free_tx.try_send(buf);
+    }
+}
+
+/// Cut `data` into chunks of `frames` samples (the last may be shorter),
+/// each in a buffer from `pool`, and hand them to `send` in order.
+pub fn split(data: &[i16], frames: usize, pool: &Pool, mut send: impl FnMut(Vec<i16>)) {
+    for part in data.chunks(frames) {
+        let mut chunk = pool.take();
+        chunk.extend_from_slice(part);
+        send(chunk);
+    }
+}
+
+/// Little-endian PCM16 bytes of `samples` into `out` (cleared first).
+pub fn le_bytes(samples: &[i16], out: &mut Vec<u8>) {
+    out.clear();
+    out.reserve(samples.len() * 2);
+    for s in samples {
+        out.extend_from_slice(&s.to_le_bytes());
+    }
+}
+
+const APPEND_HEAD: &str = r#"{"type":"input_audio_buffer.append","audio":""#;
+const APPEND_TAIL: &str = r#""}"#;
+
+/// The `input_audio_buffer.append` event for little-endian PCM16 `pcm`,
+/// built directly into one exactly sized string.
+pub fn append_event(pcm: &[u8]) -> String {
+    let mut ev = String::with_capacity(APPEND_HEAD.len() + pcm.len().div_ceil(3) * 4 + APPEND_TAIL.len());
+    ev.push_str(APPEND_HEAD);
+    B64.encode_string(pcm, &mut ev);
+    ev.push_str(APPEND_TAIL);
+    ev
+}
@@ -0,0 +1,221 @@
+// Pipe audio backend: raw PCM read from a FIFO, file or stdin and written to
+// a FIFO, file or stdout, to glue parlar into ffmpeg or GStreamer pipelines
+// without an audio device.
+//
+// With AUDIO_BACKEND=pipe, INPUT_DEVICE and OUTPUT_DEVICE are paths (`-` for
+// stdin / stdout, the default). PIPE_FORMAT (s16le or f32le), PIPE_RATE and
+// PIPE_CHANNELS declare the raw format of both streams (default s16le, SR,
+// mono); conversion to and from mono PCM16 at SR happens here.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::audio::{AudioBackend, OnInput, OnOutput, Opened};
+use crate::output;
+use crate::resample::Resampler;
+
+/// Audio is moved in blocks of this length.
+const BLOCK_MS: u32 = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    S16le,
+    F32le,
+}
+
+impl Format {
+    fn bytes(self) -> usize {
+        match self {
+            Format::S16le => 2,
+            Format::F32le => 4,
+        }
+    }
+}
+
+pub struct Pipe {
+    format: Format,
+    /// None: the session rate
+    rate: Option<u32>,
+    channels: usize,
+}
+
+/// Stops the stream's thread when dropped.
+struct Running(Arc<AtomicBool>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Pipe {
+    pub fn from_env() -> Result<Self, String> {
+        let format = match std::env::var("PIPE_FORMAT").as_deref().unwrap_or("s16le") {
+            "s16le" | "" => Format::S16le,
+            "f32le" => Format::F32le,
+            other => return Err(format!("PIPE_FORMAT must be s16le or f32le (got {other})")),
+        };
+        let rate = match std::env::var("PIPE_RATE") {
+            Ok(v) => Some(v.parse().ok().filter(|r| *r >= 8000).ok_or(format!("PIPE_RATE: invalid rate {v}"))?),
+            Err(_) => None,
+        };
+        let channels = match std::env::var("PIPE_CHANNELS") {
+            Ok(v) => v.parse().ok().filter(|c| (1..=8).contains(c)).ok_or(format!("PIPE_CHANNELS: invalid {v}"))?,
+            Err(_) => 1,
+        };
+        Ok(Pipe { format, rate, channels })
+    }
+
+    fn describe(&self, path: &str) -> String {
+        let what = if path == "-" { "stdio" } else { path };
+        let format = if self.format == Format::S16le { "s16le" } else { "f32le" };
+        format!("{what}, {format} {} ch", self.channels)
+    }
+}
+
+pub fn selected() -> bool {
+    std::env::var("AUDIO_BACKEND").as_deref() == Ok("pipe")
+}
+
+/// Whether audio is read from stdin (so stdin cannot carry commands).
+pub fn reads_stdin() -> bool {
+    selected() && std::env::var("INPUT_DEVICE").map_or(true, |d| d == "-")
+}
+
+/// Whether audio is written to stdout (so stdout cannot carry text).
+pub fn writes_stdout() -> bool {
+    selected() && std::env::var("OUTPUT_DEVICE").map_or(true, |d| d == "-")
+}
+
+impl AudioBackend for Pipe {
+    fn name(&self) -> &'static str {
+        "pipe"
+    }
+
+    fn device_names(&self, _input: bool) -> Vec<String> {
+        vec!["-".into()]
+    }
+
+    fn open_input(&self, device: Option<&str>, sr_hz: u32, mut on_data: OnInput) -> Result<Opened, String> {
+        let path = device.unwrap_or("-").to_string();
+        let (format, channels, rate) = (self.format, self.channels, self.rate.unwrap_or(sr_hz));
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let described = self.describe(&path);
+        std::thread::spawn(move || {
+            // Opening a FIFO blocks until the writer shows up
+            let mut src: Box<dyn Read> = if path == "-" {
+                Box::new(io::stdin())
+            } else {
+                match File::open(&path) {
+                    Ok(f) => Box::new(f),
+                    Err(e) => return output::notice("audio", &format!("{path}: {e}")),
+                }
+            };
+            let mut resampler = (rate != sr_hz).then(|| Resampler::new(rate, sr_hz));
+            let frames = (rate * BLOCK_MS / 1000) as usize;
+            let mut raw = vec![0u8; frames * channels * format.bytes()];
+            let (mut pcm, mut resampled) = (Vec::with_capacity(frames), Vec::new());
+            while !stopped.load(Ordering::Relaxed) {
+                if let Err(e) = src.read_exact(&mut raw) {
+                    if e.kind() == io::ErrorKind::UnexpectedEof {
+                        output::notice("audio", &format!("{path}: input ended."));
+                    } else {
+                        output::notice("audio", &format!("{path}: {e}"));
+                    }
+                    return;
+                }
+                pcm.clear();
+                pcm.extend(raw.chunks_exact(channels * format.bytes()).map(|frame| {
+                    let sum: i32 = frame.chunks_exact(format.bytes()).map(|s| decode(format, s) as i32).sum();
+                    (sum / channels as i32) as i16
+                }));
+                match &mut resampler {
+                    Some(r) => {
+                        resampled.clear();
+                        r.process(&pcm, &mut resampled);
+                        on_data(&resampled);
+                    }
+                    None => on_data(&pcm),
+                }
+            }
+        });
+        Ok(Opened { _stream: Box::new(Running(stop)), device: described, sample_rate: rate })
+    }
+
+    fn open_system_capture(&self, _sr_hz: u32, _on_data: OnInput) -> Result<Opened, String> {
+        Err("CAPTURE_SOURCE=system is not available with AUDIO_BACKEND=pipe".into())
+    }
+
+    fn open_output(&self, device: Option<&str>, sr_hz: u32, mut fill: OnOutput) -> Result<Opened, String> {
+        let path = device.unwrap_or("-").to_string();
+        let (format, channels, rate) = (self.format, self.channels, self.rate.unwrap_or(sr_hz));
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let described = self.describe(&path);
+        std::thread::spawn(move || {
+            let mut sink: Box<dyn Write> = if path == "-" {
+                Box::new(io::stdout())
+            } else {
+                match OpenOptions::new().write(true).create(true).truncate(true).open(&path) {
+                    Ok(f) => Box::new(f),
+                    Err(e) => return output::notice("audio", &format!("{path}: {e}")),
+                }
+            };
+            let mut resampler = (rate != sr_hz).then(|| Resampler::new(sr_hz, rate));
+            let mut block = vec![0i16; (sr_hz * BLOCK_MS / 1000) as usize];
+            let (mut resampled, mut raw) = (Vec::new(), Vec::new());
+            // Played out in real time, as a sound card would
+            let period = Duration::from_millis(BLOCK_MS as u64);
+            let mut next = Instant::now();
+            while !stopped.load(Ordering::Relaxed) {
+                fill(&mut block);
+                let pcm = match &mut resampler {
+                    Some(r) => {
+                        resampled.clear();
+                        r.process(&block, &mut resampled);
+                        &resampled[..]
+                    }
+                    None => &block[..],
+                };
+                raw.clear();
+                for &s in pcm {
+                    for _ in 0..channels {
+                        encode(format, s, &mut raw);
+                    }
+                }
+                if let Err(e) = sink.write_all(&raw).and_then(|_| sink.flush()) {
+                    output::notice("audio", &format!("{path}: {e}"));
+                    return;
+                }
+                next += period;
+                match next.checked_duration_since(Instant::now()) {
+                    Some(wait) => std::thread::sleep(wait),
+                    // A slow reader held us up; do not try to catch up in a burst
+                    None => next = Instant::now(),
+                }
+            }
+        });
+        Ok(Opened { _stream: Box::new(Running(stop)), device: described, sample_rate: rate })
+    }
+}
+
+fn decode(format: Format, b: &[u8]) -> i16 {
+    match format {
+        Format::S16le => i16::from_le_bytes([b[0], b[1]]),
+        Format::F32le => {
+            let f = f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+            (f.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+        }
+    }
+}
+
+fn encode(format: Format, s: i16, out: &mut Vec<u8>) {
+    match format {
+        Format::S16le => out.extend_from_slice(&s.to_le_bytes()),
+        Format::F32le => out.extend_from_slice(&(s as f32 / i16::MAX as f32).to_le_bytes()),
+    }
+}
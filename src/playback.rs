@@ -0,0 +1,211 @@
+// Playback lag. The server sends a reply faster than it plays, so a long
+// queue of assistant audio (the backlog) is normal and costs nothing. What
+// the user notices is lag: how far playback has fallen behind real time
+// since the reply started playing, after an output underrun, a device clock
+// running slow or a system stall. Both are measured in the output callback
+// and shown on the timeline strip.
+//
+// Lag beyond PLAYBACK_STRETCH_LAG_MS (default 300, 0 turns it off) is
+// caught up by playing slightly faster, PLAYBACK_STRETCH_RATE (default 1.1)
+// times real speed, until it is down to a third of that. The audio is
+// time-compressed with WSOLA: short segments are cut where the waveform
+// lines up with what would have played and cross-faded, so the pitch stays
+// the same and nothing is audibly dropped.
+//
+// PLAYBACK_MAX_LAG_MS (default 0: no limit) is the hard bound: beyond it
+// the oldest queued audio is skipped until playback is PLAYBACK_TARGET_LAG_MS
+// (default half the maximum) behind, with a short fade-in so the jump does
+// not click.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+pub struct Tracker {
+    sr_hz: u32,
+    max_lag: Option<Duration>,
+    target_lag: Duration,
+    stretch: Option<Stretch>,
+    // when the current stretch of audio started playing, and samples played since
+    started: Option<Instant>,
+    played: u64,
+    skipped_ms: u64,
+}
+
+/// What the output callback last saw.
+#[derive(Clone, Copy, Default)]
+pub struct Readout {
+    pub backlog_ms: u64,
+    pub lag_ms: u64,
+    /// Audio skipped to catch up, over the whole run.
+    pub skipped_ms: u64,
+    /// Playing faster to catch up.
+    pub stretching: bool,
+}
+
+struct Stretch {
+    rate: f32,
+    start_lag_ms: u64,
+    active: bool,
+    // queued samples at the front that are already time-compressed
+    ready: usize,
+}
+
+impl Tracker {
+    pub fn from_env(sr_hz: u32) -> Result<Self, String> {
+        let ms = |name: &str| match std::env::var(name) {
+            Ok(v) => v.trim().parse::<u64>().map(Some).map_err(|_| format!("{name}: invalid {v}")),
+            Err(_) => Ok(None),
+        };
+        let max = ms("PLAYBACK_MAX_LAG_MS")?.unwrap_or(0);
+        let target = ms("PLAYBACK_TARGET_LAG_MS")?.unwrap_or(max / 2);
+        if max > 0 && target >= max {
+            return Err(format!("PLAYBACK_TARGET_LAG_MS ({target}) must be below PLAYBACK_MAX_LAG_MS ({max})"));
+        }
+        let stretch_lag = ms("PLAYBACK_STRETCH_LAG_MS")?.unwrap_or(300);
+        let rate = match std::env::var("PLAYBACK_STRETCH_RATE") {
+            Ok(v) => match v.trim().parse::<f32>() {
+                Ok(r) if (1.01..=1.5).contains(&r) => r,
+                _ => return Err(format!("PLAYBACK_STRETCH_RATE: {v} (use 1.01 to 1.5)")),
+            },
+            Err(_) => 1.1,
+        };
+        Ok(Tracker {
+            sr_hz,
+            max_lag: (max > 0).then(|| Duration::from_millis(max)),
+            target_lag: Duration::from_millis(target),
+            stretch: (stretch_lag > 0).then_some(Stretch { rate, start_lag_ms: stretch_lag, active: false, ready: 0 }),
+            started: None,
+            played: 0,
+            skipped_ms: 0,
+        })
+    }
+
+    fn ms(&self, samples: u64) -> u64 {
+        samples * 1000 / self.sr_hz as u64
+    }
+
+    /// Call from the output callback before it takes `n` samples from `q`:
+    /// catch up if playback is too far behind, then count what will play.
+    pub fn fill(&mut self, q: &mut VecDeque<i16>, n: usize) -> Readout {
+        if q.is_empty() {
+            // Nothing to play is never behind
+            self.started = None;
+            if let Some(s) = &mut self.stretch {
+                s.active = false;
+                s.ready = 0;
+            }
+            return Readout { skipped_ms: self.skipped_ms, ..Default::default() };
+        }
+        let started = *self.started.get_or_insert_with(|| {
+            self.played = 0;
+            Instant::now()
+        });
+        let mut lag_ms = (started.elapsed().as_millis() as u64).saturating_sub(self.ms(self.played));
+        if let Some(max) = self.max_lag
+            && lag_ms > max.as_millis() as u64
+        {
+            let behind = lag_ms - self.target_lag.as_millis() as u64;
+            let skip = ((behind * self.sr_hz as u64 / 1000) as usize).min(q.len());
+            q.drain(..skip);
+            fade_in(q, self.sr_hz);
+            self.played += skip as u64;
+            self.skipped_ms += self.ms(skip as u64);
+            lag_ms = lag_ms.saturating_sub(self.ms(skip as u64));
+            if let Some(s) = &mut self.stretch {
+                s.ready = 0;
+            }
+        }
+        let mut stretching = false;
+        if let Some(s) = &mut self.stretch {
+            // Start past the threshold, stop once mostly caught up
+            s.active = if s.active { lag_ms > s.start_lag_ms / 3 } else { lag_ms > s.start_lag_ms };
+            s.ready = s.ready.min(q.len());
+            if s.active {
+                let (consumed, produced) = s.compress_front(q, n, self.sr_hz);
+                // The input consumed is what counts against real time
+                self.played += consumed.saturating_sub(produced) as u64;
+                stretching = true;
+            }
+            s.ready = s.ready.saturating_sub(n);
+        }
+        let readout =
+            Readout { backlog_ms: self.ms(q.len() as u64), lag_ms, skipped_ms: self.skipped_ms, stretching };
+        self.played += n.min(q.len()) as u64;
+        readout
+    }
+}
+
+impl Stretch {
+    /// Time-compress the front of `q` so at least `n` compressed samples are
+    /// ready. Returns the samples consumed and produced.
+    fn compress_front(&mut self, q: &mut VecDeque<i16>, n: usize, sr_hz: u32) -> (usize, usize) {
+        if self.ready >= n {
+            return (0, 0);
+        }
+        let want = n - self.ready;
+        let (seg, tol) = (sr_hz as usize / 50, sr_hz as usize / 200);
+        // Enough input for `want` output samples at this rate, plus one search window
+        let need = (want as f32 * self.rate) as usize + 2 * (seg + tol);
+        let input: Vec<i16> = q.range(self.ready..q.len().min(self.ready + need)).copied().collect();
+        let (out, consumed) = wsola(&input, want, self.rate, sr_hz);
+        if consumed == 0 {
+            return (0, 0);
+        }
+        // Each segment uses more input than it gives, so the output fits in its place
+        for (slot, s) in q.range_mut(self.ready..).zip(&out) {
+            *slot = *s;
+        }
+        q.drain(self.ready + out.len()..self.ready + consumed);
+        self.ready += out.len();
+        (consumed, out.len())
+    }
+}
+
+/// WSOLA time compression of `input` by `rate`, until at least `want`
+/// samples are out or the input runs short. Each 20 ms output segment is
+/// taken from near where `rate` puts it in the input, at the offset whose
+/// waveform best matches what would naturally have played next, and
+/// cross-fades into it over 5 ms. Returns the output and the input samples
+/// it used.
+fn wsola(input: &[i16], want: usize, rate: f32, sr_hz: u32) -> (Vec<i16>, usize) {
+    let seg = sr_hz as usize / 50;
+    let overlap = (sr_hz as usize / 200).max(1);
+    let tol = sr_hz as usize / 200;
+    let skip = (seg as f32 * (rate - 1.0)) as usize;
+    let mut out = Vec::with_capacity(want + seg);
+    // where the input would be at exactly `rate`, so choices do not drift
+    let mut nominal = 0.0f32;
+    let mut cur = 0;
+    while out.len() < want {
+        let target = nominal as usize + skip;
+        nominal += seg as f32 * rate;
+        let lo = target.saturating_sub(tol).max(cur + 1);
+        let hi = (target + tol).max(lo);
+        if hi + seg > input.len() {
+            break;
+        }
+        let natural = &input[cur..cur + overlap];
+        let similarity = |c: usize| {
+            let cand = &input[c..c + overlap];
+            let dot: f32 = natural.iter().zip(cand).map(|(&a, &b)| a as f32 * b as f32).sum();
+            let energy: f32 = cand.iter().map(|&b| b as f32 * b as f32).sum();
+            dot / energy.sqrt().max(1.0)
+        };
+        let best = (lo..=hi).map(|c| (similarity(c), c)).max_by(|a, b| a.0.total_cmp(&b.0)).map_or(lo, |(_, c)| c);
+        for i in 0..overlap {
+            let w = i as f32 / overlap as f32;
+            out.push((natural[i] as f32 * (1.0 - w) + input[best + i] as f32 * w) as i16);
+        }
+        out.extend_from_slice(&input[best + overlap..best + seg]);
+        cur = best + seg;
+    }
+    (out, cur)
+}
+
+/// Ramp the first 5 ms of `q` up from silence.
+fn fade_in(q: &mut VecDeque<i16>, sr_hz: u32) {
+    let n = (sr_hz as usize / 200).min(q.len()).max(1);
+    for (i, s) in q.iter_mut().take(n).enumerate() {
+        *s = (*s as f32 * i as f32 / n as f32) as i16;
+    }
+}
@@ -0,0 +1,240 @@
+// WASI plugins: tools and transcript filters shipped as .wasm modules, so
+// parlar can be extended without rebuilding it. Every subdirectory of
+// PLUGINS_DIR with a `plugin.toml` is one plugin:
+//
+//   name = "word_count"
+//   kind = "tool"                  # tool | filter
+//   module = "word_count.wasm"     # default plugin.wasm
+//   description = "Count the words in a text"
+//   parameters = { type = "object", properties = { text = { type = "string" } } }
+//   confirm = "auto"               # tools only, as in TOOLS_FILE
+//   timeout_secs = 5               # default TOOL_TIMEOUT_SECS
+//   memory_mb = 64                 # most linear memory it may grow to (default 64)
+//   [permissions]
+//   env = ["LANG"]                 # environment variables passed in
+//   read = ["/home/me/notes"]      # directories readable, at the same path
+//   write = ["data"]               # directories writable, at the same path
+//
+// Relative directories are taken from the plugin's own directory, and
+// the plugin sees them at their full path.
+//
+// A module is a WASI preview 1 command. A tool reads the call's arguments
+// (JSON) on stdin and prints its result; a filter reads the transcript and
+// prints a verdict as TRANSCRIPT_FILTER_CMD does. Beyond its permissions a
+// plugin sees nothing: no network, no other files or variables. Running
+// plugins needs the `plugins` feature.
+
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::tools::Policy;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Tool,
+    Filter,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+struct Permissions {
+    #[serde(default)]
+    env: Vec<String>,
+    #[serde(default)]
+    read: Vec<PathBuf>,
+    #[serde(default)]
+    write: Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+pub struct Manifest {
+    pub name: String,
+    pub kind: Kind,
+    #[serde(default = "default_module")]
+    module: PathBuf,
+    #[serde(default)]
+    pub description: String,
+    pub parameters: Option<toml::Value>,
+    #[serde(default)]
+    pub confirm: Policy,
+    pub timeout_secs: Option<u64>,
+    #[serde(default = "default_memory_mb")]
+    memory_mb: usize,
+    #[serde(default)]
+    permissions: Permissions,
+}
+
+fn default_module() -> PathBuf {
+    PathBuf::from("plugin.wasm")
+}
+
+fn default_memory_mb() -> usize {
+    64
+}
+
+pub struct Plugin {
+    pub manifest: Manifest,
+    #[cfg(feature = "plugins")]
+    module: wasmtime::Module,
+}
+
+static PLUGINS: OnceLock<Vec<Arc<Plugin>>> = OnceLock::new();
+
+/// Load the plugins in PLUGINS_DIR; call once at startup.
+pub fn init() -> Result<(), String> {
+    let plugins = match std::env::var("PLUGINS_DIR").ok().filter(|d| !d.trim().is_empty()) {
+        Some(dir) => load(&dir)?,
+        None => Vec::new(),
+    };
+    let _ = PLUGINS.set(plugins);
+    Ok(())
+}
+
+fn of_kind(kind: Kind) -> impl Iterator<Item = &'static Arc<Plugin>> {
+    PLUGINS.get().into_iter().flatten().filter(move |p| p.manifest.kind == kind)
+}
+
+pub fn tools() -> impl Iterator<Item = &'static Arc<Plugin>> {
+    of_kind(Kind::Tool)
+}
+
+pub fn filters() -> impl Iterator<Item = &'static Arc<Plugin>> {
+    of_kind(Kind::Filter)
+}
+
+#[cfg(not(feature = "plugins"))]
+fn load(_dir: &str) -> Result<Vec<Arc<Plugin>>, String> {
+    Err("PLUGINS_DIR needs a build with the `plugins` feature".into())
+}
+
+#[cfg(not(feature = "plugins"))]
+pub fn run(_plugin: &Plugin, _input: &str, _limit: Duration) -> Result<String, String> {
+    Err("built without the `plugins` feature".into())
+}
+
+#[cfg(feature = "plugins")]
+mod host {
+    use super::*;
+
+    use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+    use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+    use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+    use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, WasiCtxBuilder};
+
+    use crate::output;
+
+    // Epoch ticks bound how long a module may run
+    const TICK: Duration = Duration::from_millis(100);
+    // Largest result kept from stdout / stderr
+    const OUTPUT_CAP: usize = 1 << 20;
+
+    // What a running module's store holds
+    struct Ctx {
+        wasi: WasiP1Ctx,
+        limits: StoreLimits,
+    }
+
+    fn engine() -> &'static Engine {
+        static ENGINE: OnceLock<Engine> = OnceLock::new();
+        ENGINE.get_or_init(|| {
+            let mut config = Config::new();
+            config.epoch_interruption(true);
+            let engine = Engine::new(&config).expect("the default wasmtime configuration is valid");
+            let ticker = engine.clone();
+            std::thread::spawn(move || {
+                loop {
+                    std::thread::sleep(TICK);
+                    ticker.increment_epoch();
+                }
+            });
+            engine
+        })
+    }
+
+    pub fn load(dir: &str) -> Result<Vec<Arc<Plugin>>, String> {
+        let entries = std::fs::read_dir(dir).map_err(|e| format!("PLUGINS_DIR {dir}: {e}"))?;
+        let mut dirs: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect();
+        dirs.sort();
+        let mut plugins = Vec::new();
+        for dir in dirs {
+            let path = dir.join("plugin.toml");
+            let Ok(body) = std::fs::read_to_string(&path) else { continue };
+            let mut manifest: Manifest = toml::from_str(&body).map_err(|e| format!("{}: {e}", path.display()))?;
+            let wasm = dir.join(&manifest.module);
+            let module = Module::from_file(engine(), &wasm).map_err(|e| format!("{}: {e}", wasm.display()))?;
+            let perms = &mut manifest.permissions;
+            for p in perms.read.iter_mut().chain(perms.write.iter_mut()) {
+                // Relative to the plugin, not to wherever parlar was started
+                let resolved = std::fs::canonicalize(dir.join(&*p)).ok().filter(|r| r.is_dir());
+                let Some(resolved) = resolved else {
+                    return Err(format!("{}: permission for {}: not a directory", path.display(), p.display()));
+                };
+                *p = resolved;
+            }
+            plugins.push(Arc::new(Plugin { manifest, module }));
+        }
+        Ok(plugins)
+    }
+
+    /// Run `plugin` with `input` on stdin and return what it printed.
+    /// Blocking; the module is stopped after `limit`.
+    pub fn run(plugin: &Plugin, input: &str, limit: Duration) -> Result<String, String> {
+        let m = &plugin.manifest;
+        let stdout = MemoryOutputPipe::new(OUTPUT_CAP);
+        let stderr = MemoryOutputPipe::new(OUTPUT_CAP);
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stdin(MemoryInputPipe::new(input.as_bytes().to_vec()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .arg(&m.name);
+        for name in &m.permissions.env {
+            if let Ok(v) = std::env::var(name) {
+                wasi.env(name, v);
+            }
+        }
+        for (dirs, dir_perms, file_perms) in [
+            (&m.permissions.read, DirPerms::READ, FilePerms::READ),
+            (&m.permissions.write, DirPerms::all(), FilePerms::all()),
+        ] {
+            for dir in dirs {
+                let guest = dir.to_string_lossy();
+                wasi.preopened_dir(dir, &guest, dir_perms, file_perms).map_err(|e| format!("{}: {e}", dir.display()))?;
+            }
+        }
+        let limits = StoreLimitsBuilder::new().memory_size(m.memory_mb << 20).build();
+        let mut store = Store::new(engine(), Ctx { wasi: wasi.build_p1(), limits });
+        store.limiter(|ctx| &mut ctx.limits);
+        store.set_epoch_deadline((limit.as_millis() / TICK.as_millis()).max(1) as u64);
+        let mut linker: Linker<Ctx> = Linker::new(engine());
+        preview1::add_to_linker_sync(&mut linker, |ctx| &mut ctx.wasi).map_err(|e| e.to_string())?;
+        let instance = linker.instantiate(&mut store, &plugin.module).map_err(|e| e.to_string())?;
+        let start = instance.get_typed_func::<(), ()>(&mut store, "_start").map_err(|e| e.to_string())?;
+        let result = start.call(&mut store, ());
+        for line in String::from_utf8_lossy(&stderr.contents()).lines().filter(|l| !l.trim().is_empty()) {
+            output::notice("plugin", &format!("{}: {}", m.name, line.trim()));
+        }
+        match result {
+            Ok(()) => {}
+            Err(e) => match e.downcast_ref::<I32Exit>() {
+                Some(I32Exit(0)) => {}
+                Some(I32Exit(code)) => return Err(format!("exit status {code}")),
+                None if e.downcast_ref::<wasmtime::Trap>() == Some(&wasmtime::Trap::Interrupt) => {
+                    return Err(format!("timed out after {}s", limit.as_secs()));
+                }
+                None => return Err(e.to_string()),
+            },
+        }
+        Ok(String::from_utf8_lossy(&stdout.contents()).trim().to_string())
+    }
+}
+
+#[cfg(feature = "plugins")]
+use host::load;
+#[cfg(feature = "plugins")]
+pub use host::run;
@@ -0,0 +1,127 @@
+// Post-processing of assistant text before it is printed or logged:
+// regex replacements (a built-in markdown stripper and/or a rules file)
+// and an optional external command over the whole message.
+//
+// Rules file, one `PATTERN => REPLACEMENT` per line (`#` comments,
+// `$1`-style captures allowed):
+//   (?i)\be\.g\.  => for example
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use regex::Regex;
+
+// Markdown the model likes to emit, rewritten for a plain terminal
+const MARKDOWN_RULES: &[(&str, &str)] = &[
+    (r"(?m)^```[A-Za-z0-9_-]*\s*$\n?", ""),
+    (r"(?m)^#{1,6}\s+", ""),
+    (r"\*\*(.+?)\*\*", "$1"),
+    (r"__(.+?)__", "$1"),
+    (r"`([^`]+)`", "$1"),
+    (r"\[([^\]]+)\]\(([^)]+)\)", "$1 ($2)"),
+    (r"(?m)^(\s*)[-*+]\s+", "${1}• "),
+    (r"\*(\S[^*\n]*?)\*", "$1"),
+];
+
+pub struct PostProcess {
+    rules: Vec<(Regex, String)>,
+    command: Option<String>,
+}
+
+impl PostProcess {
+    /// Build from `ASSISTANT_TEXT_STRIP_MARKDOWN`, `ASSISTANT_TEXT_RULES` and `ASSISTANT_TEXT_CMD`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let mut rules = Vec::new();
+        // Markdown marks are read out by screen readers
+        if crate::env_flag("ASSISTANT_TEXT_STRIP_MARKDOWN", crate::output::accessible()) {
+            for (pat, with) in MARKDOWN_RULES {
+                rules.push((Regex::new(pat).map_err(|e| e.to_string())?, with.to_string()));
+            }
+        }
+        if let Ok(path) = std::env::var("ASSISTANT_TEXT_RULES") {
+            let body = std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+            for (n, line) in body.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (pat, with) = line
+                    .split_once("=>")
+                    .ok_or_else(|| format!("{path}: line {}: expected `PATTERN => TEXT`", n + 1))?;
+                let re = Regex::new(pat.trim()).map_err(|e| format!("{path}: line {}: {e}", n + 1))?;
+                rules.push((re, with.trim().to_string()));
+            }
+        }
+        let command = std::env::var("ASSISTANT_TEXT_CMD").ok().filter(|c| !c.trim().is_empty());
+        if rules.is_empty() && command.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(PostProcess { rules, command }))
+    }
+
+    /// Deltas can be shown as they arrive (line by line); a command needs the whole text.
+    pub fn streams(&self) -> bool {
+        self.command.is_none()
+    }
+
+    pub fn apply_rules(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (re, with) in &self.rules {
+            text = re.replace_all(&text, with.as_str()).into_owned();
+        }
+        text
+    }
+
+    /// Run the external command on the full (rule-processed) message.
+    /// Blocking; on failure the text is returned unchanged.
+    pub fn run_command(&self, text: &str) -> String {
+        let Some(cmd) = &self.command else { return text.to_string() };
+        let run = || -> Result<String, String> {
+            let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+            let mut child = Command::new(shell)
+                .arg(flag)
+                .arg(cmd)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|e| e.to_string())?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+            }
+            let out = child.wait_with_output().map_err(|e| e.to_string())?;
+            if !out.status.success() {
+                return Err(format!("exit status {}", out.status));
+            }
+            Ok(String::from_utf8_lossy(&out.stdout).trim_end().to_string())
+        };
+        run().unwrap_or_else(|e| {
+            eprintln!("\n[postprocess] command failed ({e}); showing raw text");
+            text.to_string()
+        })
+    }
+}
+
+/// Holds streamed text until a full line is available for the rules.
+#[derive(Default)]
+pub struct LineBuf {
+    pending: String,
+}
+
+impl LineBuf {
+    /// Add a delta; returns processed complete lines, if any.
+    pub fn push(&mut self, delta: &str, post: &PostProcess) -> Option<String> {
+        self.pending.push_str(delta);
+        let cut = self.pending.rfind('\n')? + 1;
+        let lines: String = self.pending.drain(..cut).collect();
+        Some(post.apply_rules(&lines))
+    }
+
+    /// Processed remainder at the end of a message.
+    pub fn flush(&mut self, post: &PostProcess) -> String {
+        post.apply_rules(&std::mem::take(&mut self.pending))
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
@@ -0,0 +1,154 @@
+// Speaking practice: words per minute, filler words and long pauses in
+// what the user says, from the transcripts and the server's speech
+// timings. W in the terminal UI shows the running figures on the bottom
+// row (in place of the timeline); PRACTICE=true shows them from the start,
+// and without the terminal UI prints a `[practice]` line after each turn.
+//
+// Speech time is the span the server reports for each turn (audio_start_ms
+// to audio_end_ms). A pause is the silence between two turns of the user
+// with no reply in between; the server ends a turn after TURN_SIL_MS of
+// silence, so shorter pauses stay inside a turn and are not seen.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use regex::Regex;
+use serde_json::Value;
+
+const FILLERS: &str =
+    "um, uh, er, erm, ah, hmm, like, you know, I mean, sort of, kind of, basically, actually, literally";
+
+// Words to prime the transcription with, so hesitations are written out
+// rather than cleaned up
+const TRANSCRIPTION_PROMPT: &str = "Um, so, uh, I was like, you know, thinking... hmm, I mean, basically, er, yeah.";
+
+struct Config {
+    fillers: Vec<(String, Regex)>,
+    pause_ms: u64,
+    on: bool,
+}
+
+#[derive(Default)]
+struct Stats {
+    words: u64,
+    speech_ms: u64,
+    fillers: Vec<u64>,
+    pauses: u64,
+    longest_pause_ms: u64,
+    last_wpm: Option<u64>,
+    // speech span of turns whose transcript is still to come, by item
+    pending: HashMap<String, u64>,
+    // end of the user's last turn in the input audio, until a reply starts
+    last_end_ms: Option<u64>,
+    turn_start_ms: u64,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+static STATS: Mutex<Option<Stats>> = Mutex::new(None);
+
+/// Read PRACTICE, PRACTICE_FILLERS and PRACTICE_PAUSE_MS; call once at startup.
+pub fn init() -> Result<(), String> {
+    let list = std::env::var("PRACTICE_FILLERS").unwrap_or_else(|_| FILLERS.into());
+    let mut fillers = Vec::new();
+    for word in list.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+        let re =
+            Regex::new(&format!(r"(?i)\b{}\b", regex::escape(word))).map_err(|e| format!("PRACTICE_FILLERS: {e}"))?;
+        fillers.push((word.to_lowercase(), re));
+    }
+    let pause_ms = match std::env::var("PRACTICE_PAUSE_MS") {
+        Ok(v) => v.trim().parse().map_err(|_| format!("PRACTICE_PAUSE_MS: not a number of milliseconds: {v}"))?,
+        Err(_) => 1500,
+    };
+    *STATS.lock().unwrap() = Some(Stats { fillers: vec![0; fillers.len()], ..Default::default() });
+    let _ = CONFIG.set(Config { fillers, pause_ms, on: crate::env_flag("PRACTICE", false) });
+    Ok(())
+}
+
+/// PRACTICE=true: show the figures from the start.
+pub fn enabled() -> bool {
+    CONFIG.get().is_some_and(|c| c.on)
+}
+
+/// Prompt for the transcription model in practice mode, so fillers are kept.
+pub fn transcription_prompt() -> Option<&'static str> {
+    enabled().then_some(TRANSCRIPTION_PROMPT)
+}
+
+/// Follow a server event; returns the figures after a finished user turn,
+/// for printing where there is no overlay.
+pub fn on_event(evt: &Value) -> Option<String> {
+    let cfg = CONFIG.get()?;
+    let mut guard = STATS.lock().unwrap();
+    let s = guard.as_mut()?;
+    let ms = |key: &str| evt[key].as_u64();
+    match evt["type"].as_str().unwrap_or("") {
+        "input_audio_buffer.speech_started" => {
+            let start = ms("audio_start_ms")?;
+            // A smaller time is a new connection: its audio starts from zero
+            if let Some(end) = s.last_end_ms.take()
+                && start > end
+                && start - end >= cfg.pause_ms
+            {
+                s.pauses += 1;
+                s.longest_pause_ms = s.longest_pause_ms.max(start - end);
+            }
+            s.turn_start_ms = start;
+        }
+        "input_audio_buffer.speech_stopped" => {
+            let end = ms("audio_end_ms")?;
+            s.last_end_ms = Some(end);
+            let id = evt["item_id"].as_str().unwrap_or("").to_string();
+            s.pending.insert(id, end.saturating_sub(s.turn_start_ms));
+        }
+        // The assistant answered: the silence before the next turn is not a pause
+        "response.audio.delta" | "response.text.delta" => s.last_end_ms = None,
+        "conversation.item.input_audio_transcription.completed" => {
+            let span = s.pending.remove(evt["item_id"].as_str().unwrap_or(""))?;
+            let text = evt["transcript"].as_str().unwrap_or("");
+            let words = text.split_whitespace().count() as u64;
+            for (i, (_, re)) in cfg.fillers.iter().enumerate() {
+                s.fillers[i] += re.find_iter(text).count() as u64;
+            }
+            s.words += words;
+            s.speech_ms += span;
+            s.last_wpm = (span >= 1000).then(|| words * 60_000 / span);
+            return Some(render(cfg, s));
+        }
+        _ => {}
+    }
+    None
+}
+
+fn render(cfg: &Config, s: &Stats) -> String {
+    let wpm = match (s.speech_ms >= 1000).then(|| s.words * 60_000 / s.speech_ms) {
+        Some(w) => format!("{w} wpm"),
+        None => "– wpm".into(),
+    };
+    let last = s.last_wpm.map(|w| format!(" (last turn {w})")).unwrap_or_default();
+    let total: u64 = s.fillers.iter().sum();
+    let mut top: Vec<(&str, u64)> =
+        cfg.fillers.iter().zip(&s.fillers).filter(|(_, n)| **n > 0).map(|((w, _), n)| (w.as_str(), *n)).collect();
+    top.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+    let top: Vec<String> = top.iter().take(3).map(|(w, n)| format!("{w} {n}")).collect();
+    let share = if s.words > 0 { total as f32 * 100.0 / s.words as f32 } else { 0.0 };
+    let fillers = if top.is_empty() {
+        "no fillers".to_string()
+    } else {
+        format!("fillers {total} ({share:.1}%: {})", top.join(", "))
+    };
+    let pauses = match s.pauses {
+        0 => format!("no pauses ≥{:.1}s", cfg.pause_ms as f32 / 1000.0),
+        n => format!(
+            "{n} pauses ≥{:.1}s, longest {:.1}s",
+            cfg.pause_ms as f32 / 1000.0,
+            s.longest_pause_ms as f32 / 1000.0
+        ),
+    };
+    format!("{wpm}{last} | {fillers} | {pauses}")
+}
+
+/// The running figures, e.g. "142 wpm (last turn 150) | fillers 7 (4.9%: um 3, like 2) | no pauses ≥1.5s".
+pub fn summary() -> String {
+    let Some(cfg) = CONFIG.get() else { return String::new() };
+    STATS.lock().unwrap().as_ref().map(|s| render(cfg, s)).unwrap_or_default()
+}
@@ -0,0 +1,289 @@
+// Retrieval for questions about local documents. With RAG_DIR set, a user
+// turn matching RAG_TRIGGER (default: "my notes", "my docs", "my files", …)
+// is searched for in the text files under that directory, and the best
+// RAG_TOP_K (default 3) excerpts are added to the conversation as a system
+// message just before the reply is requested.
+//
+// RAG_BACKEND picks the search:
+//   embeddings  OpenAI embeddings (RAG_EMBEDDING_MODEL, default
+//               text-embedding-3-small) of paragraph-sized chunks, indexed in
+//               the background at startup; ranked by cosine similarity
+//   command     RAG_CMD gets the question on stdin and prints the excerpts as
+//               JSON, [{"source": "…", "text": "…"}, …], best first
+// A search that fails or takes longer than RAG_TIMEOUT_MS (default 4000)
+// leaves the turn as it is.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use regex::Regex;
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::OnceCell;
+
+use crate::{net, output};
+
+const DEFAULT_TRIGGER: &str = r"(?i)\b(my|our)\s+(own\s+)?(notes?|docs?|documents?|files?|journal|wiki)\b";
+// File types indexed
+const EXTENSIONS: [&str; 5] = ["md", "markdown", "txt", "org", "rst"];
+// Chunks are paragraphs merged up to about this many characters
+const CHUNK_CHARS: usize = 1200;
+// Inputs per embeddings request
+const BATCH: usize = 96;
+
+pub struct Snippet {
+    pub source: String,
+    pub text: String,
+}
+
+/// A way of finding the excerpts that best match a question.
+pub trait Backend: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Get ready to answer searches (index the documents); called once at
+    /// startup, in the background.
+    fn prepare(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async { Ok(()) })
+    }
+    /// The `k` best excerpts for `query`, best first.
+    fn search<'a>(&'a self, query: &'a str, k: usize) -> BoxFuture<'a, Result<Vec<Snippet>, String>>;
+}
+
+pub struct Rag {
+    trigger: Regex,
+    top_k: usize,
+    timeout: Duration,
+    backend: Arc<dyn Backend>,
+}
+
+impl Rag {
+    /// Build from the RAG_* settings and start indexing; `None` without RAG_DIR.
+    pub fn from_env(api_key: &str) -> Result<Option<Self>, String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let Some(dir) = var("RAG_DIR") else { return Ok(None) };
+        let num = |name: &str, default: u64| match var(name) {
+            Some(v) => v.trim().parse::<u64>().map_err(|_| format!("{name}: not a number: {v}")),
+            None => Ok(default),
+        };
+        let trigger = var("RAG_TRIGGER").unwrap_or_else(|| DEFAULT_TRIGGER.into());
+        let trigger = Regex::new(&trigger).map_err(|e| format!("RAG_TRIGGER: {e}"))?;
+        let backend: Arc<dyn Backend> = match var("RAG_BACKEND").as_deref().unwrap_or("embeddings") {
+            "embeddings" => {
+                let model = var("RAG_EMBEDDING_MODEL").unwrap_or_else(|| "text-embedding-3-small".into());
+                Arc::new(Embeddings::new(Path::new(&dir), api_key, model)?)
+            }
+            "command" => Arc::new(CommandBackend {
+                cmd: var("RAG_CMD").ok_or("RAG_BACKEND=command needs RAG_CMD")?,
+                dir,
+            }),
+            other => return Err(format!("RAG_BACKEND: unknown backend {other} (use embeddings or command)")),
+        };
+        let indexing = backend.clone();
+        tokio::spawn(async move {
+            if let Err(e) = indexing.prepare().await {
+                output::notice("rag", &format!("indexing failed: {e}"));
+            }
+        });
+        Ok(Some(Rag {
+            trigger,
+            top_k: num("RAG_TOP_K", 3)?.max(1) as usize,
+            timeout: Duration::from_millis(num("RAG_TIMEOUT_MS", 4000)?),
+            backend,
+        }))
+    }
+
+    /// Whether `transcript` asks about the documents.
+    pub fn wants(&self, transcript: &str) -> bool {
+        self.trigger.is_match(transcript)
+    }
+
+    pub async fn search(&self, query: &str) -> Result<Vec<Snippet>, String> {
+        match tokio::time::timeout(self.timeout, self.backend.search(query, self.top_k)).await {
+            Ok(found) => found.map_err(|e| format!("{}: {e}", self.backend.name())),
+            Err(_) => Err(format!("{}: no result within {} ms", self.backend.name(), self.timeout.as_millis())),
+        }
+    }
+}
+
+/// `conversation.item.create` putting `snippets` before the reply.
+pub fn context_event(snippets: &[Snippet]) -> String {
+    let mut text = String::from("Excerpts from the user's own documents that may help with the answer:");
+    for s in snippets {
+        text.push_str(&format!("\n\n[{}]\n{}", s.source, s.text.trim()));
+    }
+    json!({
+        "type": "conversation.item.create",
+        "item": {
+            "type": "message",
+            "role": "system",
+            "content": [{"type": "input_text", "text": text}]
+        }
+    })
+    .to_string()
+}
+
+/// The text files under `dir`, in paragraph-sized chunks.
+fn chunks(dir: &Path) -> Result<Vec<Snippet>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(d) = pending.pop() {
+        let entries = std::fs::read_dir(&d).map_err(|e| format!("RAG_DIR {}: {e}", d.display()))?;
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('.')) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().and_then(|e| e.to_str()).is_some_and(|e| EXTENSIONS.contains(&e)) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    let mut chunks = Vec::new();
+    for path in files {
+        let Ok(body) = std::fs::read_to_string(&path) else { continue };
+        let source = path.strip_prefix(dir).unwrap_or(&path).display().to_string();
+        let mut text = String::new();
+        for para in body.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+            if !text.is_empty() && text.len() + para.len() > CHUNK_CHARS {
+                chunks.push(Snippet { source: source.clone(), text: std::mem::take(&mut text) });
+            }
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(para);
+        }
+        if !text.is_empty() {
+            chunks.push(Snippet { source, text });
+        }
+    }
+    Ok(chunks)
+}
+
+struct Embeddings {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    chunks: Vec<Snippet>,
+    // One vector per chunk, filled in by `prepare`
+    vectors: OnceCell<Vec<Vec<f32>>>,
+}
+
+impl Embeddings {
+    fn new(dir: &Path, api_key: &str, model: String) -> Result<Self, String> {
+        let builder = reqwest::Client::builder().timeout(Duration::from_secs(60));
+        let client = net::http_client(builder, "api.openai.com").map_err(|e| format!("RAG_DIR: {e}"))?;
+        let chunks = chunks(dir)?;
+        if chunks.is_empty() {
+            return Err(format!("RAG_DIR {}: no text files ({})", dir.display(), EXTENSIONS.join(", ")));
+        }
+        Ok(Embeddings { client, api_key: api_key.to_string(), model, chunks, vectors: OnceCell::new() })
+    }
+
+    async fn embed(&self, inputs: &[&str]) -> Result<Vec<Vec<f32>>, String> {
+        let resp: serde_json::Value = self
+            .client
+            .post("https://api.openai.com/v1/embeddings")
+            .bearer_auth(&self.api_key)
+            .json(&json!({ "model": self.model, "input": inputs }))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        let data = resp["data"].as_array().ok_or("embeddings: unexpected response")?;
+        Ok(data
+            .iter()
+            .map(|d| d["embedding"].as_array().into_iter().flatten().filter_map(|x| x.as_f64()).map(|x| x as f32).collect())
+            .collect())
+    }
+
+    async fn vectors(&self) -> Result<&Vec<Vec<f32>>, String> {
+        self.vectors
+            .get_or_try_init(|| async {
+                let mut vectors = Vec::with_capacity(self.chunks.len());
+                for batch in self.chunks.chunks(BATCH) {
+                    let inputs: Vec<&str> = batch.iter().map(|c| c.text.as_str()).collect();
+                    vectors.extend(self.embed(&inputs).await?);
+                }
+                output::notice("rag", &format!("indexed {} excerpts.", vectors.len()));
+                Ok(vectors)
+            })
+            .await
+    }
+}
+
+impl Backend for Embeddings {
+    fn name(&self) -> &'static str {
+        "embeddings"
+    }
+
+    fn prepare(&self) -> BoxFuture<'_, Result<(), String>> {
+        Box::pin(async { self.vectors().await.map(|_| ()) })
+    }
+
+    fn search<'a>(&'a self, query: &'a str, k: usize) -> BoxFuture<'a, Result<Vec<Snippet>, String>> {
+        Box::pin(async move {
+            let vectors = self.vectors().await?;
+            let q = self.embed(&[query]).await?.pop().ok_or("embeddings: empty response")?;
+            // OpenAI embeddings are unit length, so the dot product is the cosine
+            let mut ranked: Vec<(f32, usize)> =
+                vectors.iter().enumerate().map(|(i, v)| (v.iter().zip(&q).map(|(a, b)| a * b).sum(), i)).collect();
+            ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+            Ok(ranked
+                .into_iter()
+                .take(k)
+                .map(|(_, i)| Snippet { source: self.chunks[i].source.clone(), text: self.chunks[i].text.clone() })
+                .collect())
+        })
+    }
+}
+
+struct CommandBackend {
+    cmd: String,
+    // Where the command runs
+    dir: String,
+}
+
+impl Backend for CommandBackend {
+    fn name(&self) -> &'static str {
+        "command"
+    }
+
+    fn search<'a>(&'a self, query: &'a str, k: usize) -> BoxFuture<'a, Result<Vec<Snippet>, String>> {
+        Box::pin(async move {
+            let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+            let mut child = tokio::process::Command::new(shell)
+                .arg(flag)
+                .arg(&self.cmd)
+                .current_dir(PathBuf::from(&self.dir))
+                .env("RAG_TOP_K", k.to_string())
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|e| e.to_string())?;
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(query.as_bytes()).await;
+            }
+            let out = child.wait_with_output().await.map_err(|e| e.to_string())?;
+            if !out.status.success() {
+                return Err(format!("exit status {}", out.status));
+            }
+            let found: Vec<serde_json::Value> =
+                serde_json::from_slice(&out.stdout).map_err(|e| format!("bad JSON: {e}"))?;
+            Ok(found
+                .iter()
+                .take(k)
+                .filter_map(|s| {
+                    let text = s["text"].as_str()?.to_string();
+                    Some(Snippet { source: s["source"].as_str().unwrap_or("").to_string(), text })
+                })
+                .collect())
+        })
+    }
+}
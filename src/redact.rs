@@ -0,0 +1,112 @@
+// Redaction of sensitive data before text is written to disk (meeting notes,
+// transcripts, notes file, dataset manifests, event log, A/B ratings,
+// memory, bug reports), published over MQTT or handed to hooks and
+// webhooks. What is shown on screen and sent to the model is unchanged.
+//
+// `REDACT` turns on built-in patterns (comma-separated):
+//   cards   payment card numbers (13–19 digits, Luhn-checked)
+//   emails  email addresses
+// `REDACT_RULES` names a file of extra rules, one regex per line (`#`
+// comments), optionally with a replacement:
+//   (?i)\bproject falcon\b => [codename]
+//   \bEMP-\d{6}\b
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+const MASK: &str = "[REDACTED]";
+
+struct Rule {
+    re: Regex,
+    with: String,
+    /// Only redact matches that pass the Luhn checksum.
+    luhn: bool,
+}
+
+static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+
+fn builtin(name: &str) -> Result<Rule, String> {
+    let (pattern, with, luhn) = match name {
+        "cards" => (r"\b\d(?:[ -]?\d){12,18}\b", "[card]", true),
+        "emails" => (r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b", "[email]", false),
+        other => return Err(format!("REDACT: unknown pattern {other} (use cards, emails)")),
+    };
+    Ok(Rule { re: Regex::new(pattern).unwrap(), with: with.into(), luhn })
+}
+
+fn parse_rules(body: &str) -> Result<Vec<Rule>, String> {
+    let mut rules = Vec::new();
+    for (n, line) in body.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (pattern, with) = match line.split_once(" => ") {
+            Some((p, w)) => (p.trim(), w.trim()),
+            None => (line, MASK),
+        };
+        let re = Regex::new(pattern).map_err(|e| format!("line {}: {e}", n + 1))?;
+        rules.push(Rule { re, with: with.to_string(), luhn: false });
+    }
+    Ok(rules)
+}
+
+/// Read `REDACT` and `REDACT_RULES`; call once at startup.
+pub fn init() -> Result<(), String> {
+    let mut rules = Vec::new();
+    if let Ok(names) = std::env::var("REDACT") {
+        for name in names.split(',').map(str::trim).filter(|n| !n.is_empty() && *n != "off") {
+            rules.push(builtin(name)?);
+        }
+    }
+    if let Ok(path) = std::env::var("REDACT_RULES")
+        && !path.is_empty()
+    {
+        let body = std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+        rules.extend(parse_rules(&body).map_err(|e| format!("{path}: {e}"))?);
+    }
+    let _ = RULES.set(rules);
+    Ok(())
+}
+
+fn luhn_ok(digits: &str) -> bool {
+    let digits: Vec<u32> = digits.chars().filter_map(|c| c.to_digit(10)).collect();
+    // Double every second digit from the right, summing the digits of the product
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (1, dd) if dd > 9 => dd - 9,
+            (1, dd) => dd,
+            _ => d,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// `text` with every configured pattern replaced.
+pub fn apply(text: &str) -> Cow<'_, str> {
+    let Some(rules) = RULES.get().filter(|r| !r.is_empty()) else {
+        return Cow::Borrowed(text);
+    };
+    let mut out = Cow::Borrowed(text);
+    for rule in rules {
+        if !rule.re.is_match(&out) {
+            continue;
+        }
+        let replaced = if rule.luhn {
+            rule.re
+                .replace_all(&out, |c: &regex::Captures| {
+                    if luhn_ok(&c[0]) { rule.with.clone() } else { c[0].to_string() }
+                })
+                .into_owned()
+        } else {
+            rule.re.replace_all(&out, rule.with.as_str()).into_owned()
+        };
+        out = Cow::Owned(replaced);
+    }
+    out
+}
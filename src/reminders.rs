@@ -0,0 +1,213 @@
+// Reminders: built-in tools that let the assistant set, list and delete
+// reminders ("remind me at 3pm to call the plumber"), kept in a local JSON
+// file (REMINDERS_FILE, e.g. `reminders.json`) so they survive a restart.
+// When one is due parlar plays a chime and the assistant says it, outside
+// the conversation; reminders that fell due while parlar was not running
+// are given at the next start.
+
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::control::Controls;
+use crate::earcon::{self, Earcon};
+use crate::{i18n, output};
+use crate::tools::{self, Builtin};
+
+// How often due reminders are looked for
+const TICK: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Reminder {
+    id: u32,
+    // RFC 3339, local offset
+    at: String,
+    text: String,
+}
+
+impl Reminder {
+    fn due(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.at).ok().map(|t| t.with_timezone(&Local))
+    }
+}
+
+static PATH: OnceLock<Option<String>> = OnceLock::new();
+static LIST: Mutex<Vec<Reminder>> = Mutex::new(Vec::new());
+
+/// Read REMINDERS_FILE; call once at startup.
+pub fn init() -> Result<(), String> {
+    let path = std::env::var("REMINDERS_FILE").ok().filter(|p| !p.trim().is_empty());
+    if let Some(p) = &path {
+        match std::fs::read_to_string(p) {
+            Ok(body) => {
+                *LIST.lock().unwrap() = serde_json::from_str(&body).map_err(|e| format!("REMINDERS_FILE {p}: {e}"))?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("REMINDERS_FILE {p}: {e}")),
+        }
+    }
+    let _ = PATH.set(path);
+    Ok(())
+}
+
+fn enabled() -> bool {
+    matches!(PATH.get(), Some(Some(_)))
+}
+
+fn save(list: &[Reminder]) -> Result<(), String> {
+    let Some(Some(path)) = PATH.get() else { return Ok(()) };
+    let body = serde_json::to_string_pretty(list).map_err(|e| e.to_string())?;
+    std::fs::write(path, body).map_err(|e| format!("cannot write {path}: {e}"))
+}
+
+/// The reminder tools, when REMINDERS_FILE is set.
+pub fn tools() -> Vec<Builtin> {
+    if !enabled() {
+        return Vec::new();
+    }
+    vec![
+        Builtin {
+            name: "create_reminder",
+            description: "Set a reminder for the user. Give either `at` (local time, HH:MM for the next such \
+                          time, or YYYY-MM-DD HH:MM) or `in_minutes`. The result has the time it was set for.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "text": {"type": "string", "description": "What to remind the user of"},
+                    "at": {"type": "string", "description": "HH:MM or YYYY-MM-DD HH:MM, local time"},
+                    "in_minutes": {"type": "number", "description": "Minutes from now"}
+                },
+                "required": ["text"]
+            }),
+            run: create,
+        },
+        Builtin {
+            name: "list_reminders",
+            description: "List the user's reminders that are still to come, with the current local time.",
+            parameters: json!({"type": "object", "properties": {}}),
+            run: list,
+        },
+        Builtin {
+            name: "delete_reminder",
+            description: "Delete one of the user's reminders by its id (see list_reminders).",
+            parameters: json!({
+                "type": "object",
+                "properties": {"id": {"type": "integer"}},
+                "required": ["id"]
+            }),
+            run: delete,
+        },
+    ]
+}
+
+/// When `at` (HH:MM or YYYY-MM-DD HH:MM, local) is, from `now`; a bare time
+/// that has passed today means tomorrow.
+pub fn parse_at(at: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    let at = at.trim();
+    let naive = if let Ok(t) = NaiveTime::parse_from_str(at, "%H:%M") {
+        let today = now.date_naive().and_time(t);
+        if today > now.naive_local() { today } else { today + chrono::Duration::days(1) }
+    } else {
+        NaiveDateTime::parse_from_str(at, "%Y-%m-%d %H:%M")
+            .or_else(|_| NaiveDateTime::parse_from_str(at, "%Y-%m-%dT%H:%M"))
+            .map_err(|_| format!("cannot read the time {at:?}; use HH:MM or YYYY-MM-DD HH:MM"))?
+    };
+    Local.from_local_datetime(&naive).earliest().ok_or_else(|| format!("{at} does not exist in the local time zone"))
+}
+
+fn create(args: &str) -> Result<String, String> {
+    let args: serde_json::Value = serde_json::from_str(args).map_err(|e| format!("bad arguments: {e}"))?;
+    let text = args["text"].as_str().filter(|t| !t.trim().is_empty()).ok_or("`text` is required")?;
+    let now = Local::now();
+    let at = match (args["at"].as_str(), args["in_minutes"].as_f64()) {
+        (Some(at), _) => parse_at(at, now)?,
+        (None, Some(min)) if min > 0.0 => now + chrono::Duration::seconds((min * 60.0) as i64),
+        _ => return Err("give `at` or a positive `in_minutes`".into()),
+    };
+    if at <= now {
+        return Err("that time has already passed".into());
+    }
+    let mut list = LIST.lock().unwrap();
+    let id = list.iter().map(|r| r.id).max().unwrap_or(0) + 1;
+    list.push(Reminder { id, at: at.to_rfc3339(), text: text.trim().to_string() });
+    save(&list)?;
+    let when = i18n::date_time(&at);
+    output::notice("reminder", &i18n::trf("set for {when}: {text}", &[("when", &when), ("text", &text)]));
+    Ok(json!({"id": id, "at": at.format("%A %d %B %Y %H:%M").to_string(), "text": text}).to_string())
+}
+
+fn list(_args: &str) -> Result<String, String> {
+    let list = LIST.lock().unwrap();
+    let reminders: Vec<serde_json::Value> = list
+        .iter()
+        .filter_map(|r| {
+            let at = r.due()?;
+            Some(json!({"id": r.id, "at": at.format("%A %d %B %Y %H:%M").to_string(), "text": r.text}))
+        })
+        .collect();
+    Ok(json!({"now": Local::now().format("%A %d %B %Y %H:%M").to_string(), "reminders": reminders}).to_string())
+}
+
+fn delete(args: &str) -> Result<String, String> {
+    let args: serde_json::Value = serde_json::from_str(args).map_err(|e| format!("bad arguments: {e}"))?;
+    let id = args["id"].as_u64().ok_or("`id` is required")?;
+    let mut list = LIST.lock().unwrap();
+    let Some(pos) = list.iter().position(|r| u64::from(r.id) == id) else {
+        return Err(format!("no reminder {id}"));
+    };
+    let removed = list.remove(pos);
+    save(&list)?;
+    output::notice("reminder", &format!("deleted: {}", removed.text));
+    Ok(json!({"deleted": id}).to_string())
+}
+
+/// Take the reminders that are due out of the list.
+fn take_due() -> Vec<Reminder> {
+    let mut list = LIST.lock().unwrap();
+    let now = Local::now();
+    let (due, rest): (Vec<Reminder>, Vec<Reminder>) =
+        list.drain(..).partition(|r| r.due().is_none_or(|at| at <= now));
+    *list = rest;
+    if !due.is_empty()
+        && let Err(e) = save(&list)
+    {
+        output::notice("reminder", &e);
+    }
+    due
+}
+
+/// Give reminders as they fall due; call once the session is running.
+pub fn start(controls: &Controls) {
+    if !enabled() {
+        return;
+    }
+    let controls = controls.clone();
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(TICK);
+        loop {
+            tick.tick().await;
+            for r in take_due() {
+                let late = r.due().filter(|at| Local::now() - *at > chrono::Duration::minutes(2));
+                output::notice("reminder", &r.text);
+                earcon::play(&controls.cue_buf, Earcon::Reminder, controls.sr_hz, controls.earcon_gain);
+                let when = match late {
+                    Some(at) => format!(" It was due at {}, when parlar was not running; say so.", at.format("%H:%M on %A")),
+                    None => String::new(),
+                };
+                let event_id = tools::wait_idle(&controls.state).await;
+                tools::speak(
+                    &controls,
+                    event_id,
+                    &format!(
+                        "Remind the user, in one short sentence, of this reminder they asked for: {}.{when}",
+                        r.text
+                    ),
+                    "reminder",
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,274 @@
+// Session report, printed on the way out: turns, talk time per side,
+// interruptions, response latency, words per minute, and tokens with an
+// estimated cost. Everything is taken from the server events as they pass;
+// in JSON mode the report is a `report` event, and SESSION_REPORT_FILE
+// writes it as JSON too.
+//
+// Talk time is measured from the server's speech start/stop for the user,
+// and from the assistant audio received for the assistant (a cancelled
+// reply counts up to where it was cut). Latency is from the end of a user
+// turn to the first audio or text of the reply. The cost is an estimate
+// from the per-model prices below or REPORT_PRICES.
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde_json::{Value, json};
+
+use crate::output;
+
+// USD per million tokens: text in, cached text in, audio in, cached audio
+// in, text out, audio out
+const PRICES: &[(&str, [f64; 6])] = &[
+    ("gpt-realtime-mini", [0.60, 0.06, 10.0, 0.30, 2.40, 20.0]),
+    ("gpt-4o-mini-realtime", [0.60, 0.30, 10.0, 0.30, 2.40, 20.0]),
+    ("gpt-realtime", [4.0, 0.40, 32.0, 0.40, 16.0, 64.0]),
+    ("gpt-4o-realtime", [5.0, 2.50, 40.0, 2.50, 20.0, 80.0]),
+];
+
+// Input counts include the cached tokens
+#[derive(Default)]
+struct Tokens {
+    text_in: u64,
+    cached_text_in: u64,
+    audio_in: u64,
+    cached_audio_in: u64,
+    text_out: u64,
+    audio_out: u64,
+}
+
+struct Stats {
+    started: Instant,
+    // model of the current connection
+    model: String,
+    user_turns: u64,
+    replies: u64,
+    interruptions: u64,
+    user_ms: u64,
+    user_words: u64,
+    // reply audio as sent, in the output format
+    assistant_bytes: u64,
+    assistant_words: u64,
+    speech_since: Option<Instant>,
+    // end of the last user turn, until the reply starts
+    awaiting_since: Option<Instant>,
+    latencies_ms: Vec<u64>,
+    tokens: Tokens,
+    // tokens per model, for the cost
+    by_model: Vec<(String, Tokens)>,
+}
+
+static STATS: Mutex<Option<Stats>> = Mutex::new(None);
+
+/// Start counting; call once, when the session is up.
+pub fn start(model: &str) {
+    *STATS.lock().unwrap() = Some(Stats {
+        started: Instant::now(),
+        model: model.to_string(),
+        user_turns: 0,
+        replies: 0,
+        interruptions: 0,
+        user_ms: 0,
+        user_words: 0,
+        assistant_bytes: 0,
+        assistant_words: 0,
+        speech_since: None,
+        awaiting_since: None,
+        latencies_ms: Vec::new(),
+        tokens: Tokens::default(),
+        by_model: Vec::new(),
+    });
+}
+
+/// The session moved to a connection on `model`.
+pub fn connected(model: &str) {
+    if let Some(s) = STATS.lock().unwrap().as_mut() {
+        s.model = model.to_string();
+    }
+}
+
+fn words(text: &str) -> u64 {
+    text.split_whitespace().count() as u64
+}
+
+/// Follow a server event.
+pub fn on_event(evt: &Value) {
+    let mut guard = STATS.lock().unwrap();
+    let Some(s) = guard.as_mut() else { return };
+    match evt["type"].as_str().unwrap_or("") {
+        "input_audio_buffer.speech_started" => s.speech_since = Some(Instant::now()),
+        "input_audio_buffer.speech_stopped" => {
+            if let Some(t) = s.speech_since.take() {
+                s.user_ms += t.elapsed().as_millis() as u64;
+            }
+            s.awaiting_since = Some(Instant::now());
+        }
+        "conversation.item.input_audio_transcription.completed" => {
+            s.user_turns += 1;
+            s.user_words += words(evt["transcript"].as_str().unwrap_or(""));
+        }
+        // Typed messages
+        "conversation.item.created" if evt["item"]["role"] == "user" => {
+            if let Some(text) = evt["item"]["content"][0]["text"].as_str() {
+                s.user_turns += 1;
+                s.user_words += words(text);
+                s.awaiting_since = Some(Instant::now());
+            }
+        }
+        "response.audio.delta" | "response.text.delta" => {
+            if let Some(t) = s.awaiting_since.take() {
+                s.latencies_ms.push(t.elapsed().as_millis() as u64);
+            }
+            if let Some(b64) = evt["delta"].as_str().filter(|_| evt["type"] == "response.audio.delta") {
+                let bytes = (b64.len() / 4 * 3).saturating_sub(b64.bytes().rev().take_while(|&b| b == b'=').count());
+                s.assistant_bytes += bytes as u64;
+            }
+        }
+        "response.done" => {
+            let response = &evt["response"];
+            match response["status"].as_str().unwrap_or("") {
+                "cancelled" => s.interruptions += 1,
+                _ => s.replies += 1,
+            }
+            s.assistant_words += words(&crate::hooks::response_text(evt));
+            let usage = &response["usage"];
+            let get = |v: &Value| v.as_u64().unwrap_or(0);
+            let input = &usage["input_token_details"];
+            let tokens = Tokens {
+                text_in: get(&input["text_tokens"]),
+                cached_text_in: get(&input["cached_tokens_details"]["text_tokens"]),
+                audio_in: get(&input["audio_tokens"]),
+                cached_audio_in: get(&input["cached_tokens_details"]["audio_tokens"]),
+                text_out: get(&usage["output_token_details"]["text_tokens"]),
+                audio_out: get(&usage["output_token_details"]["audio_tokens"]),
+            };
+            add(&mut s.tokens, &tokens);
+            match s.by_model.iter_mut().find(|(m, _)| *m == s.model) {
+                Some((_, t)) => add(t, &tokens),
+                None => s.by_model.push((s.model.clone(), tokens)),
+            }
+        }
+        _ => {}
+    }
+}
+
+fn add(to: &mut Tokens, t: &Tokens) {
+    to.text_in += t.text_in;
+    to.cached_text_in += t.cached_text_in;
+    to.audio_in += t.audio_in;
+    to.cached_audio_in += t.cached_audio_in;
+    to.text_out += t.text_out;
+    to.audio_out += t.audio_out;
+}
+
+/// Prices for `model`: REPORT_PRICES, else the table, by longest prefix.
+fn prices(model: &str) -> Option<[f64; 6]> {
+    if let Ok(v) = std::env::var("REPORT_PRICES") {
+        let p: Vec<f64> = v.split(',').filter_map(|x| x.trim().parse().ok()).collect();
+        return p.try_into().ok();
+    }
+    PRICES.iter().filter(|(m, _)| model.starts_with(m)).max_by_key(|(m, _)| m.len()).map(|(_, p)| *p)
+}
+
+/// Check REPORT_PRICES; call once at startup.
+pub fn check_prices() -> Result<(), String> {
+    match std::env::var("REPORT_PRICES") {
+        Ok(v) if v.split(',').filter(|x| x.trim().parse::<f64>().is_ok_and(|p| p >= 0.0)).count() != 6 => Err(format!(
+            "REPORT_PRICES: expected 6 prices per million tokens (text in, cached text in, audio in, \
+             cached audio in, text out, audio out), got {v}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn cost(model: &str, t: &Tokens) -> Option<f64> {
+    let [text_in, cached_text_in, audio_in, cached_audio_in, text_out, audio_out] = prices(model)?;
+    let n = |v: u64| v as f64;
+    let input = text_in * n(t.text_in.saturating_sub(t.cached_text_in))
+        + cached_text_in * n(t.cached_text_in)
+        + audio_in * n(t.audio_in.saturating_sub(t.cached_audio_in))
+        + cached_audio_in * n(t.cached_audio_in);
+    Some((input + text_out * n(t.text_out) + audio_out * n(t.audio_out)) / 1e6)
+}
+
+fn minutes(ms: u64) -> String {
+    format!("{}:{:02}", ms / 60_000, ms / 1000 % 60)
+}
+
+fn wpm(words: u64, ms: u64) -> Option<u64> {
+    (ms >= 10_000).then(|| words * 60_000 / ms)
+}
+
+/// The report as JSON, or `None` before the session started.
+fn json() -> Option<Value> {
+    let guard = STATS.lock().unwrap();
+    let s = guard.as_ref()?;
+    let assistant_ms = crate::wire::output_ms(s.assistant_bytes);
+    let latency =
+        (!s.latencies_ms.is_empty()).then(|| s.latencies_ms.iter().sum::<u64>() / s.latencies_ms.len() as u64);
+    let costs: Vec<Option<f64>> = s.by_model.iter().map(|(m, t)| cost(m, t)).collect();
+    let cost = (!costs.is_empty() && costs.iter().all(Option::is_some)).then(|| costs.iter().flatten().sum::<f64>());
+    Some(json!({
+        "duration_ms": s.started.elapsed().as_millis() as u64,
+        "user_turns": s.user_turns,
+        "replies": s.replies,
+        "interruptions": s.interruptions,
+        "user_talk_ms": s.user_ms,
+        "assistant_talk_ms": assistant_ms,
+        "avg_latency_ms": latency,
+        "user_wpm": wpm(s.user_words, s.user_ms),
+        "assistant_wpm": wpm(s.assistant_words, assistant_ms),
+        "tokens": {
+            "text_in": s.tokens.text_in,
+            "audio_in": s.tokens.audio_in,
+            "cached_in": s.tokens.cached_text_in + s.tokens.cached_audio_in,
+            "text_out": s.tokens.text_out,
+            "audio_out": s.tokens.audio_out
+        },
+        "cost_usd": cost,
+    }))
+}
+
+/// Print the report (unless SESSION_REPORT=false) and write
+/// SESSION_REPORT_FILE; nothing before the session started.
+pub fn finish() {
+    let Some(r) = json() else { return };
+    if let Ok(path) = std::env::var("SESSION_REPORT_FILE")
+        && !path.trim().is_empty()
+        && let Err(e) = std::fs::write(&path, format!("{r:#}\n"))
+    {
+        output::notice("report", &format!("cannot write {path}: {e}"));
+    }
+    if !crate::env_flag("SESSION_REPORT", true) {
+        return;
+    }
+    if output::json_mode() {
+        output::report(&r);
+        return;
+    }
+    let ms = |k: &str| r[k].as_u64().unwrap_or(0);
+    let per_min = |k: &str| r[k].as_u64().map(|w| format!(", {w} wpm")).unwrap_or_default();
+    output::info(&format!(
+        "Session: {} — {} turns, {} replies, {} interrupted",
+        minutes(ms("duration_ms")),
+        r["user_turns"],
+        r["replies"],
+        r["interruptions"]
+    ));
+    output::info(&format!(
+        "Talk time: you {}{}, assistant {}{}",
+        minutes(ms("user_talk_ms")),
+        per_min("user_wpm"),
+        minutes(ms("assistant_talk_ms")),
+        per_min("assistant_wpm")
+    ));
+    if let Some(l) = r["avg_latency_ms"].as_u64() {
+        output::info(&format!("Response latency: {l} ms on average"));
+    }
+    let t = &r["tokens"];
+    let cost = r["cost_usd"].as_f64().map(|c| format!(", about ${c:.2}")).unwrap_or_default();
+    output::info(&format!(
+        "Tokens: in {} text + {} audio ({} cached), out {} text + {} audio{cost}",
+        t["text_in"], t["audio_in"], t["cached_in"], t["text_out"], t["audio_out"]
+    ));
+}
@@ -0,0 +1,86 @@
+// Streaming linear-interpolation resampler for mono PCM16, used to bring
+// capture devices that cannot run at the session rate to it. Going down in
+// rate (to the 8 kHz of G.711, or from a 48 kHz device), the input first
+// goes through a windowed-sinc low-pass (Blackman, stopband from the new
+// Nyquist frequency), so what the new rate cannot hold is taken out rather
+// than folded back into the band as aliasing.
+
+pub struct Resampler {
+    // input samples per output sample
+    step: f64,
+    // read position; 0 is `prev`, 1 the first sample of the next block
+    pos: f64,
+    prev: i16,
+    // anti-alias taps when going down in rate, else empty
+    taps: Vec<f32>,
+    // the last taps.len() - 1 input samples, then the block being filtered
+    hist: Vec<f32>,
+    filtered: Vec<i16>,
+}
+
+/// Low-pass taps for going from `from_hz` down to `to_hz`: passband to 0.4,
+/// stopband from 0.5 of `to_hz`.
+fn lowpass(from_hz: u32, to_hz: u32) -> Vec<f32> {
+    use std::f64::consts::PI;
+    let ratio = to_hz as f64 / from_hz as f64;
+    let cutoff = 0.45 * ratio;
+    // A Blackman window's transition band is about 5.5 / taps wide
+    let n = (5.5 / (0.1 * ratio)).ceil() as usize | 1;
+    let mid = (n - 1) as f64 / 2.0;
+    let taps: Vec<f64> = (0..n)
+        .map(|i| {
+            let t = i as f64 - mid;
+            let sinc = if t == 0.0 { 2.0 * cutoff } else { (2.0 * PI * cutoff * t).sin() / (PI * t) };
+            let x = i as f64 / (n - 1) as f64;
+            sinc * (0.42 - 0.5 * (2.0 * PI * x).cos() + 0.08 * (4.0 * PI * x).cos())
+        })
+        .collect();
+    // Unity gain at DC
+    let sum: f64 = taps.iter().sum();
+    taps.iter().map(|t| (t / sum) as f32).collect()
+}
+
+impl Resampler {
+    pub fn new(from_hz: u32, to_hz: u32) -> Self {
+        let taps = if to_hz < from_hz { lowpass(from_hz, to_hz) } else { Vec::new() };
+        let hist = vec![0.0; taps.len().saturating_sub(1)];
+        Resampler { step: from_hz as f64 / to_hz as f64, pos: 1.0, prev: 0, taps, hist, filtered: Vec::new() }
+    }
+
+    pub fn process(&mut self, input: &[i16], out: &mut Vec<i16>) {
+        if self.taps.is_empty() {
+            self.interpolate(input, out);
+            return;
+        }
+        let mut filtered = std::mem::take(&mut self.filtered);
+        self.filter(input, &mut filtered);
+        self.interpolate(&filtered, out);
+        self.filtered = filtered;
+    }
+
+    // Low-pass `input` into `out`, (taps - 1) / 2 samples late
+    fn filter(&mut self, input: &[i16], out: &mut Vec<i16>) {
+        out.clear();
+        self.hist.extend(input.iter().map(|&s| s as f32));
+        for w in self.hist.windows(self.taps.len()) {
+            let y: f32 = w.iter().zip(&self.taps).map(|(x, t)| x * t).sum();
+            out.push(y.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+        }
+        self.hist.drain(..input.len());
+    }
+
+    fn interpolate(&mut self, input: &[i16], out: &mut Vec<i16>) {
+        let Some(&last) = input.last() else { return };
+        let len = input.len() as f64;
+        while self.pos < len {
+            let i = self.pos as usize;
+            let frac = self.pos - i as f64;
+            let a = if i == 0 { self.prev } else { input[i - 1] } as f64;
+            let b = input[i] as f64;
+            out.push((a + (b - a) * frac).round() as i16);
+            self.pos += self.step;
+        }
+        self.pos -= len;
+        self.prev = last;
+    }
+}
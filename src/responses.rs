@@ -0,0 +1,229 @@
+// Reply lifecycle, which the turn-taking runs on: which reply is asked
+// for, streaming, cut off or done. Every `response.create` parlar sends is
+// asked for here (`ask`) and carries the event id it returns, and a new
+// reply is only asked for when none is under way, so the server never has
+// two at once. Requests are answered in order by `response.created`; one
+// cut off before that is marked cancelled when it arrives, so its late
+// audio is not played and its `response.done` does not end the reply that
+// replaced it. A request the server refuses (an `error` naming its event id)
+// ends the wait for it instead of leaving parlar thinking forever.
+//
+// Nothing here depends on the rest of parlar, so tests/turn_taking.rs can
+// drive it through random orders of server events.
+
+use std::collections::{HashMap, VecDeque};
+
+const EVENT_ID_PREFIX: &str = "parlar_create_";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Created,
+    Cancelled,
+    Done,
+}
+
+// One server response and the output items it produced
+struct Entry {
+    phase: Phase,
+    items: Vec<String>,
+}
+
+/// One conversation item and the response (if any) that produced it.
+pub struct Item {
+    pub role: String,
+    pub response_id: Option<String>,
+    /// Finalized input transcription (user audio items).
+    pub transcript: Option<String>,
+}
+
+// A response.create sent, not yet answered by response.created
+struct Request {
+    event_id: String,
+    cancelled: bool,
+}
+
+/// What cutting off the reply takes back.
+pub struct Cancel {
+    /// The response cut off, if the server has announced it.
+    pub response_id: Option<String>,
+    /// Its last assistant item, to truncate.
+    pub truncate: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Responses {
+    /// Reply audio is streaming in.
+    pub active: bool,
+    /// A reply is asked for or under way and its `response.done` has not come.
+    pub inflight: bool,
+    /// A committed turn waits for the reply under way to finish (TURN_QUEUE).
+    pub queued: bool,
+    pub items: HashMap<String, Item>,
+    // the response streaming now; in-flight entities are keyed by id, so
+    // late events for a cancelled response never touch the one that replaced it
+    current: Option<String>,
+    entries: HashMap<String, Entry>,
+    requests: VecDeque<Request>,
+    sent: u64,
+}
+
+impl Responses {
+    /// A reply is under way or asked for; no other may be asked for now.
+    pub fn busy(&self) -> bool {
+        self.active || self.inflight
+    }
+
+    /// A reply is about to be asked for; returns the event id its
+    /// `response.create` carries.
+    pub fn ask(&mut self) -> String {
+        self.sent += 1;
+        let event_id = format!("{EVENT_ID_PREFIX}{}", self.sent);
+        self.requests.push_back(Request { event_id: event_id.clone(), cancelled: false });
+        self.inflight = true;
+        event_id
+    }
+
+    /// Claim the next reply if none is under way; returns the event id its
+    /// `response.create` carries. Checking and claiming happen under the one
+    /// lock the caller holds, so two callers cannot both get through.
+    pub fn try_ask(&mut self) -> Option<String> {
+        (!self.busy()).then(|| self.ask())
+    }
+
+    /// A reply claimed with `try_ask` is not asked for after all.
+    pub fn withdraw(&mut self, event_id: &str) {
+        self.refused(event_id);
+    }
+
+    /// Whether a queued turn gets its reply now: it is asked for once
+    /// nothing else is under way.
+    pub fn take_queued(&mut self) -> bool {
+        if !self.queued || self.busy() {
+            return false;
+        }
+        self.queued = false;
+        true
+    }
+
+    /// `response.created`: the oldest request is answered.
+    pub fn created(&mut self, id: &str) {
+        let cancelled = self.requests.pop_front().is_some_and(|r| r.cancelled);
+        let phase = if cancelled { Phase::Cancelled } else { Phase::Created };
+        self.entries.insert(id.to_string(), Entry { phase, items: Vec::new() });
+        if !cancelled {
+            self.current = Some(id.to_string());
+            self.inflight = true;
+        }
+    }
+
+    pub fn item_added(&mut self, id: &str, role: &str, response_id: Option<&str>) {
+        if id.is_empty() {
+            return;
+        }
+        if let Some(rid) = response_id
+            && let Some(r) = self.entries.get_mut(rid)
+            && !r.items.iter().any(|i| i == id)
+        {
+            r.items.push(id.to_string());
+        }
+        let entry = self.items.entry(id.to_string()).or_insert(Item {
+            role: role.to_string(),
+            response_id: None,
+            transcript: None,
+        });
+        if entry.response_id.is_none() {
+            entry.response_id = response_id.map(str::to_string);
+        }
+    }
+
+    /// Events of response `id` are left over from a reply that was cut off.
+    pub fn is_cancelled(&self, id: Option<&str>) -> bool {
+        id.and_then(|id| self.entries.get(id)).is_some_and(|r| r.phase == Phase::Cancelled)
+    }
+
+    /// Reply audio for response `id`; false if it was cut off.
+    pub fn audio(&mut self, id: Option<&str>) -> bool {
+        if self.is_cancelled(id) {
+            return false;
+        }
+        self.active = true;
+        true
+    }
+
+    /// `response.audio.done`; false if the reply was cut off. The reply is
+    /// under way until its `response.done`.
+    pub fn audio_done(&mut self, id: Option<&str>) -> bool {
+        if self.is_cancelled(id) {
+            return false;
+        }
+        self.active = false;
+        true
+    }
+
+    /// Cut off the reply: the one streaming, and any asked for but not yet
+    /// announced.
+    pub fn cancel(&mut self) -> Cancel {
+        self.active = false;
+        self.inflight = false;
+        for r in &mut self.requests {
+            r.cancelled = true;
+        }
+        let Some(id) = self.current.take() else {
+            return Cancel { response_id: None, truncate: None };
+        };
+        let truncate = self.entries.get_mut(&id).and_then(|r| {
+            r.phase = Phase::Cancelled;
+            r.items.iter().rev().find(|i| self.items.get(*i).is_some_and(|it| it.role == "assistant")).cloned()
+        });
+        Cancel { response_id: Some(id), truncate }
+    }
+
+    /// `response.done` for `id`; returns whether it ended the reply under
+    /// way, rather than one cut off earlier.
+    pub fn done(&mut self, id: Option<&str>) -> bool {
+        let prev = id.and_then(|id| self.entries.get_mut(id)).map(|r| std::mem::replace(&mut r.phase, Phase::Done));
+        if prev == Some(Phase::Cancelled) {
+            self.forget_finished();
+            return false;
+        }
+        if id.is_none() || self.current.as_deref() == id {
+            self.current = None;
+        }
+        self.active = false;
+        self.inflight = self.requests.iter().any(|r| !r.cancelled);
+        self.forget_finished();
+        true
+    }
+
+    /// An `error` for client event `event_id`; a refused request is not
+    /// waited for any longer.
+    pub fn refused(&mut self, event_id: &str) {
+        let Some(at) = self.requests.iter().position(|r| r.event_id == event_id) else { return };
+        self.requests.remove(at);
+        if self.current.is_none() {
+            self.inflight = self.requests.iter().any(|r| !r.cancelled);
+        }
+    }
+
+    /// Forget the old connection's responses and items after a reconnect;
+    /// a queued turn stays queued.
+    pub fn reset(&mut self) {
+        *self = Responses { queued: self.queued, sent: self.sent, ..Default::default() };
+    }
+
+    // Keep the maps bounded: forget finished responses and their items
+    fn forget_finished(&mut self) {
+        if self.entries.len() <= 32 {
+            return;
+        }
+        let finished: Vec<String> =
+            self.entries.iter().filter(|(_, r)| r.phase == Phase::Done).map(|(k, _)| k.clone()).collect();
+        for k in finished {
+            if let Some(r) = self.entries.remove(&k) {
+                for item in r.items {
+                    self.items.remove(&item);
+                }
+            }
+        }
+    }
+}
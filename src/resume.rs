@@ -0,0 +1,71 @@
+// Interrupt undo. When a reply is cut off, the audio not yet played (and any
+// that still arrives for it) is kept for RESUME_WINDOW_MS (default 8000, 0
+// turns it off); `U` or the `resume` command plays it locally, for when the
+// barge-in was a cough or a door slam. The server is not told: on its side
+// the reply stays truncated where it was cut. While the resumed audio
+// plays, the mic is gated as for any reply.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+struct Kept {
+    response_id: Option<String>,
+    samples: Vec<i16>,
+    at: Instant,
+}
+
+static WINDOW: OnceLock<Duration> = OnceLock::new();
+static KEPT: Mutex<Option<Kept>> = Mutex::new(None);
+
+/// Read RESUME_WINDOW_MS.
+pub fn init() -> Result<(), String> {
+    let ms = match std::env::var("RESUME_WINDOW_MS") {
+        Ok(v) => v.trim().parse::<u64>().map_err(|_| format!("RESUME_WINDOW_MS: invalid {v}"))?,
+        Err(_) => 8000,
+    };
+    let _ = WINDOW.set(Duration::from_millis(ms));
+    Ok(())
+}
+
+fn window() -> Duration {
+    WINDOW.get().copied().unwrap_or_default()
+}
+
+/// The reply `response_id` was cancelled: take its unplayed audio out of the
+/// speaker queue `q`.
+pub fn keep(response_id: Option<String>, q: &mut VecDeque<i16>) {
+    if window().is_zero() {
+        q.clear();
+        return;
+    }
+    let samples = q.drain(..).collect();
+    *KEPT.lock().unwrap() = Some(Kept { response_id, samples, at: Instant::now() });
+}
+
+/// Audio for a cancelled reply that arrived after the cancel.
+pub fn late(response_id: &str, samples: &[i16]) {
+    let mut kept = KEPT.lock().unwrap();
+    if let Some(k) = kept.as_mut()
+        && k.response_id.as_deref() == Some(response_id)
+        && k.at.elapsed() < window()
+    {
+        k.samples.extend_from_slice(samples);
+    }
+}
+
+/// A new reply is playing; the old one can no longer be resumed.
+pub fn forget() {
+    KEPT.lock().unwrap().take();
+}
+
+/// The kept audio, if the window is still open.
+pub fn take() -> Result<Vec<i16>, &'static str> {
+    match KEPT.lock().unwrap().take() {
+        _ if window().is_zero() => Err("off (RESUME_WINDOW_MS=0)."),
+        Some(k) if k.samples.is_empty() => Err("nothing to resume."),
+        Some(k) if k.at.elapsed() < window() => Ok(k.samples),
+        Some(_) => Err("too late, the interrupted reply was dropped."),
+        None => Err("nothing to resume."),
+    }
+}
@@ -0,0 +1,155 @@
+// Failed input transcriptions. When the server cannot transcribe a turn it
+// sends `conversation.item.input_audio_transcription.failed`; the model
+// still hears the turn, but its text is missing from the screen, the hooks
+// and the logs. parlar says so, the dataset manifest records the error, and
+// with TRANSCRIPTION_RETRY=true the turn's audio goes to the audio
+// transcriptions endpoint (TRANSCRIPTION_RETRY_MODEL, default whisper-1).
+// A transcript that comes back is handled as if the server had sent it,
+// marked `"retried": true`.
+//
+// The turn's audio is cut from the forwarded mic audio kept in turnaudio.rs,
+// by the server's speech start and end, as for speaker identification.
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{net, output, turnaudio, wav};
+
+// Forwarded mic audio kept for cutting out turns
+const HISTORY_SECS: u64 = 60;
+// Turns whose transcript is still due, kept for a retry
+const PENDING_TURNS: usize = 8;
+const TIMEOUT: Duration = Duration::from_secs(30);
+const BOUNDARY: &str = "parlar-retranscribe-boundary";
+
+struct Config {
+    sr_hz: u32,
+    model: String,
+    client: reqwest::Client,
+    api_key: String,
+    events: UnboundedSender<Value>,
+}
+
+static CONFIG: OnceLock<Option<Config>> = OnceLock::new();
+// Audio of the latest turns, by item
+static TURNS: Mutex<VecDeque<(String, Vec<i16>)>> = Mutex::new(VecDeque::new());
+
+fn config() -> Option<&'static Config> {
+    CONFIG.get().and_then(Option::as_ref)
+}
+
+/// Read TRANSCRIPTION_RETRY and TRANSCRIPTION_RETRY_MODEL; retried
+/// transcripts are sent to `events`, to be handled like server events.
+pub fn init(sr_hz: u32, api_key: &str, events: UnboundedSender<Value>) -> Result<(), String> {
+    if !crate::env_flag("TRANSCRIPTION_RETRY", false) {
+        let _ = CONFIG.set(None);
+        return Ok(());
+    }
+    let model = std::env::var("TRANSCRIPTION_RETRY_MODEL")
+        .ok()
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| "whisper-1".into());
+    let builder = reqwest::Client::builder().timeout(TIMEOUT);
+    let client =
+        net::http_client(builder, "api.openai.com").map_err(|e| format!("TRANSCRIPTION_RETRY: {e}"))?;
+    turnaudio::keep(sr_hz, HISTORY_SECS);
+    let _ = CONFIG.set(Some(Config { sr_hz, model, client, api_key: api_key.to_string(), events }));
+    Ok(())
+}
+
+/// Follow a server event: keep each turn's audio until its transcript is in.
+pub fn on_event(evt: &Value) {
+    if config().is_none() {
+        return;
+    }
+    let mut turns = TURNS.lock().unwrap();
+    match evt["type"].as_str().unwrap_or("") {
+        "input_audio_buffer.speech_stopped" => {
+            let Some(item_id) = evt["item_id"].as_str() else { return };
+            if turns.len() == PENDING_TURNS {
+                turns.pop_front();
+            }
+            turns.push_back((item_id.to_string(), turnaudio::turn(evt)));
+        }
+        "conversation.item.input_audio_transcription.completed" => {
+            let item_id = evt["item_id"].as_str().unwrap_or("");
+            turns.retain(|(id, _)| id != item_id);
+        }
+        _ => {}
+    }
+}
+
+/// The server could not transcribe a turn: say so, and retry it when
+/// TRANSCRIPTION_RETRY is on. `language` is the transcription language.
+pub fn failed(evt: &Value, language: Option<&'static str>) {
+    let why = evt["error"]["message"].as_str().unwrap_or("no reason given");
+    let item_id = evt["item_id"].as_str().unwrap_or("").to_string();
+    let audio = {
+        let mut turns = TURNS.lock().unwrap();
+        let i = turns.iter().position(|(id, _)| *id == item_id);
+        i.and_then(|i| turns.remove(i)).map(|(_, audio)| audio)
+    };
+    let (Some(cfg), Some(audio)) = (config(), audio.filter(|a| !a.is_empty())) else {
+        output::notice("transcription", &format!("a turn could not be transcribed ({why})."));
+        return;
+    };
+    output::notice("transcription", &format!("a turn could not be transcribed ({why}); retrying."));
+    let content_index = evt["content_index"].clone();
+    tokio::spawn(async move {
+        match transcribe(cfg, &audio, language).await {
+            Ok(text) if !text.trim().is_empty() => {
+                let _ = cfg.events.send(json!({
+                    "type": "conversation.item.input_audio_transcription.completed",
+                    "item_id": item_id,
+                    "content_index": content_index,
+                    "transcript": text.trim(),
+                    "retried": true,
+                }));
+            }
+            Ok(_) => output::notice("transcription", "the retry heard nothing."),
+            Err(e) => output::notice("transcription", &format!("retry failed: {e}")),
+        }
+    });
+}
+
+/// POST the turn as a WAV file to /v1/audio/transcriptions.
+async fn transcribe(cfg: &Config, audio: &[i16], language: Option<&str>) -> Result<String, String> {
+    let mut body = Vec::new();
+    let mut field = |name: &str, value: &str| {
+        body.extend_from_slice(
+            format!("--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n").as_bytes(),
+        );
+    };
+    field("model", &cfg.model);
+    field("response_format", "json");
+    if let Some(code) = language {
+        field("language", code);
+    }
+    body.extend_from_slice(
+        format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"turn.wav\"\r\n\
+             Content-Type: audio/wav\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend(wav::pcm16_mono(cfg.sr_hz, audio));
+    body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+    let resp: Value = cfg
+        .client
+        .post("https://api.openai.com/v1/audio/transcriptions")
+        .bearer_auth(&cfg.api_key)
+        .header("Content-Type", format!("multipart/form-data; boundary={BOUNDARY}"))
+        .body(body)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    resp["text"].as_str().map(str::to_string).ok_or_else(|| "unexpected response".into())
+}
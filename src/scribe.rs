@@ -0,0 +1,164 @@
+// Scribe: independent transcription of everything said, written to
+// SCRIBE_TRANSCRIPT. It runs its own connections, one per speaker (the mic,
+// and the assistant's audio as it arrives), with server VAD and no
+// responses, so the transcript stays complete when the conversation
+// truncates, cancels or reconnects.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tungstenite::Message;
+
+use crate::crypt::Appender;
+use crate::{net, output, pcm, realtime_request, redact};
+
+#[derive(Clone, Copy)]
+enum Speaker {
+    User,
+    Assistant,
+}
+
+impl Speaker {
+    fn label(self) -> &'static str {
+        match self {
+            Speaker::User => "You",
+            Speaker::Assistant => "Assistant",
+        }
+    }
+}
+
+/// Feeds audio to the scribe connections; cheap to call from any thread.
+#[derive(Clone)]
+pub struct Scribe {
+    user: UnboundedSender<Vec<u8>>,
+    assistant: UnboundedSender<Vec<u8>>,
+    sr_hz: u32,
+}
+
+pub struct Config {
+    pub path: String,
+    pub model: String,
+    pub api_key: String,
+    pub language: Option<&'static str>,
+    pub sr_hz: u32,
+}
+
+impl Scribe {
+    /// Open the transcript file and start one connection per speaker.
+    pub fn start(cfg: Config) -> Result<Self, String> {
+        let file = Arc::new(Mutex::new(Appender::open(&cfg.path)?));
+        let sr_hz = cfg.sr_hz;
+        let cfg = Arc::new(cfg);
+        let (user, user_rx) = unbounded_channel();
+        let (assistant, assistant_rx) = unbounded_channel();
+        tokio::spawn(run(Speaker::User, user_rx, cfg.clone(), file.clone()));
+        tokio::spawn(run(Speaker::Assistant, assistant_rx, cfg, file));
+        Ok(Scribe { user, assistant, sr_hz })
+    }
+
+    pub fn user(&self, samples: &[i16]) {
+        let _ = self.user.send(bytes(samples));
+    }
+
+    pub fn assistant(&self, samples: &[i16]) {
+        let _ = self.assistant.send(bytes(samples));
+    }
+
+    /// End of a reply: the assistant feed has no pauses of its own, so add
+    /// a second of silence for the VAD to close the turn on.
+    pub fn assistant_done(&self) {
+        let _ = self.assistant.send(vec![0; self.sr_hz as usize * 2]);
+    }
+}
+
+fn bytes(samples: &[i16]) -> Vec<u8> {
+    let mut out = Vec::new();
+    pcm::le_bytes(samples, &mut out);
+    out
+}
+
+fn write(file: &Mutex<Appender>, speaker: Speaker, text: &str) {
+    let line = format!("[{}] {}: {}\n", chrono::Local::now().format("%H:%M:%S"), speaker.label(), text);
+    let mut file = file.lock().unwrap();
+    if let Err(e) = file.append(redact::apply(&line).as_bytes()).and_then(|_| file.sync()) {
+        output::notice("scribe", &format!("write failed: {e}"));
+    }
+}
+
+/// One speaker's connection, reopened when the server closes it (session
+/// lifetime) for as long as audio keeps coming.
+async fn run(speaker: Speaker, mut audio: UnboundedReceiver<Vec<u8>>, cfg: Arc<Config>, file: Arc<Mutex<Appender>>) {
+    loop {
+        match session(speaker, &mut audio, &cfg, &file).await {
+            Ok(()) => return,
+            Err(e) => {
+                output::notice("scribe", &format!("{} connection: {e}; reconnecting.", speaker.label()));
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                // Audio from the gap would be transcribed late and out of order
+                while audio.try_recv().is_ok() {}
+            }
+        }
+    }
+}
+
+/// Stream audio until the feed ends (`Ok`) or the connection fails.
+async fn session(
+    speaker: Speaker,
+    audio: &mut UnboundedReceiver<Vec<u8>>,
+    cfg: &Config,
+    file: &Mutex<Appender>,
+) -> Result<(), String> {
+    let (ws, _) = net::connect(realtime_request(&cfg.model, &cfg.api_key)).await.map_err(|e| e.to_string())?;
+    let (mut tx, mut rx) = ws.split();
+    let transcription = match cfg.language {
+        Some(code) => json!({ "model": "whisper-1", "language": code }),
+        None => json!({ "model": "whisper-1" }),
+    };
+    let update = json!({
+        "type": "session.update",
+        "session": {
+            "modalities": ["text"],
+            "input_audio_format": "pcm16",
+            "input_audio_transcription": transcription,
+            "turn_detection": {
+                "type": "server_vad",
+                "create_response": false,
+                "interrupt_response": false
+            }
+        }
+    });
+    tx.send(Message::Text(update.to_string())).await.map_err(|e| e.to_string())?;
+    loop {
+        tokio::select! {
+            chunk = audio.recv() => {
+                let Some(chunk) = chunk else { return Ok(()) };
+                tx.send(Message::Text(pcm::append_event(&chunk))).await.map_err(|e| e.to_string())?;
+            }
+            msg = rx.next() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(t))) => t,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.to_string()),
+                    None => return Err("closed by the server".into()),
+                };
+                let Ok(evt) = serde_json::from_str::<serde_json::Value>(&text) else { continue };
+                match evt["type"].as_str().unwrap_or("") {
+                    "conversation.item.input_audio_transcription.completed" => {
+                        let tr = evt["transcript"].as_str().unwrap_or("").trim();
+                        if !tr.is_empty() {
+                            write(file, speaker, tr);
+                        }
+                    }
+                    "error" => {
+                        let msg = evt["error"]["message"].as_str().unwrap_or("");
+                        output::notice("scribe", &format!("{}: {msg}", speaker.label()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
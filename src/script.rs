@@ -0,0 +1,212 @@
+// Rhai scripting hooks: a lighter way to customize parlar than WASI plugins
+// or external commands. SCRIPT_FILE names a .rhai script; its top level runs
+// once at startup, and these functions, when it defines them, are called on
+// conversation events, one at a time and in order:
+//
+//   fn on_user_transcript(text) { … }           each finalized user turn
+//   fn on_assistant_done(text, status) { … }    each finished reply (status
+//                                               completed, cancelled, …)
+//   fn on_interrupt(by) { … }                   user, speech or keyword
+//
+// Besides the Rhai language a script can call:
+//   send_event(map | json_string)   send a realtime client event
+//   say(text)                       send text as a user message and ask for a reply
+//   set_instructions(text)          replace the base instructions
+//   instructions()                  the current base instructions
+//   run(command [, stdin])          run a shell command, returns its stdout
+//                                   (a non-zero exit is an error)
+// print() output appears as `[script]` notices, as do errors; a hook running
+// longer than SCRIPT_TIMEOUT_SECS (default 5) is stopped. Needs the
+// `scripting` feature.
+
+use crate::control::Controls;
+
+#[cfg(feature = "scripting")]
+use std::sync::OnceLock;
+#[cfg(feature = "scripting")]
+use std::sync::mpsc::{Sender, channel};
+
+#[cfg(feature = "scripting")]
+enum Event {
+    UserTranscript(String),
+    AssistantDone(String, String),
+    Interrupt(String),
+}
+
+#[cfg(feature = "scripting")]
+static EVENTS: OnceLock<Sender<Event>> = OnceLock::new();
+
+/// Load SCRIPT_FILE and start running its hooks. Returns the script's path
+/// when one is configured.
+#[cfg(feature = "scripting")]
+pub fn start(controls: &Controls) -> Result<Option<String>, String> {
+    let Some(path) = std::env::var("SCRIPT_FILE").ok().filter(|p| !p.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let timeout = match std::env::var("SCRIPT_TIMEOUT_SECS") {
+        Ok(v) => v.trim().parse().map_err(|_| format!("SCRIPT_TIMEOUT_SECS: not a number: {v}"))?,
+        Err(_) => 5,
+    };
+    let mut host = host::Host::new(&path, controls.clone(), std::time::Duration::from_secs(timeout))?;
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        host.run_top_level();
+        for event in rx {
+            host.dispatch(event);
+        }
+    });
+    let _ = EVENTS.set(tx);
+    Ok(Some(path))
+}
+
+#[cfg(not(feature = "scripting"))]
+pub fn start(_controls: &Controls) -> Result<Option<String>, String> {
+    match std::env::var("SCRIPT_FILE") {
+        Ok(p) if !p.trim().is_empty() => Err("SCRIPT_FILE needs a build with the `scripting` feature".into()),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn queue(event: Event) {
+    if let Some(tx) = EVENTS.get() {
+        let _ = tx.send(event);
+    }
+}
+
+pub fn user_transcript(text: &str) {
+    #[cfg(feature = "scripting")]
+    queue(Event::UserTranscript(text.to_string()));
+    #[cfg(not(feature = "scripting"))]
+    let _ = text;
+}
+
+pub fn assistant_done(text: &str, status: &str) {
+    #[cfg(feature = "scripting")]
+    queue(Event::AssistantDone(text.to_string(), status.to_string()));
+    #[cfg(not(feature = "scripting"))]
+    let _ = (text, status);
+}
+
+pub fn interrupted(by: &str) {
+    #[cfg(feature = "scripting")]
+    queue(Event::Interrupt(by.to_string()));
+    #[cfg(not(feature = "scripting"))]
+    let _ = by;
+}
+
+#[cfg(feature = "scripting")]
+mod host {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use rhai::{AST, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope};
+    use tungstenite::Message;
+
+    use super::Event;
+    use crate::control::Controls;
+    use crate::output;
+
+    type Fallible<T> = Result<T, Box<EvalAltResult>>;
+
+    pub struct Host {
+        engine: Engine,
+        ast: AST,
+        scope: Scope<'static>,
+        // When the hook now running must stop
+        deadline: Arc<Mutex<Instant>>,
+        timeout: Duration,
+    }
+
+    impl Host {
+        pub fn new(path: &str, controls: Controls, timeout: Duration) -> Result<Self, String> {
+            let deadline = Arc::new(Mutex::new(Instant::now()));
+            let mut engine = Engine::new();
+            engine.on_print(|s| output::notice("script", s));
+            engine.on_debug(|s, _, pos| output::notice("script", &format!("{pos}: {s}")));
+            let limit = deadline.clone();
+            engine.on_progress(move |_| (Instant::now() > *limit.lock().unwrap()).then(|| "timed out".into()));
+
+            let c = controls.clone();
+            engine.register_fn("send_event", move |event: Map| -> Fallible<()> {
+                let event: serde_json::Value = rhai::serde::from_dynamic(&Dynamic::from_map(event))?;
+                send(&c, event.to_string())
+            });
+            let c = controls.clone();
+            engine.register_fn("send_event", move |event: &str| -> Fallible<()> {
+                let event: serde_json::Value =
+                    serde_json::from_str(event).map_err(|e| format!("send_event: bad JSON: {e}"))?;
+                send(&c, event.to_string())
+            });
+            let c = controls.clone();
+            engine.register_fn("say", move |text: &str| c.say_text(text));
+            let c = controls.clone();
+            engine.register_fn("set_instructions", move |text: &str| c.set_instructions(text));
+            let c = controls;
+            engine.register_fn("instructions", move || c.state.lock().unwrap().instructions.clone());
+            engine.register_fn("run", |cmd: &str| run(cmd, ""));
+            engine.register_fn("run", |cmd: &str, stdin: &str| run(cmd, stdin));
+
+            let ast = engine.compile_file(path.into()).map_err(|e| format!("SCRIPT_FILE {path}: {e}"))?;
+            Ok(Host { engine, ast, scope: Scope::new(), deadline, timeout })
+        }
+
+        fn arm(&self) {
+            *self.deadline.lock().unwrap() = Instant::now() + self.timeout;
+        }
+
+        /// Run the script's top-level statements (its setup).
+        pub fn run_top_level(&mut self) {
+            self.arm();
+            if let Err(e) = self.engine.run_ast_with_scope(&mut self.scope, &self.ast) {
+                output::notice("script", &e.to_string());
+            }
+        }
+
+        pub fn dispatch(&mut self, event: Event) {
+            match event {
+                Event::UserTranscript(text) => self.call("on_user_transcript", (text,)),
+                Event::AssistantDone(text, status) => self.call("on_assistant_done", (text, status)),
+                Event::Interrupt(by) => self.call("on_interrupt", (by,)),
+            }
+        }
+
+        /// Call hook `name` if the script defines it.
+        fn call(&mut self, name: &str, args: impl rhai::FuncArgs) {
+            if !self.ast.iter_functions().any(|f| f.name == name) {
+                return;
+            }
+            self.arm();
+            let options = CallFnOptions::new().eval_ast(false);
+            let called = self.engine.call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, name, args);
+            if let Err(e) = called {
+                output::notice("script", &format!("{name}: {e}"));
+            }
+        }
+    }
+
+    fn send(controls: &Controls, event: String) -> Fallible<()> {
+        controls.out_tx.send(Message::Text(event)).map_err(|_| "send_event: not connected".into())
+    }
+
+    fn run(cmd: &str, stdin: &str) -> Fallible<String> {
+        let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+        let mut child = Command::new(shell)
+            .arg(flag)
+            .arg(cmd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("run: {e}"))?;
+        if let Some(mut pipe) = child.stdin.take() {
+            let _ = pipe.write_all(stdin.as_bytes());
+        }
+        let out = child.wait_with_output().map_err(|e| format!("run: {e}"))?;
+        if !out.status.success() {
+            return Err(format!("run: {cmd}: exit status {}", out.status).into());
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).trim_end().to_string())
+    }
+}
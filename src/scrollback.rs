@@ -0,0 +1,311 @@
+// Scrollback of the conversation. Every finished user turn and assistant
+// reply is kept, with its time, for the whole session (up to
+// SCROLLBACK_ENTRIES). H in the terminal UI opens it full screen over the
+// session; `/` opens it with the search prompt. Output that arrives
+// meanwhile is held and written when the view is closed. In text mode
+// `/search words` prints the matching entries. The palette's `export`
+// writes it to a file.
+//
+// TRANSCRIPT_FILE, if set, gets every entry appended as it is recorded, in
+// the same form and synced to disk, so the transcript survives a crash; it
+// is redacted and encrypted like the other transcripts.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::crypt::Appender;
+use crate::{i18n, output, redact};
+
+const SCROLLBACK_ENTRIES: usize = 5000;
+
+struct Entry {
+    time: String,
+    who: String,
+    text: String,
+}
+
+static ENTRIES: Mutex<VecDeque<Entry>> = Mutex::new(VecDeque::new());
+// Entries recorded so far, including those dropped from the front
+static RECORDED: AtomicUsize = AtomicUsize::new(0);
+static TRANSCRIPT: OnceLock<Option<(String, Mutex<Appender>)>> = OnceLock::new();
+
+/// Open TRANSCRIPT_FILE; call once at startup, after `crypt::init`. Returns
+/// its path when set.
+pub fn init() -> Result<Option<String>, String> {
+    let path = std::env::var("TRANSCRIPT_FILE").ok().filter(|p| !p.trim().is_empty());
+    let file = match &path {
+        Some(path) => Some((path.clone(), Mutex::new(Appender::open(path)?))),
+        None => None,
+    };
+    let _ = TRANSCRIPT.set(file);
+    Ok(path)
+}
+
+fn line(e: &Entry) -> String {
+    format!("[{}] {}: {}\n", e.time, e.who, e.text)
+}
+
+/// Keep a finished turn or reply, e.g. `record("User", "hello")`.
+pub fn record(who: &str, text: &str) {
+    if text.trim().is_empty() {
+        return;
+    }
+    let mut entries = ENTRIES.lock().unwrap();
+    if entries.len() == SCROLLBACK_ENTRIES {
+        entries.pop_front();
+    }
+    let entry = Entry {
+        time: chrono::Local::now().format("%H:%M:%S").to_string(),
+        who: who.to_string(),
+        text: text.trim().to_string(),
+    };
+    if let Some(Some((path, file))) = TRANSCRIPT.get() {
+        let mut file = file.lock().unwrap();
+        let text = format!("{}\n", redact::apply(&line(&entry)));
+        if let Err(e) = file.append(text.as_bytes()).and_then(|_| file.sync()) {
+            output::notice("transcript", &format!("{path}: {e}"));
+        }
+    }
+    entries.push_back(entry);
+    RECORDED.fetch_add(1, Ordering::Relaxed);
+}
+
+fn matches(text: &str, query: &str) -> bool {
+    text.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// `/search`: print the entries that contain `query`, oldest first.
+pub fn search(query: &str) {
+    let query = query.trim();
+    if query.is_empty() {
+        output::notice("search", i18n::tr("usage: /search words"));
+        return;
+    }
+    let entries = ENTRIES.lock().unwrap();
+    let found: Vec<String> = entries
+        .iter()
+        .filter(|e| matches(&e.text, query))
+        .map(|e| format!("[{}] {}: {}", e.time, e.who, e.text))
+        .collect();
+    if found.is_empty() {
+        output::notice("search", &i18n::trf("nothing matches \"{query}\".", &[("query", &query)]));
+    } else {
+        output::info(&format!("\n{}", found.join("\n")));
+    }
+}
+
+/// Write the conversation so far to `path`, encrypted with
+/// TRANSCRIPT_PASSPHRASE; returns the file written and its entries.
+pub fn export(path: &Path) -> Result<(PathBuf, usize), String> {
+    let entries = ENTRIES.lock().unwrap();
+    let mut text = String::new();
+    for e in entries.iter() {
+        let _ = writeln!(text, "{}", line(e));
+    }
+    let written = crate::crypt::write_file(path, text.as_bytes()).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok((written, entries.len()))
+}
+
+#[cfg(feature = "tui")]
+pub use view::open;
+
+#[cfg(feature = "tui")]
+mod view {
+    use std::io::Write;
+    use std::time::Duration;
+
+    use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+    use crossterm::{cursor, execute, queue, terminal};
+
+    use std::sync::atomic::Ordering;
+
+    use super::{ENTRIES, RECORDED, matches};
+    use crate::output;
+
+    const HELP: &str = "↑↓ PgUp PgDn Home End scroll · / search · n older · N newer · q back";
+
+    struct View {
+        // the entries as display lines, cut to the width
+        lines: Vec<String>,
+        // RECORDED when laid out
+        entries: usize,
+        // first line shown; None follows the end
+        top: Option<usize>,
+        query: String,
+        // the search being typed
+        typing: Option<String>,
+        status: Option<String>,
+    }
+
+    impl View {
+        fn layout(&mut self, cols: usize) {
+            let entries = ENTRIES.lock().unwrap();
+            self.entries = RECORDED.load(Ordering::Relaxed);
+            self.lines.clear();
+            for e in entries.iter() {
+                let text = format!("[{}] {}: {}", e.time, e.who, e.text);
+                for para in text.split('\n') {
+                    let chars: Vec<char> = para.chars().collect();
+                    if chars.is_empty() {
+                        self.lines.push(String::new());
+                    }
+                    for chunk in chars.chunks(cols.max(1)) {
+                        self.lines.push(chunk.iter().collect());
+                    }
+                }
+                self.lines.push(String::new());
+            }
+        }
+
+        fn top(&self, rows: usize) -> usize {
+            let last = self.lines.len().saturating_sub(rows);
+            self.top.unwrap_or(last).min(last)
+        }
+
+        fn scroll(&mut self, by: isize, rows: usize) {
+            let last = self.lines.len().saturating_sub(rows);
+            let top = self.top(rows).saturating_add_signed(by).min(last);
+            self.top = (top < last).then_some(top);
+        }
+
+        // Move to the next match above (`older`) or below line `from`.
+        fn find(&mut self, from: usize, older: bool, rows: usize) {
+            if self.query.is_empty() {
+                return;
+            }
+            let hit = if older {
+                (0..from.min(self.lines.len())).rev().find(|&i| matches(&self.lines[i], &self.query))
+            } else {
+                (from + 1..self.lines.len()).find(|&i| matches(&self.lines[i], &self.query))
+            };
+            match hit {
+                Some(i) => {
+                    self.top = Some(i);
+                    self.scroll(0, rows);
+                    self.status = None;
+                }
+                None => {
+                    let dir = if older { "above" } else { "below" };
+                    self.status = Some(format!("no more matches for \"{}\" {dir}", self.query));
+                }
+            }
+        }
+
+        fn draw(&self, out: &mut impl Write, cols: usize, rows: usize) {
+            let _ = queue!(out, terminal::Clear(terminal::ClearType::All));
+            let top = self.top(rows);
+            for (row, line) in self.lines.iter().skip(top).take(rows).enumerate() {
+                let _ = queue!(out, cursor::MoveTo(0, row as u16));
+                let _ = write!(out, "{}", highlight(line, &self.query));
+            }
+            let status = match (&self.typing, &self.status) {
+                (Some(q), _) => format!("/{q}"),
+                (None, Some(s)) => s.clone(),
+                (None, None) => {
+                    let end = (top + rows).min(self.lines.len());
+                    format!("Scrollback {}–{} of {} lines · {HELP}", top + 1, end, self.lines.len())
+                }
+            };
+            let status: String = status.chars().take(cols).collect();
+            let _ = queue!(out, cursor::MoveTo(0, rows as u16));
+            let _ = write!(out, "\x1b[7m{status}\x1b[0m");
+            let _ = out.flush();
+        }
+    }
+
+    // Matches of `query` in reverse video; lines whose lowercase form moves
+    // byte offsets are left plain
+    fn highlight(line: &str, query: &str) -> String {
+        let lower = line.to_lowercase();
+        if query.is_empty() || lower.len() != line.len() {
+            return line.to_string();
+        }
+        let query = query.to_lowercase();
+        let mut out = String::new();
+        let mut at = 0;
+        while let Some(i) = lower[at..].find(&query) {
+            let (start, end) = (at + i, at + i + query.len());
+            out.push_str(&line[at..start]);
+            out.push_str(&format!("\x1b[7m{}\x1b[0m", &line[start..end]));
+            at = end;
+        }
+        out.push_str(&line[at..]);
+        out
+    }
+
+    /// Show the scrollback full screen until Esc or q; `search` starts with
+    /// the search prompt. Runs on the keyboard thread, in raw mode.
+    pub fn open(search: bool) {
+        output::hold();
+        let mut out = output::terminal();
+        let _ = execute!(out, terminal::EnterAlternateScreen, cursor::Hide);
+        // The timeline's scroll region would keep the last row
+        let _ = write!(out, "\x1b[r");
+        let mut view = View {
+            lines: Vec::new(),
+            entries: 0,
+            top: None,
+            query: String::new(),
+            typing: search.then(String::new),
+            status: None,
+        };
+        let mut size = (0, 0);
+        loop {
+            let (cols, rows) = terminal::size().map(|(c, r)| (c as usize, r as usize)).unwrap_or((80, 24));
+            let rows = rows.saturating_sub(1).max(1);
+            let changed = RECORDED.load(Ordering::Relaxed) != view.entries;
+            if changed || size != (cols, rows) {
+                view.layout(cols);
+                size = (cols, rows);
+            }
+            view.draw(&mut out, cols, rows);
+            // Redraw now and then, for turns that come in meanwhile
+            if !event::poll(Duration::from_millis(500)).unwrap_or(false) {
+                continue;
+            }
+            let Ok(Event::Key(k)) = event::read() else { continue };
+            if !crate::keys::pressed(&k) {
+                continue;
+            }
+            crate::typing::key_pressed();
+            if let Some(typed) = &mut view.typing {
+                match k.code {
+                    KeyCode::Char(c) => typed.push(c),
+                    KeyCode::Backspace => {
+                        typed.pop();
+                    }
+                    KeyCode::Enter => {
+                        view.query = view.typing.take().unwrap_or_default();
+                        // From the end of the screen upwards: the latest match first
+                        view.find(view.top(rows) + rows, true, rows);
+                    }
+                    _ => view.typing = None,
+                }
+                continue;
+            }
+            view.status = None;
+            let page = rows as isize;
+            match k.code {
+                KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Char('h') | KeyCode::Char('H') => {
+                    break;
+                }
+                KeyCode::Char('c') if k.modifiers.contains(KeyModifiers::CONTROL) => break,
+                KeyCode::Up | KeyCode::Char('k') => view.scroll(-1, rows),
+                KeyCode::Down | KeyCode::Char('j') => view.scroll(1, rows),
+                KeyCode::PageUp | KeyCode::Char('b') => view.scroll(-page, rows),
+                KeyCode::PageDown | KeyCode::Char(' ') => view.scroll(page, rows),
+                KeyCode::Home | KeyCode::Char('g') => view.top = Some(0),
+                KeyCode::End | KeyCode::Char('G') => view.top = None,
+                KeyCode::Char('/') => view.typing = Some(String::new()),
+                KeyCode::Char('n') => view.find(view.top(rows), true, rows),
+                KeyCode::Char('N') => view.find(view.top(rows), false, rows),
+                _ => {}
+            }
+        }
+        let _ = execute!(out, cursor::Show, terminal::LeaveAlternateScreen);
+        output::release();
+    }
+}
@@ -0,0 +1,121 @@
+// Service mode (`--service`), for running parlar as a background voice
+// daemon under systemd or launchd, e.g. on a Raspberry Pi kiosk. Nothing
+// is read from stdin and the spinner and timeline stay off; control comes
+// from MQTT, gRPC, hotkeys, voice commands and signals.
+//
+// Under systemd (`Type=notify`, `NotifyAccess=main`):
+//   READY=1     once the session is up, so dependent units start after it
+//   STATUS=     the current state, shown by `systemctl status`
+//   WATCHDOG=1  every half of `WatchdogSec=`, while the runtime is responsive
+//   STOPPING=1  on the way out
+// and when stderr goes to the journal, state changes are also logged with
+// structured fields (PARLAR_EVENT, PARLAR_STATE, PARLAR_MODEL) to filter on,
+// e.g. `journalctl -u parlar PARLAR_STATE=speaking`. Outside systemd
+// (launchd, a shell) only the usual stderr text is written.
+
+use std::time::Duration;
+
+use crate::control::Controls;
+use crate::{indicator, output, shutdown};
+
+/// Tell the service manager parlar is up, and keep it informed until exit.
+pub fn start(controls: &Controls) {
+    let model = controls.state.lock().unwrap().model.clone();
+    notify(&format!("READY=1\nSTATUS=idle, model {model}"));
+    journal("ready", "Session ready.", &[("PARLAR_MODEL", &model)]);
+    shutdown::on_exit(|| {
+        notify("STOPPING=1");
+        journal("stopping", "Shutting down.", &[]);
+    });
+
+    let state = controls.state.clone();
+    std::thread::spawn(move || {
+        let mut last = "";
+        loop {
+            let (now, model) = match state.lock() {
+                Ok(st) => (indicator::state(&st).name(), st.model.clone()),
+                Err(_) => return,
+            };
+            if now != last {
+                notify(&format!("STATUS={now}, model {model}"));
+                journal("state", &format!("State: {now}."), &[("PARLAR_STATE", now), ("PARLAR_MODEL", &model)]);
+                last = now;
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    });
+
+    if let Some(period) = watchdog_period() {
+        let state = controls.state.clone();
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(period);
+            loop {
+                tick.tick().await;
+                // A panic while holding the state leaves parlar unusable: let systemd restart it
+                if state.is_poisoned() {
+                    output::notice("service", "state lost after a panic; stopping watchdog pings.");
+                    return;
+                }
+                notify("WATCHDOG=1");
+            }
+        });
+    }
+}
+
+/// Half of `WatchdogSec=`, when systemd asks this process for pings.
+fn watchdog_period() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID")
+        && pid.parse() != Ok(std::process::id())
+    {
+        return None;
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// Send `state` (newline-separated assignments) to NOTIFY_SOCKET, if set.
+#[cfg(unix)]
+fn notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+    let Ok(path) = std::env::var("NOTIFY_SOCKET") else { return };
+    let Ok(sock) = UnixDatagram::unbound() else { return };
+    let sent = match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            std::os::unix::net::SocketAddr::from_abstract_name(name)
+                .and_then(|addr| sock.send_to_addr(state.as_bytes(), &addr))
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return,
+        None => sock.send_to(state.as_bytes(), &path),
+    };
+    if let Err(e) = sent {
+        output::notice("service", &format!("sd_notify to {path}: {e}"));
+    }
+}
+
+#[cfg(not(unix))]
+fn notify(_state: &str) {}
+
+/// One journal entry with `fields`, when stderr is connected to the journal.
+#[cfg(unix)]
+fn journal(event: &str, message: &str, fields: &[(&str, &str)]) {
+    use std::os::unix::net::UnixDatagram;
+    if std::env::var_os("JOURNAL_STREAM").is_none() {
+        return;
+    }
+    let Ok(sock) = UnixDatagram::unbound() else { return };
+    // Values with a newline would need the binary field format
+    let mut entry = format!(
+        "MESSAGE={}\nPRIORITY=6\nSYSLOG_IDENTIFIER=parlar\nPARLAR_EVENT={event}\n",
+        message.replace('\n', " ")
+    );
+    for (key, value) in fields {
+        entry.push_str(&format!("{key}={}\n", value.replace('\n', " ")));
+    }
+    let _ = sock.send_to(entry.as_bytes(), "/run/systemd/journal/socket");
+}
+
+#[cfg(not(unix))]
+fn journal(_event: &str, _message: &str, _fields: &[(&str, &str)]) {}
@@ -0,0 +1,140 @@
+// Opening the realtime session, and opening it again on a new connection
+// (to switch models mid-run, or before the server's session lifetime runs
+// out) with the conversation carried over: the new session gets the same
+// settings plus a replay of the recent turns as conversation items.
+//
+// SESSION_EXTRA is a JSON object merged into the session object of the
+// `session.update` sent on every connect, for server features parlar does
+// not know yet (a custom voice, a voice speed):
+// `SESSION_EXTRA='{"speed": 1.2, "voice": {"id": "voice_1234"}}'`. Objects
+// are merged key by key, anything else replaces what parlar would send,
+// and `null` removes the field. The server checks the result; a field it
+// does not accept comes back as a `[realtime_error]`.
+
+use std::sync::{Arc, Mutex};
+
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tungstenite::Message;
+
+use crate::error::ParlarError;
+use crate::{State, net, passthrough, realtime_request, tools, traffic, turnaudio};
+
+type Ws = WebSocketStream<MaybeTlsStream<TcpStream>>;
+pub type WsSink = SplitSink<Ws, Message>;
+pub type WsSource = SplitStream<Ws>;
+/// The sending half shared with the send task, swapped on reconnect.
+pub type SharedSink = Arc<tokio::sync::Mutex<WsSink>>;
+
+/// Settings that do not change over the run.
+pub struct Settings {
+    pub api_key: String,
+    pub noise_reduction: Option<String>,
+    /// SESSION_EXTRA
+    pub extra: Option<Value>,
+}
+
+/// Read SESSION_EXTRA; it must be a JSON object.
+pub fn extra_from_env() -> Result<Option<Value>, String> {
+    let Some(raw) = std::env::var("SESSION_EXTRA").ok().filter(|v| !v.trim().is_empty()) else {
+        return Ok(None);
+    };
+    match serde_json::from_str::<Value>(&raw) {
+        Ok(v) if v.is_object() => Ok(Some(v)),
+        Ok(_) => Err("SESSION_EXTRA: not a JSON object".into()),
+        Err(e) => Err(format!("SESSION_EXTRA: {e}")),
+    }
+}
+
+/// Merge `extra` into `base`: objects key by key, `null` removes, anything
+/// else replaces.
+fn merge(base: &mut Value, extra: &Value) {
+    match (base, extra) {
+        (Value::Object(base), Value::Object(extra)) => {
+            for (key, value) in extra {
+                if value.is_null() {
+                    base.remove(key);
+                } else {
+                    merge(base.entry(key.clone()).or_insert(Value::Null), value);
+                }
+            }
+        }
+        (base, extra) => *base = extra.clone(),
+    }
+}
+
+/// `session.update` for the settings currently in effect.
+fn update_event(st: &State, settings: &Settings) -> String {
+    let text = st.text_only || crate::tts::text_replies();
+    let modalities = if text { json!(["text"]) } else { json!(["audio", "text"]) };
+    let mut session = json!({
+        "modalities": modalities,
+        "voice": st.voice,
+        "instructions": st.session_instructions(),
+        "max_response_output_tokens": st.verbosity.max_tokens(),
+        "input_audio_format": crate::wire::input_format(),
+        "output_audio_format": crate::wire::output_format(),
+        // Let server VAD detect end-of-speech, but do NOT auto-create responses
+        "turn_detection": st.turn.to_json(),
+        // Realtime's built-in input transcription (to print "User: ...")
+        "input_audio_transcription": st.transcription_config(),
+        "input_audio_noise_reduction": settings.noise_reduction.as_ref().map(|t| json!({ "type": t })),
+        // Functions from TOOLS_FILE
+        "tools": tools::definitions(),
+        "tool_choice": "auto"
+    });
+    if let Some(extra) = &settings.extra {
+        merge(&mut session, extra);
+    }
+    json!({"type": "session.update", "session": session}).to_string()
+}
+
+/// Connect to `model`, configure the session, replay the conversation so
+/// far and send the CONNECT_EVENTS. Returns both halves of the socket and the number of turns replayed.
+pub async fn open(
+    model: &str,
+    settings: &Settings,
+    state: &Arc<Mutex<State>>,
+) -> Result<(WsSink, WsSource, usize), ParlarError> {
+    let (ws, _) = net::connect(realtime_request(model, &settings.api_key))
+        .await
+        .map_err(ParlarError::from_connect)?;
+    let (mut tx, rx) = ws.split();
+    let (update, replay) = {
+        let st = state.lock().unwrap();
+        (update_event(&st, settings), st.history.replay_events())
+    };
+    let replayed = replay.len();
+    for msg in std::iter::once(update).chain(replay).chain(passthrough::connect_events()) {
+        traffic::sent(msg.len());
+        tx.send(Message::Text(msg)).await.map_err(ParlarError::from_connect)?;
+    }
+    Ok((tx, rx, replayed))
+}
+
+/// Move the session to a new connection on `model`. The old socket keeps
+/// carrying audio until the new session is configured, then the send task
+/// switches over; a failure leaves the current session untouched. Returns
+/// the new receiving half and the number of turns replayed.
+pub async fn reconnect(
+    model: &str,
+    settings: &Settings,
+    state: &Arc<Mutex<State>>,
+    sink: &SharedSink,
+) -> Result<(WsSource, usize), ParlarError> {
+    let (tx, rx, replayed) = open(model, settings, state).await?;
+    let mut old = {
+        let mut sink = sink.lock().await;
+        // Audio from here on is counted by the new session, from 0
+        turnaudio::reset();
+        std::mem::replace(&mut *sink, tx)
+    };
+    let _ = old.close().await;
+    let mut st = state.lock().unwrap();
+    st.reset_connection();
+    st.model = model.to_string();
+    Ok((rx, replayed))
+}
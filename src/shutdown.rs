@@ -0,0 +1,81 @@
+// Clean exit. Every way out (Q, /quit, the `quit` JSON command, the voice
+// command, SIGTERM/SIGINT) goes through `exit`, which runs the registered
+// flush steps (dataset turns still in memory, the meeting notes tail, a
+// close frame for the session), puts the terminal back and prints the
+// traffic summary and the session report, then writes the bug report. SIGHUP re-reads the .env file, like a change to it does.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::control::Controls;
+use crate::{bugreport, i18n, output, report, timeline, traffic};
+
+type Step = Box<dyn FnOnce() + Send>;
+
+static STEPS: Mutex<Vec<Step>> = Mutex::new(Vec::new());
+
+/// Run `step` on the way out, after the steps registered before it.
+pub fn on_exit(step: impl FnOnce() + Send + 'static) {
+    STEPS.lock().unwrap().push(Box::new(step));
+}
+
+/// Flush, restore the terminal and exit with `code`.
+pub fn exit(code: i32) -> ! {
+    // Taken out first, so a second signal during the flush exits at once
+    let steps = std::mem::take(&mut *STEPS.lock().unwrap());
+    for step in steps {
+        step();
+    }
+    #[cfg(feature = "tui")]
+    {
+        let _ = crossterm::execute!(output::terminal(), crossterm::event::DisableFocusChange);
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+    timeline::release_row();
+    output::info(&format!("\n{}", i18n::tr("Quit.")));
+    output::info(&i18n::trf("Traffic: {summary}", &[("summary", &traffic::summary())]));
+    report::finish();
+    bugreport::finish();
+    std::process::exit(code);
+}
+
+/// Handle termination signals: SIGTERM and SIGINT (Ctrl+C on Windows) exit
+/// cleanly with status 0, SIGHUP reloads `env_file`.
+pub fn watch_signals(controls: &Controls, env_file: Option<PathBuf>) {
+    let controls = controls.clone();
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            let (Ok(mut term), Ok(mut int), Ok(mut hup)) =
+                (signal(SignalKind::terminate()), signal(SignalKind::interrupt()), signal(SignalKind::hangup()))
+            else {
+                output::notice("signal", "cannot install signal handlers.");
+                return;
+            };
+            loop {
+                tokio::select! {
+                    _ = term.recv() => break,
+                    _ = int.recv() => break,
+                    _ = hup.recv() => match &env_file {
+                        Some(path) => {
+                            let reloading = i18n::trf("SIGHUP: reloading {path}.", &[("path", &path.display())]);
+                            output::notice("config", &reloading);
+                            controls.reload_config(path);
+                        }
+                        None => output::notice("config", i18n::tr("SIGHUP: no .env file to reload.")),
+                    },
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (&controls, &env_file);
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+        }
+        // Leave the runtime thread to the rest of the shutdown
+        tokio::task::spawn_blocking(|| exit(0));
+    });
+}
@@ -0,0 +1,296 @@
+// Speaker identification, for a device shared by a household. Voiceprints
+// and per-speaker instructions are kept locally in SPEAKERS_FILE (JSON):
+//   [{"name": "Ana", "instructions": "Ana is 8; keep answers short.", "print": [...]}]
+// `parlar enroll NAME recording.wav [--instructions TEXT]` adds or updates a
+// speaker. Each user turn is matched against the prints as soon as the
+// server reports the end of speech; the speaker found is named in front of
+// the session instructions together with their instructions, so the reply
+// is already for them, and transcripts are tagged with the name.
+//
+// The built-in print is the speaker's long-term average spectrum: cheap and
+// enough to tell apart clearly different voices (an adult and a child,
+// deeper and higher voices), not similar ones. SPEAKER_ID_CMD replaces it
+// with any recognizer: it gets the turn as a WAV file on stdin and prints
+// the speaker's name, or nothing when it does not know the voice.
+
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+
+use crate::cli::Cli;
+use crate::control::Controls;
+use crate::{output, turnaudio, wav};
+
+// Forwarded mic audio kept for cutting out turns
+const HISTORY_SECS: u64 = 30;
+// Shortest voiced audio worth matching
+const MIN_VOICED_MS: u64 = 800;
+// Band centers of the print, log-spaced over the speech range
+const BANDS: usize = 24;
+const LOW_HZ: f32 = 100.0;
+const HIGH_HZ: f32 = 5000.0;
+const CMD_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Speaker {
+    name: String,
+    #[serde(default)]
+    instructions: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    print: Vec<f32>,
+}
+
+struct Config {
+    speakers: Vec<Speaker>,
+    min_score: f32,
+    cmd: Option<String>,
+    sr_hz: u32,
+}
+
+static CONFIG: OnceLock<Option<Config>> = OnceLock::new();
+static CURRENT: Mutex<Option<String>> = Mutex::new(None);
+
+fn load(path: &str) -> Result<Vec<Speaker>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(body) => serde_json::from_str(&body).map_err(|e| format!("SPEAKERS_FILE {path}: {e}")),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("SPEAKERS_FILE {path}: {e}")),
+    }
+}
+
+/// Read SPEAKERS_FILE and the SPEAKER_* settings; call once at startup.
+pub fn init(sr_hz: u32) -> Result<(), String> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let config = match var("SPEAKERS_FILE") {
+        Some(path) => {
+            let speakers = load(&path)?;
+            let cmd = var("SPEAKER_ID_CMD");
+            if cmd.is_none() && !speakers.iter().any(|s| !s.print.is_empty()) {
+                return Err(format!("SPEAKERS_FILE {path}: nobody enrolled yet (see `parlar enroll`)"));
+            }
+            let min_score = match var("SPEAKER_MIN_SCORE") {
+                Some(v) => v.trim().parse::<f32>().ok().filter(|s| (0.0..=1.0).contains(s)).ok_or_else(|| {
+                    format!("SPEAKER_MIN_SCORE: {v} (use 0 to 1)")
+                })?,
+                None => 0.85,
+            };
+            turnaudio::keep(sr_hz, HISTORY_SECS);
+            Some(Config { speakers, min_score, cmd, sr_hz })
+        }
+        None if var("SPEAKER_ID_CMD").is_some() => return Err("SPEAKER_ID_CMD needs SPEAKERS_FILE".into()),
+        None => None,
+    };
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+fn config() -> Option<&'static Config> {
+    CONFIG.get().and_then(Option::as_ref)
+}
+
+/// The speaker of the latest turn, if recognized.
+pub fn current() -> Option<String> {
+    CURRENT.lock().unwrap().clone()
+}
+
+/// What goes before the instructions: who is talking and their own
+/// instructions; empty when nobody is recognized.
+pub fn preamble() -> String {
+    let (Some(cfg), Some(name)) = (config(), current()) else { return String::new() };
+    let extra = cfg.speakers.iter().find(|s| s.name == name).map_or("", |s| s.instructions.trim());
+    if extra.is_empty() {
+        format!("You are talking with {name}.\n\n")
+    } else {
+        format!("You are talking with {name}. {extra}\n\n")
+    }
+}
+
+/// Feed `input_audio_buffer.speech_stopped`; at the end of a turn, work
+/// out who spoke and update the instructions.
+pub fn on_event(evt: &Value, controls: &Controls) {
+    let Some(cfg) = config() else { return };
+    if evt["type"] != "input_audio_buffer.speech_stopped" {
+        return;
+    }
+    let audio = turnaudio::turn(evt);
+    if audio.is_empty() {
+        return;
+    }
+    match &cfg.cmd {
+        None => {
+            let Some(print) = voiceprint(&audio, cfg.sr_hz) else { return };
+            let best = best_match(&cfg.speakers, &print).filter(|(_, score)| *score >= cfg.min_score);
+            set_current(best.map(|(name, _)| name.to_string()), controls);
+        }
+        Some(cmd) => {
+            let (cmd, controls) = (cmd.clone(), controls.clone());
+            tokio::spawn(async move {
+                match run_cmd(&cmd, &wav::pcm16_mono(cfg.sr_hz, &audio)).await {
+                    Ok(name) => set_current(name, &controls),
+                    Err(e) => output::notice("speaker", &format!("SPEAKER_ID_CMD: {e}")),
+                }
+            });
+        }
+    }
+}
+
+fn set_current(name: Option<String>, controls: &Controls) {
+    {
+        let mut current = CURRENT.lock().unwrap();
+        if *current == name {
+            return;
+        }
+        current.clone_from(&name);
+    }
+    output::notice("speaker", name.as_deref().unwrap_or("not recognized"));
+    // Resend the instructions, now for this speaker
+    let instructions = controls.state.lock().unwrap().instructions.clone();
+    controls.set_instructions(&instructions);
+}
+
+async fn run_cmd(cmd: &str, wav: &[u8]) -> Result<Option<String>, String> {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = tokio::process::Command::new(shell)
+        .arg(flag)
+        .arg(cmd)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(wav).await;
+    }
+    let out = tokio::time::timeout(CMD_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| format!("no answer within {} s", CMD_TIMEOUT.as_secs()))?
+        .map_err(|e| e.to_string())?;
+    if !out.status.success() {
+        return Err(format!("exit status {}", out.status));
+    }
+    let name = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    Ok((!name.is_empty()).then_some(name))
+}
+
+/// The enrolled speaker closest to `print`, with the score (cosine, 0 to 1).
+fn best_match<'a>(speakers: &'a [Speaker], print: &[f32]) -> Option<(&'a str, f32)> {
+    speakers
+        .iter()
+        .filter(|s| s.print.len() == print.len())
+        .map(|s| (s.name.as_str(), similarity(&s.print, print)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    (dot / (norm(a) * norm(b)).max(f32::EPSILON)).max(0.0)
+}
+
+/// Long-term average spectrum of the voiced frames: log band energies in
+/// 25 ms frames, each relative to its own mean so loudness does not count,
+/// averaged. `None` with too little voiced audio.
+fn voiceprint(samples: &[i16], sr_hz: u32) -> Option<Vec<f32>> {
+    let (frame, hop) = (sr_hz as usize / 40, sr_hz as usize / 100);
+    let window: Vec<f32> = (0..frame)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / frame as f32).cos())
+        .collect();
+    let coeffs: Vec<f32> = (0..BANDS)
+        .map(|b| {
+            let hz = LOW_HZ * (HIGH_HZ / LOW_HZ).powf(b as f32 / (BANDS - 1) as f32);
+            2.0 * (2.0 * std::f32::consts::PI * hz / sr_hz as f32).cos()
+        })
+        .collect();
+    // Voiced: within 30 dB of the loudest frame
+    let rms: Vec<f32> = samples
+        .windows(frame)
+        .step_by(hop)
+        .map(|f| (f.iter().map(|&s| (s as f32).powi(2)).sum::<f32>() / frame as f32).sqrt())
+        .collect();
+    let floor = rms.iter().copied().fold(0.0f32, f32::max) * 10f32.powf(-30.0 / 20.0);
+    let mut sum = vec![0.0f32; BANDS];
+    let mut voiced = 0u64;
+    for (f, _) in samples.windows(frame).step_by(hop).zip(&rms).filter(|(_, r)| **r > floor.max(30.0)) {
+        // Goertzel power at each band center
+        let logs: Vec<f32> = coeffs
+            .iter()
+            .map(|&c| {
+                let (mut s1, mut s2) = (0.0f32, 0.0f32);
+                for (x, w) in f.iter().zip(&window) {
+                    let s = *x as f32 * w + c * s1 - s2;
+                    s2 = s1;
+                    s1 = s;
+                }
+                (s1 * s1 + s2 * s2 - c * s1 * s2).max(1.0).ln()
+            })
+            .collect();
+        let mean = logs.iter().sum::<f32>() / BANDS as f32;
+        for (acc, l) in sum.iter_mut().zip(&logs) {
+            *acc += l - mean;
+        }
+        voiced += 1;
+    }
+    if voiced * hop as u64 * 1000 / (sr_hz as u64) < MIN_VOICED_MS {
+        return None;
+    }
+    Some(sum.into_iter().map(|s| s / voiced as f32).collect())
+}
+
+/// `parlar enroll NAME RECORDING.wav [--instructions TEXT]`.
+pub fn run_enroll(cli: &Cli) -> i32 {
+    let [name, file] = cli.operands()[..] else {
+        eprintln!("usage: parlar enroll NAME RECORDING.wav [--instructions TEXT]");
+        return 2;
+    };
+    let Some(path) = std::env::var("SPEAKERS_FILE").ok().filter(|p| !p.trim().is_empty()) else {
+        eprintln!("[config] set SPEAKERS_FILE to the file that keeps the voiceprints");
+        return 2;
+    };
+    let samples = std::fs::read(file).map_err(|e| e.to_string()).and_then(|b| {
+        wav::read_pcm16_mono(&b).ok_or_else(|| "not a 16-bit mono WAV file".to_string())
+    });
+    let (sr_hz, samples) = match samples {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{file}: {e}");
+            return 1;
+        }
+    };
+    let Some(print) = voiceprint(&samples, sr_hz) else {
+        eprintln!("{file}: too little speech; record at least 10 seconds of talking");
+        return 1;
+    };
+    let mut speakers = match load(&path) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("[config] {e}");
+            return 2;
+        }
+    };
+    for other in speakers.iter().filter(|s| s.name != name && s.print.len() == print.len()) {
+        println!("similarity to {}: {:.2}", other.name, similarity(&other.print, &print));
+    }
+    match speakers.iter_mut().find(|s| s.name == name) {
+        Some(s) => {
+            s.print = print;
+            if let Some(text) = cli.value("instructions") {
+                s.instructions = text;
+            }
+        }
+        None => speakers.push(Speaker {
+            name: name.to_string(),
+            instructions: cli.value("instructions").unwrap_or_default(),
+            print,
+        }),
+    }
+    let body = serde_json::to_string_pretty(&speakers).unwrap_or_default();
+    if let Err(e) = std::fs::write(&path, body) {
+        eprintln!("cannot write {path}: {e}");
+        return 1;
+    }
+    println!("Enrolled {name} in {path}.");
+    0
+}
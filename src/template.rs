@@ -0,0 +1,178 @@
+// Instruction templates. The instructions (INSTRUCTIONS, or the file
+// INSTRUCTIONS_FILE; default "You are a concise, helpful assistant.") can
+// use variables in double braces:
+//   {{date}} {{time}} {{weekday}}  now, in local time (2026-10-16, 14:05, Friday)
+//   {{user_name}}                  USER_NAME
+//   {{persona}}                    the persona's name
+//   {{persona.FIELD}}              its PERSONA_<NAME>_<FIELD> setting, e.g.
+//                                  {{persona.subject}} for PERSONA_TUTOR_SUBJECT
+//   {{memory}}                     MEMORY_FILE's facts, which are then not
+//                                  put in front of the instructions as well
+// The instructions are kept as written and filled in whenever they are
+// sent: at the start of each session, on a settings reload (SIGHUP or a
+// changed .env file, which also re-reads INSTRUCTIONS_FILE), and when they
+// are replaced (a persona, `set_instructions`). Unknown variables are a
+// configuration error; instructions replaced while running are checked
+// the same way and refused.
+//
+// Personas are named instructions and voice: PERSONA_<NAME>_INSTRUCTIONS
+// and/or PERSONA_<NAME>_VOICE, plus any fields of their own. PERSONA picks
+// one at startup; the palette's `persona NAME` switches.
+
+use std::sync::Mutex;
+
+use crate::config;
+
+const DEFAULT: &str = "You are a concise, helpful assistant.";
+const VARIABLES: &str = "date, time, weekday, user_name, persona, persona.FIELD, memory";
+
+pub struct Persona {
+    pub name: String,
+    pub instructions: Option<String>,
+    pub voice: Option<String>,
+    // the other PERSONA_<NAME>_<FIELD> settings, field in lowercase
+    fields: Vec<(String, String)>,
+}
+
+// The persona in use
+static PERSONA: Mutex<Option<String>> = Mutex::new(None);
+// INSTRUCTIONS or INSTRUCTIONS_FILE as last read
+static CONFIGURED: Mutex<String> = Mutex::new(String::new());
+
+/// The PERSONA_<NAME>_* settings, by name. Names may not contain `_`.
+pub fn personas() -> Vec<Persona> {
+    let mut out: Vec<Persona> = Vec::new();
+    for (key, value) in config::vars() {
+        let Some((name, field)) = key.strip_prefix("PERSONA_").and_then(|rest| rest.split_once('_')) else {
+            continue;
+        };
+        if name.is_empty() || value.trim().is_empty() {
+            continue;
+        }
+        let name = name.to_lowercase();
+        let at = match out.iter().position(|p| p.name == name) {
+            Some(i) => i,
+            None => {
+                out.push(Persona { name, instructions: None, voice: None, fields: Vec::new() });
+                out.len() - 1
+            }
+        };
+        match field {
+            "INSTRUCTIONS" => out[at].instructions = Some(value),
+            "VOICE" => out[at].voice = Some(value.trim().to_string()),
+            _ => out[at].fields.push((field.to_lowercase(), value)),
+        }
+    }
+    out.sort_by(|a, b| a.name.cmp(&b.name));
+    out
+}
+
+fn persona(name: &str) -> Option<Persona> {
+    personas().into_iter().find(|p| p.name == name)
+}
+
+/// Make `name` the persona that {{persona}} and its fields refer to;
+/// returns the one before.
+pub fn set_persona(name: Option<String>) -> Option<String> {
+    std::mem::replace(&mut *PERSONA.lock().unwrap(), name)
+}
+
+/// Read PERSONA, INSTRUCTIONS and INSTRUCTIONS_FILE; returns the startup
+/// instructions (the persona's, if it has some) and the persona's voice.
+pub fn init() -> Result<(String, Option<String>), String> {
+    let configured = configured()?;
+    *CONFIGURED.lock().unwrap() = configured.clone();
+    let chosen = match config::var("PERSONA").filter(|p| !p.trim().is_empty()) {
+        Some(name) => {
+            let name = name.trim().to_lowercase();
+            let p = persona(&name).ok_or_else(|| format!("PERSONA: no PERSONA_{}_* settings", name.to_uppercase()))?;
+            set_persona(Some(name));
+            Some(p)
+        }
+        None => None,
+    };
+    let voice = chosen.as_ref().and_then(|p| p.voice.clone());
+    let instructions = chosen.and_then(|p| p.instructions).unwrap_or(configured);
+    check(&instructions).map_err(|e| format!("instructions: {e}"))?;
+    Ok((instructions, voice))
+}
+
+fn configured() -> Result<String, String> {
+    if let Some(path) = config::var("INSTRUCTIONS_FILE").filter(|p| !p.trim().is_empty()) {
+        return std::fs::read_to_string(path.trim()).map_err(|e| format!("INSTRUCTIONS_FILE {path}: {e}"));
+    }
+    Ok(config::var("INSTRUCTIONS").filter(|i| !i.trim().is_empty()).unwrap_or_else(|| DEFAULT.into()))
+}
+
+/// On a settings reload: the configured instructions, when they changed
+/// since they were last read.
+pub fn reload() -> Result<Option<String>, String> {
+    let configured = configured()?;
+    check(&configured).map_err(|e| format!("instructions: {e}"))?;
+    let mut last = CONFIGURED.lock().unwrap();
+    if *last == configured {
+        return Ok(None);
+    }
+    last.clone_from(&configured);
+    Ok(Some(configured))
+}
+
+/// Whether `text` uses {{memory}}.
+pub fn uses_memory(text: &str) -> bool {
+    variables(text).any(|v| v == "memory")
+}
+
+fn variables(text: &str) -> impl Iterator<Item = &str> {
+    text.split("{{").skip(1).filter_map(|s| s.split_once("}}")).map(|(name, _)| name.trim())
+}
+
+/// The value of variable `name`.
+fn value(name: &str) -> Result<String, String> {
+    let now = chrono::Local::now();
+    let current = || PERSONA.lock().unwrap().clone().ok_or_else(|| format!("{{{{{name}}}}} needs a persona"));
+    Ok(match name {
+        "date" => now.format("%Y-%m-%d").to_string(),
+        "time" => now.format("%H:%M").to_string(),
+        "weekday" => now.format("%A").to_string(),
+        "user_name" => config::var("USER_NAME")
+            .filter(|n| !n.trim().is_empty())
+            .ok_or("{{user_name}} needs USER_NAME")?,
+        "persona" => current()?,
+        "memory" => crate::memory::facts(),
+        _ => match name.strip_prefix("persona.") {
+            Some(field) => {
+                let p = persona(&current()?).ok_or_else(|| format!("{{{{{name}}}}}: the persona is gone"))?;
+                let setting = format!("PERSONA_{}_{}", p.name.to_uppercase(), field.to_uppercase());
+                p.fields
+                    .into_iter()
+                    .find(|(f, _)| *f == field.to_lowercase())
+                    .map(|(_, v)| v)
+                    .ok_or_else(|| format!("{{{{{name}}}}} needs {setting}"))?
+            }
+            None => return Err(format!("unknown variable {{{{{name}}}}} (use {VARIABLES})")),
+        },
+    })
+}
+
+/// Whether every variable in `text` can be filled in.
+pub fn check(text: &str) -> Result<(), String> {
+    variables(text).try_for_each(|name| value(name).map(drop))
+}
+
+/// `text` with its variables filled in; any that cannot be are left as written.
+pub fn expand(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else { break };
+        out.push_str(&rest[..start]);
+        let raw = &rest[start..start + len + 2];
+        match value(raw[2..raw.len() - 2].trim()) {
+            Ok(v) => out.push_str(&v),
+            Err(_) => out.push_str(raw),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
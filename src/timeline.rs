@@ -0,0 +1,149 @@
+// Turn-taking timeline: user/assistant speech segments plus commit and
+// cancel marks over the last minute, drawn as a one-line strip pinned to
+// the bottom row of the terminal, with the playback backlog and lag.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::playback::Readout;
+
+pub const WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Track {
+    User,
+    Assistant,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    Commit,
+    Cancel,
+}
+
+#[derive(Default)]
+pub struct Timeline {
+    // (track, start, end); end is None while the segment is open
+    segments: VecDeque<(Track, Instant, Option<Instant>)>,
+    marks: VecDeque<(Mark, Instant)>,
+}
+
+impl Timeline {
+    pub fn start(&mut self, track: Track) {
+        if !self.is_open(track) {
+            self.segments.push_back((track, Instant::now(), None));
+        }
+        self.prune();
+    }
+
+    pub fn stop(&mut self, track: Track) {
+        let now = Instant::now();
+        for seg in self.segments.iter_mut().rev() {
+            if seg.0 == track && seg.2.is_none() {
+                seg.2 = Some(now);
+            }
+        }
+    }
+
+    pub fn mark(&mut self, mark: Mark) {
+        self.marks.push_back((mark, Instant::now()));
+        self.prune();
+    }
+
+    pub fn is_open(&self, track: Track) -> bool {
+        self.segments.iter().any(|s| s.0 == track && s.2.is_none())
+    }
+
+    fn prune(&mut self) {
+        let Some(cutoff) = Instant::now().checked_sub(WINDOW) else { return };
+        self.segments.retain(|s| s.2.is_none_or(|end| end >= cutoff));
+        self.marks.retain(|m| m.1 >= cutoff);
+    }
+
+    /// Render `width` columns covering the last `WINDOW`, oldest on the left.
+    pub fn render(&self, width: usize) -> String {
+        let now = Instant::now();
+        let width = width.max(1);
+        let col = WINDOW / width as u32;
+        let mut out = String::with_capacity(width);
+        for i in 0..width {
+            let back = col * (width - i) as u32;
+            let (Some(a), Some(b)) = (now.checked_sub(back), now.checked_sub(back - col)) else {
+                out.push(' ');
+                continue;
+            };
+            let overlaps = |track: Track| {
+                self.segments
+                    .iter()
+                    .any(|s| s.0 == track && s.1 < b && s.2.unwrap_or(now) >= a)
+            };
+            let has_mark = |mark: Mark| self.marks.iter().any(|m| m.0 == mark && m.1 >= a && m.1 < b);
+            out.push(if has_mark(Mark::Cancel) {
+                '!'
+            } else if has_mark(Mark::Commit) {
+                '|'
+            } else {
+                match (overlaps(Track::User), overlaps(Track::Assistant)) {
+                    (true, true) => 'X',
+                    (true, false) => 'u',
+                    (false, true) => 'a',
+                    (false, false) => '·',
+                }
+            });
+        }
+        out
+    }
+}
+
+const LEGEND: &str = " u=you a=assistant X=overlap |=commit !=cancel";
+
+// Columns and rows; unknown without the `tui` feature, so nothing is drawn
+fn size() -> Option<(u16, u16)> {
+    #[cfg(feature = "tui")]
+    return crossterm::terminal::size().ok();
+    #[cfg(not(feature = "tui"))]
+    None
+}
+
+/// Reserve the bottom row: scroll region covers every other row.
+pub fn reserve_row() {
+    if let Some((_, rows)) = size() {
+        eprint!("\x1b[1;{}r", rows.saturating_sub(1).max(1));
+        std::io::stderr().flush().ok();
+    }
+}
+
+/// Give the bottom row back and clear it.
+pub fn release_row() {
+    if let Some((_, rows)) = size() {
+        eprint!("\x1b7\x1b[r\x1b[{rows};1H\x1b[2K\x1b8");
+        std::io::stderr().flush().ok();
+    }
+}
+
+/// Draw `text` on the bottom row instead of the strip, cut to the width.
+pub fn draw_line(text: &str) {
+    let Some((cols, rows)) = size() else { return };
+    let text: String = text.chars().take(cols as usize).collect();
+    eprint!("\x1b7\x1b[{rows};1H\x1b[2K{text}\x1b8");
+    std::io::stderr().flush().ok();
+}
+
+/// Draw the strip on the bottom row without moving the cursor.
+pub fn draw(timeline: &Timeline, playback: Readout) {
+    let Some((cols, rows)) = size() else { return };
+    let prefix = "VAD 60s [";
+    let secs = |ms: u64| ms as f32 / 1000.0;
+    let mut audio = format!(" | out {:.1}s lag {:.1}s", secs(playback.backlog_ms), secs(playback.lag_ms));
+    if playback.stretching {
+        audio.push_str(" catching up");
+    }
+    if playback.skipped_ms > 0 {
+        audio.push_str(&format!(" skipped {:.1}s", secs(playback.skipped_ms)));
+    }
+    let width = (cols as usize).saturating_sub(prefix.len() + 1 + LEGEND.len() + audio.len()).max(10);
+    let strip = timeline.render(width);
+    eprint!("\x1b7\x1b[{rows};1H\x1b[2K{prefix}{strip}]{LEGEND}{audio}\x1b8");
+    std::io::stderr().flush().ok();
+}
@@ -0,0 +1,184 @@
+// Timers and alarms: built-in tools for "set a timer for 10 minutes" and
+// "wake me at 7". They are scheduled locally, so they go off even if the
+// connection to the realtime server dropped in the meantime: the alarm
+// sound is played by parlar itself, and the assistant adds a spoken
+// announcement when it can. Timers last as long as the run. TIMERS=false
+// leaves the tools out.
+
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde_json::json;
+use tokio::task::AbortHandle;
+
+use crate::control::Controls;
+use crate::earcon::{self, Earcon};
+use crate::tools::{self, Builtin};
+use crate::{i18n, output, reminders};
+
+// Times the alarm sound is repeated
+const RINGS: u32 = 4;
+
+struct Timer {
+    id: u32,
+    label: String,
+    at: DateTime<Local>,
+    task: AbortHandle,
+}
+
+static CONTROLS: OnceLock<Controls> = OnceLock::new();
+static TIMERS: Mutex<Vec<Timer>> = Mutex::new(Vec::new());
+
+fn enabled() -> bool {
+    crate::env_flag("TIMERS", true)
+}
+
+/// The timer tools, unless TIMERS=false.
+pub fn tools() -> Vec<Builtin> {
+    if !enabled() {
+        return Vec::new();
+    }
+    vec![
+        Builtin {
+            name: "set_timer",
+            description: "Start a countdown timer; parlar rings and announces it when it runs out.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "seconds": {"type": "number", "description": "Length of the timer in seconds"},
+                    "label": {"type": "string", "description": "What the timer is for, if the user said"}
+                },
+                "required": ["seconds"]
+            }),
+            run: set_timer,
+        },
+        Builtin {
+            name: "set_alarm",
+            description: "Set an alarm for a local clock time (HH:MM, the next such time, or YYYY-MM-DD HH:MM).",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "at": {"type": "string", "description": "HH:MM or YYYY-MM-DD HH:MM, local time"},
+                    "label": {"type": "string"}
+                },
+                "required": ["at"]
+            }),
+            run: set_alarm,
+        },
+        Builtin {
+            name: "list_timers",
+            description: "List the running timers and alarms with the time left, and the current local time.",
+            parameters: json!({"type": "object", "properties": {}}),
+            run: list,
+        },
+        Builtin {
+            name: "cancel_timer",
+            description: "Cancel a timer or alarm by its id (see list_timers).",
+            parameters: json!({
+                "type": "object",
+                "properties": {"id": {"type": "integer"}},
+                "required": ["id"]
+            }),
+            run: cancel,
+        },
+    ]
+}
+
+/// Make the controls available to ring timers; call once the session is running.
+pub fn start(controls: &Controls) {
+    let _ = CONTROLS.set(controls.clone());
+}
+
+fn args(args: &str) -> Result<serde_json::Value, String> {
+    serde_json::from_str(args).map_err(|e| format!("bad arguments: {e}"))
+}
+
+fn set_timer(a: &str) -> Result<String, String> {
+    let a = args(a)?;
+    let secs = a["seconds"].as_f64().filter(|s| *s > 0.0).ok_or("`seconds` must be a positive number")?;
+    let at = Local::now() + chrono::Duration::milliseconds((secs * 1000.0) as i64);
+    let label = a["label"].as_str().unwrap_or("").trim();
+    let label = if label.is_empty() { format!("{} timer", length(secs as u64)) } else { label.to_string() };
+    schedule(label, at)
+}
+
+fn set_alarm(a: &str) -> Result<String, String> {
+    let a = args(a)?;
+    let at = reminders::parse_at(a["at"].as_str().ok_or("`at` is required")?, Local::now())?;
+    let label = a["label"].as_str().map(str::trim).filter(|l| !l.is_empty());
+    let label = label.map_or_else(|| format!("{} alarm", at.format("%H:%M")), str::to_string);
+    schedule(label, at)
+}
+
+/// "10 minutes", "1 hour 30 minutes", "45 seconds".
+fn length(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    let part = |n: u64, unit: &str| match n {
+        0 => None,
+        1 => Some(format!("1 {unit}")),
+        n => Some(format!("{n} {unit}s")),
+    };
+    let parts: Vec<String> = [part(h, "hour"), part(m, "minute"), part(s, "second")].into_iter().flatten().collect();
+    if parts.is_empty() { "0 seconds".into() } else { parts.join(" ") }
+}
+
+fn schedule(label: String, at: DateTime<Local>) -> Result<String, String> {
+    let controls = CONTROLS.get().ok_or("timers are not running yet")?.clone();
+    let wait = (at - Local::now()).to_std().unwrap_or_default();
+    let mut timers = TIMERS.lock().unwrap();
+    let id = timers.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+    let ring_label = label.clone();
+    let task = tokio::spawn(async move {
+        tokio::time::sleep(wait).await;
+        TIMERS.lock().unwrap().retain(|t| t.id != id);
+        ring(&controls, &ring_label).await;
+    });
+    timers.push(Timer { id, label: label.clone(), at, task: task.abort_handle() });
+    let time = at.format("%H:%M:%S");
+    output::notice("timer", &i18n::trf("{label}: set for {time}", &[("label", &label), ("time", &time)]));
+    Ok(json!({"id": id, "label": label, "rings_at": at.format("%H:%M:%S").to_string()}).to_string())
+}
+
+/// Sound the alarm, then have the assistant announce it. The sound is local,
+/// so it plays whether or not the session is connected.
+async fn ring(controls: &Controls, label: &str) {
+    output::notice("timer", &i18n::trf("{label}: time is up.", &[("label", &label)]));
+    let gap = Duration::from_millis(earcon::duration_ms(Earcon::Alarm) + 250);
+    for _ in 0..RINGS {
+        // Loud enough to hear across the room, whatever EARCON_GAIN is
+        earcon::play(&controls.cue_buf, Earcon::Alarm, controls.sr_hz, controls.earcon_gain.max(0.3));
+        tokio::time::sleep(gap).await;
+    }
+    let event_id = tools::wait_idle(&controls.state).await;
+    tools::speak(
+        controls,
+        event_id,
+        &format!("In one short sentence, tell the user that their {label} is done. Say nothing else."),
+        "timer",
+    );
+}
+
+fn list(_args: &str) -> Result<String, String> {
+    let now = Local::now();
+    let timers: Vec<serde_json::Value> = TIMERS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|t| {
+            let left = (t.at - now).num_seconds().max(0) as u64;
+            json!({"id": t.id, "label": t.label, "rings_at": t.at.format("%H:%M:%S").to_string(), "left": length(left)})
+        })
+        .collect();
+    Ok(json!({"now": now.format("%H:%M:%S").to_string(), "timers": timers}).to_string())
+}
+
+fn cancel(a: &str) -> Result<String, String> {
+    let id = args(a)?["id"].as_u64().ok_or("`id` is required")?;
+    let mut timers = TIMERS.lock().unwrap();
+    let pos = timers.iter().position(|t| u64::from(t.id) == id).ok_or_else(|| format!("no timer {id}"))?;
+    let timer = timers.remove(pos);
+    timer.task.abort();
+    output::notice("timer", &format!("{}: cancelled.", timer.label));
+    Ok(json!({"cancelled": id}).to_string())
+}
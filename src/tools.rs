@@ -0,0 +1,509 @@
+// Tool calling. TOOLS_FILE names a TOML file of tools the model may call;
+// each runs a shell command with the call's arguments (a JSON object) on
+// stdin, and what it prints is the result:
+//
+//   [[tool]]
+//   name = "delete_note"
+//   description = "Delete one of the user's notes"
+//   command = "notes delete \"$(jq -r .title)\""
+//   confirm = "ask-verbally"
+//   parameters = { type = "object", properties = { title = { type = "string" } }, required = ["title"] }
+//
+// `confirm` decides who runs a call:
+//   auto          right away (default)
+//   ask-via-key   parlar shows the call; Y runs it, N declines
+//   ask-verbally  the question is also spoken, and a spoken yes or no answers
+// A call nobody answers within TOOL_CONFIRM_SECS (default 60) is declined;
+// the model is told, so it can say so.
+//
+// Calls run side by side, each in its own process, for at most the tool's
+// `timeout_secs` (default TOOL_TIMEOUT_SECS, 30); the model continues once
+// every call it made has a result. Lines a command writes to stderr are
+// shown as its status, and while a call is still busy every
+// TOOL_STATUS_SECS (default 10, 0 turns it off) the assistant says so.
+// Interrupting (I, the interrupt commands, a barge-in keyword) stops the
+// running calls and declines the waiting ones.
+//
+// Tool plugins from PLUGINS_DIR (see plugins.rs) and parlar's own tools
+// (reminders, timers) join the list and are confirmed, timed and stopped the same
+// way.
+
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{oneshot, watch};
+use tungstenite::Message;
+
+use crate::control::Controls;
+use crate::plugins::{self, Plugin};
+use crate::timeline::Track;
+use crate::{State, i18n, language, output, reminders, timers};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Policy {
+    #[default]
+    Auto,
+    AskViaKey,
+    AskVerbally,
+}
+
+#[derive(Deserialize)]
+struct Tool {
+    name: String,
+    #[serde(default)]
+    description: String,
+    command: String,
+    #[serde(default)]
+    confirm: Policy,
+    parameters: Option<toml::Value>,
+    timeout_secs: Option<u64>,
+    // set for plugin tools, which have no command
+    #[serde(skip)]
+    plugin: Option<Arc<Plugin>>,
+    // set for built-in tools
+    #[serde(skip)]
+    builtin: Option<BuiltinFn>,
+}
+
+type BuiltinFn = fn(&str) -> Result<String, String>;
+
+/// A tool parlar implements itself: `run` gets the call's arguments (JSON)
+/// and returns the result.
+pub struct Builtin {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub parameters: serde_json::Value,
+    pub run: BuiltinFn,
+}
+
+#[derive(Deserialize)]
+struct ToolFile {
+    #[serde(default)]
+    tool: Vec<Tool>,
+}
+
+struct Tools {
+    list: Vec<Tool>,
+    confirm_secs: u64,
+    timeout_secs: u64,
+    status_secs: u64,
+    // bumped on every interrupt; running calls watch it
+    cancel: watch::Sender<u64>,
+    // answers come from the keyboard and stdin threads too
+    runtime: tokio::runtime::Handle,
+}
+
+static TOOLS: OnceLock<Tools> = OnceLock::new();
+// Calls of the current batch without a result yet
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+// A call waiting for the user's yes or no; the first one is being asked
+struct Pending {
+    call_id: String,
+    tool: String,
+    args: String,
+    verbal: bool,
+    answer: oneshot::Sender<bool>,
+}
+
+static PENDING: Mutex<VecDeque<Pending>> = Mutex::new(VecDeque::new());
+
+fn secs(name: &str, default: u64) -> Result<u64, String> {
+    match std::env::var(name) {
+        Ok(v) => v.trim().parse().map_err(|_| format!("{name}: invalid {v}")),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Read TOOLS_FILE and the TOOL_* timings; call once at startup, inside the
+/// runtime.
+pub fn init() -> Result<(), String> {
+    let confirm_secs = secs("TOOL_CONFIRM_SECS", 60)?;
+    let timeout_secs = secs("TOOL_TIMEOUT_SECS", 30)?;
+    let status_secs = secs("TOOL_STATUS_SECS", 10)?;
+    let mut list = match std::env::var("TOOLS_FILE").ok().filter(|p| !p.trim().is_empty()) {
+        Some(path) => {
+            let body = std::fs::read_to_string(&path).map_err(|e| format!("TOOLS_FILE {path}: {e}"))?;
+            let file: ToolFile = toml::from_str(&body).map_err(|e| format!("TOOLS_FILE {path}: {e}"))?;
+            if let Some(t) = file.tool.iter().find(|t| t.name.trim().is_empty() || t.command.trim().is_empty()) {
+                return Err(format!("TOOLS_FILE {path}: tool `{}` needs a name and a command", t.name));
+            }
+            file.tool
+        }
+        None => Vec::new(),
+    };
+    list.extend(plugins::tools().map(|p| Tool {
+        name: p.manifest.name.clone(),
+        description: p.manifest.description.clone(),
+        command: String::new(),
+        confirm: p.manifest.confirm,
+        parameters: p.manifest.parameters.clone(),
+        timeout_secs: p.manifest.timeout_secs,
+        plugin: Some(p.clone()),
+        builtin: None,
+    }));
+    list.extend(reminders::tools().into_iter().chain(timers::tools()).map(|b| Tool {
+        name: b.name.to_string(),
+        description: b.description.to_string(),
+        command: String::new(),
+        confirm: Policy::Auto,
+        parameters: toml::Value::try_from(b.parameters).ok(),
+        timeout_secs: None,
+        plugin: None,
+        builtin: Some(b.run),
+    }));
+    let _ = TOOLS.set(Tools {
+        list,
+        confirm_secs,
+        timeout_secs,
+        status_secs,
+        cancel: watch::channel(0).0,
+        runtime: tokio::runtime::Handle::current(),
+    });
+    Ok(())
+}
+
+fn find(name: &str) -> Option<&'static Tool> {
+    TOOLS.get()?.list.iter().find(|t| t.name == name)
+}
+
+/// The `tools` array for `session.update`; empty without any tools.
+pub fn definitions() -> Vec<serde_json::Value> {
+    let Some(tools) = TOOLS.get() else { return Vec::new() };
+    tools
+        .list
+        .iter()
+        .map(|t| {
+            let parameters = t
+                .parameters
+                .as_ref()
+                .and_then(|p| serde_json::to_value(p).ok())
+                .unwrap_or_else(|| json!({"type": "object", "properties": {}}));
+            json!({"type": "function", "name": t.name, "description": t.description, "parameters": parameters})
+        })
+        .collect()
+}
+
+/// The model called a tool (a finished `function_call` output item): run
+/// it, once approved if its policy asks, and hand the result back.
+pub fn call(item: &serde_json::Value, controls: &Controls) {
+    let Some(tools) = TOOLS.get() else { return };
+    let name = item["name"].as_str().unwrap_or("").to_string();
+    let call_id = item["call_id"].as_str().unwrap_or("").to_string();
+    let args = item["arguments"].as_str().unwrap_or("{}").to_string();
+    let controls = controls.clone();
+    let cancelled = tools.cancel.subscribe();
+    RUNNING.fetch_add(1, Ordering::SeqCst);
+    tokio::spawn(async move {
+        let Some(tool) = find(&name) else {
+            output::notice("tool", &i18n::trf("the model called an unknown tool {name}.", &[("name", &name)]));
+            finish(&controls, &call_id, json!({"error": format!("no tool named {name}")}), &cancelled).await;
+            return;
+        };
+        let approved = match tool.confirm {
+            Policy::Auto => true,
+            policy => ask(&controls, tool, &call_id, &args, policy == Policy::AskVerbally).await,
+        };
+        if !approved {
+            finish(&controls, &call_id, json!({"error": "the user declined to run this tool"}), &cancelled).await;
+            return;
+        }
+        output::notice("tool", &i18n::trf("running {name}…", &[("name", &name)]));
+        let status = Arc::new(Mutex::new(None));
+        let reporter = (tools.status_secs > 0).then(|| {
+            let every = Duration::from_secs(tools.status_secs);
+            tokio::spawn(report(controls.clone(), name.clone(), status.clone(), every))
+        });
+        let limit = Duration::from_secs(tool.timeout_secs.unwrap_or(tools.timeout_secs));
+        let ran = match (&tool.plugin, tool.builtin) {
+            (Some(plugin), _) => run_plugin(plugin, &args, limit, cancelled.clone()).await,
+            (None, Some(builtin)) => builtin(&args),
+            (None, None) => run(tool, &args, limit, &status, cancelled.clone()).await,
+        };
+        let result = match ran {
+            Ok(out) => json!({"result": out}),
+            Err(e) => {
+                output::notice("tool", &format!("{name}: {e}"));
+                json!({"error": e})
+            }
+        };
+        if let Some(r) = reporter {
+            r.abort();
+        }
+        finish(&controls, &call_id, result, &cancelled).await;
+    });
+}
+
+/// Ask the user about a call and wait for the answer (or the timeout).
+async fn ask(controls: &Controls, tool: &Tool, call_id: &str, args: &str, verbal: bool) -> bool {
+    let (tx, rx) = oneshot::channel();
+    let first = {
+        let mut pending = PENDING.lock().unwrap();
+        pending.push_back(Pending {
+            call_id: call_id.to_string(),
+            tool: tool.name.clone(),
+            args: args.trim().to_string(),
+            verbal,
+            answer: tx,
+        });
+        pending.len() == 1
+    };
+    if first {
+        announce(controls).await;
+    }
+    let secs = TOOLS.get().map_or(60, |t| t.confirm_secs);
+    match tokio::time::timeout(Duration::from_secs(secs), rx).await {
+        Ok(Ok(yes)) => yes,
+        _ => {
+            let next = {
+                let mut pending = PENDING.lock().unwrap();
+                let was_first = pending.front().is_some_and(|p| p.call_id == call_id);
+                pending.retain(|p| p.call_id != call_id);
+                was_first && !pending.is_empty()
+            };
+            output::notice("tool", &i18n::trf("no answer; {name} not run.", &[("name", &tool.name)]));
+            if next {
+                announce(controls).await;
+            }
+            false
+        }
+    }
+}
+
+/// Put the first pending question to the user.
+async fn announce(controls: &Controls) {
+    let Some((tool, args, verbal)) =
+        PENDING.lock().unwrap().front().map(|p| (p.tool.clone(), p.args.clone(), p.verbal))
+    else {
+        return;
+    };
+    let how = if output::json_mode() {
+        "send confirm_tool"
+    } else if controls.state.lock().unwrap().text_only || !cfg!(feature = "tui") {
+        i18n::tr("type /yes or /no")
+    } else if verbal {
+        i18n::tr("say yes or no, or press Y / N")
+    } else {
+        i18n::tr("press Y to run it, N to decline")
+    };
+    let question = i18n::trf("run {name} with {args}? ({how})", &[("how", &how), ("name", &tool), ("args", &args)]);
+    output::notice("tool", &question);
+    if verbal {
+        let event_id = wait_idle(&controls.state).await;
+        speak(
+            controls,
+            event_id,
+            &format!(
+                "In one short sentence, ask the user whether you should run {tool} with {args}? \
+                 Describe the arguments in plain words and ask for a yes or no."
+            ),
+            "tool_confirm",
+        );
+    }
+}
+
+/// Have the assistant say something outside the conversation, so it does
+/// not become part of it, as the reply claimed as `event_id`.
+pub fn speak(controls: &Controls, event_id: String, instructions: &str, purpose: &str) {
+    let _ = controls.out_tx.send(Message::Text(
+        json!({
+            "type": "response.create",
+            "event_id": event_id,
+            "response": {
+                "conversation": "none",
+                "instructions": instructions,
+                "metadata": {"purpose": purpose}
+            }
+        })
+        .to_string(),
+    ));
+}
+
+/// Answer the first pending question; false when there is none.
+pub fn answer(yes: bool, controls: &Controls) -> bool {
+    let (pending, more) = {
+        let mut q = PENDING.lock().unwrap();
+        (q.pop_front(), !q.is_empty())
+    };
+    let Some(p) = pending else { return false };
+    output::notice("tool", if yes { i18n::tr("approved.") } else { i18n::tr("declined.") });
+    let _ = p.answer.send(yes);
+    if more && let Some(tools) = TOOLS.get() {
+        let controls = controls.clone();
+        tools.runtime.spawn(async move { announce(&controls).await });
+    }
+    true
+}
+
+/// The user interrupted: stop running calls and decline waiting ones.
+pub fn cancel() {
+    let Some(tools) = TOOLS.get() else { return };
+    let waiting: Vec<Pending> = PENDING.lock().unwrap().drain(..).collect();
+    if RUNNING.load(Ordering::SeqCst) == 0 && waiting.is_empty() {
+        return;
+    }
+    tools.cancel.send_modify(|n| *n += 1);
+    for p in waiting {
+        let _ = p.answer.send(false);
+    }
+    output::notice("tool", i18n::tr("tool calls stopped."));
+}
+
+/// A question is waiting for a spoken answer.
+pub fn awaiting_voice() -> bool {
+    PENDING.lock().unwrap().front().is_some_and(|p| p.verbal)
+}
+
+/// Yes or no, if that is what `transcript` says.
+pub fn spoken_answer(transcript: &str) -> Option<bool> {
+    let words: Vec<String> = language::words(transcript).collect();
+    let has = |list: &[&str]| words.iter().any(|w| list.contains(&w.as_str()));
+    let no = has(&["no", "nope", "don't", "cancel", "stop", "decline"]);
+    let yes = has(&["yes", "yeah", "yep", "sure", "okay", "ok", "go", "approve", "confirm"]);
+    match (yes, no) {
+        (true, false) => Some(true),
+        (false, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Wait until no reply is under way and claim the next one; returns the
+/// event id its `response.create` carries. A turn, a timer or another call
+/// finishing at the same moment waits for the reply claimed here.
+pub async fn wait_idle(state: &Mutex<State>) -> String {
+    loop {
+        if let Some(event_id) = state.lock().unwrap().responses.try_ask() {
+            return event_id;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// Hand a call's output to the model. The last call of a batch to finish
+/// asks it to go on, unless the user interrupted meanwhile.
+async fn finish(controls: &Controls, call_id: &str, output: serde_json::Value, cancelled: &watch::Receiver<u64>) {
+    let event_id = wait_idle(&controls.state).await;
+    let item = json!({
+        "type": "conversation.item.create",
+        "item": {"type": "function_call_output", "call_id": call_id, "output": output.to_string()}
+    });
+    let _ = controls.out_tx.send(Message::Text(item.to_string()));
+    if RUNNING.fetch_sub(1, Ordering::SeqCst) != 1 || cancelled.has_changed().unwrap_or(false) {
+        let create = controls.state.lock().unwrap().withdraw(&event_id);
+        if let Some(create) = create {
+            let _ = controls.out_tx.send(Message::Text(create.to_string()));
+        }
+        return;
+    }
+    let create = {
+        let mut st = controls.state.lock().unwrap();
+        st.thinking_since = Some(Instant::now());
+        st.create_event(&event_id)
+    };
+    let _ = controls.out_tx.send(Message::Text(create.to_string()));
+}
+
+/// While a call runs, have the assistant say so every `every` when nothing
+/// else is going on.
+async fn report(controls: Controls, name: String, status: Arc<Mutex<Option<String>>>, every: Duration) {
+    let mut tick = tokio::time::interval_at(tokio::time::Instant::now() + every, every);
+    loop {
+        tick.tick().await;
+        if !PENDING.lock().unwrap().is_empty() {
+            continue;
+        }
+        let claimed = {
+            let mut st = controls.state.lock().unwrap();
+            if st.timeline.is_open(Track::User) {
+                None
+            } else {
+                st.responses.try_ask()
+            }
+        };
+        let Some(event_id) = claimed else { continue };
+        let what = status.lock().unwrap().clone().unwrap_or_else(|| format!("running {name}"));
+        speak(
+            &controls,
+            event_id,
+            &format!("In a few words, tell the user you are still working on it ({what}). Say nothing else."),
+            "tool_status",
+        );
+    }
+}
+
+/// Run `tool`'s command with `args` on stdin; stderr lines go to `status`.
+/// The process is killed on timeout or interrupt.
+async fn run(
+    tool: &Tool,
+    args: &str,
+    limit: Duration,
+    status: &Arc<Mutex<Option<String>>>,
+    mut cancelled: watch::Receiver<u64>,
+) -> Result<String, String> {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = tokio::process::Command::new(shell)
+        .arg(flag)
+        .arg(&tool.command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    // Commands that ignore their arguments may exit before reading them
+    if let Some(mut pipe) = child.stdin.take() {
+        let _ = pipe.write_all(args.as_bytes()).await;
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let (name, status) = (tool.name.clone(), status.clone());
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let line = line.trim().to_string();
+                if !line.is_empty() {
+                    output::notice("tool", &format!("{name}: {line}"));
+                    *status.lock().unwrap() = Some(line);
+                }
+            }
+        });
+    }
+    let mut stdout = child.stdout.take().ok_or("no stdout")?;
+    let work = async {
+        let mut out = Vec::new();
+        stdout.read_to_end(&mut out).await?;
+        let exit = child.wait().await?;
+        Ok::<_, std::io::Error>((exit, out))
+    };
+    tokio::select! {
+        done = tokio::time::timeout(limit, work) => match done {
+            Ok(Ok((exit, out))) if exit.success() => Ok(String::from_utf8_lossy(&out).trim().to_string()),
+            Ok(Ok((exit, _))) => Err(format!("exit status {exit}")),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(format!("timed out after {}s", limit.as_secs())),
+        },
+        _ = cancelled.changed() => Err("stopped: the user interrupted".into()),
+    }
+}
+
+/// Run a tool plugin off the runtime; it stops itself after `limit`, an
+/// interrupt only stops waiting for it.
+async fn run_plugin(
+    plugin: &Arc<Plugin>,
+    args: &str,
+    limit: Duration,
+    mut cancelled: watch::Receiver<u64>,
+) -> Result<String, String> {
+    let (plugin, args) = (plugin.clone(), args.to_string());
+    let work = tokio::task::spawn_blocking(move || plugins::run(&plugin, &args, limit));
+    tokio::select! {
+        done = work => done.map_err(|e| e.to_string())?,
+        _ = cancelled.changed() => Err("stopped: the user interrupted".into()),
+    }
+}
@@ -0,0 +1,53 @@
+// Bytes over the WebSocket per direction (message payloads), and how much
+// of that is raw PCM, so the cost of the JSON + base64 framing is visible.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static SENT: AtomicU64 = AtomicU64::new(0);
+static RECEIVED: AtomicU64 = AtomicU64::new(0);
+static SENT_AUDIO: AtomicU64 = AtomicU64::new(0);
+static RECEIVED_AUDIO: AtomicU64 = AtomicU64::new(0);
+
+pub fn sent(wire: usize) {
+    SENT.fetch_add(wire as u64, Ordering::Relaxed);
+}
+
+pub fn received(wire: usize) {
+    RECEIVED.fetch_add(wire as u64, Ordering::Relaxed);
+}
+
+/// Raw PCM bytes carried by messages counted with `sent`.
+pub fn sent_audio(pcm: usize) {
+    SENT_AUDIO.fetch_add(pcm as u64, Ordering::Relaxed);
+}
+
+/// Raw PCM bytes carried by messages counted with `received`.
+pub fn received_audio(pcm: usize) {
+    RECEIVED_AUDIO.fetch_add(pcm as u64, Ordering::Relaxed);
+}
+
+fn human(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} kB", b as f64 / (1 << 10) as f64),
+        b => format!("{b} B"),
+    }
+}
+
+fn direction(wire: u64, audio: u64) -> String {
+    if audio == 0 {
+        return human(wire);
+    }
+    let overhead = (wire as f64 / audio as f64 - 1.0) * 100.0;
+    format!("{} (audio {}, framing {overhead:+.0}%)", human(wire), human(audio))
+}
+
+/// e.g. "sent 1.2 MB (audio 0.9 MB, framing +34%), received …"
+pub fn summary() -> String {
+    let load = |c: &AtomicU64| c.load(Ordering::Relaxed);
+    format!(
+        "sent {}, received {}",
+        direction(load(&SENT), load(&SENT_AUDIO)),
+        direction(load(&RECEIVED), load(&RECEIVED_AUDIO))
+    )
+}
@@ -0,0 +1,195 @@
+// Local speech for replies that come without audio. When the server sends a
+// reply as text only (its audio failed, or the reply was text by design),
+// TTS_FALLBACK speaks it on this machine, through the same speaker buffer
+// as the server's audio, so barge-in and the mic gate treat it alike:
+//   say      macOS `say`
+//   espeak   `espeak-ng`
+//   piper    `piper` with the voice model in PIPER_MODEL
+//   command  TTS_CMD, run through the shell: the text on stdin, a PCM16
+//            mono WAV file on stdout
+// TTS_TEXT_REPLIES=true asks the server for text replies only and speaks
+// every reply locally: far less data, at the cost of the realtime voice.
+// Speech the user starts while a reply is being synthesized drops it.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde_json::Value;
+
+use crate::resample::Resampler;
+use crate::{output, wav};
+
+/// A speech synthesizer: a program that turns text into a WAV file.
+enum Engine {
+    Say,
+    Espeak,
+    Piper(String),
+    Command(String),
+}
+
+struct Config {
+    engine: Engine,
+    sr_hz: u32,
+    spk_buf: Arc<Mutex<VecDeque<i16>>>,
+    text_replies: bool,
+}
+
+// Responses that brought audio of their own (the latest few)
+static VOICED: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+// Bumped when the user starts speaking: synthesis begun before is dropped
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static CONFIG: OnceLock<Option<Config>> = OnceLock::new();
+
+fn config() -> Option<&'static Config> {
+    CONFIG.get().and_then(Option::as_ref)
+}
+
+/// Read TTS_FALLBACK, TTS_CMD, PIPER_MODEL and TTS_TEXT_REPLIES; call once
+/// the speaker buffer exists. Off in text-only sessions.
+pub fn init(sr_hz: u32, spk_buf: &Arc<Mutex<VecDeque<i16>>>, text_only: bool) -> Result<(), String> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let engine = match var("TTS_FALLBACK").as_deref().map(str::trim) {
+        None | Some("off") => None,
+        Some("say") => Some(Engine::Say),
+        Some("espeak") => Some(Engine::Espeak),
+        Some("piper") => Some(Engine::Piper(var("PIPER_MODEL").ok_or("TTS_FALLBACK=piper needs PIPER_MODEL")?)),
+        Some("command") => Some(Engine::Command(var("TTS_CMD").ok_or("TTS_FALLBACK=command needs TTS_CMD")?)),
+        Some(other) => return Err(format!("TTS_FALLBACK: {other} (use off, say, espeak, piper or command)")),
+    };
+    let text_replies = crate::env_flag("TTS_TEXT_REPLIES", false);
+    if text_replies && engine.is_none() {
+        return Err("TTS_TEXT_REPLIES needs TTS_FALLBACK".into());
+    }
+    let cfg = engine
+        .filter(|_| !text_only)
+        .map(|engine| Config { engine, sr_hz, spk_buf: spk_buf.clone(), text_replies });
+    let _ = CONFIG.set(cfg);
+    Ok(())
+}
+
+/// TTS_TEXT_REPLIES: the session asks for text replies only.
+pub fn text_replies() -> bool {
+    config().is_some_and(|c| c.text_replies)
+}
+
+/// Follow a server event: speak finished replies that had no audio.
+pub fn on_event(evt: &Value) {
+    let Some(cfg) = config() else { return };
+    match evt["type"].as_str().unwrap_or("") {
+        "input_audio_buffer.speech_started" => {
+            GENERATION.fetch_add(1, Ordering::Relaxed);
+        }
+        "response.audio.delta" => {
+            let id = evt["response_id"].as_str().unwrap_or("");
+            let mut voiced = VOICED.lock().unwrap();
+            if !voiced.iter().any(|v| v == id) {
+                if voiced.len() == 8 {
+                    voiced.pop_front();
+                }
+                voiced.push_back(id.to_string());
+            }
+        }
+        "response.done" => {
+            let response = &evt["response"];
+            let id = response["id"].as_str().unwrap_or("");
+            // Out-of-band responses without audio (meeting summaries) are text by design
+            if response["status"] == "cancelled"
+                || !response["metadata"].is_null()
+                || VOICED.lock().unwrap().iter().any(|v| v == id)
+            {
+                return;
+            }
+            let text = crate::hooks::response_text(evt);
+            if text.trim().is_empty() {
+                return;
+            }
+            let generation = GENERATION.load(Ordering::Relaxed);
+            std::thread::spawn(move || match cfg.engine.synthesize(&text, cfg.sr_hz) {
+                Ok(samples) if GENERATION.load(Ordering::Relaxed) == generation => {
+                    cfg.spk_buf.lock().unwrap().extend(samples);
+                }
+                Ok(_) => {}
+                Err(e) => output::notice("tts", &format!("cannot speak the reply: {e}")),
+            });
+        }
+        _ => {}
+    }
+}
+
+impl Engine {
+    /// Speak `text` into samples at `sr_hz`.
+    fn synthesize(&self, text: &str, sr_hz: u32) -> Result<Vec<i16>, String> {
+        static N: AtomicU64 = AtomicU64::new(0);
+        // Engines that write a file rather than stdout get a temporary one
+        let file: PathBuf = std::env::temp_dir().join(format!(
+            "parlar-tts-{}-{}.wav",
+            std::process::id(),
+            N.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut cmd = match self {
+            Engine::Say => {
+                let mut c = Command::new("say");
+                c.arg("-o").arg(&file).arg("--file-format=WAVE").arg(format!("--data-format=LEI16@{sr_hz}"));
+                c
+            }
+            Engine::Espeak => {
+                let mut c = Command::new("espeak-ng");
+                c.args(["--stdin", "--stdout"]);
+                c
+            }
+            Engine::Piper(model) => {
+                let mut c = Command::new("piper");
+                c.arg("--model").arg(model).arg("--output_file").arg(&file);
+                c
+            }
+            Engine::Command(line) => {
+                let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+                let mut c = Command::new(shell);
+                c.arg(flag).arg(line);
+                c
+            }
+        };
+        let name = match self {
+            Engine::Say => "say",
+            Engine::Espeak => "espeak-ng",
+            Engine::Piper(_) => "piper",
+            Engine::Command(_) => "TTS_CMD",
+        };
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("{name}: {e}"))?;
+        // Written from another thread, so a long reply cannot block on a full stdout
+        if let Some(mut stdin) = child.stdin.take() {
+            let text = text.to_string();
+            std::thread::spawn(move || stdin.write_all(text.as_bytes()));
+        }
+        let out = child.wait_with_output().map_err(|e| format!("{name}: {e}"))?;
+        if !out.status.success() {
+            let _ = std::fs::remove_file(&file);
+            return Err(format!("{name}: exit status {}", out.status));
+        }
+        let bytes = match self {
+            Engine::Say | Engine::Piper(_) => {
+                let bytes = std::fs::read(&file).map_err(|e| format!("{name}: {e}"));
+                let _ = std::fs::remove_file(&file);
+                bytes?
+            }
+            Engine::Espeak | Engine::Command(_) => out.stdout,
+        };
+        let (rate, samples) =
+            wav::read_pcm16_mono(&bytes).ok_or_else(|| format!("{name}: no PCM16 mono WAV audio"))?;
+        if rate == sr_hz {
+            return Ok(samples);
+        }
+        let mut resampled = Vec::with_capacity(samples.len() * sr_hz as usize / rate.max(1) as usize + 1);
+        Resampler::new(rate, sr_hz).process(&samples, &mut resampled);
+        Ok(resampled)
+    }
+}
@@ -0,0 +1,217 @@
+// Turn-taking settings: the server's `turn_detection` object plus the local
+// adaptive response delays. Read from the environment with validation, and
+// re-read on config reload so they can be retuned while running.
+//
+// The adaptive delay depends on how the user's turn ended: a question gets
+// the quickest answer, a finished statement a short pause, and a turn that
+// trails off (a conjunction, a comma, a filler word) or has no transcript
+// the long pause, since the user is likely to go on. The transcript usually
+// completes after the commit, so the scheduler waits for it (bounded by
+// RESP_TRANSCRIPT_WAIT_MS); the wait counts toward the delay.
+//
+// A user who starts speaking again within TURN_MERGE_MS of a commit was
+// only pausing: the reply still pending for the first part is not created
+// (or, already under way, is cut off as by any barge-in), and the reply to
+// the next commit answers both parts together.
+//
+// A turn committed while another reply is under way (a tool's follow-up, a
+// reminder, a cut-off that is still cooling down) is handled by TURN_QUEUE:
+// queued until that reply is done (the default), answered at once by
+// cutting it off, or dropped. Queued turns share one reply.
+
+use std::str::FromStr;
+
+use serde_json::{Value, json};
+
+use crate::config;
+use crate::language::{self, LANGS, Lang};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TurnConfig {
+    /// `semantic_vad` instead of silence-based `server_vad`.
+    pub semantic: bool,
+    /// semantic_vad: low | medium | high | auto
+    pub eagerness: String,
+    /// server_vad: activation threshold, 0–1
+    pub threshold: f32,
+    /// server_vad: audio kept before detected speech
+    pub prefix_padding_ms: u64,
+    /// server_vad: silence before the turn is committed
+    pub silence_ms: u64,
+    /// server_vad: commit after this much silence even without speech end (optional)
+    pub idle_timeout_ms: Option<u64>,
+    /// Extra local delay after a question / a finished statement / an
+    /// unfinished or unknown end (server_vad only).
+    pub resp_delay_question_ms: u64,
+    pub resp_delay_short_ms: u64,
+    pub resp_delay_long_ms: u64,
+    /// How long to wait for the turn's transcript before choosing the delay.
+    pub transcript_wait_ms: u64,
+    /// Speech starting this soon after a commit continues the turn: its
+    /// reply is not created and the next commit answers both (0: off).
+    pub merge_ms: u64,
+    /// A turn committed while a reply is under way: `queue` answers it once
+    /// the reply is done, `latest` cuts the reply off for it, `drop` ignores it.
+    pub queue: String,
+}
+
+impl Default for TurnConfig {
+    fn default() -> Self {
+        TurnConfig {
+            semantic: false,
+            eagerness: "auto".into(),
+            threshold: 0.55,
+            prefix_padding_ms: 100,
+            silence_ms: 350,
+            idle_timeout_ms: None,
+            resp_delay_question_ms: 100,
+            resp_delay_short_ms: 200,
+            resp_delay_long_ms: 700,
+            transcript_wait_ms: 800,
+            merge_ms: 1500,
+            queue: "queue".into(),
+        }
+    }
+}
+
+fn parse<T: FromStr + PartialOrd + Copy + std::fmt::Display>(
+    name: &str,
+    default: T,
+    min: T,
+    max: T,
+) -> Result<T, String> {
+    let Some(raw) = config::var(name) else { return Ok(default) };
+    let v: T = raw.trim().parse().map_err(|_| format!("{name}={raw} is not a number"))?;
+    if v < min || v > max {
+        return Err(format!("{name}={v} is out of range ({min}–{max})"));
+    }
+    Ok(v)
+}
+
+impl TurnConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let d = TurnConfig::default();
+        let semantic = match config::var("TURN_MODE").as_deref().unwrap_or("server_vad") {
+            "server_vad" => false,
+            "semantic_vad" => true,
+            other => return Err(format!("TURN_MODE must be server_vad or semantic_vad (got {other})")),
+        };
+        let eagerness = config::var("TURN_EAGERNESS").unwrap_or(d.eagerness);
+        if !["low", "medium", "high", "auto"].contains(&eagerness.as_str()) {
+            return Err(format!("TURN_EAGERNESS must be low, medium, high or auto (got {eagerness})"));
+        }
+        let queue = config::var("TURN_QUEUE").unwrap_or(d.queue);
+        if !["queue", "latest", "drop"].contains(&queue.as_str()) {
+            return Err(format!("TURN_QUEUE must be queue, latest or drop (got {queue})"));
+        }
+        let idle_timeout_ms = match config::var("TURN_IDLE_TIMEOUT_MS") {
+            Some(_) => Some(parse("TURN_IDLE_TIMEOUT_MS", 0, 1000, 600_000)?),
+            None => None,
+        };
+        Ok(TurnConfig {
+            semantic,
+            eagerness,
+            threshold: parse("TURN_VAD_THRESH", d.threshold, 0.0, 1.0)?,
+            prefix_padding_ms: parse("TURN_PREFIX_MS", d.prefix_padding_ms, 0, 5000)?,
+            silence_ms: parse("TURN_SIL_MS", d.silence_ms, 50, 10_000)?,
+            idle_timeout_ms,
+            resp_delay_question_ms: parse("RESP_DELAY_QUESTION_MS", d.resp_delay_question_ms, 0, 10_000)?,
+            resp_delay_short_ms: parse("RESP_DELAY_SHORT_MS", d.resp_delay_short_ms, 0, 10_000)?,
+            resp_delay_long_ms: parse("RESP_DELAY_LONG_MS", d.resp_delay_long_ms, 0, 10_000)?,
+            transcript_wait_ms: parse("RESP_TRANSCRIPT_WAIT_MS", d.transcript_wait_ms, 0, 5000)?,
+            merge_ms: parse("TURN_MERGE_MS", d.merge_ms, 0, 10_000)?,
+            queue,
+        })
+    }
+
+    /// The `turn_detection` session object. Responses are always created by
+    /// the client, so `create_response` is off.
+    pub fn to_json(&self) -> Value {
+        if self.semantic {
+            return json!({
+                "type": "semantic_vad",
+                "eagerness": self.eagerness,
+                "create_response": false
+            });
+        }
+        let mut td = json!({
+            "type": "server_vad",
+            "threshold": self.threshold,
+            "silence_duration_ms": self.silence_ms,
+            "prefix_padding_ms": self.prefix_padding_ms,
+            "create_response": false
+        });
+        if let Some(ms) = self.idle_timeout_ms {
+            td["idle_timeout_ms"] = json!(ms);
+        }
+        td
+    }
+
+    /// Local delay before answering a turn that ended like `ending`.
+    pub fn delay_for(&self, ending: Ending) -> u64 {
+        if self.semantic {
+            // semantic VAD already waited for a complete thought
+            return 0;
+        }
+        match ending {
+            Ending::Question => self.resp_delay_question_ms,
+            Ending::Statement => self.resp_delay_short_ms,
+            Ending::Unfinished | Ending::Unknown => self.resp_delay_long_ms,
+        }
+    }
+}
+
+/// How a user turn ended, judged from its transcript.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ending {
+    Question,
+    Statement,
+    /// Trails off: the user is probably still thinking.
+    Unfinished,
+    /// No transcript, or no cue either way.
+    Unknown,
+}
+
+impl Ending {
+    pub fn name(self) -> &'static str {
+        match self {
+            Ending::Question => "question",
+            Ending::Statement => "statement",
+            Ending::Unfinished => "unfinished",
+            Ending::Unknown => "unknown",
+        }
+    }
+
+    /// Classify `text` using the word lists of `lang`, or of every language
+    /// when it is not known.
+    pub fn classify(text: &str, lang: Option<&Lang>) -> Ending {
+        let text = text.trim();
+        let langs: &[Lang] = match lang {
+            Some(l) => std::slice::from_ref(l),
+            None => LANGS,
+        };
+        let words: Vec<String> = language::words(text).collect();
+        let (Some(first), Some(last)) = (words.first(), words.last()) else {
+            return Ending::Unknown;
+        };
+        if text.ends_with('?') || text.starts_with('¿') {
+            return Ending::Question;
+        }
+        if text.ends_with([',', ';', ':', '-', '…']) || text.ends_with("...") {
+            return Ending::Unfinished;
+        }
+        if langs.iter().any(|l| l.dangling.contains(&last.as_str())) {
+            return Ending::Unfinished;
+        }
+        if text.ends_with(['.', '!']) {
+            return Ending::Statement;
+        }
+        // Without terminal punctuation: transcripts often drop the question
+        // mark on spoken questions
+        if langs.iter().any(|l| l.question_words.contains(&first.as_str())) {
+            Ending::Question
+        } else {
+            Ending::Unknown
+        }
+    }
+}
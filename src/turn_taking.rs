@@ -0,0 +1,125 @@
+// Turn-taking: what the reply lifecycle (responses.rs) does with each thing
+// that happens in a conversation. The user finishing a turn or typing a
+// message, an interrupt, the server finishing or refusing a reply, a tool,
+// timer or reminder wanting to speak: each is decided here, and main.rs,
+// interrupt.rs, control.rs and tools.rs only act on the result (send the
+// events, update the screen). Nothing here does I/O or reads the clock, so
+// tests/turn_taking.rs drives this same code through random orders of
+// events instead of a copy of it.
+
+use std::time::{Duration, Instant};
+
+use crate::responses::{Cancel, Responses};
+
+/// TURN_QUEUE: what a turn committed while a reply is under way gets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// Answered once the reply under way is done (the default).
+    Queue,
+    /// The reply under way is cut off and the turn answered instead.
+    Latest,
+    /// Not answered.
+    Drop,
+}
+
+impl Policy {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "latest" => Policy::Latest,
+            "drop" => Policy::Drop,
+            _ => Policy::Queue,
+        }
+    }
+}
+
+/// What a committed turn gets.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Turn {
+    /// A reply, asked for with this event id.
+    Answer(String),
+    /// It waits for the reply under way.
+    Queued,
+    /// It waits, and the reply under way is to be cut off.
+    CutOff,
+    /// Nothing.
+    Dropped,
+}
+
+/// The user finished a turn (`input_audio_buffer.committed`, once the
+/// response delay is over).
+pub fn committed(r: &mut Responses, policy: Policy) -> Turn {
+    if let Some(event_id) = r.try_ask() {
+        return Turn::Answer(event_id);
+    }
+    match policy {
+        Policy::Queue => {
+            r.queued = true;
+            Turn::Queued
+        }
+        Policy::Latest => {
+            r.queued = true;
+            Turn::CutOff
+        }
+        Policy::Drop => Turn::Dropped,
+    }
+}
+
+/// The user typed a message: the event id of its reply, or `None` when it
+/// waits for the reply under way, which is to be cut off. Should the cut
+/// be held back by its cooldown, the message is answered after that reply.
+pub fn typed(r: &mut Responses) -> Option<String> {
+    let event_id = r.try_ask();
+    if event_id.is_none() {
+        r.queued = true;
+    }
+    event_id
+}
+
+/// The assistant is speaking or about to, so speech or a barge-in keyword
+/// cuts it off; `playing` is whether its audio is still coming out.
+pub fn speaking(r: &Responses, playing: bool) -> bool {
+    r.busy() || playing
+}
+
+/// Cut off the reply, unless the cooldown since the last cut-off (`last`,
+/// on any path) is still running at `now`.
+pub fn cut_off(r: &mut Responses, last: &mut Option<Instant>, now: Instant, cooldown: Duration) -> Option<Cancel> {
+    if last.is_some_and(|t| now.saturating_duration_since(t) < cooldown) {
+        return None;
+    }
+    *last = Some(now);
+    Some(r.cancel())
+}
+
+/// What `response.done` leads to.
+pub struct Done {
+    /// It ended the reply under way, not one cut off earlier.
+    pub ended: bool,
+    /// The event id of the reply asked for turns queued meanwhile.
+    pub next: Option<String>,
+}
+
+/// `response.done` for response `id`.
+pub fn done(r: &mut Responses, id: Option<&str>) -> Done {
+    let ended = r.done(id);
+    Done { ended, next: next(r) }
+}
+
+/// An `error` naming client event `event_id`; returns the event id of the
+/// reply asked for a turn queued behind the refused one.
+pub fn refused(r: &mut Responses, event_id: &str) -> Option<String> {
+    r.refused(event_id);
+    next(r)
+}
+
+/// A reply claimed with `Responses::try_ask` is not asked for after all;
+/// returns the event id of the reply asked for a turn queued behind it.
+pub fn withdraw(r: &mut Responses, event_id: &str) -> Option<String> {
+    r.withdraw(event_id);
+    next(r)
+}
+
+// The reply for queued turns, once nothing else is under way
+fn next(r: &mut Responses) -> Option<String> {
+    r.take_queued().then(|| r.ask())
+}
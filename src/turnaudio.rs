@@ -0,0 +1,82 @@
+// Turn audio: the mic audio as forwarded to the server, kept for a while so
+// what works on a turn's audio (speaker identification, transcription
+// retries, the noise filter) can cut it out by the server's speech start
+// and end (`audio_start_ms`, `audio_end_ms`). The server counts from the
+// start of its session, so the count starts over when the session moves to
+// a new connection (session.rs).
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde_json::Value;
+
+struct Audio {
+    sr_hz: u32,
+    // at most `cap` samples; `start` is the session index of samples[0]
+    samples: VecDeque<i16>,
+    cap: usize,
+    start: u64,
+    speech_start_ms: Option<u64>,
+}
+
+static AUDIO: Mutex<Option<Audio>> = Mutex::new(None);
+
+/// Keep the last `secs` of forwarded audio; each user calls it at startup,
+/// and the longest wanted is kept. Without a call nothing is kept.
+pub fn keep(sr_hz: u32, secs: u64) {
+    let cap = (sr_hz as u64 * secs) as usize;
+    let mut guard = AUDIO.lock().unwrap();
+    match guard.as_mut() {
+        Some(a) => a.cap = a.cap.max(cap),
+        None => {
+            *guard = Some(Audio { sr_hz, samples: VecDeque::new(), cap, start: 0, speech_start_ms: None });
+        }
+    }
+}
+
+/// Mic samples exactly as forwarded to the server.
+pub fn on_mic(samples: &[i16]) {
+    let mut guard = AUDIO.lock().unwrap();
+    let Some(a) = guard.as_mut() else { return };
+    a.samples.extend(samples.iter().copied());
+    let excess = a.samples.len().saturating_sub(a.cap);
+    if excess > 0 {
+        a.samples.drain(..excess);
+        a.start += excess as u64;
+    }
+}
+
+/// Follow a server event, before the users of `turn` do.
+pub fn on_event(evt: &Value) {
+    if evt["type"] != "input_audio_buffer.speech_started" {
+        return;
+    }
+    if let Some(a) = AUDIO.lock().unwrap().as_mut() {
+        a.speech_start_ms = evt["audio_start_ms"].as_u64();
+    }
+}
+
+/// The audio of the turn an `input_audio_buffer.speech_stopped` event
+/// ends, from the speech start before it; empty when it is not kept.
+pub fn turn(evt: &Value) -> Vec<i16> {
+    let guard = AUDIO.lock().unwrap();
+    let (Some(a), Some(end_ms)) = (guard.as_ref(), evt["audio_end_ms"].as_u64()) else {
+        return Vec::new();
+    };
+    let start_ms = a.speech_start_ms.unwrap_or(end_ms);
+    let to_idx = |ms: u64| (ms * a.sr_hz as u64 / 1000).saturating_sub(a.start) as usize;
+    let (from, to) = (to_idx(start_ms), to_idx(end_ms).min(a.samples.len()));
+    if from >= to {
+        return Vec::new();
+    }
+    a.samples.range(from..to).copied().collect()
+}
+
+/// The session moved to a new connection, whose clock starts at 0.
+pub fn reset() {
+    if let Some(a) = AUDIO.lock().unwrap().as_mut() {
+        a.samples.clear();
+        a.start = 0;
+        a.speech_start_ms = None;
+    }
+}
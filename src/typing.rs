@@ -0,0 +1,67 @@
+// Keyboard noise. The clack of a mechanical keyboard is often loud enough
+// for the server's voice detection to take it for speech, so pressing a
+// control key, typing the PIN or searching the scrollback starts a turn
+// the assistant then answers. With TYPING_SUPPRESS_MS set (default 0:
+// off), the mic audio of that long after each key press in the terminal is
+// scaled by TYPING_SUPPRESS_GAIN (default 0: silence) before anything else
+// sees it. The stream itself goes on, so the server's timing is unchanged;
+// the mic watchdog still hears the unscaled audio.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+struct Config {
+    window_ms: u64,
+    gain: f32,
+    start: Instant,
+}
+
+static CONFIG: OnceLock<Option<Config>> = OnceLock::new();
+// Milliseconds from `start` to the latest key press, plus one; 0 for none
+static LAST_KEY: AtomicU64 = AtomicU64::new(0);
+
+fn config() -> Option<&'static Config> {
+    CONFIG.get().and_then(Option::as_ref)
+}
+
+/// Read TYPING_SUPPRESS_MS and TYPING_SUPPRESS_GAIN; call once at startup.
+pub fn init() -> Result<(), String> {
+    let window_ms = match std::env::var("TYPING_SUPPRESS_MS") {
+        Ok(v) => v.trim().parse::<u64>().map_err(|_| format!("TYPING_SUPPRESS_MS: not a number of milliseconds: {v}"))?,
+        Err(_) => 0,
+    };
+    let gain = match std::env::var("TYPING_SUPPRESS_GAIN") {
+        Ok(v) => v
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|g| (0.0..=1.0).contains(g))
+            .ok_or_else(|| format!("TYPING_SUPPRESS_GAIN: {v} (use 0 to 1)"))?,
+        Err(_) => 0.0,
+    };
+    let _ = CONFIG.set((window_ms > 0).then(|| Config { window_ms, gain, start: Instant::now() }));
+    Ok(())
+}
+
+/// A key was pressed in the terminal.
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn key_pressed() {
+    if let Some(cfg) = config() {
+        LAST_KEY.store(cfg.start.elapsed().as_millis() as u64 + 1, Ordering::Relaxed);
+    }
+}
+
+/// Scale a mic chunk that falls in the window after a key press; returns
+/// whether it did.
+pub fn suppress(chunk: &mut [i16]) -> bool {
+    let Some(cfg) = config() else { return false };
+    let last = LAST_KEY.load(Ordering::Relaxed);
+    if last == 0 || (cfg.start.elapsed().as_millis() as u64 + 1).saturating_sub(last) > cfg.window_ms {
+        return false;
+    }
+    for s in chunk.iter_mut() {
+        *s = (*s as f32 * cfg.gain) as i16;
+    }
+    true
+}
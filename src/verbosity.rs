@@ -0,0 +1,66 @@
+// Verbosity presets: how much the assistant says, set with one word instead
+// of rewriting the instructions. Each preset adds a line to the session
+// instructions and caps the reply length (`max_response_output_tokens`).
+
+use serde_json::{Value, json};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    Terse,
+    #[default]
+    Normal,
+    Chatty,
+}
+
+const ALL: [Verbosity; 3] = [Verbosity::Terse, Verbosity::Normal, Verbosity::Chatty];
+
+impl Verbosity {
+    pub fn parse(name: &str) -> Option<Self> {
+        ALL.into_iter().find(|v| v.name() == name.trim().to_ascii_lowercase())
+    }
+
+    /// From `VERBOSITY`.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("VERBOSITY") {
+            Ok(v) if !v.trim().is_empty() => {
+                Self::parse(&v).ok_or(format!("VERBOSITY must be terse, normal or chatty (got {v})"))
+            }
+            _ => Ok(Verbosity::Normal),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Verbosity::Terse => "terse",
+            Verbosity::Normal => "normal",
+            Verbosity::Chatty => "chatty",
+        }
+    }
+
+    /// The preset after this one, wrapping around.
+    pub fn next(self) -> Self {
+        ALL[(ALL.iter().position(|v| *v == self).unwrap_or(0) + 1) % ALL.len()]
+    }
+
+    /// Suffix for the session instructions.
+    pub fn instruction_hint(self) -> &'static str {
+        match self {
+            Verbosity::Terse => {
+                " Keep every answer to one or two short sentences. No preamble, no recap, no follow-up questions."
+            }
+            Verbosity::Normal => "",
+            Verbosity::Chatty => {
+                " Feel free to elaborate: explain your reasoning, give an example, and suggest what to explore next."
+            }
+        }
+    }
+
+    /// `max_response_output_tokens` for the session.
+    pub fn max_tokens(self) -> Value {
+        match self {
+            // Spoken audio counts too; this still leaves room for a few sentences
+            Verbosity::Terse => json!(300),
+            Verbosity::Normal | Verbosity::Chatty => json!("inf"),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// Voice commands. A finalized user turn that starts with the wake word
+// (VOICE_COMMAND_WAKE, default `parlar` plus the spellings transcription
+// tends to produce) and matches one of these phrases is carried out locally
+// and removed from the conversation, so the model never answers it:
+//   mute [yourself] / stop listening        microphone muted
+//   switch to / speak <language>            conversation language
+//   [change|switch|use] voice [to] <name>   assistant voice (reconnects)
+//   quit / exit / goodbye                   end parlar
+//   remember [that] <fact>                  add the fact to MEMORY_FILE
+// A leading "hey"/"ok" and a trailing "please" are ignored. Anything else
+// after the wake word is an ordinary turn. VOICE_COMMANDS=false turns this
+// off.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use regex::Regex;
+
+use crate::control::Controls;
+use crate::earcon::{self, Earcon};
+use crate::language::{self, LANGS, Lang};
+use crate::{childsafe, init, memory, output, shutdown};
+
+pub enum Command {
+    Mute,
+    Language(&'static Lang),
+    Voice(&'static str),
+    Remember(String),
+    Quit,
+}
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Mute => "mute",
+            Command::Language(_) => "language",
+            Command::Voice(_) => "voice",
+            Command::Remember(_) => "remember",
+            Command::Quit => "quit",
+        }
+    }
+}
+
+pub struct Grammar {
+    // wake phrases, as words
+    wake: Vec<Vec<String>>,
+}
+
+impl Grammar {
+    pub fn from_env() -> Option<Self> {
+        if !crate::env_flag("VOICE_COMMANDS", true) {
+            return None;
+        }
+        let wake = std::env::var("VOICE_COMMAND_WAKE").unwrap_or_else(|_| "parlar,parler,parlor".into());
+        let wake: Vec<Vec<String>> = wake
+            .split(',')
+            .map(|w| language::words(w).collect::<Vec<_>>())
+            .filter(|w| !w.is_empty())
+            .collect();
+        (!wake.is_empty()).then_some(Grammar { wake })
+    }
+
+    /// The command in `transcript`, if it is addressed to parlar; `Err` when
+    /// it names a voice or language that does not exist.
+    pub fn parse(&self, transcript: &str) -> Option<Result<Command, String>> {
+        let words: Vec<String> = language::words(transcript).collect();
+        let start = words.iter().take_while(|w| matches!(w.as_str(), "hey" | "ok" | "okay")).count();
+        let words = &words[start..];
+        let wake = self.wake.iter().find(|w| words.starts_with(w))?;
+        let mut rest: Vec<&str> = words[wake.len()..].iter().map(String::as_str).collect();
+        if rest.first() == Some(&"please") {
+            rest.remove(0);
+        }
+        if rest.last() == Some(&"please") {
+            rest.pop();
+        }
+        let last = *rest.last()?;
+        match rest.join(" ").as_str() {
+            "mute" | "mute yourself" | "mute the microphone" | "stop listening" => return Some(Ok(Command::Mute)),
+            "quit" | "exit" | "goodbye" | "bye" | "shut down" => return Some(Ok(Command::Quit)),
+            _ => {}
+        }
+        if rest[0] == "remember" {
+            return remembered(transcript).map(|fact| Ok(Command::Remember(fact)));
+        }
+        if rest.contains(&"voice") {
+            return Some(match init::VOICES.iter().find(|v| **v == last) {
+                Some(v) => Ok(Command::Voice(v)),
+                None => Err(format!("no voice called {last}; voices: {}.", init::VOICES.join(", "))),
+            });
+        }
+        let to_language = matches!(rest[..], ["switch", "to", _] | ["speak", _] | ["change", "language", "to", _]);
+        if to_language {
+            return Some(match LANGS.iter().find(|l| l.name.eq_ignore_ascii_case(last)) {
+                Some(lang) => Ok(Command::Language(lang)),
+                None => Err(format!("no language called {last}.")),
+            });
+        }
+        None
+    }
+}
+
+/// The fact in "… remember that <fact>", as it was transcribed.
+fn remembered(transcript: &str) -> Option<String> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"(?i)\bremember\b[\s,]*(?:that\b)?\s*(.+)").unwrap());
+    let fact = re.captures(transcript)?.get(1)?.as_str();
+    let fact = fact.trim_end_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace());
+    let fact = fact.strip_suffix("please").unwrap_or(fact).trim_end_matches([' ', ',']);
+    (!fact.is_empty()).then(|| fact.to_string())
+}
+
+/// Carry out `command`, with a confirmation tone (the command gets no
+/// spoken reply, so the tone plays even with EARCONS off).
+pub fn execute(command: Command, controls: &Controls) {
+    let confirm = || earcon::play(&controls.cue_buf, Earcon::Command, controls.sr_hz, controls.earcon_gain);
+    match command {
+        Command::Mute => {
+            controls.set_muted(Some(true));
+            if !controls.status_earcons {
+                confirm();
+            }
+        }
+        Command::Language(_) | Command::Voice(_) if childsafe::locked() => {
+            reject("settings are locked; changing them needs the PIN.", controls)
+        }
+        Command::Language(lang) => {
+            controls.set_language(lang);
+            confirm();
+        }
+        Command::Voice(voice) => {
+            controls.set_voice(voice);
+            confirm();
+        }
+        Command::Remember(fact) => match memory::remember(&fact) {
+            Ok(()) => {
+                // Resend the instructions, now with the new fact in front
+                let instructions = controls.state.lock().unwrap().instructions.clone();
+                controls.set_instructions(&instructions);
+                output::notice("memory", &format!("remembered: {fact}"));
+                confirm();
+            }
+            Err(e) => reject(&e, controls),
+        },
+        Command::Quit => {
+            confirm();
+            std::thread::sleep(Duration::from_millis(earcon::duration_ms(Earcon::Command) + 100));
+            shutdown::exit(0);
+        }
+    }
+}
+
+/// A command was recognized but cannot be carried out.
+pub fn reject(why: &str, controls: &Controls) {
+    output::notice("command", why);
+    earcon::play(&controls.cue_buf, Earcon::Error, controls.sr_hz, controls.earcon_gain);
+}
@@ -0,0 +1,55 @@
+// Minimal PCM16 mono WAV writer, and a reader for the files it writes.
+
+/// The complete WAV file for `samples`.
+pub fn pcm16_mono(sr_hz: u32, samples: &[i16]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut f = Vec::with_capacity(44 + samples.len() * 2);
+    f.extend_from_slice(b"RIFF");
+    f.extend_from_slice(&(36 + data_len).to_le_bytes());
+    f.extend_from_slice(b"WAVE");
+    f.extend_from_slice(b"fmt ");
+    f.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    f.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    f.extend_from_slice(&1u16.to_le_bytes()); // mono
+    f.extend_from_slice(&sr_hz.to_le_bytes());
+    f.extend_from_slice(&(sr_hz * 2).to_le_bytes()); // byte rate
+    f.extend_from_slice(&2u16.to_le_bytes()); // block align
+    f.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    f.extend_from_slice(b"data");
+    f.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+        f.extend_from_slice(&s.to_le_bytes());
+    }
+    f
+}
+
+/// Sample rate and samples of a PCM16 mono WAV file.
+pub fn read_pcm16_mono(file: &[u8]) -> Option<(u32, Vec<i16>)> {
+    if file.len() < 12 || &file[..4] != b"RIFF" || &file[8..12] != b"WAVE" {
+        return None;
+    }
+    let (mut sr_hz, mut at) = (None, 12);
+    while at + 8 <= file.len() {
+        let id = &file[at..at + 4];
+        let len = u32::from_le_bytes(file[at + 4..at + 8].try_into().ok()?) as usize;
+        let body = file.get(at + 8..(at + 8 + len).min(file.len()))?;
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let format = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                if format != 1 || channels != 1 || bits != 16 {
+                    return None;
+                }
+                sr_hz = Some(u32::from_le_bytes(body[4..8].try_into().ok()?));
+            }
+            b"data" => {
+                let samples = body.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+                return Some((sr_hz?, samples));
+            }
+            _ => {}
+        }
+        at += 8 + len + len % 2;
+    }
+    None
+}
@@ -0,0 +1,198 @@
+// Audio format on the connection. The Realtime API takes and sends PCM16 at
+// 24 kHz, or G.711 (μ-law or A-law) at 8 kHz; AUDIO_FORMAT picks one for
+// both directions (pcm16, g711_ulaw or g711_alaw; default pcm16) and
+// OUTPUT_AUDIO_FORMAT, if set, another for the replies. Everything local
+// (devices, gating, the speaker buffer, recordings) runs at SR; audio is
+// converted to and from the wire rate here, so SR=16000 or SR=8000 keeps a
+// constrained device at a lower rate end to end, and G.711 sends a sixth of
+// the data of PCM16 (64 instead of 384 kbit/s each way).
+
+use std::sync::OnceLock;
+
+use crate::resample::Resampler;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Pcm16,
+    Ulaw,
+    Alaw,
+}
+
+impl Format {
+    fn parse(name: &str, value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "pcm16" => Ok(Format::Pcm16),
+            "g711_ulaw" => Ok(Format::Ulaw),
+            "g711_alaw" => Ok(Format::Alaw),
+            other => Err(format!("{name}: {other} (use pcm16, g711_ulaw or g711_alaw)")),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Pcm16 => "pcm16",
+            Format::Ulaw => "g711_ulaw",
+            Format::Alaw => "g711_alaw",
+        }
+    }
+
+    fn rate(self) -> u32 {
+        match self {
+            Format::Pcm16 => 24_000,
+            Format::Ulaw | Format::Alaw => 8_000,
+        }
+    }
+
+    fn bytes_per_sample(self) -> usize {
+        if self == Format::Pcm16 { 2 } else { 1 }
+    }
+}
+
+struct Config {
+    sr_hz: u32,
+    input: Format,
+    output: Format,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+fn config() -> &'static Config {
+    CONFIG.get_or_init(|| Config { sr_hz: 24_000, input: Format::Pcm16, output: Format::Pcm16 })
+}
+
+/// Read AUDIO_FORMAT and OUTPUT_AUDIO_FORMAT; call once at startup.
+pub fn init(sr_hz: u32) -> Result<(), String> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let input = match var("AUDIO_FORMAT") {
+        Some(v) => Format::parse("AUDIO_FORMAT", &v)?,
+        None => Format::Pcm16,
+    };
+    let output = match var("OUTPUT_AUDIO_FORMAT") {
+        Some(v) => Format::parse("OUTPUT_AUDIO_FORMAT", &v)?,
+        None => input,
+    };
+    let _ = CONFIG.set(Config { sr_hz, input, output });
+    Ok(())
+}
+
+/// `input_audio_format` for session.update.
+pub fn input_format() -> &'static str {
+    config().input.name()
+}
+
+/// `output_audio_format` for session.update.
+pub fn output_format() -> &'static str {
+    config().output.name()
+}
+
+/// Milliseconds of reply audio in `bytes` of it as sent by the server.
+pub fn output_ms(bytes: u64) -> u64 {
+    let f = config().output;
+    bytes / f.bytes_per_sample() as u64 * 1000 / f.rate() as u64
+}
+
+/// Mic audio at SR to the input format, reusing its buffers.
+#[derive(Default)]
+pub struct Encoder {
+    resampler: Option<Resampler>,
+    buf: Vec<i16>,
+}
+
+impl Encoder {
+    /// The wire bytes of `samples` into `out` (cleared first).
+    pub fn encode(&mut self, samples: &[i16], out: &mut Vec<u8>) {
+        let cfg = config();
+        let samples = if cfg.sr_hz == cfg.input.rate() {
+            samples
+        } else {
+            let r = self.resampler.get_or_insert_with(|| Resampler::new(cfg.sr_hz, cfg.input.rate()));
+            self.buf.clear();
+            r.process(samples, &mut self.buf);
+            &self.buf
+        };
+        match cfg.input {
+            Format::Pcm16 => crate::pcm::le_bytes(samples, out),
+            Format::Ulaw => {
+                out.clear();
+                out.extend(samples.iter().map(|&s| ulaw_encode(s)));
+            }
+            Format::Alaw => {
+                out.clear();
+                out.extend(samples.iter().map(|&s| alaw_encode(s)));
+            }
+        }
+    }
+}
+
+/// Reply audio in the output format to samples at SR, reusing its buffers.
+#[derive(Default)]
+pub struct Decoder {
+    resampler: Option<Resampler>,
+    wire: Vec<i16>,
+    out: Vec<i16>,
+}
+
+impl Decoder {
+    /// The samples at SR of the wire `bytes`; valid until the next call.
+    pub fn decode(&mut self, bytes: &[u8]) -> &[i16] {
+        let cfg = config();
+        self.wire.clear();
+        match cfg.output {
+            Format::Pcm16 => self.wire.extend(bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]]))),
+            Format::Ulaw => self.wire.extend(bytes.iter().map(|&b| ulaw_decode(b))),
+            Format::Alaw => self.wire.extend(bytes.iter().map(|&b| alaw_decode(b))),
+        }
+        if cfg.sr_hz == cfg.output.rate() {
+            return &self.wire;
+        }
+        let r = self.resampler.get_or_insert_with(|| Resampler::new(cfg.output.rate(), cfg.sr_hz));
+        self.out.clear();
+        r.process(&self.wire, &mut self.out);
+        &self.out
+    }
+}
+
+// G.711 as in ITU-T G.711 (the segment tables written out as shifts)
+
+const ULAW_BIAS: i32 = 0x84;
+const ULAW_CLIP: i32 = 32_635;
+
+fn ulaw_encode(s: i16) -> u8 {
+    let sign = if s < 0 { 0x80 } else { 0 };
+    let mag = (s as i32).abs().min(ULAW_CLIP) + ULAW_BIAS;
+    let exponent = (31 - (mag as u32).leading_zeros()).saturating_sub(7).min(7);
+    let mantissa = (mag >> (exponent + 3)) & 0x0F;
+    !(sign | (exponent << 4) as u8 | mantissa as u8)
+}
+
+fn ulaw_decode(b: u8) -> i16 {
+    let b = !b;
+    let exponent = (b >> 4) & 0x07;
+    let mantissa = (b & 0x0F) as i32;
+    let mag = (((mantissa << 3) + ULAW_BIAS) << exponent) - ULAW_BIAS;
+    if b & 0x80 != 0 { -mag as i16 } else { mag as i16 }
+}
+
+fn alaw_encode(s: i16) -> u8 {
+    let (sign, mag) = if s < 0 { (0x00, (-(s as i32) - 1).min(0x7FFF)) } else { (0x80, s as i32) };
+    let mag = mag >> 3;
+    let byte = if mag < 32 {
+        (mag >> 1) as u8
+    } else {
+        let exponent = (31 - (mag as u32).leading_zeros()) - 4;
+        let mantissa = (mag >> exponent) & 0x0F;
+        ((exponent << 4) as u8) | mantissa as u8
+    };
+    (sign | byte) ^ 0x55
+}
+
+fn alaw_decode(b: u8) -> i16 {
+    let b = b ^ 0x55;
+    let exponent = (b >> 4) & 0x07;
+    let mantissa = (b & 0x0F) as i32;
+    let mag = match exponent {
+        0 => (mantissa << 4) + 8,
+        e => ((mantissa << 4) + 0x108) << (e - 1),
+    };
+    if b & 0x80 != 0 { mag as i16 } else { -mag as i16 }
+}
@@ -0,0 +1,242 @@
+// Golden tests for the audio path. A PCM fixture (tests/fixtures/mic_24k.wav:
+// silence, a quiet tone, a loud voiced burst, decaying noise) goes through
+// the stages mic and reply audio take (chunking, metering, gating,
+// resampling, wire conversion) and each result is compared with its file in
+// tests/golden, so a DSP change that alters the audio fails here rather than
+// in someone's ears. After an intended change, rewrite the golden files
+// with `BLESS=1 cargo test --test audio_pipeline` and review their diff.
+
+use std::path::PathBuf;
+use std::sync::Once;
+
+#[allow(dead_code)]
+#[path = "../src/gate.rs"]
+mod gate;
+#[allow(dead_code)]
+#[path = "../src/pcm.rs"]
+mod pcm;
+#[allow(dead_code)]
+#[path = "../src/resample.rs"]
+mod resample;
+#[allow(dead_code)]
+#[path = "../src/wav.rs"]
+mod wav;
+#[allow(dead_code)]
+#[path = "../src/wire.rs"]
+mod wire;
+
+const SR: u32 = 24_000;
+/// 20 ms at SR, the default CHUNK_MS.
+const CHUNK: usize = 480;
+
+fn path(dir: &str, name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join(dir).join(name)
+}
+
+fn fixture() -> Vec<i16> {
+    let file = std::fs::read(path("fixtures", "mic_24k.wav")).expect("fixture");
+    let (sr_hz, samples) = wav::read_pcm16_mono(&file).expect("a PCM16 mono WAV");
+    assert_eq!(sr_hz, SR);
+    samples
+}
+
+fn bytes(samples: &[i16]) -> Vec<u8> {
+    let mut out = Vec::new();
+    pcm::le_bytes(samples, &mut out);
+    out
+}
+
+/// Compare `actual` with the golden file `name`, or write it with BLESS set.
+fn golden(name: &str, actual: &[u8]) {
+    let file = path("golden", name);
+    if std::env::var_os("BLESS").is_some() {
+        std::fs::write(&file, actual).expect("golden file written");
+        return;
+    }
+    let expected = std::fs::read(&file)
+        .unwrap_or_else(|e| panic!("{}: {e} (create it with BLESS=1)", file.display()));
+    if expected != actual {
+        let at = expected.iter().zip(actual).position(|(a, b)| a != b).unwrap_or(expected.len().min(actual.len()));
+        panic!(
+            "{name}: differs from the golden file at byte {at} ({} bytes expected, {} produced); \
+             if the change is intended, rerun with BLESS=1 and review the diff",
+            expected.len(),
+            actual.len()
+        );
+    }
+}
+
+/// The fixture as the mic thread gets it: device callbacks of uneven sizes,
+/// each cut into CHUNK-sample buffers from the pool.
+fn chunks(samples: &[i16]) -> Vec<Vec<i16>> {
+    let pool = pcm::Pool::new(8, CHUNK);
+    let mut out = Vec::new();
+    let mut rest = samples;
+    for size in [441, 1024, 256, 960].iter().cycle() {
+        if rest.is_empty() {
+            break;
+        }
+        let (callback, tail) = rest.split_at((*size).min(rest.len()));
+        pcm::split(callback, CHUNK, &pool, |chunk| out.push(chunk));
+        rest = tail;
+    }
+    out
+}
+
+#[test]
+fn fixture_is_the_wav_writer_output() {
+    let file = std::fs::read(path("fixtures", "mic_24k.wav")).expect("fixture");
+    assert_eq!(wav::pcm16_mono(SR, &fixture()), file);
+}
+
+#[test]
+fn chunking() {
+    let samples = fixture();
+    let chunks = chunks(&samples);
+    assert_eq!(chunks.concat(), samples, "chunking must not lose or reorder samples");
+    assert!(chunks.iter().all(|c| !c.is_empty() && c.len() <= CHUNK));
+    let lengths: Vec<String> = chunks.iter().map(|c| c.len().to_string()).collect();
+    golden("chunk_lengths.txt", format!("{}\n", lengths.join("\n")).as_bytes());
+}
+
+#[test]
+fn metering() {
+    let samples = fixture();
+    let peaks: String = samples.chunks(CHUNK).map(|c| format!("{:.4}\n", pcm::peak_level(c))).collect();
+    golden("peaks.txt", peaks.as_bytes());
+    assert_eq!(pcm::peak_level(&[]), 0.0);
+    assert_eq!(pcm::peak_level(&[i16::MIN]), 1.0);
+}
+
+#[test]
+fn append_event() {
+    let samples = fixture();
+    // The first chunk of the quiet tone
+    let chunk = &samples[SR as usize / 10..SR as usize / 10 + CHUNK];
+    let event = pcm::append_event(&bytes(chunk));
+    let parsed: serde_json::Value = serde_json::from_str(&event).expect("valid JSON");
+    assert_eq!(parsed["type"], "input_audio_buffer.append");
+    golden("append_event.json", format!("{event}\n").as_bytes());
+}
+
+#[test]
+fn gating() {
+    // SAFETY: no other test in this binary reads or writes these variables
+    unsafe {
+        std::env::set_var("INT_ONSET_PEAK", "0.22");
+        std::env::set_var("INT_ONSET_MIN_CHUNKS", "2");
+        std::env::set_var("GATE_BUFFER_MS", "300");
+        std::env::set_var("PREROLL_MS", "200");
+    }
+    let samples = fixture();
+    for mode in ["discard", "buffer", "always"] {
+        // SAFETY: as above
+        unsafe { std::env::set_var("GATE_MODE", mode) };
+        let mut gate = gate::Gate::from_env(20, pcm::Pool::new(16, CHUNK)).expect("gate settings");
+        let mut sent = Vec::new();
+        for (i, chunk) in samples.chunks(CHUNK).enumerate() {
+            let chunk = chunk.to_vec();
+            let peak = pcm::peak_level(&chunk);
+            match i {
+                // Muted through the first silence: pre-roll only
+                0..4 => gate.muted(chunk, false),
+                // The assistant speaks over the quiet tone and into the burst
+                4..20 => gate.feed(chunk, peak, true, &mut |pcm: &[i16]| sent.extend_from_slice(pcm)),
+                _ => gate.feed(chunk, peak, false, &mut |pcm: &[i16]| sent.extend_from_slice(pcm)),
+            }
+        }
+        if mode == "always" {
+            assert_eq!(sent, samples, "GATE_MODE=always passes everything, the muted start as pre-roll");
+        }
+        golden(&format!("gate_{mode}.pcm"), &bytes(&sent));
+    }
+}
+
+#[test]
+fn resampling() {
+    let samples = fixture();
+    for to_hz in [8_000, 16_000, 44_100, 48_000] {
+        let mut r = resample::Resampler::new(SR, to_hz);
+        let mut out = Vec::new();
+        for chunk in samples.chunks(CHUNK) {
+            r.process(chunk, &mut out);
+        }
+        // The last input sample waits for the next block: up to a sample's worth short
+        let expected = samples.len() as u64 * to_hz as u64 / SR as u64;
+        let slack = (to_hz / SR) as usize + 1;
+        assert!(out.len().abs_diff(expected as usize) <= slack, "{to_hz} Hz: {} samples", out.len());
+        golden(&format!("resample_{to_hz}.pcm"), &bytes(&out));
+    }
+}
+
+// RMS level of `tone_hz` at SR after going down to `to_hz`, past the filter's start-up
+fn resampled_level(tone_hz: f64, to_hz: u32) -> f64 {
+    let tone: Vec<i16> = (0..SR as usize)
+        .map(|i| (10_000.0 * (2.0 * std::f64::consts::PI * tone_hz * i as f64 / SR as f64).sin()) as i16)
+        .collect();
+    let mut r = resample::Resampler::new(SR, to_hz);
+    let mut out = Vec::new();
+    for chunk in tone.chunks(CHUNK) {
+        r.process(chunk, &mut out);
+    }
+    let settled = &out[out.len() / 4..];
+    (settled.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / settled.len() as f64).sqrt()
+}
+
+#[test]
+fn resampling_filters_above_nyquist() {
+    let full = 10_000.0 / 2f64.sqrt();
+    for to_hz in [8_000, 16_000] {
+        let nyquist = to_hz as f64 / 2.0;
+        // Folded back, these would land at 2 kHz and 6 kHz, well inside the band
+        let above = resampled_level(nyquist + 2_000.0, to_hz);
+        assert!(above < full * 0.01, "{to_hz} Hz: a tone above Nyquist kept {:.1} dB", 20.0 * (above / full).log10());
+        let below = resampled_level(nyquist * 0.5, to_hz);
+        assert!((below / full - 1.0).abs() < 0.05, "{to_hz} Hz: a tone in the band came out at {below:.0}");
+    }
+}
+
+// The wire format is read once per process: μ-law out, A-law back
+fn wire_setup() {
+    static SETUP: Once = Once::new();
+    SETUP.call_once(|| {
+        // SAFETY: only the wire tests read these, after this has run
+        unsafe {
+            std::env::set_var("AUDIO_FORMAT", "g711_ulaw");
+            std::env::set_var("OUTPUT_AUDIO_FORMAT", "g711_alaw");
+        }
+        wire::init(SR).expect("wire settings");
+    });
+}
+
+#[test]
+fn wire_encode() {
+    wire_setup();
+    assert_eq!(wire::input_format(), "g711_ulaw");
+    let mut encoder = wire::Encoder::default();
+    let (mut out, mut all) = (Vec::new(), Vec::new());
+    for chunk in fixture().chunks(CHUNK) {
+        encoder.encode(chunk, &mut out);
+        // 20 ms at 8 kHz, one byte a sample
+        assert!(out.len().abs_diff(160) <= 1, "{} bytes for a 20 ms chunk", out.len());
+        all.extend_from_slice(&out);
+    }
+    golden("wire_ulaw.bin", &all);
+}
+
+#[test]
+fn wire_decode() {
+    wire_setup();
+    assert_eq!(wire::output_format(), "g711_alaw");
+    // Every A-law code, twice, so each is heard at the wire rate
+    let codes: Vec<u8> = (0..=255u8).flat_map(|b| [b, b]).collect();
+    let mut decoder = wire::Decoder::default();
+    let mut out = Vec::new();
+    for block in codes.chunks(160) {
+        out.extend_from_slice(decoder.decode(block));
+    }
+    // 8 kHz to SR, but for the last code, which waits for more
+    assert!(out.len().abs_diff(codes.len() * 3) <= 4, "{} samples", out.len());
+    assert_eq!(wire::output_ms(codes.len() as u64), 64);
+    golden("wire_alaw_decoded.pcm", &bytes(&out));
+}
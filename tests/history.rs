@@ -0,0 +1,51 @@
+// Replay of the conversation into a new session (src/history.rs): a turn
+// deleted from the server conversation (a voice command, noise, a filtered
+// or blocked turn) must not come back when the session moves to a new
+// connection, whether its transcript came before the delete or after.
+
+#[allow(dead_code)]
+#[path = "../src/history.rs"]
+mod history;
+
+use history::History;
+use serde_json::Value;
+
+fn replayed(h: &History) -> Vec<(String, String)> {
+    h.replay_events()
+        .iter()
+        .map(|e| {
+            let v: Value = serde_json::from_str(e).unwrap();
+            (v["item"]["role"].as_str().unwrap().to_string(), v["item"]["content"][0]["text"].as_str().unwrap().to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn suppressed_turn_is_not_replayed() {
+    let mut h = History::default();
+    h.remember("user", "what's the weather", Some("item_1"));
+    h.remember("assistant", "Sunny.", Some("item_2"));
+    h.remember("user", "something the filter suppresses", Some("item_3"));
+    h.forget("item_3");
+    assert_eq!(
+        replayed(&h),
+        [("user".to_string(), "what's the weather".to_string()), ("assistant".to_string(), "Sunny.".to_string())]
+    );
+}
+
+#[test]
+fn transcript_after_the_delete_is_not_replayed() {
+    let mut h = History::default();
+    h.forget("item_1");
+    h.remember("user", "parlar, mute", Some("item_1"));
+    assert!(replayed(&h).is_empty());
+}
+
+#[test]
+fn replaced_turn_replays_the_replacement() {
+    let mut h = History::default();
+    h.remember("user", "my card is 4111 1111 1111 1111", Some("item_1"));
+    h.forget("item_1");
+    h.remember("user", "my card is [redacted]", Some("item_2"));
+    assert_eq!(replayed(&h), [("user".to_string(), "my card is [redacted]".to_string())]);
+}
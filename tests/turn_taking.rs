@@ -0,0 +1,403 @@
+// Property tests for turn-taking (src/turn_taking.rs and the reply lifecycle
+// in src/responses.rs). A simulated server and parlar exchange events
+// through two queues, and proptest picks the order things happen in: the
+// user finishing a turn, typing, starting to speak or pressing I; a tool,
+// timer or reminder waiting to speak; time passing, so interrupts are held
+// back by their cooldown or not; the server starting, streaming, finishing
+// or refusing a reply, or reporting an unrelated error; either queue
+// delivering its next message. Whatever the order:
+// - the server never gets a response.create while a reply is under way
+// - every truncate names an assistant item the server produced
+// - audio of a reply that was cut off is never played
+// - once everything is delivered and the server is done, no reply is under
+//   way or queued and nothing waits to speak, so the mic is not left gated
+//   and turns are not lost
+// The decisions are the ones main.rs, interrupt.rs, control.rs and tools.rs
+// act on; only sending the events they return is simulated here.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use proptest::prelude::*;
+
+#[allow(dead_code)]
+#[path = "../src/responses.rs"]
+mod responses;
+#[allow(dead_code)]
+#[path = "../src/turn_taking.rs"]
+mod turn_taking;
+
+use responses::Responses;
+use turn_taking::{Policy, Turn};
+
+// CANCEL_COOLDOWN_USER_MS and CANCEL_COOLDOWN_SPEECH_MS at their defaults
+const USER_COOLDOWN: Duration = Duration::ZERO;
+const SPEECH_COOLDOWN: Duration = Duration::from_millis(400);
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    // the user (the client acts at once)
+    Commit,
+    Type,
+    Interrupt,
+    // a tool call, timer or reminder: speaks, or (a tool call that is not the
+    // last of its batch) claims the reply only to hand in its output
+    Wait { speaks: bool },
+    Poll,
+    Tick,
+    // the server
+    SpeechStarted,
+    Advance,
+    Error,
+    // the network
+    ToServer { refuse: bool },
+    ToClient,
+}
+
+#[derive(Debug)]
+enum ClientEvent {
+    Create(String),
+    Cancel,
+    Truncate(String),
+}
+
+#[derive(Debug)]
+enum ServerEvent {
+    Created(String),
+    ItemAdded { item: String, response: String },
+    AudioDelta(String),
+    AudioDone(String),
+    Done(String),
+    SpeechStarted,
+    Error(Option<String>),
+}
+
+struct Client {
+    r: Responses,
+    policy: Policy,
+    out: VecDeque<ClientEvent>,
+    now: Instant,
+    last_cancel_at: Option<Instant>,
+    // tools::wait_idle callers still waiting, and whether each speaks
+    waiting: VecDeque<bool>,
+    // event ids of every response.create, and how many of them were cut off
+    asked: Vec<String>,
+    cut: usize,
+    // response ids whose audio was played
+    played: Vec<String>,
+}
+
+impl Client {
+    fn create(&mut self, event_id: Option<String>) {
+        if let Some(event_id) = event_id {
+            self.asked.push(event_id.clone());
+            self.out.push_back(ClientEvent::Create(event_id));
+        }
+    }
+
+    // interrupt::cancel
+    fn cancel(&mut self, cooldown: Duration) {
+        let Some(cancel) = turn_taking::cut_off(&mut self.r, &mut self.last_cancel_at, self.now, cooldown) else {
+            return;
+        };
+        self.cut = self.asked.len();
+        self.out.push_back(ClientEvent::Cancel);
+        if let Some(item) = cancel.truncate {
+            self.out.push_back(ClientEvent::Truncate(item));
+        }
+    }
+
+    // input_audio_buffer.committed, once the response delay is over
+    fn committed(&mut self) {
+        match turn_taking::committed(&mut self.r, self.policy) {
+            Turn::Answer(event_id) => self.create(Some(event_id)),
+            Turn::CutOff => self.cancel(SPEECH_COOLDOWN),
+            Turn::Queued | Turn::Dropped => {}
+        }
+    }
+
+    // Controls::say_text
+    fn typed(&mut self) {
+        match turn_taking::typed(&mut self.r) {
+            Some(event_id) => self.create(Some(event_id)),
+            None => self.cancel(USER_COOLDOWN),
+        }
+    }
+
+    // tools::wait_idle, then tools::speak or tools::finish
+    fn poll(&mut self) {
+        let Some(&speaks) = self.waiting.front() else { return };
+        let Some(event_id) = self.r.try_ask() else { return };
+        self.waiting.pop_front();
+        if speaks {
+            self.create(Some(event_id));
+        } else {
+            let next = turn_taking::withdraw(&mut self.r, &event_id);
+            self.create(next);
+        }
+    }
+
+    fn on(&mut self, evt: ServerEvent) {
+        match evt {
+            ServerEvent::Created(id) => self.r.created(&id),
+            ServerEvent::ItemAdded { item, response } => self.r.item_added(&item, "assistant", Some(&response)),
+            ServerEvent::AudioDelta(id) => {
+                if self.r.audio(Some(&id)) {
+                    self.played.push(id);
+                }
+            }
+            ServerEvent::AudioDone(id) => {
+                self.r.audio_done(Some(&id));
+            }
+            ServerEvent::Done(id) => {
+                let next = turn_taking::done(&mut self.r, Some(&id)).next;
+                self.create(next);
+            }
+            // Playback is left out: speaking is the reply's own state
+            ServerEvent::SpeechStarted => {
+                if turn_taking::speaking(&self.r, false) {
+                    self.cancel(SPEECH_COOLDOWN);
+                }
+            }
+            ServerEvent::Error(Some(event_id)) => {
+                let next = turn_taking::refused(&mut self.r, &event_id);
+                self.create(next);
+            }
+            ServerEvent::Error(None) => {}
+        }
+    }
+}
+
+// The reply the server is producing
+struct Reply {
+    id: String,
+    item: String,
+    step: u8,
+}
+
+#[derive(Default)]
+struct Server {
+    current: Option<Reply>,
+    out: VecDeque<ServerEvent>,
+    replies: u32,
+    // response id to the event id of its response.create
+    asked_by: HashMap<String, String>,
+    // assistant items the server produced
+    items: Vec<String>,
+    violations: Vec<String>,
+}
+
+impl Server {
+    fn receive(&mut self, evt: ClientEvent, refuse: bool) {
+        match evt {
+            ClientEvent::Create(event_id) => {
+                if let Some(r) = &self.current {
+                    self.violations.push(format!("{event_id} sent while {} is under way", r.id));
+                    self.out.push_back(ServerEvent::Error(Some(event_id)));
+                } else if refuse {
+                    self.out.push_back(ServerEvent::Error(Some(event_id)));
+                } else {
+                    self.replies += 1;
+                    let n = self.replies;
+                    let r = Reply { id: format!("resp_{n}"), item: format!("item_{n}"), step: 0 };
+                    self.asked_by.insert(r.id.clone(), event_id);
+                    self.out.push_back(ServerEvent::Created(r.id.clone()));
+                    self.current = Some(r);
+                }
+            }
+            ClientEvent::Cancel => match self.current.take() {
+                Some(r) => self.out.push_back(ServerEvent::Done(r.id)),
+                // response_cancel_not_active
+                None => self.out.push_back(ServerEvent::Error(None)),
+            },
+            ClientEvent::Truncate(item) => {
+                if item.is_empty() || !self.items.contains(&item) {
+                    self.violations.push(format!("truncate of {item:?}, which is no assistant item"));
+                }
+            }
+        }
+    }
+
+    // The next event of the reply under way
+    fn advance(&mut self) {
+        let Some(r) = &mut self.current else { return };
+        r.step += 1;
+        let evt = match r.step {
+            1 => {
+                self.items.push(r.item.clone());
+                ServerEvent::ItemAdded { item: r.item.clone(), response: r.id.clone() }
+            }
+            2..=4 => ServerEvent::AudioDelta(r.id.clone()),
+            5 => ServerEvent::AudioDone(r.id.clone()),
+            _ => ServerEvent::Done(self.current.take().expect("a reply").id),
+        };
+        self.out.push_back(evt);
+    }
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => Just(Op::Commit),
+        1 => Just(Op::Type),
+        1 => Just(Op::Interrupt),
+        1 => Just(Op::Wait { speaks: true }),
+        1 => Just(Op::Wait { speaks: false }),
+        3 => Just(Op::Poll),
+        2 => Just(Op::Tick),
+        1 => Just(Op::SpeechStarted),
+        8 => Just(Op::Advance),
+        1 => Just(Op::Error),
+        6 => Just(Op::ToServer { refuse: false }),
+        1 => Just(Op::ToServer { refuse: true }),
+        10 => Just(Op::ToClient),
+    ]
+}
+
+fn policy() -> impl Strategy<Value = Policy> {
+    prop_oneof![Just(Policy::Queue), Just(Policy::Latest), Just(Policy::Drop)]
+}
+
+fn run(policy: Policy, ops: &[Op]) -> (Client, Server) {
+    let mut client = Client {
+        r: Responses::default(),
+        policy,
+        out: VecDeque::new(),
+        now: Instant::now(),
+        last_cancel_at: None,
+        waiting: VecDeque::new(),
+        asked: Vec::new(),
+        cut: 0,
+        played: Vec::new(),
+    };
+    let mut server = Server::default();
+    let check_played = |client: &Client, server: &mut Server| {
+        if let Some(id) = client.played.last() {
+            let asked = &server.asked_by[id];
+            if client.asked[..client.cut].contains(asked) {
+                server.violations.push(format!("audio of {id} played after it was cut off"));
+            }
+        }
+    };
+    for &op in ops {
+        match op {
+            Op::Commit => client.committed(),
+            Op::Type => client.typed(),
+            Op::Interrupt => client.cancel(USER_COOLDOWN),
+            Op::Wait { speaks } => client.waiting.push_back(speaks),
+            Op::Poll => client.poll(),
+            Op::Tick => client.now += Duration::from_millis(200),
+            Op::SpeechStarted => server.out.push_back(ServerEvent::SpeechStarted),
+            Op::Advance => server.advance(),
+            Op::Error => server.out.push_back(ServerEvent::Error(None)),
+            Op::ToServer { refuse } => {
+                if let Some(evt) = client.out.pop_front() {
+                    server.receive(evt, refuse);
+                }
+            }
+            Op::ToClient => {
+                if let Some(evt) = server.out.pop_front() {
+                    let played = client.played.len();
+                    client.on(evt);
+                    if client.played.len() > played {
+                        check_played(&client, &mut server);
+                    }
+                }
+            }
+        }
+    }
+    // Let everything settle: deliver, and have the server finish its replies
+    for _ in 0..10_000 {
+        if let Some(evt) = client.out.pop_front() {
+            server.receive(evt, false);
+        } else if server.current.is_some() {
+            server.advance();
+        } else if !client.waiting.is_empty() && !client.r.busy() {
+            client.poll();
+        } else if let Some(evt) = server.out.pop_front() {
+            let played = client.played.len();
+            client.on(evt);
+            if client.played.len() > played {
+                check_played(&client, &mut server);
+            }
+        } else {
+            break;
+        }
+    }
+    (client, server)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(1024))]
+
+    #[test]
+    fn turn_taking_invariants(policy in policy(), ops in prop::collection::vec(op(), 0..200)) {
+        let (client, server) = run(policy, &ops);
+        prop_assert!(server.violations.is_empty(), "{:?}", server.violations);
+        prop_assert!(client.out.is_empty() && server.out.is_empty(), "the exchange did not settle");
+        prop_assert!(!client.r.busy(), "a reply is still under way once the server is done");
+        prop_assert!(!client.r.queued, "a queued turn never got its reply");
+        prop_assert!(client.waiting.is_empty(), "a tool, timer or reminder never got to speak");
+    }
+}
+
+// The orders behind races the lifecycle once had
+
+#[test]
+fn commit_between_audio_done_and_response_done() {
+    let to_server = Op::ToServer { refuse: false };
+    // The client has the whole reply but its response.done
+    let ops = [&[Op::Commit, to_server][..], &[Op::Advance; 5], &[Op::ToClient; 6], &[Op::Commit, to_server]].concat();
+    let (client, server) = run(Policy::Queue, &ops);
+    assert!(server.violations.is_empty(), "{:?}", server.violations);
+    assert_eq!(client.asked.len(), 2, "the second turn is answered once the first reply is done");
+}
+
+#[test]
+fn cut_off_before_response_created() {
+    let to_server = Op::ToServer { refuse: false };
+    // The first reply starts streaming while the client has not heard of it yet
+    let ops = [Op::Commit, to_server, Op::Advance, Op::Advance, Op::Interrupt, Op::Type, Op::ToClient, Op::ToClient];
+    let (client, server) = run(Policy::Queue, &ops);
+    assert!(server.violations.is_empty(), "{:?}", server.violations);
+    assert!(client.played.iter().all(|id| id != "resp_1"), "{:?}", client.played);
+}
+
+#[test]
+fn refused_with_a_turn_queued() {
+    let ops = [Op::Commit, Op::Commit, Op::ToServer { refuse: true }, Op::ToClient];
+    let (client, server) = run(Policy::Queue, &ops);
+    assert!(!client.r.busy() && !client.r.queued);
+    assert_eq!(server.replies, 1, "the queued turn is answered");
+}
+
+#[test]
+fn tool_speaks_as_a_turn_is_committed() {
+    let to_server = Op::ToServer { refuse: false };
+    // A timer claims the reply; the user's turn a moment later waits for it
+    let ops = [Op::Wait { speaks: true }, Op::Poll, Op::Commit, to_server, to_server];
+    let (_, server) = run(Policy::Queue, &ops);
+    assert!(server.violations.is_empty(), "{:?}", server.violations);
+    assert_eq!(server.replies, 2, "the turn is answered after the timer");
+}
+
+#[test]
+fn cut_off_held_back_by_its_cooldown() {
+    let to_server = Op::ToServer { refuse: false };
+    // Speech cuts off the first reply; the latest turn comes within the cooldown
+    let ops = [
+        Op::Commit,
+        to_server,
+        Op::ToClient,
+        Op::SpeechStarted,
+        Op::ToClient,
+        Op::Commit,
+        to_server,
+        to_server,
+        Op::ToClient,
+        Op::ToClient,
+        Op::Commit,
+    ];
+    let (client, server) = run(Policy::Latest, &ops);
+    assert!(server.violations.is_empty(), "{:?}", server.violations);
+    assert_eq!(client.asked.len(), 3, "the last turn is answered once the reply it did not cut off is done");
+}